@@ -868,7 +868,8 @@ class AstationHubManager: ObservableObject {
         } else {
             wsScheme = stationRelayUrl.replacingOccurrences(of: "http://", with: "ws://")
         }
-        let wsUrl = "\(wsScheme)/ws?role=astation&code=\(code)"
+        // raw=1: AstationMessage frames predate the relay's versioned envelope
+        let wsUrl = "\(wsScheme)/ws?role=astation&code=\(code)&raw=1"
 
         Log.info("[AstationHub] Connecting to relay: \(wsUrl)")
 
@@ -11,6 +11,8 @@ class SessionLinkManager {
         let url: String
         let channel: String
         let createdAt: Date
+        /// Host token from creation; required to revoke the link.
+        var hostToken: String? = nil
     }
 
     init(hubManager: AstationHubManager) {
@@ -96,7 +98,8 @@ class SessionLinkManager {
             id: id,
             url: linkUrl,
             channel: channel,
-            createdAt: Date()
+            createdAt: Date(),
+            hostToken: json["host_token"] as? String
         )
 
         activeLinks.append(link)
@@ -111,6 +114,9 @@ class SessionLinkManager {
 
         var request = URLRequest(url: url)
         request.httpMethod = "DELETE"
+        if let hostToken = link.hostToken {
+            request.setValue("Bearer \(hostToken)", forHTTPHeaderField: "Authorization")
+        }
 
         do {
             NetworkDebugLogger.logRequest(request, label: "SessionLink")
@@ -0,0 +1,24 @@
+//! Stamps the build time into the `BUILD_TIMESTAMP` env var (Unix seconds),
+//! reported by `GET /api/version`. `SOURCE_DATE_EPOCH` overrides it for
+//! reproducible builds.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    // Any rerun-if line replaces cargo's default of rerunning on every
+    // package change, so name the sources explicitly
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let timestamp = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or(0)
+        });
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", timestamp);
+}
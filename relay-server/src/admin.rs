@@ -0,0 +1,308 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{bearer_token, constant_time_eq};
+use crate::rtc_session;
+use crate::AppState;
+
+#[derive(Serialize, Deserialize)]
+pub struct CapacityStats {
+    pub used: usize,
+    pub max: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AdminStatsResponse {
+    pub rtc_sessions: CapacityStats,
+    /// Generated pairing codes that were already in use (each one regenerated).
+    pub pair_code_collisions: u64,
+    /// Auth sessions neither granted nor denied; cleanup removes them once expired.
+    pub pending_auth_sessions: usize,
+}
+
+/// Store sizes before and after a manual cleanup.
+#[derive(Serialize, Deserialize)]
+pub struct CleanupResponse {
+    pub cleaned_at: DateTime<Utc>,
+    pub sessions_before: usize,
+    pub sessions_after: usize,
+    pub rtc_before: usize,
+    pub rtc_after: usize,
+    pub pair_rooms_before: usize,
+    pub pair_rooms_after: usize,
+    pub voice_before: usize,
+    pub voice_after: usize,
+    pub verify_cache_before: usize,
+    pub verify_cache_after: usize,
+}
+
+/// Require the admin token (`ADMIN_TOKEN`) for an admin endpoint.
+/// Admin API disabled (no token configured) → 404, missing token → 401,
+/// wrong token → 403.
+pub fn require_admin(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let expected = state.admin_token.as_deref().ok_or((
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({ "error": "Admin API is disabled" })),
+    ))?;
+    let token = bearer_token(headers).ok_or((
+        StatusCode::UNAUTHORIZED,
+        Json(serde_json::json!({ "error": "Missing admin token" })),
+    ))?;
+    if !constant_time_eq(token, expected) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Invalid admin token" })),
+        ));
+    }
+    Ok(())
+}
+
+/// GET /api/admin/stats
+pub async fn admin_stats_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    require_admin(&state, &headers)?;
+    Ok::<_, (StatusCode, Json<serde_json::Value>)>(Json(AdminStatsResponse {
+        rtc_sessions: CapacityStats {
+            used: state.rtc_sessions.capacity_used().await,
+            max: state.rtc_sessions.max_sessions(),
+        },
+        pair_code_collisions: state.relay.code_collisions(),
+        pending_auth_sessions: state.sessions.pending_expiry_count(),
+    }))
+}
+
+/// Sizes of the auth session, RTC session, pair room, voice session and
+/// verify cache stores, in that order.
+async fn store_sizes(state: &AppState) -> (usize, usize, usize, usize, usize) {
+    tokio::join!(
+        state.sessions.session_count(),
+        state.rtc_sessions.capacity_used(),
+        state.relay.room_count(),
+        state.voice_sessions.session_count(),
+        state.session_verify_cache.entry_count(),
+    )
+}
+
+/// POST /api/admin/cleanup
+///
+/// Run every store's expiry sweep now instead of waiting for the background tasks.
+pub async fn admin_cleanup_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    require_admin(&state, &headers)?;
+    let before = store_sizes(&state).await;
+    let expire_rtc = async {
+        for session in state.rtc_sessions.cleanup_expired().await {
+            rtc_session::notify_pair_room(&state.relay, &session, "rtc_session_expired").await;
+        }
+    };
+    tokio::join!(
+        state.sessions.cleanup_expired(),
+        expire_rtc,
+        state.relay.cleanup_expired(),
+        state.voice_sessions.cleanup_expired(),
+        state.session_verify_cache.cleanup_expired(),
+    );
+    let after = store_sizes(&state).await;
+    tracing::info!("Manual cleanup: {:?} -> {:?}", before, after);
+    Ok::<_, (StatusCode, Json<serde_json::Value>)>(Json(CleanupResponse {
+        cleaned_at: Utc::now(),
+        sessions_before: before.0,
+        sessions_after: after.0,
+        rtc_before: before.1,
+        rtc_after: after.1,
+        pair_rooms_before: before.2,
+        pair_rooms_after: after.2,
+        voice_before: before.3,
+        voice_after: after.3,
+        verify_cache_before: before.4,
+        verify_cache_after: after.4,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relay::{RelayHub, Role};
+    use crate::rtc_session::RtcSessionStore;
+    use crate::session_store::SessionStore;
+    use crate::session_verify::SessionVerifyCache;
+    use crate::voice_session::VoiceSessionStore;
+    use axum::body::Body;
+    use axum::http::{HeaderValue, Request};
+    use crate::auth::{Session, SessionStatus};
+    use crate::relay::RelayConfig;
+    use crate::voice_session::VoiceSession;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use tower::ServiceExt;
+
+    fn state_with_token(token: Option<&str>) -> AppState {
+        AppState {
+            sessions: SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: token.map(Into::into),
+        }
+    }
+
+    fn headers(auth: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_str(auth).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_admin_disabled_without_token() {
+        let state = state_with_token(None);
+        let err = require_admin(&state, &headers("Bearer anything")).unwrap_err();
+        assert_eq!(err.0, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_admin_token_checks() {
+        let state = state_with_token(Some("secret"));
+        assert_eq!(
+            require_admin(&state, &HeaderMap::new()).unwrap_err().0,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            require_admin(&state, &headers("Bearer wrong")).unwrap_err().0,
+            StatusCode::FORBIDDEN
+        );
+        assert!(require_admin(&state, &headers("Bearer secret")).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_admin_stats_reports_rtc_capacity() {
+        let mut state = state_with_token(Some("secret"));
+        state.rtc_sessions = RtcSessionStore::new().with_max_sessions(5);
+        state
+            .rtc_sessions
+            .create("s1".into(), "app".into(), "ch".into(), "tok".into(), 1)
+            .await
+            .unwrap();
+        let app = Router::new()
+            .route("/api/admin/stats", get(admin_stats_handler))
+            .with_state(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/admin/stats")
+                    .header("authorization", "Bearer secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let stats: AdminStatsResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats.rtc_sessions.used, 1);
+        assert_eq!(stats.rtc_sessions.max, 5);
+        assert_eq!(stats.pair_code_collisions, 0);
+        assert_eq!(stats.pending_auth_sessions, 0);
+    }
+
+    fn cleanup_app(state: AppState) -> Router {
+        Router::new()
+            .route("/api/admin/cleanup", post(admin_cleanup_handler))
+            .with_state(state)
+    }
+
+    async fn post_cleanup(app: Router, auth: Option<&str>) -> axum::response::Response {
+        let mut request = Request::builder().method("POST").uri("/api/admin/cleanup");
+        if let Some(auth) = auth {
+            request = request.header("authorization", auth);
+        }
+        app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_admin_cleanup_removes_expired_objects() {
+        let mut state = state_with_token(Some("secret"));
+        // Rooms expire as soon as they are created
+        state.relay = RelayHub::new().with_config(RelayConfig { room_ttl_secs: 0, ..Default::default() });
+        let now = Utc::now();
+
+        for (id, expires_at) in [("lapsed", now - chrono::Duration::minutes(1)), ("live", now + chrono::Duration::minutes(5))] {
+            state
+                .sessions
+                .create(Session {
+                    id: id.into(),
+                    otp: "12345678".into(),
+                    hostname: "host".into(),
+                    status: SessionStatus::Pending,
+                    token: None,
+                    created_at: now - chrono::Duration::minutes(6),
+                    expires_at,
+                    client_ip: None,
+                    created_by: None,
+                })
+                .await;
+        }
+        state.rtc_sessions.insert_expired("old-rtc", now - chrono::Duration::hours(3)).await;
+        state
+            .rtc_sessions
+            .create("new-rtc".into(), "app".into(), "ch".into(), "tok".into(), 1)
+            .await
+            .unwrap();
+        let _atem_rx = state.relay.connect_test_side("GONE-ROOM", Role::Atem).await;
+        let mut idle = VoiceSession::new("idle".into(), "atem".into(), "ch".into());
+        idle.last_activity = now - chrono::Duration::minutes(2);
+        state.voice_sessions.insert_session(idle).await;
+        state
+            .voice_sessions
+            .create("busy".into(), "atem".into(), "ch".into())
+            .await
+            .unwrap();
+        state.session_verify_cache.set("gone".into(), "astation".into(), true, 0).await;
+        state.session_verify_cache.set("kept".into(), "astation".into(), true, 300).await;
+
+        let response = post_cleanup(cleanup_app(state.clone()), Some("Bearer secret")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let cleanup: CleanupResponse = serde_json::from_slice(&body).unwrap();
+        assert!(cleanup.cleaned_at >= now);
+        assert_eq!((cleanup.sessions_before, cleanup.sessions_after), (2, 1));
+        assert_eq!((cleanup.rtc_before, cleanup.rtc_after), (2, 1));
+        assert_eq!((cleanup.pair_rooms_before, cleanup.pair_rooms_after), (1, 0));
+        assert_eq!((cleanup.voice_before, cleanup.voice_after), (2, 1));
+        assert_eq!((cleanup.verify_cache_before, cleanup.verify_cache_after), (2, 1));
+
+        assert!(state.sessions.get("live").await.is_some());
+        assert!(state.voice_sessions.get("busy").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_admin_cleanup_requires_token() {
+        let state = state_with_token(Some("secret"));
+        state.session_verify_cache.set("gone".into(), "astation".into(), true, 0).await;
+
+        let response = post_cleanup(cleanup_app(state.clone()), None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(state.session_verify_cache.entry_count().await, 1);
+    }
+}
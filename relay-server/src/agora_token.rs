@@ -0,0 +1,256 @@
+use base64::Engine;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+use std::io::Write;
+
+// Agora AccessToken2 ("007") for the RTC service.
+//
+// Layout (all integers little-endian, strings are u16 length + bytes):
+//   "007" + base64(zlib(string(signature) + signing_info))
+//   signing_info = string(app_id) u32(issue_ts) u32(expire) u32(salt) u16(service_count) services...
+//   rtc service  = u16(type) u16(privilege_count) [u16(privilege) u32(expire)]... string(channel) string(uid)
+//   signing key  = hmac(salt, hmac(issue_ts, app_certificate))
+//   signature    = hmac(signing key, signing_info)
+
+const VERSION: &str = "007";
+const SERVICE_TYPE_RTC: u16 = 1;
+const PRIVILEGE_JOIN_CHANNEL: u16 = 1;
+const PRIVILEGE_PUBLISH_AUDIO: u16 = 2;
+const PRIVILEGE_PUBLISH_VIDEO: u16 = 3;
+const PRIVILEGE_PUBLISH_DATA: u16 = 4;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Privileges granted by an RTC token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RtcTokenRole {
+    /// Join plus publish audio/video/data.
+    Publisher,
+    /// Join only.
+    Subscriber,
+}
+
+/// Inputs for a per-uid RTC token.
+pub struct RtcTokenRequest<'a> {
+    pub app_id: &'a str,
+    pub app_certificate: &'a str,
+    pub channel: &'a str,
+    pub uid: u32,
+    pub role: RtcTokenRole,
+    /// Token and privilege lifetime in seconds from issue.
+    pub expire_secs: u32,
+}
+
+impl RtcTokenRequest<'_> {
+    /// Build a token issued now with a random salt.
+    pub fn build(&self) -> String {
+        let issue_ts = chrono::Utc::now().timestamp() as u32;
+        let salt = rand::thread_rng().gen_range(1..=99_999_999);
+        self.build_at(issue_ts, salt)
+    }
+
+    /// Build a token with a fixed issue timestamp and salt (deterministic).
+    pub fn build_at(&self, issue_ts: u32, salt: u32) -> String {
+        let mut signing_info = Vec::new();
+        pack_string(&mut signing_info, self.app_id.as_bytes());
+        pack_u32(&mut signing_info, issue_ts);
+        pack_u32(&mut signing_info, self.expire_secs);
+        pack_u32(&mut signing_info, salt);
+        pack_u16(&mut signing_info, 1);
+        self.pack_rtc_service(&mut signing_info);
+
+        let signing_key = hmac_sha256(&issue_ts.to_le_bytes(), self.app_certificate.as_bytes());
+        let signing_key = hmac_sha256(&salt.to_le_bytes(), &signing_key);
+        let signature = hmac_sha256(&signing_key, &signing_info);
+
+        let mut content = Vec::new();
+        pack_string(&mut content, &signature);
+        content.extend_from_slice(&signing_info);
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(&content)
+            .expect("writing to an in-memory zlib encoder cannot fail");
+        let compressed = encoder
+            .finish()
+            .expect("finishing an in-memory zlib encoder cannot fail");
+
+        format!(
+            "{}{}",
+            VERSION,
+            base64::engine::general_purpose::STANDARD.encode(compressed)
+        )
+    }
+
+    fn pack_rtc_service(&self, buf: &mut Vec<u8>) {
+        let privileges: &[u16] = match self.role {
+            RtcTokenRole::Publisher => &[
+                PRIVILEGE_JOIN_CHANNEL,
+                PRIVILEGE_PUBLISH_AUDIO,
+                PRIVILEGE_PUBLISH_VIDEO,
+                PRIVILEGE_PUBLISH_DATA,
+            ],
+            RtcTokenRole::Subscriber => &[PRIVILEGE_JOIN_CHANNEL],
+        };
+
+        pack_u16(buf, SERVICE_TYPE_RTC);
+        pack_u16(buf, privileges.len() as u16);
+        for privilege in privileges {
+            pack_u16(buf, *privilege);
+            pack_u32(buf, self.expire_secs);
+        }
+        pack_string(buf, self.channel.as_bytes());
+        // uid 0 means "any uid" and is encoded as an empty string
+        let uid = if self.uid == 0 {
+            String::new()
+        } else {
+            self.uid.to_string()
+        };
+        pack_string(buf, uid.as_bytes());
+    }
+}
+
+fn hmac_sha256(key: &[u8], msg: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(msg);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn pack_u16(buf: &mut Vec<u8>, v: u16) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn pack_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn pack_string(buf: &mut Vec<u8>, s: &[u8]) {
+    pack_u16(buf, s.len() as u16);
+    buf.extend_from_slice(s);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    const APP_ID: &str = "970CA35de60c44645bbae8a215061b33";
+    const APP_CERT: &str = "5CFd2fd1755d40ecb72977518be15d3b";
+
+    fn request(uid: u32, role: RtcTokenRole) -> RtcTokenRequest<'static> {
+        RtcTokenRequest {
+            app_id: APP_ID,
+            app_certificate: APP_CERT,
+            channel: "test-channel",
+            uid,
+            role,
+            expire_secs: 600,
+        }
+    }
+
+    /// Minimal reader for the packed token content.
+    struct Reader<'a> {
+        buf: &'a [u8],
+    }
+
+    impl<'a> Reader<'a> {
+        fn u16(&mut self) -> u16 {
+            let v = u16::from_le_bytes([self.buf[0], self.buf[1]]);
+            self.buf = &self.buf[2..];
+            v
+        }
+        fn u32(&mut self) -> u32 {
+            let v = u32::from_le_bytes([self.buf[0], self.buf[1], self.buf[2], self.buf[3]]);
+            self.buf = &self.buf[4..];
+            v
+        }
+        fn string(&mut self) -> &'a [u8] {
+            let len = self.u16() as usize;
+            let (s, rest) = self.buf.split_at(len);
+            self.buf = rest;
+            s
+        }
+    }
+
+    fn decode(token: &str) -> Vec<u8> {
+        assert!(token.starts_with("007"));
+        let compressed = base64::engine::general_purpose::STANDARD
+            .decode(&token[3..])
+            .unwrap();
+        let mut content = Vec::new();
+        ZlibDecoder::new(&compressed[..])
+            .read_to_end(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn test_build_is_deterministic_for_fixed_inputs() {
+        let req = request(1000, RtcTokenRole::Publisher);
+        assert_eq!(req.build_at(1_700_000_000, 42), req.build_at(1_700_000_000, 42));
+        assert_ne!(req.build_at(1_700_000_000, 42), req.build_at(1_700_000_000, 43));
+    }
+
+    #[test]
+    fn test_token_structure_and_signature() {
+        let token = request(1000, RtcTokenRole::Publisher).build_at(1_700_000_000, 42);
+        let content = decode(&token);
+
+        let mut r = Reader { buf: &content };
+        let signature = r.string().to_vec();
+        let signing_info = r.buf.to_vec();
+
+        assert_eq!(r.string(), APP_ID.as_bytes());
+        assert_eq!(r.u32(), 1_700_000_000);
+        assert_eq!(r.u32(), 600);
+        assert_eq!(r.u32(), 42);
+        assert_eq!(r.u16(), 1, "one service");
+        assert_eq!(r.u16(), SERVICE_TYPE_RTC);
+        assert_eq!(r.u16(), 4, "publisher has four privileges");
+        for expected in [1u16, 2, 3, 4] {
+            assert_eq!(r.u16(), expected);
+            assert_eq!(r.u32(), 600);
+        }
+        assert_eq!(r.string(), b"test-channel");
+        assert_eq!(r.string(), b"1000");
+        assert!(r.buf.is_empty());
+
+        let key = hmac_sha256(&1_700_000_000u32.to_le_bytes(), APP_CERT.as_bytes());
+        let key = hmac_sha256(&42u32.to_le_bytes(), &key);
+        assert_eq!(signature, hmac_sha256(&key, &signing_info));
+    }
+
+    #[test]
+    fn test_subscriber_only_has_join_privilege() {
+        let token = request(1000, RtcTokenRole::Subscriber).build_at(1, 1);
+        let content = decode(&token);
+        let mut r = Reader { buf: &content };
+        r.string(); // signature
+        r.string(); // app_id
+        r.u32();
+        r.u32();
+        r.u32();
+        r.u16();
+        assert_eq!(r.u16(), SERVICE_TYPE_RTC);
+        assert_eq!(r.u16(), 1);
+        assert_eq!(r.u16(), PRIVILEGE_JOIN_CHANNEL);
+    }
+
+    #[test]
+    fn test_uid_zero_encoded_as_empty_string() {
+        let content = decode(&request(0, RtcTokenRole::Subscriber).build_at(1, 1));
+        // Last field is the uid string: an empty string is just a zero length prefix
+        assert_eq!(&content[content.len() - 2..], &[0, 0]);
+    }
+
+    #[test]
+    fn test_tokens_differ_per_uid() {
+        let a = request(1000, RtcTokenRole::Publisher).build_at(1, 1);
+        let b = request(1001, RtcTokenRole::Publisher).build_at(1, 1);
+        assert_ne!(a, b);
+    }
+}
@@ -1,6 +1,24 @@
+//! Auth sessions and the secrets behind them.
+//!
+//! OTPs and session tokens gate access to an Atem, so they come straight from
+//! the OS CSPRNG ([`OsRng`]: `getrandom`/`/dev/urandom` on Unix, `BCryptGenRandom`
+//! on Windows) rather than `thread_rng`, a userspace generator that is only
+//! periodically reseeded from the OS.
+//!
+//! With `OTP_HMAC_KEY` set, OTPs are instead derived from the session itself
+//! ([`generate_signed_otp`]), so any relay holding the key can check one
+//! without the session store.
+
+use axum::http::{header, HeaderMap};
 use chrono::{DateTime, Duration, Utc};
-use rand::Rng;
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::{Rng, RngCore};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::LazyLock;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -21,25 +39,109 @@ pub struct Session {
     pub token: Option<String>,
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
+    /// Address that requested the session (audit only; admin API exposes it).
+    #[serde(default)]
+    pub client_ip: Option<IpAddr>,
+    /// Opaque caller identifier from `X-Created-By` (e.g. a user email), for
+    /// relays shared by several accounts.
+    #[serde(default)]
+    pub created_by: Option<String>,
 }
 
+/// Hostnames accepted when creating auth sessions and pair rooms: letters,
+/// digits, dots, underscores and hyphens, which also covers IPv4 addresses.
+pub static HOSTNAME_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9._-]+$").unwrap());
+
+/// Random bytes in a session token.
+pub const SESSION_TOKEN_BYTES: usize = 32;
+
+/// Hex characters in a session token.
+pub const SESSION_TOKEN_HEX_LEN: usize = 64;
+
+const _: () = assert!(SESSION_TOKEN_BYTES == 32 && SESSION_TOKEN_BYTES * 2 == SESSION_TOKEN_HEX_LEN);
+
 /// Generate an 8-digit numeric OTP.
 pub fn generate_otp() -> String {
-    let mut rng = rand::thread_rng();
-    let otp: u32 = rng.gen_range(10_000_000..100_000_000);
+    let otp: u32 = OsRng.gen_range(10_000_000..100_000_000);
     otp.to_string()
 }
 
+type HmacSha256 = Hmac<Sha256>;
+
+/// 8-digit OTP from HMAC-SHA256 of `"{session_id}:{hostname}:{expires_at_unix}"`:
+/// the first four bytes of the MAC, big-endian, modulo 10^8.
+fn signed_otp(session_id: &str, hostname: &str, expires_at: DateTime<Utc>, key: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}:{}:{}", session_id, hostname, expires_at.timestamp()).as_bytes());
+    let digest = mac.finalize().into_bytes();
+    let value = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+    format!("{:08}", value % 100_000_000)
+}
+
+/// The OTP for `session` under `key` (`OTP_HMAC_KEY` mode).
+pub fn generate_signed_otp(session: &Session, key: &[u8]) -> String {
+    signed_otp(&session.id, &session.hostname, session.expires_at, key)
+}
+
+/// Check a signed OTP from the session's id, hostname and expiry alone.
+/// False once `expires_at` has passed.
+pub fn verify_signed_otp(session_id: &str, hostname: &str, expires_at: DateTime<Utc>, otp: &str, key: &[u8]) -> bool {
+    Utc::now() <= expires_at && constant_time_eq(otp, &signed_otp(session_id, hostname, expires_at, key))
+}
+
 /// Generate a 64-character hex session token.
 pub fn generate_session_token() -> String {
-    let mut rng = rand::thread_rng();
-    let bytes: Vec<u8> = (0..32).map(|_| rng.gen::<u8>()).collect();
+    let mut bytes = [0u8; SESSION_TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
     bytes.iter().map(|b| format!("{:02x}", b)).collect()
 }
 
+/// Compare a presented secret with the stored one in time that does not depend
+/// on where they first differ, so response timing cannot be used to guess it
+/// byte by byte. Only the length can leak, and tokens have a fixed length.
+pub fn constant_time_eq(presented: &str, expected: &str) -> bool {
+    let (a, b) = (presented.as_bytes(), expected.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Extract the token from an `Authorization: Bearer <token>` header.
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Best-effort client address. Behind a trusted proxy this is the first
+/// `X-Forwarded-For` hop, then `X-Real-IP`; those headers are client-supplied
+/// otherwise, so only the TCP peer address is used.
+pub fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>, trust_proxy: bool) -> Option<IpAddr> {
+    let header = |name: &str| headers.get(name).and_then(|h| h.to_str().ok());
+    let parse = |ip: &str| ip.trim().parse::<IpAddr>().ok();
+
+    let forwarded = || {
+        header("x-forwarded-for")
+            .and_then(|v| v.split(',').next())
+            .and_then(parse)
+            .or_else(|| header("x-real-ip").and_then(parse))
+    };
+    trust_proxy
+        .then(forwarded)
+        .flatten()
+        .or_else(|| peer.map(|addr| addr.ip()))
+}
+
 /// Create a new session with the given hostname.
 /// The session has status=Pending, a generated UUID and OTP, and expires in 5 minutes.
+#[cfg(test)]
 pub fn create_session(hostname: &str) -> Session {
+    create_session_with_ip(hostname, None)
+}
+
+/// Create a new pending session for `hostname`, recording the requesting
+/// client's address. It gets a generated UUID and OTP and expires in 5 minutes.
+pub fn create_session_with_ip(hostname: &str, ip: Option<IpAddr>) -> Session {
     let now = Utc::now();
     Session {
         id: Uuid::new_v4().to_string(),
@@ -49,12 +151,18 @@ pub fn create_session(hostname: &str) -> Session {
         token: None,
         created_at: now,
         expires_at: now + Duration::minutes(5),
+        client_ip: ip,
+        created_by: None,
     }
 }
 
 /// Validate an OTP against a session.
-/// Returns true if the OTP matches and the session has not expired.
-pub fn validate_otp(session: &Session, otp: &str) -> bool {
+/// Returns true if the OTP matches and the session has not expired. With
+/// `hmac_key`, the OTP is checked as a signed one instead of against `session.otp`.
+pub fn validate_otp(session: &Session, otp: &str, hmac_key: Option<&[u8]>) -> bool {
+    if let Some(key) = hmac_key {
+        return verify_signed_otp(&session.id, &session.hostname, session.expires_at, otp, key);
+    }
     if session.otp != otp {
         return false;
     }
@@ -104,6 +212,31 @@ mod tests {
         assert!(unique.len() > 1, "Generated tokens should vary");
     }
 
+    #[test]
+    fn test_constant_time_eq() {
+        let token = generate_session_token();
+        assert!(constant_time_eq(&token, &token.clone()));
+        assert!(!constant_time_eq(&generate_session_token(), &token));
+        assert!(!constant_time_eq(&token[..63], &token));
+        assert!(!constant_time_eq("", &token));
+        assert!(constant_time_eq("", ""));
+    }
+
+    #[test]
+    fn test_session_tokens_cover_hex_alphabet() {
+        let mut seen = std::collections::HashSet::new();
+        let mut unique = std::collections::HashSet::new();
+        for _ in 0..10_000 {
+            let token = generate_session_token();
+            assert_eq!(token.len(), SESSION_TOKEN_HEX_LEN);
+            seen.extend(token.chars());
+            unique.insert(token);
+        }
+        assert_eq!(unique.len(), 10_000, "tokens should not repeat");
+        let alphabet: std::collections::HashSet<char> = "0123456789abcdef".chars().collect();
+        assert_eq!(seen, alphabet);
+    }
+
     #[test]
     fn test_create_session() {
         let session = create_session("my-machine");
@@ -128,14 +261,14 @@ mod tests {
     fn test_validate_otp_correct() {
         let session = create_session("test-host");
         let otp = session.otp.clone();
-        assert!(validate_otp(&session, &otp), "Correct OTP should validate");
+        assert!(validate_otp(&session, &otp, None), "Correct OTP should validate");
     }
 
     #[test]
     fn test_validate_otp_wrong() {
         let session = create_session("test-host");
         assert!(
-            !validate_otp(&session, "00000000"),
+            !validate_otp(&session, "00000000", None),
             "Wrong OTP should not validate"
         );
     }
@@ -151,13 +284,59 @@ mod tests {
             token: None,
             created_at: now - Duration::minutes(10),
             expires_at: now - Duration::minutes(5), // Already expired
+            client_ip: None,
+            created_by: None,
         };
         assert!(
-            !validate_otp(&session, "12345678"),
+            !validate_otp(&session, "12345678", None),
             "Expired session OTP should not validate"
         );
     }
 
+    #[test]
+    fn test_signed_otp_is_derived_from_the_session() {
+        let key = b"relay-shared-key";
+        let session = create_session("test-host");
+        let otp = generate_signed_otp(&session, key);
+        assert_eq!(otp.len(), 8, "Signed OTP should be 8 digits");
+        assert!(otp.chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(otp, generate_signed_otp(&session, key), "Signing is deterministic");
+        assert!(verify_signed_otp(&session.id, &session.hostname, session.expires_at, &otp, key));
+    }
+
+    #[test]
+    fn test_verify_signed_otp_rejects_any_mismatch() {
+        let key = b"relay-shared-key";
+        let session = create_session("test-host");
+        let otp = generate_signed_otp(&session, key);
+        let (id, host, expires_at) = (session.id.as_str(), session.hostname.as_str(), session.expires_at);
+        assert!(!verify_signed_otp(id, host, expires_at, &otp, b"other-key"));
+        assert!(!verify_signed_otp(id, "other-host", expires_at, &otp, key));
+        assert!(!verify_signed_otp("other-id", host, expires_at, &otp, key));
+        assert!(!verify_signed_otp(id, host, expires_at + Duration::minutes(1), &otp, key));
+        let wrong = if otp == "00000000" { "00000001" } else { "00000000" };
+        assert!(!verify_signed_otp(id, host, expires_at, wrong, key));
+
+        // A correctly signed OTP is still refused once the session has expired
+        let expires_at = Utc::now() - Duration::seconds(1);
+        let stale = signed_otp(id, host, expires_at, key);
+        assert!(!verify_signed_otp(id, host, expires_at, &stale, key));
+    }
+
+    #[test]
+    fn test_validate_otp_in_signed_mode_ignores_the_stored_otp() {
+        let key: &[u8] = b"relay-shared-key";
+        let mut session = create_session("test-host");
+        let random = session.otp.clone();
+        let signed = generate_signed_otp(&session, key);
+        assert!(validate_otp(&session, &signed, Some(key)));
+        assert!(random == signed || !validate_otp(&session, &random, Some(key)));
+        // Random mode still compares against the stored OTP only
+        assert!(validate_otp(&session, &random, None));
+        session.otp = signed.clone();
+        assert!(validate_otp(&session, &signed, None));
+    }
+
     #[test]
     fn test_session_status_serialization() {
         let status = SessionStatus::Pending;
@@ -185,4 +364,54 @@ mod tests {
         let status: SessionStatus = serde_json::from_str("\"granted\"").unwrap();
         assert_eq!(status, SessionStatus::Granted);
     }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_client_ip_prefers_forwarded_for() {
+        let h = headers(&[
+            ("x-forwarded-for", "203.0.113.7, 10.0.0.2"),
+            ("x-real-ip", "198.51.100.1"),
+        ]);
+        let peer: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        assert_eq!(client_ip(&h, Some(peer), true), Some("203.0.113.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_client_ip_falls_back_to_real_ip() {
+        let h = headers(&[("x-forwarded-for", "garbage"), ("x-real-ip", "2001:db8::1")]);
+        let peer: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        assert_eq!(client_ip(&h, Some(peer), true), Some("2001:db8::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_client_ip_ignores_forwarding_headers_without_trusted_proxy() {
+        let h = headers(&[("x-forwarded-for", "203.0.113.7"), ("x-real-ip", "198.51.100.1")]);
+        let peer: SocketAddr = "192.0.2.44:5000".parse().unwrap();
+        assert_eq!(client_ip(&h, Some(peer), false), Some("192.0.2.44".parse().unwrap()));
+        assert_eq!(client_ip(&h, None, false), None);
+    }
+
+    #[test]
+    fn test_client_ip_falls_back_to_peer_address() {
+        let peer: SocketAddr = "192.0.2.44:5000".parse().unwrap();
+        assert_eq!(
+            client_ip(&HeaderMap::new(), Some(peer), true),
+            Some("192.0.2.44".parse().unwrap())
+        );
+        assert_eq!(client_ip(&HeaderMap::new(), None, true), None);
+    }
+
+    #[test]
+    fn test_create_session_with_ip() {
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        assert_eq!(create_session_with_ip("host", Some(ip)).client_ip, Some(ip));
+        assert_eq!(create_session("host").client_ip, None);
+    }
 }
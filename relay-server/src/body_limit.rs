@@ -0,0 +1,107 @@
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use tower_http::limit::RequestBodyLimitLayer;
+
+/// Default request body cap (`MAX_BODY_BYTES`).
+pub const DEFAULT_MAX_BODY_BYTES: usize = 32 * 1024;
+
+/// Cap request bodies on every route registered so far at `limit` bytes.
+///
+/// Bodies with a larger `Content-Length` are refused before they are read;
+/// chunked bodies are cut off once they pass the limit. Either way the client
+/// gets `413 {"error": "request_body_too_large", "limit_bytes": N}`.
+pub fn limit_request_bodies<S>(router: Router<S>, limit: usize) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router
+        .layer(RequestBodyLimitLayer::new(limit))
+        .layer(middleware::from_fn_with_state(limit, too_large_as_json))
+}
+
+/// Rewrite any 413 (from the limit layer or a body extractor) as the JSON error.
+async fn too_large_as_json(State(limit): State<usize>, request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    if response.status() != StatusCode::PAYLOAD_TOO_LARGE {
+        return response;
+    }
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(serde_json::json!({
+            "error": "request_body_too_large",
+            "limit_bytes": limit,
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Body, Bytes};
+    use axum::routing::{get, post};
+    use tower::ServiceExt;
+
+    const LIMIT: usize = 1024;
+
+    fn app() -> Router {
+        let router = Router::new().route("/echo", post(|body: Bytes| async move { body.len().to_string() }));
+        limit_request_bodies(router, LIMIT).route("/ws", get(|| async { "upgrade" }))
+    }
+
+    async fn post_bytes(len: usize, with_length: bool) -> Response {
+        let body = if with_length {
+            Body::from(vec![b'x'; len])
+        } else {
+            // No Content-Length: the limit is enforced while reading
+            let chunks = vec![Ok::<_, std::io::Error>(Bytes::from(vec![b'x'; len]))];
+            Body::from_stream(futures_util::stream::iter(chunks))
+        };
+        app()
+            .oneshot(Request::builder().method("POST").uri("/echo").body(body).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_bodies_up_to_the_limit_are_accepted() {
+        for with_length in [true, false] {
+            assert_eq!(post_bytes(LIMIT - 1, with_length).await.status(), StatusCode::OK);
+            assert_eq!(post_bytes(LIMIT, with_length).await.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_body_over_the_limit_is_rejected_with_json() {
+        for with_length in [true, false] {
+            let response = post_bytes(LIMIT + 1, with_length).await;
+            assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["error"], "request_body_too_large");
+            assert_eq!(json["limit_bytes"], LIMIT);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routes_added_after_the_limit_are_excluded() {
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .uri("/ws")
+                    .header("content-length", (LIMIT * 4).to_string())
+                    .body(Body::from(vec![b'x'; LIMIT * 4]))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,457 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::relay::{self, RelayConfig};
+use crate::{body_limit, cors, device_pairing, rtc_session, session_store, voice_session};
+
+/// Default `CORS_ORIGIN`.
+pub const DEFAULT_CORS_ORIGIN: &str = "https://station.agora.build";
+
+/// Default `PORT`.
+pub const DEFAULT_PORT: u16 = 3000;
+
+/// Default seconds between sweeps of expired auth sessions, pair rooms, RTC
+/// sessions and voice sessions (`CLEANUP_INTERVAL_SECS`).
+pub const DEFAULT_CLEANUP_INTERVAL_SECS: u64 = 60;
+
+/// Default seconds between session verify cache sweeps.
+pub const DEFAULT_VERIFY_CACHE_CLEANUP_INTERVAL_SECS: u64 = 300;
+
+/// Default seconds open connections get to finish after a shutdown signal.
+pub const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 10;
+
+/// An environment variable that is set to something the server cannot use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// `value` does not parse as the type `var` needs.
+    Invalid { var: &'static str, value: String, expected: &'static str },
+    /// `value` parses but is below the smallest value `var` accepts.
+    TooSmall { var: &'static str, value: u64, min: u64 },
+    /// The relay settings are inconsistent with each other.
+    Relay(String),
+    /// `CORS_ORIGIN` lists an origin that is not a valid header value.
+    Cors(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Invalid { var, value, expected } => {
+                write!(f, "{} must be {}, got {:?}", var, expected, value)
+            }
+            ConfigError::TooSmall { var, value, min } => write!(f, "{} must be at least {}, got {}", var, min, value),
+            ConfigError::Relay(message) | ConfigError::Cors(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Every setting the server reads from the environment, parsed and checked once at startup.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub port: u16,
+    pub cors_origin: String,
+    /// Bearer token for `/api/admin/*`; admin endpoints are disabled when unset.
+    pub admin_token: Option<String>,
+    /// Sign auth session OTPs with this key instead of generating them randomly.
+    pub otp_hmac_key: Option<String>,
+    pub agora_app_certificate: Option<String>,
+    pub max_body_bytes: usize,
+    pub record_client_ip: bool,
+    /// Take client IPs from `X-Forwarded-For`/`X-Real-IP` (only behind a proxy).
+    pub trust_proxy: bool,
+    pub max_pending_per_hostname: usize,
+    pub relay_buffer_size: usize,
+    pub pair_code_length: usize,
+    pub pair_room_ttl_secs: u64,
+    pub ws_ping_interval_secs: u64,
+    pub ws_idle_timeout_secs: u64,
+    pub relay_channel_capacity: usize,
+    pub relay_saturation_timeout_secs: u64,
+    pub pair_multi_astation: bool,
+    pub relay_reject_duplicate_roles: bool,
+    pub pair_disconnect_grace_secs: u64,
+    pub device_pair_ttl_days: i64,
+    pub relay_message_rate: u32,
+    pub relay_message_burst: u32,
+    pub relay_rate_limit_close_secs: u64,
+    pub relay_latency_probe_secs: u64,
+    pub rtc_max_sessions: usize,
+    pub max_sessions_per_app_id: usize,
+    pub rtc_max_idle_hours: i64,
+    /// Default for RTC sessions that do not set `enforce_unique_names`.
+    pub rtc_enforce_unique_names: bool,
+    pub rtc_webhook_url: Option<String>,
+    /// Hosts per-session webhooks may reach; empty means any public host.
+    pub rtc_webhook_allowed_hosts: Vec<String>,
+    pub rtc_persist_path: Option<PathBuf>,
+    pub device_pair_persist_path: Option<PathBuf>,
+    pub verify_cache_persist_path: Option<PathBuf>,
+    pub max_voice_sessions_per_atem: usize,
+    pub llm_dedup_window: usize,
+    pub dedup_overlap_words: usize,
+    pub llm_health_stuck_threshold: usize,
+    pub llm_health_stuck_secs: u64,
+    pub cleanup_interval_secs: u64,
+    pub verify_cache_cleanup_interval_secs: u64,
+    /// After a shutdown signal, connections still open this long are dropped.
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Config {
+    /// Read the configuration from the process environment.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_lookup(|var| std::env::var(var).ok())
+    }
+
+    /// Read the configuration through `lookup`, which returns a variable's value if set.
+    /// Unset and empty variables take their defaults.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let env = Env(lookup);
+        let config = Self {
+            port: env.number("PORT", DEFAULT_PORT, 1)?,
+            cors_origin: env.string("CORS_ORIGIN").unwrap_or_else(|| DEFAULT_CORS_ORIGIN.to_string()),
+            admin_token: env.string("ADMIN_TOKEN"),
+            otp_hmac_key: env.string("OTP_HMAC_KEY"),
+            agora_app_certificate: env.string("AGORA_APP_CERTIFICATE"),
+            max_body_bytes: env.number("MAX_BODY_BYTES", body_limit::DEFAULT_MAX_BODY_BYTES, 1)?,
+            record_client_ip: env.flag("RECORD_CLIENT_IP", true)?,
+            trust_proxy: env.flag("TRUST_PROXY", false)?,
+            max_pending_per_hostname: env.number(
+                "MAX_PENDING_PER_HOSTNAME",
+                session_store::DEFAULT_MAX_PENDING_PER_HOSTNAME,
+                1,
+            )?,
+            relay_buffer_size: env.number("RELAY_BUFFER_SIZE", relay::DEFAULT_BUFFER_SIZE, 0)?,
+            pair_code_length: env.number("PAIR_CODE_LENGTH", relay::DEFAULT_CODE_LENGTH, 0)?,
+            pair_room_ttl_secs: env.number("PAIR_ROOM_TTL_SECS", relay::DEFAULT_ROOM_TTL_SECS, 0)?,
+            ws_ping_interval_secs: env.number("RELAY_PING_INTERVAL_SECS", relay::DEFAULT_PING_INTERVAL_SECS, 0)?,
+            ws_idle_timeout_secs: env.number("RELAY_IDLE_TIMEOUT_SECS", relay::DEFAULT_IDLE_TIMEOUT_SECS, 0)?,
+            relay_channel_capacity: env.number("RELAY_CHANNEL_CAPACITY", relay::DEFAULT_CHANNEL_CAPACITY, 0)?,
+            relay_saturation_timeout_secs: env.number(
+                "RELAY_SATURATION_TIMEOUT_SECS",
+                relay::DEFAULT_SATURATION_TIMEOUT_SECS,
+                0,
+            )?,
+            pair_multi_astation: env.flag("PAIR_MULTI_ASTATION", true)?,
+            relay_reject_duplicate_roles: env.flag("RELAY_REJECT_DUPLICATE_ROLES", false)?,
+            pair_disconnect_grace_secs: env.number(
+                "PAIR_DISCONNECT_GRACE_SECS",
+                relay::DEFAULT_DISCONNECT_GRACE_SECS,
+                0,
+            )?,
+            device_pair_ttl_days: env.number(
+                "DEVICE_PAIR_TTL_DAYS",
+                device_pairing::DEFAULT_DEVICE_PAIR_TTL_DAYS,
+                1,
+            )?,
+            relay_message_rate: env.number("RELAY_MESSAGE_RATE", relay::DEFAULT_MESSAGE_RATE, 0)?,
+            relay_message_burst: env.number("RELAY_MESSAGE_BURST", relay::DEFAULT_MESSAGE_BURST, 0)?,
+            relay_rate_limit_close_secs: env.number(
+                "RELAY_RATE_LIMIT_CLOSE_SECS",
+                relay::DEFAULT_RATE_LIMIT_CLOSE_SECS,
+                0,
+            )?,
+            relay_latency_probe_secs: env.number("RELAY_LATENCY_PROBE_SECS", relay::DEFAULT_LATENCY_PROBE_SECS, 0)?,
+            rtc_max_sessions: env.number("RTC_MAX_SESSIONS", rtc_session::DEFAULT_MAX_SESSIONS, 1)?,
+            max_sessions_per_app_id: env.number(
+                "MAX_SESSIONS_PER_APP_ID",
+                rtc_session::DEFAULT_MAX_SESSIONS_PER_APP_ID,
+                1,
+            )?,
+            rtc_max_idle_hours: env.number("RTC_MAX_IDLE_HOURS", rtc_session::DEFAULT_MAX_IDLE_HOURS, 1)?,
+            rtc_enforce_unique_names: env.flag("RTC_ENFORCE_UNIQUE_NAMES", false)?,
+            rtc_webhook_url: env.string("RTC_WEBHOOK_URL"),
+            rtc_webhook_allowed_hosts: env.list("RTC_WEBHOOK_ALLOWED_HOSTS"),
+            rtc_persist_path: env.string("RTC_PERSIST_PATH").map(PathBuf::from),
+            device_pair_persist_path: env.string("DEVICE_PAIR_PERSIST_PATH").map(PathBuf::from),
+            verify_cache_persist_path: env.string("VERIFY_CACHE_PERSIST_PATH").map(PathBuf::from),
+            max_voice_sessions_per_atem: env.number(
+                "MAX_VOICE_SESSIONS_PER_ATEM",
+                voice_session::DEFAULT_MAX_SESSIONS_PER_ATEM,
+                1,
+            )?,
+            llm_dedup_window: env.number("LLM_DEDUP_WINDOW", voice_session::DEFAULT_DEDUP_WINDOW, 0)?,
+            dedup_overlap_words: env.number(
+                "DEDUP_OVERLAP_WORDS",
+                voice_session::DEFAULT_DEDUP_OVERLAP_WORDS,
+                0,
+            )?,
+            llm_health_stuck_threshold: env.number(
+                "LLM_HEALTH_STUCK_THRESHOLD",
+                voice_session::DEFAULT_LLM_HEALTH_STUCK_THRESHOLD,
+                1,
+            )?,
+            llm_health_stuck_secs: env.number("LLM_HEALTH_STUCK_SECS", voice_session::DEFAULT_LLM_HEALTH_STUCK_SECS, 1)?,
+            cleanup_interval_secs: env.number("CLEANUP_INTERVAL_SECS", DEFAULT_CLEANUP_INTERVAL_SECS, 1)?,
+            verify_cache_cleanup_interval_secs: env.number(
+                "VERIFY_CACHE_CLEANUP_INTERVAL_SECS",
+                DEFAULT_VERIFY_CACHE_CLEANUP_INTERVAL_SECS,
+                1,
+            )?,
+            shutdown_timeout_secs: env.number("SHUTDOWN_TIMEOUT_SECS", DEFAULT_SHUTDOWN_TIMEOUT_SECS, 0)?,
+        };
+        config.relay_config().validate().map_err(ConfigError::Relay)?;
+        if config.cors_origin.trim() != "*" {
+            cors::parse_origins(&config.cors_origin).map_err(ConfigError::Cors)?;
+        }
+        Ok(config)
+    }
+
+    /// The pairing settings handed to [`relay::RelayHub`].
+    pub fn relay_config(&self) -> RelayConfig {
+        RelayConfig {
+            code_length: self.pair_code_length,
+            room_ttl_secs: self.pair_room_ttl_secs,
+            reject_duplicate_roles: self.relay_reject_duplicate_roles,
+            ping_interval: Duration::from_secs(self.ws_ping_interval_secs),
+            idle_timeout: Duration::from_secs(self.ws_idle_timeout_secs),
+            channel_capacity: self.relay_channel_capacity,
+            saturation_timeout: Duration::from_secs(self.relay_saturation_timeout_secs),
+            multi_astation: self.pair_multi_astation,
+            disconnect_grace: Duration::from_secs(self.pair_disconnect_grace_secs),
+            message_rate: self.relay_message_rate,
+            message_burst: self.relay_message_burst,
+            rate_limit_close_after: Duration::from_secs(self.relay_rate_limit_close_secs),
+            latency_probe_interval: Duration::from_secs(self.relay_latency_probe_secs),
+        }
+    }
+}
+
+/// One `NAME = value` line per setting; secrets show only whether they are set.
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn secret(value: &Option<String>) -> &'static str {
+            if value.is_some() {
+                "<redacted>"
+            } else {
+                "(unset)"
+            }
+        }
+        fn optional<T: fmt::Display>(value: Option<T>) -> String {
+            value.map_or_else(|| "(unset)".to_string(), |value| value.to_string())
+        }
+
+        writeln!(f, "PORT = {}", self.port)?;
+        writeln!(f, "CORS_ORIGIN = {}", self.cors_origin)?;
+        writeln!(f, "ADMIN_TOKEN = {}", secret(&self.admin_token))?;
+        writeln!(f, "OTP_HMAC_KEY = {}", secret(&self.otp_hmac_key))?;
+        writeln!(f, "AGORA_APP_CERTIFICATE = {}", secret(&self.agora_app_certificate))?;
+        writeln!(f, "MAX_BODY_BYTES = {}", self.max_body_bytes)?;
+        writeln!(f, "RECORD_CLIENT_IP = {}", self.record_client_ip)?;
+        writeln!(f, "TRUST_PROXY = {}", self.trust_proxy)?;
+        writeln!(f, "MAX_PENDING_PER_HOSTNAME = {}", self.max_pending_per_hostname)?;
+        writeln!(f, "RELAY_BUFFER_SIZE = {}", self.relay_buffer_size)?;
+        writeln!(f, "PAIR_CODE_LENGTH = {}", self.pair_code_length)?;
+        writeln!(f, "PAIR_ROOM_TTL_SECS = {}", self.pair_room_ttl_secs)?;
+        writeln!(f, "RELAY_PING_INTERVAL_SECS = {}", self.ws_ping_interval_secs)?;
+        writeln!(f, "RELAY_IDLE_TIMEOUT_SECS = {}", self.ws_idle_timeout_secs)?;
+        writeln!(f, "RELAY_CHANNEL_CAPACITY = {}", self.relay_channel_capacity)?;
+        writeln!(f, "RELAY_SATURATION_TIMEOUT_SECS = {}", self.relay_saturation_timeout_secs)?;
+        writeln!(f, "PAIR_MULTI_ASTATION = {}", self.pair_multi_astation)?;
+        writeln!(f, "RELAY_REJECT_DUPLICATE_ROLES = {}", self.relay_reject_duplicate_roles)?;
+        writeln!(f, "PAIR_DISCONNECT_GRACE_SECS = {}", self.pair_disconnect_grace_secs)?;
+        writeln!(f, "DEVICE_PAIR_TTL_DAYS = {}", self.device_pair_ttl_days)?;
+        writeln!(f, "RELAY_MESSAGE_RATE = {}", self.relay_message_rate)?;
+        writeln!(f, "RELAY_MESSAGE_BURST = {}", self.relay_message_burst)?;
+        writeln!(f, "RELAY_RATE_LIMIT_CLOSE_SECS = {}", self.relay_rate_limit_close_secs)?;
+        writeln!(f, "RELAY_LATENCY_PROBE_SECS = {}", self.relay_latency_probe_secs)?;
+        writeln!(f, "RTC_MAX_SESSIONS = {}", self.rtc_max_sessions)?;
+        writeln!(f, "MAX_SESSIONS_PER_APP_ID = {}", self.max_sessions_per_app_id)?;
+        writeln!(f, "RTC_MAX_IDLE_HOURS = {}", self.rtc_max_idle_hours)?;
+        writeln!(f, "RTC_ENFORCE_UNIQUE_NAMES = {}", self.rtc_enforce_unique_names)?;
+        // Webhook URLs often carry a token in the path or query
+        writeln!(f, "RTC_WEBHOOK_URL = {}", secret(&self.rtc_webhook_url))?;
+        let allowed_hosts = self.rtc_webhook_allowed_hosts.join(",");
+        writeln!(f, "RTC_WEBHOOK_ALLOWED_HOSTS = {}", optional(Some(allowed_hosts).filter(|hosts| !hosts.is_empty())))?;
+        writeln!(f, "RTC_PERSIST_PATH = {}", optional(self.rtc_persist_path.as_ref().map(|p| p.display())))?;
+        writeln!(
+            f,
+            "DEVICE_PAIR_PERSIST_PATH = {}",
+            optional(self.device_pair_persist_path.as_ref().map(|p| p.display()))
+        )?;
+        writeln!(
+            f,
+            "VERIFY_CACHE_PERSIST_PATH = {}",
+            optional(self.verify_cache_persist_path.as_ref().map(|p| p.display()))
+        )?;
+        writeln!(f, "MAX_VOICE_SESSIONS_PER_ATEM = {}", self.max_voice_sessions_per_atem)?;
+        writeln!(f, "LLM_DEDUP_WINDOW = {}", self.llm_dedup_window)?;
+        writeln!(f, "DEDUP_OVERLAP_WORDS = {}", self.dedup_overlap_words)?;
+        writeln!(f, "LLM_HEALTH_STUCK_THRESHOLD = {}", self.llm_health_stuck_threshold)?;
+        writeln!(f, "LLM_HEALTH_STUCK_SECS = {}", self.llm_health_stuck_secs)?;
+        writeln!(f, "CLEANUP_INTERVAL_SECS = {}", self.cleanup_interval_secs)?;
+        writeln!(f, "VERIFY_CACHE_CLEANUP_INTERVAL_SECS = {}", self.verify_cache_cleanup_interval_secs)?;
+        write!(f, "SHUTDOWN_TIMEOUT_SECS = {}", self.shutdown_timeout_secs)
+    }
+}
+
+/// Typed access to environment variables through a lookup function.
+struct Env<F>(F);
+
+impl<F: Fn(&str) -> Option<String>> Env<F> {
+    /// The trimmed value of `var`, or `None` when it is unset or empty.
+    fn string(&self, var: &str) -> Option<String> {
+        (self.0)(var).map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+    }
+
+    /// `var` as a comma-separated list with blank entries dropped; empty when unset.
+    fn list(&self, var: &str) -> Vec<String> {
+        self.string(var)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// `var` as a non-negative number no smaller than `min`, or `default` when unset.
+    fn number<T>(&self, var: &'static str, default: T, min: u64) -> Result<T, ConfigError>
+    where
+        T: FromStr + TryInto<u64> + Copy,
+    {
+        let Some(raw) = self.string(var) else {
+            return Ok(default);
+        };
+        let invalid = || ConfigError::Invalid {
+            var,
+            value: raw.clone(),
+            expected: "a whole number",
+        };
+        let value: T = raw.parse().map_err(|_| invalid())?;
+        let as_u64: u64 = value.try_into().map_err(|_| invalid())?;
+        if as_u64 < min {
+            return Err(ConfigError::TooSmall { var, value: as_u64, min });
+        }
+        Ok(value)
+    }
+
+    /// `var` as a boolean: `0` and `false` (any case) are false, `1` and `true` are true,
+    /// or `default` when unset.
+    fn flag(&self, var: &'static str, default: bool) -> Result<bool, ConfigError> {
+        match self.string(var) {
+            None => Ok(default),
+            Some(value) if value == "0" || value.eq_ignore_ascii_case("false") => Ok(false),
+            Some(value) if value == "1" || value.eq_ignore_ascii_case("true") => Ok(true),
+            Some(value) => Err(ConfigError::Invalid {
+                var,
+                value,
+                expected: "0, 1, true or false",
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Build a config from `vars` alone, as if they were the whole environment.
+    fn config_with(vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let env: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Config::from_lookup(|var| env.get(var).cloned())
+    }
+
+    #[test]
+    fn test_missing_values_take_defaults() {
+        let config = config_with(&[]).unwrap();
+        assert_eq!(config.port, DEFAULT_PORT);
+        assert_eq!(config.cors_origin, DEFAULT_CORS_ORIGIN);
+        assert_eq!(config.admin_token, None);
+        assert_eq!(config.max_body_bytes, body_limit::DEFAULT_MAX_BODY_BYTES);
+        assert!(config.record_client_ip);
+        assert!(!config.trust_proxy);
+        assert_eq!(config.relay_config(), RelayConfig::default());
+        assert_eq!(config.cleanup_interval_secs, DEFAULT_CLEANUP_INTERVAL_SECS);
+        assert_eq!(config.shutdown_timeout_secs, DEFAULT_SHUTDOWN_TIMEOUT_SECS);
+        // Empty counts as unset
+        assert_eq!(config_with(&[("PORT", ""), ("ADMIN_TOKEN", " ")]).unwrap(), config);
+    }
+
+    #[test]
+    fn test_valid_values_are_read() {
+        let config = config_with(&[
+            ("PORT", "8080"),
+            ("CORS_ORIGIN", "https://a.example,https://b.example"),
+            ("ADMIN_TOKEN", "admin-secret"),
+            ("RELAY_PING_INTERVAL_SECS", "5"),
+            ("RELAY_IDLE_TIMEOUT_SECS", "20"),
+            ("PAIR_MULTI_ASTATION", "0"),
+            ("RECORD_CLIENT_IP", "FALSE"),
+            ("TRUST_PROXY", "1"),
+            ("RTC_PERSIST_PATH", "/tmp/rtc.json"),
+            ("RTC_WEBHOOK_ALLOWED_HOSTS", "hooks.example.com, ,10.0.0.5"),
+            ("SHUTDOWN_TIMEOUT_SECS", "0"),
+        ])
+        .unwrap();
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.admin_token.as_deref(), Some("admin-secret"));
+        assert_eq!(config.relay_config().ping_interval, Duration::from_secs(5));
+        assert!(!config.pair_multi_astation);
+        assert!(!config.record_client_ip);
+        assert!(config.trust_proxy);
+        assert_eq!(config.rtc_persist_path, Some(PathBuf::from("/tmp/rtc.json")));
+        assert_eq!(config.rtc_webhook_allowed_hosts, ["hooks.example.com", "10.0.0.5"]);
+        assert_eq!(config.shutdown_timeout_secs, 0);
+    }
+
+    #[test]
+    fn test_invalid_values_name_the_variable() {
+        assert_eq!(
+            config_with(&[("PORT", "http")]).unwrap_err(),
+            ConfigError::Invalid { var: "PORT", value: "http".into(), expected: "a whole number" }
+        );
+        let err = config_with(&[("RTC_MAX_IDLE_HOURS", "-3")]).unwrap_err();
+        assert_eq!(err.to_string(), "RTC_MAX_IDLE_HOURS must be a whole number, got \"-3\"");
+        assert_eq!(
+            config_with(&[("CLEANUP_INTERVAL_SECS", "0")]).unwrap_err(),
+            ConfigError::TooSmall { var: "CLEANUP_INTERVAL_SECS", value: 0, min: 1 }
+        );
+        assert!(matches!(
+            config_with(&[("RELAY_PING_INTERVAL_SECS", "60"), ("RELAY_IDLE_TIMEOUT_SECS", "30")]),
+            Err(ConfigError::Relay(_))
+        ));
+        assert!(matches!(
+            config_with(&[("CORS_ORIGIN", "https://ok.example,bad\norigin")]),
+            Err(ConfigError::Cors(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_flag_is_rejected() {
+        assert_eq!(
+            config_with(&[("TRUST_PROXY", "yes")]).unwrap_err(),
+            ConfigError::Invalid { var: "TRUST_PROXY", value: "yes".into(), expected: "0, 1, true or false" }
+        );
+        let err = config_with(&[("RECORD_CLIENT_IP", "off")]).unwrap_err();
+        assert_eq!(err.to_string(), "RECORD_CLIENT_IP must be 0, 1, true or false, got \"off\"");
+    }
+
+    #[test]
+    fn test_display_redacts_secrets() {
+        let config = config_with(&[
+            ("ADMIN_TOKEN", "admin-secret"),
+            ("AGORA_APP_CERTIFICATE", "cert-secret"),
+            ("OTP_HMAC_KEY", "otp-secret"),
+            ("RTC_WEBHOOK_URL", "https://hooks.example.com/rtc?key=hook-secret"),
+        ])
+        .unwrap();
+        let shown = config.to_string();
+        assert!(shown.contains("ADMIN_TOKEN = <redacted>"));
+        assert!(shown.contains("AGORA_APP_CERTIFICATE = <redacted>"));
+        assert!(shown.contains("OTP_HMAC_KEY = <redacted>"));
+        assert!(shown.contains("RTC_WEBHOOK_URL = <redacted>"));
+        assert!(!shown.contains("admin-secret") && !shown.contains("cert-secret") && !shown.contains("otp-secret"));
+        assert!(!shown.contains("hook-secret"));
+        assert!(shown.contains("PORT = 3000"));
+        assert!(config_with(&[]).unwrap().to_string().contains("ADMIN_TOKEN = (unset)"));
+    }
+}
@@ -0,0 +1,183 @@
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// Identifies who created an auth session.
+const X_CREATED_BY: HeaderName = HeaderName::from_static("x-created-by");
+/// Identifies who is granting an auth session.
+const X_REQUESTER: HeaderName = HeaderName::from_static("x-requester");
+
+/// Parse a comma-separated `CORS_ORIGIN` value into header values.
+/// Empty entries are ignored; an unparsable entry is an error naming it.
+pub fn parse_origins(spec: &str) -> Result<Vec<HeaderValue>, String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .map(|origin| {
+            origin
+                .parse::<HeaderValue>()
+                .map_err(|_| format!("Invalid CORS origin: {:?}", origin))
+        })
+        .collect()
+}
+
+/// Build the CORS layer for a `CORS_ORIGIN` value: `*` allows every origin
+/// (development only), otherwise only the listed origins are allowed.
+pub fn cors_layer(spec: &str) -> Result<CorsLayer, String> {
+    if spec.trim() == "*" {
+        tracing::warn!("CORS configured to allow ALL origins - only use in development!");
+        return Ok(CorsLayer::permissive());
+    }
+
+    let origins = parse_origins(spec)?;
+    if origins.is_empty() {
+        return Err("CORS_ORIGIN contains no origins".to_string());
+    }
+    for origin in &origins {
+        tracing::info!("CORS configured to allow origin: {}", origin.to_str().unwrap_or("?"));
+    }
+    let allow_origin = if origins.len() == 1 {
+        AllowOrigin::exact(origins[0].clone())
+    } else {
+        AllowOrigin::list(origins)
+    };
+
+    Ok(CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            header::IF_NONE_MATCH,
+            X_CREATED_BY,
+            X_REQUESTER,
+        ])
+        .expose_headers([header::ETAG])
+        .allow_credentials(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    const ORIGINS: &str = "https://station.agora.build, https://beta.station.agora.build";
+
+    async fn allowed_origin(spec: &str, origin: &str) -> Option<String> {
+        let app = Router::new()
+            .route("/ping", get(|| async { "pong" }))
+            .layer(cors_layer(spec).unwrap());
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/ping")
+                    .header(header::ORIGIN, origin)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        response
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .map(|v| v.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn test_parse_origins() {
+        let origins = parse_origins(ORIGINS).unwrap();
+        assert_eq!(origins.len(), 2);
+        assert_eq!(origins[1], "https://beta.station.agora.build");
+        assert_eq!(parse_origins("https://a.example,,").unwrap().len(), 1);
+        assert!(parse_origins("https://ok.example, bad\norigin").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_each_listed_origin_is_echoed() {
+        for origin in ["https://station.agora.build", "https://beta.station.agora.build"] {
+            assert_eq!(allowed_origin(ORIGINS, origin).await.as_deref(), Some(origin));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_origin_is_rejected() {
+        assert_eq!(allowed_origin(ORIGINS, "https://evil.example").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_single_origin() {
+        let spec = "https://station.agora.build";
+        assert_eq!(
+            allowed_origin(spec, spec).await.as_deref(),
+            Some("https://station.agora.build")
+        );
+        // A single origin is sent as-is; browsers reject it for other origins
+        assert_eq!(
+            allowed_origin(spec, "https://beta.station.agora.build").await.as_deref(),
+            Some("https://station.agora.build")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wildcard_allows_any_origin() {
+        assert_eq!(
+            allowed_origin("*", "https://anything.example").await.as_deref(),
+            Some("*")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_preflight_allows_put_and_identity_headers() {
+        let app = Router::new()
+            .route("/ping", get(|| async { "pong" }))
+            .layer(cors_layer(ORIGINS).unwrap());
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::OPTIONS)
+                    .uri("/ping")
+                    .header(header::ORIGIN, "https://station.agora.build")
+                    .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+                    .header(
+                        header::ACCESS_CONTROL_REQUEST_HEADERS,
+                        "authorization,x-created-by,x-requester,if-none-match",
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let header_value = |name| response.headers().get(name).unwrap().to_str().unwrap().to_ascii_lowercase();
+        assert!(header_value(header::ACCESS_CONTROL_ALLOW_METHODS).contains("put"));
+        let allowed = header_value(header::ACCESS_CONTROL_ALLOW_HEADERS);
+        for name in ["authorization", "x-created-by", "x-requester", "if-none-match"] {
+            assert!(allowed.contains(name), "{} not in {}", name, allowed);
+        }
+
+        // Browsers only let scripts read ETag if it is exposed
+        let response = Router::new()
+            .route("/ping", get(|| async { "pong" }))
+            .layer(cors_layer(ORIGINS).unwrap())
+            .oneshot(
+                Request::builder()
+                    .uri("/ping")
+                    .header(header::ORIGIN, "https://station.agora.build")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let exposed = response.headers().get(header::ACCESS_CONTROL_EXPOSE_HEADERS).unwrap();
+        assert_eq!(exposed.to_str().unwrap().to_ascii_lowercase(), "etag");
+    }
+
+    #[test]
+    fn test_empty_spec_is_an_error() {
+        assert!(cors_layer(" , ").is_err());
+    }
+}
@@ -0,0 +1,675 @@
+//! Persistent device pairs: two devices that paired once can get a fresh room
+//! later without exchanging a code. The shared `device_pair_token` is only
+//! held by the devices; the server keeps its SHA-256.
+//!
+//! Pairings live in memory. With `DEVICE_PAIR_PERSIST_PATH` set, a background
+//! task snapshots the stored records (hashes, never tokens) whenever they
+//! change, and startup restores the unexpired ones.
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use validator::Validate;
+
+use crate::auth::{bearer_token, generate_session_token, HOSTNAME_REGEX};
+use crate::relay::{self, RelayHub, Role};
+use crate::supervisor::{spawn_supervised, TaskRestarts};
+use crate::AppState;
+
+/// Default lifetime of a persisted pairing (`DEVICE_PAIR_TTL_DAYS`).
+pub const DEFAULT_DEVICE_PAIR_TTL_DAYS: i64 = 30;
+
+/// Bumped if the on-disk format changes incompatibly.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// A persisted pairing, without its token.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DevicePair {
+    pub id: String,
+    pub atem_hostname: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub astation_hostname: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+struct StoredPair {
+    pair: DevicePair,
+    token_hash: [u8; 32],
+    /// Room the pairing was persisted from; persisting it again reuses the pairing.
+    room_code: Option<String>,
+    /// Room made by the first device to resume, kept for the second one.
+    resume: Option<ResumeRoom>,
+}
+
+impl StoredPair {
+    /// Whether `code` is the room this pairing came from or resumed into.
+    fn belongs_to_room(&self, code: &str) -> bool {
+        self.room_code.as_deref() == Some(code) || self.resume.as_ref().is_some_and(|resume| resume.code == code)
+    }
+}
+
+/// On-disk form of a pairing. Room codes are left out: rooms do not survive
+/// a restart, and a reissued code would belong to someone else.
+#[derive(Serialize, Deserialize)]
+struct SavedPair {
+    #[serde(flatten)]
+    pair: DevicePair,
+    token_hash: [u8; 32],
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    saved_at: DateTime<Utc>,
+    pairs: Vec<SavedPair>,
+}
+
+struct ResumeRoom {
+    code: String,
+    owner_token: String,
+    role: Role,
+}
+
+/// Room handed out by [`DevicePairStore::resume`].
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ResumedRoom {
+    pub pair_id: String,
+    pub code: String,
+    /// Owner token of the new room (`DELETE /api/pair/:code`).
+    pub owner_token: String,
+    /// The other device resumed first and is waiting in this room.
+    pub peer_waiting: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ResumeError {
+    /// Unknown, revoked or expired token.
+    InvalidToken,
+    /// No free pairing code.
+    NoCode,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RevokeError {
+    NotFound,
+    /// The token belongs to a different pairing.
+    Forbidden,
+}
+
+#[derive(Clone)]
+pub struct DevicePairStore {
+    pairs: Arc<RwLock<HashMap<String, StoredPair>>>,
+    ttl: Duration,
+    /// Set when pairings change, cleared by the persister.
+    dirty: Arc<AtomicBool>,
+}
+
+impl Default for DevicePairStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DevicePairStore {
+    pub fn new() -> Self {
+        Self {
+            pairs: Arc::new(RwLock::new(HashMap::new())),
+            ttl: Duration::days(DEFAULT_DEVICE_PAIR_TTL_DAYS),
+            dirty: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// How long a persisted pairing stays usable after it is issued.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Number of stored pairings, expired ones included until cleanup.
+    pub async fn pair_count(&self) -> usize {
+        self.pairs.read().await.len()
+    }
+
+    /// Whether pairings changed since the last call; clears the flag.
+    pub fn take_dirty(&self) -> bool {
+        self.dirty.swap(false, Ordering::AcqRel)
+    }
+
+    fn mark_dirty(&self) {
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// Store the pairing of room `code` and return it with its token, which
+    /// is not kept, and whether it already existed. Persisting a room again
+    /// reuses its unexpired pairing under a new token; the old one stops working.
+    pub async fn persist(
+        &self,
+        code: &str,
+        atem_hostname: String,
+        astation_hostname: Option<String>,
+    ) -> (DevicePair, String, bool) {
+        let token = generate_session_token();
+        let now = Utc::now();
+        let mut pairs = self.pairs.write().await;
+        self.mark_dirty();
+        if let Some(stored) = pairs
+            .values_mut()
+            .find(|stored| stored.belongs_to_room(code) && stored.pair.expires_at > now)
+        {
+            stored.token_hash = relay::hash_token(&token);
+            if astation_hostname.is_some() {
+                stored.pair.astation_hostname = astation_hostname;
+            }
+            return (stored.pair.clone(), token, true);
+        }
+
+        let pair = DevicePair {
+            id: uuid::Uuid::new_v4().to_string(),
+            atem_hostname,
+            astation_hostname,
+            created_at: now,
+            expires_at: now + self.ttl,
+        };
+        pairs.insert(
+            pair.id.clone(),
+            StoredPair {
+                pair: pair.clone(),
+                token_hash: relay::hash_token(&token),
+                room_code: Some(code.to_string()),
+                resume: None,
+            },
+        );
+        (pair, token, false)
+    }
+
+    /// Room for `role` to meet the other device in. The first device to resume
+    /// gets a new room; the other one is sent to the same room while it exists.
+    pub async fn resume(&self, hub: &RelayHub, token: &str, role: Role) -> Result<ResumedRoom, ResumeError> {
+        let hash = relay::hash_token(token);
+        let now = Utc::now();
+        let mut pairs = self.pairs.write().await;
+        let stored = pairs
+            .values_mut()
+            .find(|stored| stored.token_hash == hash && stored.pair.expires_at > now)
+            .ok_or(ResumeError::InvalidToken)?;
+
+        if let Some(resume) = &stored.resume {
+            if hub.room_exists(&resume.code).await {
+                return Ok(ResumedRoom {
+                    pair_id: stored.pair.id.clone(),
+                    code: resume.code.clone(),
+                    owner_token: resume.owner_token.clone(),
+                    peer_waiting: resume.role != role,
+                });
+            }
+        }
+
+        let owner_token = generate_session_token();
+        let code = hub
+            .create_room(stored.pair.atem_hostname.clone(), owner_token.clone())
+            .await
+            .ok_or(ResumeError::NoCode)?;
+        stored.resume = Some(ResumeRoom {
+            code: code.clone(),
+            owner_token: owner_token.clone(),
+            role,
+        });
+        Ok(ResumedRoom {
+            pair_id: stored.pair.id.clone(),
+            code,
+            owner_token,
+            peer_waiting: false,
+        })
+    }
+
+    /// Delete pairing `id`, proving ownership with its token.
+    pub async fn revoke(&self, id: &str, token: &str) -> Result<(), RevokeError> {
+        let mut pairs = self.pairs.write().await;
+        let stored = pairs.get(id).ok_or(RevokeError::NotFound)?;
+        if stored.token_hash != relay::hash_token(token) {
+            return Err(RevokeError::Forbidden);
+        }
+        pairs.remove(id);
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Remove expired pairings, returning how many were removed.
+    pub async fn cleanup_expired(&self) -> usize {
+        let now = Utc::now();
+        let mut pairs = self.pairs.write().await;
+        let before = pairs.len();
+        pairs.retain(|_, stored| stored.pair.expires_at > now);
+        let removed = before - pairs.len();
+        if removed > 0 {
+            self.mark_dirty();
+        }
+        removed
+    }
+
+    /// Write every unexpired pairing to `path` with mode 0600. Returns how
+    /// many were written.
+    pub async fn save(&self, path: &std::path::Path) -> io::Result<usize> {
+        let now = Utc::now();
+        let pairs: Vec<SavedPair> = self
+            .pairs
+            .read()
+            .await
+            .values()
+            .filter(|stored| stored.pair.expires_at > now)
+            .map(|stored| SavedPair {
+                pair: stored.pair.clone(),
+                token_hash: stored.token_hash,
+            })
+            .collect();
+        let snapshot = Snapshot {
+            version: SNAPSHOT_VERSION,
+            saved_at: now,
+            pairs,
+        };
+        let json = serde_json::to_vec(&snapshot)?;
+        crate::rtc_persist::write_snapshot(path, &json).await?;
+        Ok(snapshot.pairs.len())
+    }
+
+    /// Restore the unexpired pairings saved at `path`. A missing file restores
+    /// nothing. Returns how many were restored.
+    pub async fn load(&self, path: &std::path::Path) -> io::Result<usize> {
+        let bytes = match tokio::fs::read(path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let snapshot: Snapshot = serde_json::from_slice(&bytes)?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported snapshot version {}", snapshot.version),
+            ));
+        }
+        let now = Utc::now();
+        let mut pairs = self.pairs.write().await;
+        let mut restored = 0;
+        for saved in snapshot.pairs {
+            if saved.pair.expires_at <= now {
+                continue;
+            }
+            pairs.insert(
+                saved.pair.id.clone(),
+                StoredPair {
+                    pair: saved.pair,
+                    token_hash: saved.token_hash,
+                    room_code: None,
+                    resume: None,
+                },
+            );
+            restored += 1;
+        }
+        Ok(restored)
+    }
+}
+
+/// Periodically write a snapshot whenever pairings have changed. Mirrors
+/// [`crate::rtc_persist::spawn_persister`]: a failed save is retried on the
+/// next tick.
+pub fn spawn_persister(
+    restarts: &Arc<TaskRestarts>,
+    store: DevicePairStore,
+    path: PathBuf,
+    every: std::time::Duration,
+) {
+    spawn_supervised(restarts, "device_pair_persister", move || {
+        let store = store.clone();
+        let path = path.clone();
+        async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                if !store.take_dirty() {
+                    continue;
+                }
+                if let Err(e) = store.save(&path).await {
+                    tracing::warn!("Failed to persist device pairings to {}: {}", path.display(), e);
+                    // Retry on the next tick
+                    store.mark_dirty();
+                }
+            }
+        }
+    });
+}
+
+// --- Request / Response types ---
+
+#[derive(Deserialize, Validate, Default)]
+pub struct PersistPairRequest {
+    /// Hostname of the astation device, shown alongside the room's atem hostname.
+    #[validate(length(min = 1, max = 255), regex(path = *HOSTNAME_REGEX))]
+    pub astation_hostname: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PersistPairResponse {
+    #[serde(flatten)]
+    pub pair: DevicePair,
+    pub device_pair_token: String,
+    /// The room was persisted before; its pairing got a new token.
+    pub reused: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ResumePairRequest {
+    pub device_pair_token: String,
+    /// Which side is resuming: `atem` or `astation`.
+    pub role: String,
+}
+
+fn error(status: StatusCode, message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (status, Json(serde_json::json!({ "error": message })))
+}
+
+// --- Handlers ---
+
+/// POST /api/pair/:code/persist — Remember a paired room's two devices
+/// (owner token required). Both sides also receive the token as
+/// `{"type":"device_paired",...}`.
+pub async fn persist_pair_handler(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+    headers: HeaderMap,
+    body: Option<Json<PersistPairRequest>>,
+) -> impl IntoResponse {
+    let body = body.map(|Json(body)| body).unwrap_or_default();
+    if body.validate().is_err() {
+        return Err(error(StatusCode::BAD_REQUEST, "invalid_hostname"));
+    }
+    let code = relay::parse_code(&state.relay, &code)?;
+    relay::require_owner(&state.relay, &code, &headers).await?;
+    let Some(status) = state.relay.room_status(&code).await else {
+        return Err(error(StatusCode::NOT_FOUND, "Room not found"));
+    };
+    if !status.paired {
+        return Err(error(StatusCode::CONFLICT, "Both sides must be connected to persist the pairing"));
+    }
+
+    let (pair, device_pair_token, reused) = state
+        .device_pairs
+        .persist(&code, status.hostname, body.astation_hostname)
+        .await;
+    let notice = serde_json::json!({
+        "type": "device_paired",
+        "id": pair.id,
+        "device_pair_token": device_pair_token,
+        "expires_at": pair.expires_at,
+    })
+    .to_string();
+    for role in Role::ALL {
+        state.relay.send_to(&code, role, notice.clone()).await;
+    }
+    tracing::info!("Device pairing {} persisted from room {}", pair.id, code);
+    let status = if reused { StatusCode::OK } else { StatusCode::CREATED };
+    Ok((status, Json(PersistPairResponse { pair, device_pair_token, reused })))
+}
+
+/// POST /api/pair/resume — Get a fresh room for a persisted pairing.
+pub async fn resume_pair_handler(
+    State(state): State<AppState>,
+    Json(body): Json<ResumePairRequest>,
+) -> impl IntoResponse {
+    let Some(role) = Role::parse(&body.role) else {
+        return Err(error(StatusCode::BAD_REQUEST, "role must be atem or astation"));
+    };
+    match state.device_pairs.resume(&state.relay, &body.device_pair_token, role).await {
+        Ok(room) => {
+            tracing::info!("Device pairing {} resumed by {} in room {}", room.pair_id, role, room.code);
+            Ok(Json(room))
+        }
+        Err(ResumeError::InvalidToken) => Err(error(StatusCode::UNAUTHORIZED, "Invalid or expired device pair token")),
+        Err(ResumeError::NoCode) => Err(error(StatusCode::SERVICE_UNAVAILABLE, "No free pairing code, try again")),
+    }
+}
+
+/// DELETE /api/pair/persisted/:id — Revoke a persisted pairing
+/// (`Authorization: Bearer <device_pair_token>`).
+pub async fn revoke_pair_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let Some(token) = bearer_token(&headers) else {
+        return Err(error(StatusCode::UNAUTHORIZED, "Missing device pair token"));
+    };
+    match state.device_pairs.revoke(&id, token).await {
+        Ok(()) => {
+            tracing::info!("Device pairing {} revoked", id);
+            Ok(StatusCode::OK)
+        }
+        Err(RevokeError::NotFound) => Err(error(StatusCode::NOT_FOUND, "Pairing not found")),
+        Err(RevokeError::Forbidden) => Err(error(StatusCode::FORBIDDEN, "Invalid device pair token")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_store::SessionStore;
+    use crate::session_verify::SessionVerifyCache;
+    use crate::voice_session::VoiceSessionStore;
+    use axum::{body::Body, http::Request, routing::{delete, post}, Router};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn resume_bridges_both_devices_into_one_room() {
+        let hub = RelayHub::new();
+        let store = DevicePairStore::new();
+        let (pair, token, _) = store.persist("ROOM-ABCD", "laptop".into(), Some("desktop".into())).await;
+        assert_eq!(pair.expires_at - pair.created_at, Duration::days(DEFAULT_DEVICE_PAIR_TTL_DAYS));
+
+        let first = store.resume(&hub, &token, Role::Atem).await.unwrap();
+        assert_eq!(first.pair_id, pair.id);
+        assert!(!first.peer_waiting);
+        assert_eq!(hub.room_status(&first.code).await.unwrap().hostname, "laptop");
+
+        // The other device lands in the same room and learns the peer is there
+        let second = store.resume(&hub, &token, Role::Astation).await.unwrap();
+        assert_eq!((second.code.as_str(), second.peer_waiting), (first.code.as_str(), true));
+        assert_eq!(second.owner_token, first.owner_token);
+        assert_eq!(hub.room_count().await, 1);
+
+        assert_eq!(store.resume(&hub, "wrong", Role::Atem).await, Err(ResumeError::InvalidToken));
+    }
+
+    #[tokio::test]
+    async fn resume_after_the_room_expired_opens_a_new_one() {
+        let hub = RelayHub::new().with_config(relay::RelayConfig { room_ttl_secs: 1, ..Default::default() });
+        let store = DevicePairStore::new();
+        let (_, token, _) = store.persist("ROOM-ABCD", "laptop".into(), None).await;
+        let first = store.resume(&hub, &token, Role::Atem).await.unwrap();
+
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        hub.cleanup_expired().await;
+        assert!(!hub.room_exists(&first.code).await);
+        let second = store.resume(&hub, &token, Role::Astation).await.unwrap();
+        assert_ne!(second.code, first.code);
+        assert!(!second.peer_waiting);
+        assert!(hub.room_exists(&second.code).await);
+    }
+
+    #[tokio::test]
+    async fn revoked_and_expired_pairings_stop_resuming() {
+        let hub = RelayHub::new();
+        let store = DevicePairStore::new();
+        let (pair, token, _) = store.persist("ROOM-ABCD", "laptop".into(), None).await;
+        let (_, other_token, _) = store.persist("ROOM-WXYZ", "other".into(), None).await;
+        assert_eq!(store.revoke(&pair.id, &other_token).await, Err(RevokeError::Forbidden));
+        assert_eq!(store.revoke("missing", &token).await, Err(RevokeError::NotFound));
+        assert_eq!(store.revoke(&pair.id, &token).await, Ok(()));
+        assert_eq!(store.resume(&hub, &token, Role::Atem).await, Err(ResumeError::InvalidToken));
+        assert!(store.resume(&hub, &other_token, Role::Atem).await.is_ok());
+
+        let expired = DevicePairStore::new().with_ttl(Duration::zero());
+        let (_, token, _) = expired.persist("ROOM-ABCD", "laptop".into(), None).await;
+        assert_eq!(expired.resume(&hub, &token, Role::Atem).await, Err(ResumeError::InvalidToken));
+        assert_eq!(expired.cleanup_expired().await, 1);
+        assert_eq!(expired.pair_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn persisting_a_room_again_reuses_its_pairing() {
+        let hub = RelayHub::new();
+        let store = DevicePairStore::new();
+        let (pair, old_token, reused) = store.persist("ROOM-ABCD", "laptop".into(), None).await;
+        assert!(!reused);
+
+        let (again, token, reused) = store.persist("ROOM-ABCD", "laptop".into(), Some("desktop".into())).await;
+        assert!(reused);
+        assert_eq!(again.id, pair.id);
+        assert_eq!(again.astation_hostname.as_deref(), Some("desktop"));
+        assert_eq!(store.pair_count().await, 1);
+        assert_eq!(store.resume(&hub, &old_token, Role::Atem).await, Err(ResumeError::InvalidToken));
+
+        // A room resumed into belongs to the same pairing
+        let resumed = store.resume(&hub, &token, Role::Atem).await.unwrap();
+        let (from_resumed, _, reused) = store.persist(&resumed.code, "laptop".into(), None).await;
+        assert!(reused);
+        assert_eq!(from_resumed.id, pair.id);
+        assert_eq!(store.pair_count().await, 1);
+
+        let (other, _, reused) = store.persist("ROOM-WXYZ", "laptop".into(), None).await;
+        assert!(!reused);
+        assert_ne!(other.id, pair.id);
+    }
+
+    #[tokio::test]
+    async fn snapshot_restores_unexpired_pairings_owner_only() {
+        let path = std::env::temp_dir().join(format!("device-pairs-{}.json", uuid::Uuid::new_v4()));
+        let hub = RelayHub::new();
+        let store = DevicePairStore::new();
+        let (pair, token, _) = store.persist("ROOM-ABCD", "laptop".into(), Some("desktop".into())).await;
+        assert!(store.take_dirty());
+        assert!(!store.take_dirty());
+        store.pairs.write().await.insert(
+            "stale".into(),
+            StoredPair {
+                pair: DevicePair {
+                    id: "stale".into(),
+                    expires_at: Utc::now() - Duration::minutes(1),
+                    ..pair.clone()
+                },
+                token_hash: relay::hash_token("stale-token"),
+                room_code: None,
+                resume: None,
+            },
+        );
+        assert_eq!(store.save(&path).await.unwrap(), 1);
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains(&token), "tokens are never written");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        let restored = DevicePairStore::new();
+        assert_eq!(restored.load(&path).await.unwrap(), 1);
+        assert_eq!(restored.pair_count().await, 1);
+        assert_eq!(restored.resume(&hub, &token, Role::Atem).await.unwrap().pair_id, pair.id);
+        // Room codes are not restored, so the old room starts a new pairing
+        let (_, _, reused) = restored.persist("ROOM-ABCD", "laptop".into(), None).await;
+        assert!(!reused);
+
+        assert_eq!(DevicePairStore::new().load(&path.with_extension("missing")).await.unwrap(), 0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn persist_resume_and_revoke_over_http() {
+        let state = AppState {
+            sessions: SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: DevicePairStore::new(),
+            rtc_sessions: crate::rtc_session::RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        let hub = state.relay.clone();
+        let code = hub.create_room("laptop".into(), "owner".into()).await.unwrap();
+        let app = Router::new()
+            .route("/api/pair/resume", post(resume_pair_handler))
+            .route("/api/pair/persisted/:id", delete(revoke_pair_handler))
+            .route("/api/pair/:code/persist", post(persist_pair_handler))
+            .with_state(state.clone());
+        let send = |method: &str, uri: String, token: Option<&str>, body: &str| {
+            let mut request = Request::builder().method(method).uri(uri).header("content-type", "application/json");
+            if let Some(token) = token {
+                request = request.header("authorization", format!("Bearer {}", token));
+            }
+            let request = request.body(Body::from(body.to_string())).unwrap();
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_default())
+            }
+        };
+        let persist_uri = format!("/api/pair/{}/persist", code);
+
+        // Only the owner, and only once both sides are connected
+        assert_eq!(send("POST", persist_uri.clone(), Some("wrong"), "{}").await.0, StatusCode::FORBIDDEN);
+        let mut atem_rx = hub.connect_test_side(&code, Role::Atem).await;
+        assert_eq!(send("POST", persist_uri.clone(), Some("owner"), "{}").await.0, StatusCode::CONFLICT);
+        let mut astation_rx = hub.connect_test_side(&code, Role::Astation).await;
+        let body = r#"{"astation_hostname":"bad host"}"#;
+        assert_eq!(send("POST", persist_uri.clone(), Some("owner"), body).await.0, StatusCode::BAD_REQUEST);
+
+        let (status, persisted) =
+            send("POST", persist_uri.clone(), Some("owner"), r#"{"astation_hostname":"desktop"}"#).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(persisted["reused"], false);
+        assert_eq!((persisted["atem_hostname"].as_str(), persisted["astation_hostname"].as_str()), (Some("laptop"), Some("desktop")));
+        let token = persisted["device_pair_token"].as_str().unwrap().to_string();
+        let id = persisted["id"].as_str().unwrap().to_string();
+        for rx in [&mut atem_rx, &mut astation_rx] {
+            let notice: serde_json::Value = serde_json::from_str(&rx.try_recv().unwrap()).unwrap();
+            assert_eq!((notice["type"].as_str(), notice["device_pair_token"].as_str()), (Some("device_paired"), Some(token.as_str())));
+        }
+
+        // Persisting the same room again keeps the pairing and rotates its token
+        let (status, again) = send("POST", persist_uri, Some("owner"), "{}").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((again["id"].as_str(), again["reused"].as_bool()), (Some(id.as_str()), Some(true)));
+        assert_eq!(again["astation_hostname"], "desktop");
+        let stale = format!(r#"{{"device_pair_token":"{}","role":"astation"}}"#, token);
+        assert_eq!(send("POST", "/api/pair/resume".into(), None, &stale).await.0, StatusCode::UNAUTHORIZED);
+        let token = again["device_pair_token"].as_str().unwrap().to_string();
+
+        let resume = format!(r#"{{"device_pair_token":"{}","role":"astation"}}"#, token);
+        let (status, room) = send("POST", "/api/pair/resume".into(), None, &resume).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_ne!(room["code"], code);
+        assert_eq!(room["peer_waiting"], false);
+        let bad_role = format!(r#"{{"device_pair_token":"{}","role":"observer"}}"#, token);
+        assert_eq!(send("POST", "/api/pair/resume".into(), None, &bad_role).await.0, StatusCode::BAD_REQUEST);
+
+        let revoke_uri = format!("/api/pair/persisted/{}", id);
+        assert_eq!(send("DELETE", revoke_uri.clone(), None, "").await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(send("DELETE", revoke_uri.clone(), Some(&token), "").await.0, StatusCode::OK);
+        assert_eq!(send("DELETE", revoke_uri, Some(&token), "").await.0, StatusCode::NOT_FOUND);
+        assert_eq!(send("POST", "/api/pair/resume".into(), None, &resume).await.0, StatusCode::UNAUTHORIZED);
+    }
+}
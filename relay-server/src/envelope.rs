@@ -0,0 +1,224 @@
+use serde_json::{json, Value};
+
+/// Envelope protocol version this server speaks.
+pub const VERSION: u64 = 1;
+
+/// End-to-end key exchange message. Relayed like the forwarded types, but
+/// never logged or copied to observers; once both sides have sent one the room
+/// only carries envelopes and binary frames.
+pub const E2E_INIT: &str = "e2e_init";
+
+/// Round-trip probe the server sends each side, `{"payload":{"nonce":...}}`.
+pub const LATENCY_PROBE: &str = "latency_probe";
+
+/// A client's answer to [`LATENCY_PROBE`], echoing its nonce. Consumed by the
+/// server and never relayed.
+pub const LATENCY_PROBE_ACK: &str = "latency_probe_ack";
+
+/// Envelope types relayed to the other side unchanged.
+pub const FORWARDED_TYPES: &[&str] = &["message", "command", "response", "event", E2E_INIT];
+
+/// Types the server answers or sends itself. Of these, clients may only send `ping`.
+pub const SERVER_TYPES: &[&str] = &[
+    "ping",
+    "pong",
+    "error",
+    "pair_established",
+    "peer_disconnected",
+    "connection_replaced",
+    "room_deleted",
+    LATENCY_PROBE,
+];
+
+/// What to do with a frame that passed [`check`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum Frame {
+    /// An envelope of a forwarded type; relay the original text as-is.
+    Forward,
+    /// `ping`, answered with a `pong` carrying the same `seq`.
+    Ping { seq: Option<u64> },
+    /// A `{"relay": ...}` frame, which keeps its own handling.
+    Relay,
+    /// `latency_probe_ack`, timed by the server and not forwarded.
+    ProbeAck,
+}
+
+/// Why a frame was bounced instead of forwarded.
+#[derive(Debug, PartialEq, Eq)]
+pub struct EnvelopeError {
+    pub code: &'static str,
+    pub message: String,
+    /// The offending frame's `seq`, when it had a usable one.
+    pub seq: Option<u64>,
+}
+
+impl EnvelopeError {
+    fn new(code: &'static str, message: impl Into<String>, seq: Option<u64>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            seq,
+        }
+    }
+
+    /// Frames are being dropped for exceeding the connection's message rate.
+    pub fn rate_limited() -> Self {
+        Self::new("rate_limited", "Too many messages; frames are being dropped", None)
+    }
+
+    /// An observer tried to send a frame.
+    pub fn read_only() -> Self {
+        Self::new("observer_read_only", "Observers cannot send frames", None)
+    }
+
+    /// A plaintext frame was sent into an end-to-end encrypted room.
+    pub fn e2e_plaintext() -> Self {
+        Self::new(
+            "e2e_plaintext_refused",
+            "Room is end-to-end encrypted; only envelopes and binary frames are relayed",
+            None,
+        )
+    }
+
+    /// A `set_metadata` control would leave room metadata over `max` bytes.
+    pub fn metadata_too_large(max: usize) -> Self {
+        Self::new("metadata_too_large", format!("Room metadata is limited to {} bytes", max), None)
+    }
+
+    /// The `error` envelope sent back to the client.
+    pub fn to_frame(&self) -> String {
+        let mut frame = json!({
+            "v": VERSION,
+            "type": "error",
+            "payload": { "code": self.code, "message": self.message },
+        });
+        if let Some(seq) = self.seq {
+            frame["seq"] = seq.into();
+        }
+        frame.to_string()
+    }
+}
+
+/// The `pong` envelope answering a `ping`.
+pub fn pong(seq: Option<u64>) -> String {
+    let mut frame = json!({ "v": VERSION, "type": "pong" });
+    if let Some(seq) = seq {
+        frame["seq"] = seq.into();
+    }
+    frame.to_string()
+}
+
+/// The `latency_probe` envelope carrying `nonce`.
+pub fn latency_probe(nonce: &str) -> String {
+    json!({ "v": VERSION, "type": LATENCY_PROBE, "payload": { "nonce": nonce } }).to_string()
+}
+
+/// Validate one text frame as a `{"v":1,"type":...,"payload":{...},"seq":n}`
+/// envelope. `payload` and `seq` are optional, but must be an object and a
+/// non-negative integer when present.
+pub fn check(text: &str) -> Result<Frame, EnvelopeError> {
+    let value: Value = serde_json::from_str(text)
+        .map_err(|e| EnvelopeError::new("malformed_json", format!("Frame is not valid JSON: {}", e), None))?;
+    let Value::Object(fields) = value else {
+        return Err(EnvelopeError::new("malformed_json", "Frame must be a JSON object", None));
+    };
+    if fields.contains_key("relay") {
+        return Ok(Frame::Relay);
+    }
+
+    let seq = match fields.get("seq") {
+        None => None,
+        Some(seq) => Some(
+            seq.as_u64()
+                .ok_or_else(|| EnvelopeError::new("invalid_seq", "seq must be a non-negative integer", None))?,
+        ),
+    };
+    if fields.get("v").and_then(Value::as_u64) != Some(VERSION) {
+        return Err(EnvelopeError::new(
+            "unsupported_version",
+            format!("v must be {}", VERSION),
+            seq,
+        ));
+    }
+    let Some(kind) = fields.get("type").and_then(Value::as_str) else {
+        return Err(EnvelopeError::new("missing_type", "type must be a string", seq));
+    };
+    if kind == "ping" {
+        return Ok(Frame::Ping { seq });
+    }
+    if kind == LATENCY_PROBE_ACK {
+        return Ok(Frame::ProbeAck);
+    }
+    if SERVER_TYPES.contains(&kind) {
+        return Err(EnvelopeError::new(
+            "reserved_type",
+            format!("{:?} is sent by the server only", kind),
+            seq,
+        ));
+    }
+    if !FORWARDED_TYPES.contains(&kind) {
+        return Err(EnvelopeError::new("unknown_type", format!("Unknown type {:?}", kind), seq));
+    }
+    if fields.get("payload").is_some_and(|payload| !payload.is_object()) {
+        return Err(EnvelopeError::new("invalid_payload", "payload must be an object", seq));
+    }
+    Ok(Frame::Forward)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_of(text: &str) -> &'static str {
+        check(text).unwrap_err().code
+    }
+
+    #[test]
+    fn test_valid_envelopes_are_forwarded() {
+        for kind in FORWARDED_TYPES {
+            let text = json!({ "v": 1, "type": kind, "payload": { "n": 1 }, "seq": 3 }).to_string();
+            assert_eq!(check(&text), Ok(Frame::Forward), "{}", kind);
+        }
+        // payload and seq are optional
+        assert_eq!(check(r#"{"v":1,"type":"event"}"#), Ok(Frame::Forward));
+    }
+
+    #[test]
+    fn test_ping_and_relay_frames_are_recognised() {
+        assert_eq!(check(r#"{"v":1,"type":"ping","seq":9}"#), Ok(Frame::Ping { seq: Some(9) }));
+        assert_eq!(check(r#"{"relay":"ping","id":"a"}"#), Ok(Frame::Relay));
+        let pong: Value = serde_json::from_str(&pong(Some(9))).unwrap();
+        assert_eq!(pong, json!({ "v": 1, "type": "pong", "seq": 9 }));
+        let probe: Value = serde_json::from_str(&latency_probe("n1")).unwrap();
+        assert_eq!(probe, json!({ "v": 1, "type": "latency_probe", "payload": { "nonce": "n1" } }));
+        assert_eq!(
+            check(r#"{"v":1,"type":"latency_probe_ack","payload":{"nonce":"n1"}}"#),
+            Ok(Frame::ProbeAck)
+        );
+        assert_eq!(code_of(r#"{"v":1,"type":"latency_probe"}"#), "reserved_type");
+    }
+
+    #[test]
+    fn test_invalid_frames_are_bounced_with_a_reason() {
+        assert_eq!(code_of("hello"), "malformed_json");
+        assert_eq!(code_of("[1,2]"), "malformed_json");
+        assert_eq!(code_of(r#"{"type":"message"}"#), "unsupported_version");
+        assert_eq!(code_of(r#"{"v":2,"type":"message"}"#), "unsupported_version");
+        assert_eq!(code_of(r#"{"v":1}"#), "missing_type");
+        assert_eq!(code_of(r#"{"v":1,"type":"teleport"}"#), "unknown_type");
+        assert_eq!(code_of(r#"{"v":1,"type":"pair_established"}"#), "reserved_type");
+        assert_eq!(code_of(r#"{"v":1,"type":"message","payload":"text"}"#), "invalid_payload");
+        assert_eq!(code_of(r#"{"v":1,"type":"message","seq":-1}"#), "invalid_seq");
+    }
+
+    #[test]
+    fn test_error_frame_echoes_seq() {
+        let error = check(r#"{"v":1,"type":"teleport","seq":42}"#).unwrap_err();
+        let frame: Value = serde_json::from_str(&error.to_frame()).unwrap();
+        assert_eq!(frame["type"], "error");
+        assert_eq!(frame["seq"], 42);
+        assert_eq!(frame["payload"]["code"], "unknown_type");
+        let frame: Value = serde_json::from_str(&check("{").unwrap_err().to_frame()).unwrap();
+        assert!(frame.get("seq").is_none());
+    }
+}
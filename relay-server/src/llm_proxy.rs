@@ -1,27 +1,43 @@
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde::{Deserialize, Serialize};
+use crate::admin::require_admin;
 use crate::AppState;
-use crate::voice_session::VoiceSessionState;
+use crate::voice_session::{LlmRequestParams, VoiceSessionState};
+
+/// Model name reported when the request does not name one
+const DEFAULT_MODEL: &str = "atem-voice-proxy";
 
 /// OpenAI-compatible chat completion request format
 #[derive(Debug, Deserialize)]
 pub struct ChatCompletionRequest {
     pub messages: Vec<ChatMessage>,
     #[serde(default)]
+    #[allow(dead_code)]
     pub stream: bool,
     #[serde(skip)]
+    #[allow(dead_code)]
     pub session_id: Option<String>, // Will be extracted from headers or heuristics
+    /// Echoed back in the response `model`
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ChatMessage {
     pub role: String,
     pub content: String,
+    /// ASR confidence for user turns (passed through from Agora when present)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
 }
 
 /// Query parameters for /api/llm/chat (ConvoAI passes session_id via URL)
@@ -38,6 +54,15 @@ pub struct ChatCompletionResponse {
     pub created: i64,
     pub model: String,
     pub choices: Vec<Choice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+/// Token counts for one completion, as reported by Atem's LLM provider
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
 }
 
 #[derive(Debug, Serialize)]
@@ -82,16 +107,24 @@ pub async fn llm_chat_handler(
     };
 
     tracing::debug!("Processing /api/llm/chat for session: {}", session_id);
+    tracing::debug!("Session {}: requested model {:?}", session_id, req.model);
+    let model = req.model.clone().unwrap_or_else(|| DEFAULT_MODEL.to_string());
 
     // Get last user message for logging
     let last_message = req.messages.last().map(|m| m.content.clone()).unwrap_or_default();
+    let confidence = req.messages.last().and_then(|m| m.confidence);
     tracing::info!("Session {}: User message: {}", session_id, last_message);
 
     // Increment request counter
     state.voice_sessions.increment_requests(&session_id).await;
+    state.voice_sessions.set_llm_params(&session_id, LlmRequestParams {
+        model: req.model,
+        temperature: req.temperature,
+        max_tokens: req.max_tokens,
+    }).await;
 
     // Add transcription to buffer
-    state.voice_sessions.add_transcription(&session_id, last_message).await;
+    state.voice_sessions.add_transcription(&session_id, last_message, confidence).await;
 
     // Get session state
     let session_state = state.voice_sessions.get_state(&session_id).await;
@@ -100,12 +133,21 @@ pub async fn llm_chat_handler(
         Some(VoiceSessionState::Accumulating) => {
             // Return empty response immediately
             tracing::debug!("Session {} in Accumulating state - returning empty response", session_id);
-            return create_empty_response().into_response();
+            create_empty_response(model).into_response()
         }
         Some(VoiceSessionState::Triggered) => {
             // Block and wait for Atem response
             tracing::info!("Session {} in Triggered state - blocking for Atem response", session_id);
-            let waiter = state.voice_sessions.register_waiter(session_id.clone()).await;
+            let system_prompt = state.voice_sessions.get(&session_id).await.and_then(|s| s.system_prompt);
+            let messages = prepend_system_prompt(req.messages, system_prompt);
+            state.voice_sessions.set_llm_messages(&session_id, messages).await;
+            let Some(waiter) = state.voice_sessions.get_or_wait(&session_id).await else {
+                tracing::warn!("Session {} vanished before its request could wait", session_id);
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(serde_json::json!({"error": "Session not found"}))
+                ).into_response();
+            };
 
             // Wait for response with timeout (30 seconds)
             match tokio::time::timeout(
@@ -114,21 +156,26 @@ pub async fn llm_chat_handler(
             ).await {
                 Ok(Ok(response_text)) => {
                     tracing::info!("Session {}: Received response from Atem", session_id);
-                    return create_response(response_text).into_response();
+                    let usage = state.voice_sessions.claim_usage(&session_id).await;
+                    create_response(response_text, model, usage).into_response()
                 }
                 Ok(Err(_)) => {
-                    tracing::error!("Session {}: Waiter channel closed", session_id);
-                    return (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(serde_json::json!({"error": "Response channel closed"}))
-                    ).into_response();
+                    // Sender dropped without a response (Atem went away or session was swept)
+                    tracing::warn!("Session {}: Waiter dropped before Atem responded", session_id);
+                    (
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        Json(serde_json::json!({
+                            "error": "atem_disconnected",
+                            "session_id": session_id,
+                        }))
+                    ).into_response()
                 }
                 Err(_) => {
                     tracing::error!("Session {}: Timeout waiting for Atem response", session_id);
-                    return (
+                    (
                         StatusCode::GATEWAY_TIMEOUT,
                         Json(serde_json::json!({"error": "Timeout waiting for Atem response"}))
-                    ).into_response();
+                    ).into_response()
                 }
             }
         }
@@ -137,27 +184,52 @@ pub async fn llm_chat_handler(
             if let Some(session) = state.voice_sessions.get(&session_id).await {
                 if let Some(response_text) = session.response {
                     tracing::debug!("Session {} in ResponseReady state - returning cached response", session_id);
+                    let usage = state.voice_sessions.claim_usage(&session_id).await;
                     // Clean up session after delivering response
                     state.voice_sessions.delete(&session_id).await;
-                    return create_response(response_text).into_response();
+                    return create_response(response_text, model, usage).into_response();
                 }
             }
             tracing::error!("Session {} in ResponseReady but no cached response", session_id);
-            return (
+            (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({"error": "Response ready but not found"}))
-            ).into_response();
+            ).into_response()
         }
         None => {
             tracing::warn!("Session {} not found", session_id);
-            return (
+            (
                 StatusCode::NOT_FOUND,
                 Json(serde_json::json!({"error": "Session not found"}))
-            ).into_response();
+            ).into_response()
         }
     }
 }
 
+/// GET /api/llm/health
+///
+/// Voice session and waiter counts for checking the proxy is being fed
+/// (admin token required)
+pub async fn llm_health_handler(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    require_admin(&state, &headers)?;
+    Ok::<_, (StatusCode, Json<serde_json::Value>)>(Json(state.voice_sessions.health().await))
+}
+
+/// Put the session's system prompt, if any, ahead of the request messages
+fn prepend_system_prompt(messages: Vec<ChatMessage>, system_prompt: Option<String>) -> Vec<ChatMessage> {
+    let Some(content) = system_prompt else {
+        return messages;
+    };
+    let mut prompted = Vec::with_capacity(messages.len() + 1);
+    prompted.push(ChatMessage {
+        role: "system".to_string(),
+        content,
+        confidence: None,
+    });
+    prompted.extend(messages);
+    prompted
+}
+
 /// Extract session ID from HTTP headers.
 ///
 /// Priority:
@@ -192,38 +264,42 @@ fn extract_session_id_from_headers(headers: &axum::http::HeaderMap) -> Option<St
 }
 
 /// Create empty response (Accumulating state)
-fn create_empty_response() -> Json<ChatCompletionResponse> {
+fn create_empty_response(model: String) -> Json<ChatCompletionResponse> {
     Json(ChatCompletionResponse {
         id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
         object: "chat.completion".to_string(),
         created: chrono::Utc::now().timestamp(),
-        model: "atem-voice-proxy".to_string(),
+        model,
         choices: vec![Choice {
             index: 0,
             message: ChatMessage {
                 role: "assistant".to_string(),
                 content: "".to_string(),
+                confidence: None,
             },
             finish_reason: "stop".to_string(),
         }],
+        usage: None,
     })
 }
 
 /// Create response with content (ResponseReady state)
-fn create_response(content: String) -> Json<ChatCompletionResponse> {
+fn create_response(content: String, model: String, usage: Option<Usage>) -> Json<ChatCompletionResponse> {
     Json(ChatCompletionResponse {
         id: format!("chatcmpl-{}", uuid::Uuid::new_v4()),
         object: "chat.completion".to_string(),
         created: chrono::Utc::now().timestamp(),
-        model: "atem-voice-proxy".to_string(),
+        model,
         choices: vec![Choice {
             index: 0,
             message: ChatMessage {
                 role: "assistant".to_string(),
                 content,
+                confidence: None,
             },
             finish_reason: "stop".to_string(),
         }],
+        usage,
     })
 }
 
@@ -241,9 +317,13 @@ mod tests {
         AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         }
     }
 
@@ -254,15 +334,19 @@ mod tests {
             "test-123".to_string(),
             "atem-1".to_string(),
             "channel-1".to_string(),
-        ).await;
+        ).await.unwrap();
 
         let req = ChatCompletionRequest {
             messages: vec![ChatMessage {
                 role: "user".to_string(),
                 content: "Hello world".to_string(),
+                confidence: None,
             }],
             stream: false,
             session_id: None,
+            model: None,
+            temperature: None,
+            max_tokens: None,
         };
 
         let mut headers = axum::http::HeaderMap::new();
@@ -290,18 +374,22 @@ mod tests {
             "test-123".to_string(),
             "atem-1".to_string(),
             "channel-1".to_string(),
-        ).await;
+        ).await.unwrap();
 
         // Trigger the session
-        state.voice_sessions.trigger("test-123").await;
+        state.voice_sessions.trigger("test-123").await.unwrap();
 
         let req = ChatCompletionRequest {
             messages: vec![ChatMessage {
                 role: "user".to_string(),
                 content: "Create a function".to_string(),
+                confidence: None,
             }],
             stream: false,
             session_id: None,
+            model: None,
+            temperature: None,
+            max_tokens: None,
         };
 
         let mut headers = axum::http::HeaderMap::new();
@@ -314,7 +402,7 @@ mod tests {
             state_clone.voice_sessions.set_response(
                 "test-123",
                 "Here's the function implementation...".to_string(),
-            ).await;
+            ).await.unwrap();
         });
 
         let response = llm_chat_handler(
@@ -328,6 +416,128 @@ mod tests {
         assert_eq!(status, StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_triggered_request_gets_system_prompt_first() {
+        let state = create_test_state();
+        state.voice_sessions.create(
+            "test-prompt".to_string(),
+            "atem-1".to_string(),
+            "channel-1".to_string(),
+        ).await.unwrap();
+        state.voice_sessions.set_system_prompt("test-prompt", Some("Reply in Rust only.".to_string())).await.unwrap();
+        state.voice_sessions.trigger("test-prompt").await.unwrap();
+
+        let req = ChatCompletionRequest {
+            messages: vec![ChatMessage {
+                role: "user".to_string(),
+                content: "Create a function".to_string(),
+                confidence: None,
+            }],
+            stream: false,
+            session_id: None,
+            model: None,
+            temperature: None,
+            max_tokens: None,
+        };
+
+        // Atem answers once it sees the prompted messages
+        let state_clone = state.clone();
+        let atem = tokio::spawn(async move {
+            loop {
+                let session = state_clone.voice_sessions.get("test-prompt").await.unwrap();
+                if !session.llm_messages.is_empty() {
+                    state_clone.voice_sessions.set_response("test-prompt", "fn f() {}".to_string()).await.unwrap();
+                    return session.llm_messages;
+                }
+                tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+            }
+        });
+
+        let response = llm_chat_handler(
+            State(state.clone()),
+            Query(LlmChatQuery { session_id: Some("test-prompt".to_string()) }),
+            axum::http::HeaderMap::new(),
+            Json(req),
+        ).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let messages = atem.await.unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, "system");
+        assert_eq!(messages[0].content, "Reply in Rust only.");
+        assert_eq!(messages[1].role, "user");
+        assert_eq!(messages[1].content, "Create a function");
+    }
+
+    #[test]
+    fn test_no_system_prompt_leaves_messages_alone() {
+        let messages = vec![ChatMessage {
+            role: "user".to_string(),
+            content: "hi".to_string(),
+            confidence: None,
+        }];
+        let unchanged = prepend_system_prompt(messages, None);
+        assert_eq!(unchanged.len(), 1);
+        assert_eq!(unchanged[0].role, "user");
+    }
+
+    #[tokio::test]
+    async fn test_triggered_response_reports_and_accumulates_usage() {
+        let state = create_test_state();
+        state.voice_sessions.create(
+            "test-usage".to_string(),
+            "atem-1".to_string(),
+            "channel-1".to_string(),
+        ).await.unwrap();
+
+        for round in 1..=2u64 {
+            if round > 1 {
+                state.voice_sessions.reset("test-usage").await.unwrap();
+            }
+            state.voice_sessions.trigger("test-usage").await.unwrap();
+            let req = ChatCompletionRequest {
+                messages: vec![ChatMessage {
+                    role: "user".to_string(),
+                    content: "Create a function".to_string(),
+                    confidence: None,
+                }],
+                stream: false,
+                session_id: None,
+                model: None,
+                temperature: None,
+                max_tokens: None,
+            };
+
+            let state_clone = state.clone();
+            tokio::spawn(async move {
+                while state_clone.voice_sessions.waiter_count("test-usage").await == 0 {
+                    tokio::task::yield_now().await;
+                }
+                state_clone.voice_sessions.set_response_with_usage(
+                    "test-usage",
+                    "Done".to_string(),
+                    Some(Usage { prompt_tokens: 30, completion_tokens: 7 }),
+                ).await.unwrap();
+            });
+
+            let response = llm_chat_handler(
+                State(state.clone()),
+                Query(LlmChatQuery { session_id: Some("test-usage".to_string()) }),
+                axum::http::HeaderMap::new(),
+                Json(req),
+            ).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["usage"]["prompt_tokens"], 30);
+            assert_eq!(json["usage"]["completion_tokens"], 7);
+
+            let session = state.voice_sessions.get("test-usage").await.unwrap();
+            assert_eq!(session.total_prompt_tokens, 30 * round);
+            assert_eq!(session.total_completion_tokens, 7 * round);
+        }
+    }
+
     #[tokio::test]
     async fn test_missing_session_id() {
         let state = create_test_state();
@@ -336,9 +546,13 @@ mod tests {
             messages: vec![ChatMessage {
                 role: "user".to_string(),
                 content: "Hello".to_string(),
+                confidence: None,
             }],
             stream: false,
             session_id: None,
+            model: None,
+            temperature: None,
+            max_tokens: None,
         };
 
         let headers = axum::http::HeaderMap::new(); // No session ID header
@@ -362,9 +576,13 @@ mod tests {
             messages: vec![ChatMessage {
                 role: "user".to_string(),
                 content: "Hello".to_string(),
+                confidence: None,
             }],
             stream: false,
             session_id: None,
+            model: None,
+            temperature: None,
+            max_tokens: None,
         };
 
         let mut headers = axum::http::HeaderMap::new();
@@ -388,21 +606,26 @@ mod tests {
             "test-ready".to_string(),
             "atem-1".to_string(),
             "channel-1".to_string(),
-        ).await;
+        ).await.unwrap();
 
         // Set response directly (simulating Atem already replied)
+        state.voice_sessions.trigger("test-ready").await.unwrap();
         state.voice_sessions.set_response(
             "test-ready",
             "Here is the implementation".to_string(),
-        ).await;
+        ).await.unwrap();
 
         let req = ChatCompletionRequest {
             messages: vec![ChatMessage {
                 role: "user".to_string(),
                 content: "Final request".to_string(),
+                confidence: None,
             }],
             stream: false,
             session_id: None,
+            model: None,
+            temperature: None,
+            max_tokens: None,
         };
 
         let mut headers = axum::http::HeaderMap::new();
@@ -429,15 +652,19 @@ mod tests {
             "test-buf".to_string(),
             "atem-1".to_string(),
             "channel-1".to_string(),
-        ).await;
+        ).await.unwrap();
 
         let req = ChatCompletionRequest {
             messages: vec![ChatMessage {
                 role: "user".to_string(),
                 content: "First chunk".to_string(),
+                confidence: None,
             }],
             stream: false,
             session_id: None,
+            model: None,
+            temperature: None,
+            max_tokens: None,
         };
 
         let mut headers = axum::http::HeaderMap::new();
@@ -465,15 +692,19 @@ mod tests {
             "test-fallback".to_string(),
             "atem-1".to_string(),
             "channel-1".to_string(),
-        ).await;
+        ).await.unwrap();
 
         let req = ChatCompletionRequest {
             messages: vec![ChatMessage {
                 role: "user".to_string(),
                 content: "Test".to_string(),
+                confidence: None,
             }],
             stream: false,
             session_id: None,
+            model: None,
+            temperature: None,
+            max_tokens: None,
         };
 
         // Use X-Session-ID instead of X-Voice-Session-ID
@@ -497,15 +728,19 @@ mod tests {
             "query-sess".to_string(),
             "atem-1".to_string(),
             "channel-1".to_string(),
-        ).await;
+        ).await.unwrap();
 
         let req = ChatCompletionRequest {
             messages: vec![ChatMessage {
                 role: "user".to_string(),
                 content: "Hello via query param".to_string(),
+                confidence: None,
             }],
             stream: false,
             session_id: None,
+            model: None,
+            temperature: None,
+            max_tokens: None,
         };
 
         // No header — session ID comes from query param
@@ -533,20 +768,24 @@ mod tests {
             "from-query".to_string(),
             "atem-1".to_string(),
             "channel-1".to_string(),
-        ).await;
+        ).await.unwrap();
         state.voice_sessions.create(
             "from-header".to_string(),
             "atem-1".to_string(),
             "channel-1".to_string(),
-        ).await;
+        ).await.unwrap();
 
         let req = ChatCompletionRequest {
             messages: vec![ChatMessage {
                 role: "user".to_string(),
                 content: "Which session?".to_string(),
+                confidence: None,
             }],
             stream: false,
             session_id: None,
+            model: None,
+            temperature: None,
+            max_tokens: None,
         };
 
         // Both query param and header set — query param should win
@@ -569,4 +808,148 @@ mod tests {
         let header_session = state.voice_sessions.get("from-header").await.unwrap();
         assert!(header_session.get_accumulated_text().is_empty());
     }
+
+    #[tokio::test]
+    async fn test_triggered_waiter_dropped_returns_503() {
+        let state = create_test_state();
+        state.voice_sessions.create(
+            "test-drop".to_string(),
+            "atem-1".to_string(),
+            "channel-1".to_string(),
+        ).await.unwrap();
+        state.voice_sessions.trigger("test-drop").await.unwrap();
+
+        let req = ChatCompletionRequest {
+            messages: vec![ChatMessage {
+                role: "user".to_string(),
+                content: "Create a function".to_string(),
+                confidence: None,
+            }],
+            stream: false,
+            session_id: None,
+            model: None,
+            temperature: None,
+            max_tokens: None,
+        };
+
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("x-voice-session-id", "test-drop".parse().unwrap());
+
+        // Simulate Atem disconnecting: drop the waiter without a response
+        let state_clone = state.clone();
+        tokio::spawn(async move {
+            while state_clone.voice_sessions.waiter_count("test-drop").await == 0 {
+                tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+            }
+            state_clone.voice_sessions.drop_waiters("test-drop").await;
+        });
+
+        let response = llm_chat_handler(
+            State(state),
+            Query(LlmChatQuery { session_id: None }),
+            headers,
+            Json(req),
+        ).await;
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"], "atem_disconnected");
+        assert_eq!(json["session_id"], "test-drop");
+    }
+
+    async fn chat_response_model(state: &AppState, session_id: &str, body: &str) -> String {
+        let req: ChatCompletionRequest = serde_json::from_str(body).unwrap();
+        let response = llm_chat_handler(
+            State(state.clone()),
+            Query(LlmChatQuery { session_id: Some(session_id.to_string()) }),
+            axum::http::HeaderMap::new(),
+            Json(req),
+        ).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        json["model"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_requested_model_is_echoed() {
+        let state = create_test_state();
+        state.voice_sessions.create(
+            "test-model".to_string(),
+            "atem-1".to_string(),
+            "channel-1".to_string(),
+        ).await.unwrap();
+
+        let model = chat_response_model(
+            &state,
+            "test-model",
+            r#"{"messages":[{"role":"user","content":"Hi"}],"model":"gpt-4o-mini","temperature":0.7,"max_tokens":256}"#,
+        ).await;
+        assert_eq!(model, "gpt-4o-mini");
+
+        // Sampling parameters are kept on the session for Atem
+        let session = state.voice_sessions.get("test-model").await.unwrap();
+        assert_eq!(session.llm_params.model.as_deref(), Some("gpt-4o-mini"));
+        assert_eq!(session.llm_params.temperature, Some(0.7));
+        assert_eq!(session.llm_params.max_tokens, Some(256));
+
+        // Cached responses echo the model as well
+        state.voice_sessions.trigger("test-model").await.unwrap();
+        state.voice_sessions.set_response("test-model", "Done".to_string()).await.unwrap();
+        let model = chat_response_model(
+            &state,
+            "test-model",
+            r#"{"messages":[{"role":"user","content":"Hi"}],"model":"gpt-4o"}"#,
+        ).await;
+        assert_eq!(model, "gpt-4o");
+    }
+
+    #[tokio::test]
+    async fn test_model_defaults_to_proxy_name() {
+        let state = create_test_state();
+        state.voice_sessions.create(
+            "test-no-model".to_string(),
+            "atem-1".to_string(),
+            "channel-1".to_string(),
+        ).await.unwrap();
+
+        let model = chat_response_model(
+            &state,
+            "test-no-model",
+            r#"{"messages":[{"role":"user","content":"Hi"}]}"#,
+        ).await;
+        assert_eq!(model, "atem-voice-proxy");
+
+        let session = state.voice_sessions.get("test-no-model").await.unwrap();
+        assert_eq!(session.llm_params, LlmRequestParams::default());
+    }
+
+    #[tokio::test]
+    async fn test_health_requires_admin_token() {
+        let mut state = create_test_state();
+        state.admin_token = Some("admin-secret".into());
+        state.voice_sessions.create(
+            "test-health".to_string(),
+            "atem-1".to_string(),
+            "channel-1".to_string(),
+        ).await.unwrap();
+
+        let mut headers = HeaderMap::new();
+        let response = llm_health_handler(State(state.clone()), headers.clone()).await.into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        headers.insert("Authorization", "Bearer admin-secret".parse().unwrap());
+        let response = llm_health_handler(State(state), headers).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let health: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(health["healthy"], true);
+        assert_eq!(health["total_sessions"], 1);
+        assert_eq!(health["by_state"]["accumulating"], 1);
+    }
 }
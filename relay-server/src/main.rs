@@ -1,35 +1,58 @@
+mod admin;
+mod agora_token;
 mod auth;
+mod body_limit;
+mod config;
+mod cors;
+mod device_pairing;
+mod envelope;
 mod relay;
 mod routes;
+mod rtc_persist;
 mod rtc_session;
 mod session_store;
 mod session_verify;
 mod voice_session;
 mod voice_routes;
+mod webhook;
 mod llm_proxy;
+mod metrics;
+mod supervisor;
+mod version;
 mod web;
 
-use axum::http::{header, HeaderValue, Method};
-use axum::routing::{get, post};
-use axum::Router;
+use axum::routing::{delete, get, post, put};
+use axum::{middleware, Router};
+use config::Config;
+use device_pairing::DevicePairStore;
+use metrics::RequestMetrics;
 use relay::RelayHub;
 use rtc_session::RtcSessionStore;
 use session_store::SessionStore;
 use session_verify::SessionVerifyCache;
+use supervisor::{spawn_supervised, TaskRestarts};
 use voice_session::VoiceSessionStore;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tower_governor::governor::GovernorConfigBuilder;
-use tower_http::cors::CorsLayer;
 
+/// Whether the governor layers below are applied. They are commented out
+/// while testing behind the nginx proxy; flip this back when they return.
+const RATE_LIMITING_ENABLED: bool = false;
 
 /// Shared state accessible by all route handlers.
 #[derive(Clone)]
 pub struct AppState {
     pub sessions: SessionStore,
     pub relay: RelayHub,
+    pub device_pairs: DevicePairStore,
     pub rtc_sessions: RtcSessionStore,
     pub session_verify_cache: SessionVerifyCache,
     pub voice_sessions: VoiceSessionStore,
+    pub request_metrics: Arc<RequestMetrics>,
+    pub task_restarts: Arc<TaskRestarts>,
+    /// Bearer token for `/api/admin/*`; admin endpoints are disabled when unset.
+    pub admin_token: Option<Arc<str>>,
 }
 
 #[tokio::main]
@@ -42,92 +65,206 @@ async fn main() {
 
     tracing::info!("Starting Astation server...");
 
+    let config = Config::from_env().unwrap_or_else(|e| panic!("Invalid configuration: {}", e));
+    tracing::info!("Configuration:\n{}", config);
+
     // Initialize stores
-    let sessions = SessionStore::new();
-    let relay = RelayHub::new();
-    let rtc_sessions = RtcSessionStore::new();
+    let sessions = SessionStore::new()
+        .with_record_client_ip(config.record_client_ip)
+        .with_trust_proxy(config.trust_proxy)
+        .with_max_pending_per_hostname(config.max_pending_per_hostname)
+        .with_otp_hmac_key(config.otp_hmac_key.as_deref());
+    if config.otp_hmac_key.is_some() {
+        tracing::info!("Auth session OTPs are HMAC-signed (OTP_HMAC_KEY)");
+    }
+    if !config.record_client_ip {
+        tracing::info!("Client IP recording disabled for auth sessions");
+    }
+    let relay = RelayHub::new()
+        .with_config(config.relay_config())
+        .with_buffer_size(config.relay_buffer_size);
+    let device_pairs = DevicePairStore::new().with_ttl(chrono::Duration::days(config.device_pair_ttl_days));
+    if let Some(path) = &config.device_pair_persist_path {
+        match device_pairs.load(path).await {
+            Ok(restored) => tracing::info!(
+                "Restored {} device pairings from {}",
+                restored,
+                path.display()
+            ),
+            Err(e) => tracing::warn!("Could not restore device pairings from {}: {}", path.display(), e),
+        }
+    }
+    let rtc_sessions = RtcSessionStore::new()
+        .with_app_certificate(config.agora_app_certificate.clone())
+        .with_max_sessions(config.rtc_max_sessions)
+        .with_max_idle(chrono::Duration::hours(config.rtc_max_idle_hours))
+        .with_webhook_url(config.rtc_webhook_url.clone())
+        .with_webhook_allowed_hosts(config.rtc_webhook_allowed_hosts.clone())
+        .with_max_sessions_per_app_id(config.max_sessions_per_app_id)
+        .with_enforce_unique_names(config.rtc_enforce_unique_names);
+    if rtc_sessions.can_generate_tokens() {
+        tracing::info!("Server-side RTC token generation enabled");
+    }
+    if let Some(path) = config.rtc_persist_path.clone() {
+        match rtc_persist::load(&rtc_sessions, &path).await {
+            Ok(restored) => tracing::info!(
+                "Restored {} RTC sessions from {}",
+                restored,
+                path.display()
+            ),
+            Err(e) => tracing::warn!("Could not restore RTC sessions from {}: {}", path.display(), e),
+        }
+    }
     let session_verify_cache = SessionVerifyCache::new();
-    let voice_sessions = VoiceSessionStore::new();
+    if let Some(path) = &config.verify_cache_persist_path {
+        match session_verify_cache.load(path).await {
+            Ok(loaded) => tracing::info!(
+                "Loaded {} session verify cache entries from {}",
+                loaded,
+                path.display()
+            ),
+            Err(e) => tracing::warn!("Could not load session verify cache from {}: {}", path.display(), e),
+        }
+    }
+    let voice_sessions = VoiceSessionStore::new()
+        .with_max_sessions_per_atem(config.max_voice_sessions_per_atem)
+        .with_dedup_window(config.llm_dedup_window)
+        .with_overlap_words(config.dedup_overlap_words)
+        .with_health_thresholds(config.llm_health_stuck_threshold, config.llm_health_stuck_secs);
+    let request_metrics = Arc::new(RequestMetrics::default());
+    let admin_token: Option<Arc<str>> = config.admin_token.as_deref().map(Into::into);
+    if admin_token.is_none() {
+        tracing::info!("ADMIN_TOKEN not set - admin API disabled");
+    }
+    let cleanup_interval = std::time::Duration::from_secs(config.cleanup_interval_secs);
+    let verify_cache_cleanup_interval = std::time::Duration::from_secs(config.verify_cache_cleanup_interval_secs);
+
+    // Background cleanup tasks are restarted if they exit or panic
+    let task_restarts = Arc::new(TaskRestarts::default());
 
     // Spawn background cleanup for expired sessions
     let cleanup_sessions = sessions.clone();
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
-        loop {
-            interval.tick().await;
-            cleanup_sessions.cleanup_expired().await;
-            tracing::debug!("Cleaned up expired sessions");
+    spawn_supervised(&task_restarts, "session_cleanup", move || {
+        let cleanup_sessions = cleanup_sessions.clone();
+        async move {
+            let mut interval = tokio::time::interval(cleanup_interval);
+            loop {
+                interval.tick().await;
+                cleanup_sessions.cleanup_expired().await;
+                tracing::debug!("Cleaned up expired sessions");
+            }
         }
     });
 
+    if let Some(path) = config.rtc_persist_path.clone() {
+        rtc_persist::spawn_persister(
+            &task_restarts,
+            rtc_sessions.clone(),
+            path,
+            std::time::Duration::from_secs(5),
+        );
+    }
+
+    // Snapshot device pairings whenever they change
+    if let Some(path) = config.device_pair_persist_path.clone() {
+        device_pairing::spawn_persister(
+            &task_restarts,
+            device_pairs.clone(),
+            path,
+            std::time::Duration::from_secs(5),
+        );
+    }
+
     // Spawn background cleanup for expired pair rooms
     let cleanup_relay = relay.clone();
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
-        loop {
-            interval.tick().await;
-            cleanup_relay.cleanup_expired().await;
-            tracing::debug!("Cleaned up expired pair rooms");
+    spawn_supervised(&task_restarts, "pair_room_cleanup", move || {
+        let cleanup_relay = cleanup_relay.clone();
+        async move {
+            let mut interval = tokio::time::interval(cleanup_interval);
+            loop {
+                interval.tick().await;
+                cleanup_relay.cleanup_expired().await;
+                tracing::debug!("Cleaned up expired pair rooms");
+            }
+        }
+    });
+
+    // Spawn background cleanup for expired device pairings
+    let cleanup_device_pairs = device_pairs.clone();
+    spawn_supervised(&task_restarts, "device_pair_cleanup", move || {
+        let cleanup_device_pairs = cleanup_device_pairs.clone();
+        async move {
+            let mut interval = tokio::time::interval(cleanup_interval);
+            loop {
+                interval.tick().await;
+                let removed = cleanup_device_pairs.cleanup_expired().await;
+                tracing::debug!("Cleaned up {} expired device pairings", removed);
+            }
         }
     });
 
     // Spawn background cleanup for expired RTC sessions
     let cleanup_rtc = rtc_sessions.clone();
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
-        loop {
-            interval.tick().await;
-            cleanup_rtc.cleanup_expired().await;
-            tracing::debug!("Cleaned up expired RTC sessions");
+    let cleanup_rtc_relay = relay.clone();
+    spawn_supervised(&task_restarts, "rtc_session_cleanup", move || {
+        let cleanup_rtc = cleanup_rtc.clone();
+        let cleanup_rtc_relay = cleanup_rtc_relay.clone();
+        async move {
+            let mut interval = tokio::time::interval(cleanup_interval);
+            loop {
+                interval.tick().await;
+                for session in cleanup_rtc.cleanup_expired().await {
+                    rtc_session::notify_pair_room(&cleanup_rtc_relay, &session, "rtc_session_expired").await;
+                }
+                tracing::debug!("Cleaned up expired RTC sessions");
+            }
         }
     });
 
     // Spawn background cleanup for session verify cache
     let cleanup_verify = session_verify_cache.clone();
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(300)); // 5 minutes
-        loop {
-            interval.tick().await;
-            cleanup_verify.cleanup_expired().await;
+    spawn_supervised(&task_restarts, "verify_cache_cleanup", move || {
+        let cleanup_verify = cleanup_verify.clone();
+        async move {
+            let mut interval = tokio::time::interval(verify_cache_cleanup_interval);
+            loop {
+                interval.tick().await;
+                cleanup_verify.cleanup_expired().await;
+            }
         }
     });
 
     // Spawn background cleanup for expired voice sessions
     let cleanup_voice = voice_sessions.clone();
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
-        loop {
-            interval.tick().await;
-            cleanup_voice.cleanup_expired().await;
-            tracing::debug!("Cleaned up expired voice sessions");
+    spawn_supervised(&task_restarts, "voice_session_cleanup", move || {
+        let cleanup_voice = cleanup_voice.clone();
+        async move {
+            let mut interval = tokio::time::interval(cleanup_interval);
+            loop {
+                interval.tick().await;
+                cleanup_voice.cleanup_expired().await;
+                tracing::debug!("Cleaned up expired voice sessions");
+            }
         }
     });
 
     let state = AppState {
         sessions,
         relay,
+        device_pairs,
         rtc_sessions,
-        session_verify_cache,
+        session_verify_cache: session_verify_cache.clone(),
         voice_sessions,
+        request_metrics: request_metrics.clone(),
+        task_restarts,
+        admin_token,
     };
+    let shutdown_relay = state.relay.clone();
+    let shutdown_rtc_sessions = state.rtc_sessions.clone();
+    let shutdown_device_pairs = state.device_pairs.clone();
 
-    // Configure CORS - Allow specific origin or default to localhost for development
-    let allowed_origin = std::env::var("CORS_ORIGIN")
-        .unwrap_or_else(|_| "https://station.agora.build".to_string());
-
-    let cors = if allowed_origin == "*" {
-        // Development mode: allow all origins
-        tracing::warn!("CORS configured to allow ALL origins - only use in development!");
-        CorsLayer::permissive()
-    } else {
-        // Production mode: whitelist specific domain
-        tracing::info!("CORS configured to allow origin: {}", allowed_origin);
-        CorsLayer::new()
-            .allow_origin(allowed_origin.parse::<HeaderValue>().expect("Invalid CORS_ORIGIN"))
-            .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::OPTIONS])
-            .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
-            .allow_credentials(true)
-    };
+    // Configure CORS - comma-separated list of allowed origins, or * for development
+    let cors = cors::cors_layer(&config.cors_origin).unwrap_or_else(|e| panic!("{}", e));
 
     // Configure rate limiting
     // OTP/grant endpoints: 60 requests per minute per IP (strict)
@@ -168,6 +305,10 @@ async fn main() {
             "/api/sessions/:id/status",
             get(routes::get_session_status_handler),
         )
+        .route(
+            "/api/sessions/:id/events",
+            get(routes::session_events_handler),
+        )
         .route(
             "/api/sessions/:id/deny",
             post(routes::deny_session_handler),
@@ -186,6 +327,46 @@ async fn main() {
             "/api/rtc-sessions/:id/join",
             post(rtc_session::join_rtc_session_handler),
         )
+        .route(
+            "/api/rtc-sessions/:id/participants",
+            get(rtc_session::get_participants_handler),
+        )
+        .route(
+            "/api/rtc-sessions/:id/participants/:uid",
+            delete(rtc_session::remove_participant_handler),
+        )
+        .route(
+            "/api/rtc-sessions/:id/participants/:uid/capabilities",
+            put(rtc_session::update_capabilities_handler),
+        )
+        .route(
+            "/api/rtc-sessions/:id/transfer-host",
+            post(rtc_session::transfer_host_handler),
+        )
+        .route(
+            "/api/rtc-sessions/:id/lock",
+            post(rtc_session::lock_rtc_session_handler),
+        )
+        .route(
+            "/api/rtc-sessions/:id/host-heartbeat",
+            post(rtc_session::host_heartbeat_handler),
+        )
+        .route(
+            "/api/rtc-sessions/:id/recording",
+            get(rtc_session::get_recording_handler),
+        )
+        .route(
+            "/api/rtc-sessions/:id/recording/start",
+            post(rtc_session::start_recording_handler),
+        )
+        .route(
+            "/api/rtc-sessions/:id/recording/stop",
+            post(rtc_session::stop_recording_handler),
+        )
+        .route(
+            "/api/rtc-sessions/:id/participants/:uid/role",
+            post(rtc_session::set_participant_role_handler),
+        )
         // Voice Session API routes
         .route(
             "/api/voice-sessions",
@@ -197,10 +378,22 @@ async fn main() {
             get(voice_routes::get_voice_session_handler)
                 .delete(voice_routes::delete_voice_session_handler),
         )
+        .route(
+            "/api/voice-sessions/:id/buffer",
+            get(voice_routes::get_voice_buffer_handler),
+        )
         .route(
             "/api/voice-sessions/:id/trigger",
             post(voice_routes::trigger_voice_session_handler),
         )
+        .route(
+            "/api/voice-sessions/:id/reset",
+            post(voice_routes::reset_voice_session_handler),
+        )
+        .route(
+            "/api/voice-sessions/:id/system-prompt",
+            put(voice_routes::set_system_prompt_handler),
+        )
         .route(
             "/api/voice-sessions/response",
             post(voice_routes::atem_response_handler),
@@ -210,42 +403,171 @@ async fn main() {
             "/api/llm/chat",
             post(llm_proxy::llm_chat_handler),
         )
+        .route("/api/llm/health", get(llm_proxy::llm_health_handler))
         // Relay API routes
         .route("/api/pair", post(relay::create_pair_handler))
-        .route("/api/pair/:code", get(relay::pair_status_handler));
+        .route("/api/pair/resume", post(device_pairing::resume_pair_handler))
+        .route("/api/pair/persisted/:id", delete(device_pairing::revoke_pair_handler))
+        .route("/api/pair/:code/persist", post(device_pairing::persist_pair_handler))
+        .route("/api/pair/:code/renew", post(relay::renew_pair_handler))
+        .route(
+            "/api/pair/:code",
+            get(relay::pair_status_handler).delete(relay::delete_pair_handler),
+        )
+        // Admin API routes (require ADMIN_TOKEN)
+        .route("/api/admin/sessions", get(routes::list_sessions_admin_handler))
+        .route("/api/admin/stats", get(admin::admin_stats_handler))
+        .route("/api/admin/cleanup", post(admin::admin_cleanup_handler))
+        .route("/api/admin/relay/stats", get(relay::relay_stats_handler))
+        .route("/api/admin/pair-rooms", get(relay::list_pair_rooms_admin_handler))
+        .route("/api/relay/rooms/events", get(relay::room_events_handler))
+        .route("/api/admin/pair-rooms/:code", get(relay::get_pair_room_admin_handler))
+        .route(
+            "/api/admin/pair-rooms/:code/events",
+            get(relay::pair_room_history_admin_handler),
+        )
+        .route(
+            "/api/admin/rtc-sessions/snapshot",
+            get(rtc_session::rtc_sessions_snapshot_admin_handler),
+        )
+        .route(
+            "/api/admin/verify-cache/:session_id",
+            get(session_verify::get_verify_cache_entry_handler),
+        )
+        .route("/api/admin/verify-cache", delete(session_verify::invalidate_all_handler))
+        .route(
+            "/api/admin/verify-cache/astation/:id",
+            delete(session_verify::invalidate_by_astation_handler),
+        )
+        .route(
+            "/api/admin/voice-sessions/token-usage",
+            get(voice_routes::voice_token_usage_admin_handler),
+        )
+        .route(
+            "/api/admin/metrics/requests",
+            get(metrics::get_request_metrics_handler)
+                .delete(metrics::reset_request_metrics_handler),
+        );
         // Rate limiting temporarily disabled for local testing with nginx proxy
         // .layer(GovernorLayer {
         //     config: governor_conf_general.clone(),
         // });
 
     // Combine all routes
-    let app = Router::new()
-        .merge(auth_routes)
-        .merge(general_routes)
+    // Body size limit covers the API; the WebSocket upgrade and pages have no body
+    let api_routes = Router::new().merge(auth_routes).merge(general_routes);
+    let app = body_limit::limit_request_bodies(api_routes, config.max_body_bytes)
         .route("/ws", get(relay::ws_handler))
         .route("/pair", get(relay::pair_page_handler))
+        .route("/pair/qr", get(relay::pair_qr_handler))
         .route("/auth", get(routes::auth_page_handler))
+        .route("/health", get(supervisor::health_handler))
+        .route("/api/version", get(version::version_handler))
+        .layer(middleware::from_fn_with_state(
+            request_metrics,
+            metrics::track_requests,
+        ))
         .layer(cors)
         .with_state(state);
 
-    tracing::info!("Rate limiting configured:");
-    tracing::info!("  - OTP validation: 60 requests/min per IP (burst: 10)");
-    tracing::info!("  - General API: 600 requests/min per IP (burst: 20)");
-
-    // Read port from PORT env var (default 3000)
-    let port: u16 = std::env::var("PORT")
-        .ok()
-        .and_then(|p| p.parse().ok())
-        .unwrap_or(3000);
-
-    let addr = format!("0.0.0.0:{}", port);
+    let addr = format!("0.0.0.0:{}", config.port);
     let listener = tokio::net::TcpListener::bind(&addr)
         .await
         .unwrap_or_else(|_| panic!("Failed to bind to {}", addr));
 
+    print_startup_banner(&config);
     tracing::info!("Astation server listening on http://{}", addr);
 
-    axum::serve(listener, app)
-        .await
-        .expect("Server error");
+    // Open connections get SHUTDOWN_TIMEOUT_SECS to finish once a shutdown
+    // signal arrives; relay WebSockets are told why with a server_shutdown close
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+    let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            let closed = shutdown_relay
+                .close_all_connections(relay::CloseReason::ServerShutdown)
+                .await;
+            tracing::info!("Closing {} relay WebSocket(s)", closed);
+            let _ = shutdown_tx.send(());
+        });
+    let shutdown_timeout = std::time::Duration::from_secs(config.shutdown_timeout_secs);
+    tokio::select! {
+        result = server => result.expect("Server error"),
+        _ = async {
+            if shutdown_rx.await.is_ok() {
+                tokio::time::sleep(shutdown_timeout).await;
+            } else {
+                std::future::pending::<()>().await;
+            }
+        } => tracing::warn!("Connections still open after {:?}, shutting down anyway", shutdown_timeout),
+    }
+
+    // Flush RTC sessions changed since the persister's last tick
+    if let Some(path) = &config.rtc_persist_path {
+        match rtc_persist::save(&shutdown_rtc_sessions, path).await {
+            Ok(()) => tracing::info!("Saved RTC sessions to {}", path.display()),
+            Err(e) => tracing::warn!("Could not save RTC sessions to {}: {}", path.display(), e),
+        }
+    }
+
+    if let Some(path) = &config.device_pair_persist_path {
+        match shutdown_device_pairs.save(path).await {
+            Ok(saved) => tracing::info!("Saved {} device pairings to {}", saved, path.display()),
+            Err(e) => tracing::warn!("Could not save device pairings to {}: {}", path.display(), e),
+        }
+    }
+
+    if let Some(path) = &config.verify_cache_persist_path {
+        match session_verify_cache.save(path).await {
+            Ok(saved) => tracing::info!(
+                "Saved {} session verify cache entries to {}",
+                saved,
+                path.display()
+            ),
+            Err(e) => tracing::warn!("Could not save session verify cache to {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Log what is running and how it is set up, once the listener is bound.
+fn print_startup_banner(config: &Config) {
+    tracing::info!(
+        binary = version::BINARY_NAME,
+        version = version::VERSION,
+        build_timestamp = %version::build_timestamp(),
+        address = %format!("0.0.0.0:{}", config.port),
+        cors_origin = %config.cors_origin,
+        rate_limiting = RATE_LIMITING_ENABLED,
+        admin_token = config.admin_token.is_some(),
+        cleanup_interval_secs = config.cleanup_interval_secs,
+        verify_cache_cleanup_interval_secs = config.verify_cache_cleanup_interval_secs,
+        "{} {}",
+        version::BINARY_NAME,
+        version::VERSION,
+    );
+}
+
+/// Resolve on Ctrl+C or SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    tracing::info!("Shutdown signal received");
 }
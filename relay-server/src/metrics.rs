@@ -0,0 +1,346 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use dashmap::DashMap;
+use serde::Serialize;
+
+use crate::admin::require_admin;
+use crate::AppState;
+
+/// Key used for requests that did not match any route, so that random
+/// URLs cannot grow the map without bound.
+const UNMATCHED_PATH: &str = "<unmatched>";
+
+/// Key prefix for requests with a non-standard method, for the same reason.
+const OTHER_METHOD: &str = "OTHER";
+
+/// Per-route request counters, keyed by `"METHOD /route/:pattern"`.
+#[derive(Default)]
+pub struct RequestMetrics {
+    paths: DashMap<String, PathCounters>,
+}
+
+#[derive(Default)]
+struct PathCounters {
+    total: AtomicU64,
+    ok: AtomicU64,
+    client_errors: AtomicU64,
+    server_errors: AtomicU64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PathMetrics {
+    pub total: u64,
+    pub ok: u64,
+    #[serde(rename = "4xx")]
+    pub client_errors: u64,
+    #[serde(rename = "5xx")]
+    pub server_errors: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RequestMetricsResponse {
+    pub paths: BTreeMap<String, PathMetrics>,
+}
+
+impl RequestMetrics {
+    /// Count a request as received.
+    pub fn record_request(&self, key: &str) {
+        // Fast path: existing keys only take a shard read lock
+        if let Some(c) = self.paths.get(key) {
+            c.total.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.paths
+            .entry(key.to_string())
+            .or_default()
+            .total
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record the response status of a request previously counted.
+    /// Requests in flight across a reset are not recorded.
+    pub fn record_response(&self, key: &str, status: StatusCode) {
+        if let Some(c) = self.paths.get(key) {
+            let counter = if status.is_server_error() {
+                &c.server_errors
+            } else if status.is_client_error() {
+                &c.client_errors
+            } else {
+                &c.ok
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Snapshot all counters.
+    pub fn snapshot(&self) -> BTreeMap<String, PathMetrics> {
+        self.paths
+            .iter()
+            .map(|entry| {
+                let c = entry.value();
+                (
+                    entry.key().clone(),
+                    PathMetrics {
+                        total: c.total.load(Ordering::Relaxed),
+                        ok: c.ok.load(Ordering::Relaxed),
+                        client_errors: c.client_errors.load(Ordering::Relaxed),
+                        server_errors: c.server_errors.load(Ordering::Relaxed),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Drop all counters.
+    pub fn reset(&self) {
+        self.paths.clear();
+    }
+}
+
+/// Middleware recording every request and its response status in
+/// [`RequestMetrics`]. Must be added with `Router::layer` so the matched
+/// route pattern is available.
+pub async fn track_requests(
+    State(metrics): State<Arc<RequestMetrics>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str())
+        .unwrap_or(UNMATCHED_PATH);
+    let method = match *req.method() {
+        Method::GET
+        | Method::POST
+        | Method::PUT
+        | Method::DELETE
+        | Method::PATCH
+        | Method::HEAD
+        | Method::OPTIONS
+        | Method::CONNECT
+        | Method::TRACE => req.method().as_str(),
+        _ => OTHER_METHOD,
+    };
+    let key = format!("{} {}", method, path);
+
+    metrics.record_request(&key);
+    let response = next.run(req).await;
+    metrics.record_response(&key, response.status());
+    response
+}
+
+/// GET /api/admin/metrics/requests
+///
+/// Per-route request counts since startup or the last reset.
+pub async fn get_request_metrics_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<RequestMetricsResponse>, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&state, &headers)?;
+    Ok(Json(RequestMetricsResponse {
+        paths: state.request_metrics.snapshot(),
+    }))
+}
+
+/// DELETE /api/admin/metrics/requests
+///
+/// Reset all request counters.
+pub async fn reset_request_metrics_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&state, &headers)?;
+    state.request_metrics.reset();
+    tracing::info!("Request metrics reset");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relay::RelayHub;
+    use crate::rtc_session::RtcSessionStore;
+    use crate::session_store::SessionStore;
+    use crate::session_verify::SessionVerifyCache;
+    use crate::voice_session::VoiceSessionStore;
+    use axum::body::Body;
+    use axum::http::Request as HttpRequest;
+    use axum::middleware;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    fn create_test_state() -> AppState {
+        AppState {
+            sessions: SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: Some("admin-secret".into()),
+        }
+    }
+
+    fn app(state: AppState) -> Router {
+        Router::new()
+            .route("/items/:id", get(|| async { "ok" }))
+            .route(
+                "/broken",
+                get(|| async { StatusCode::INTERNAL_SERVER_ERROR }),
+            )
+            .route(
+                "/api/admin/metrics/requests",
+                get(get_request_metrics_handler).delete(reset_request_metrics_handler),
+            )
+            .layer(middleware::from_fn_with_state(
+                state.request_metrics.clone(),
+                track_requests,
+            ))
+            .with_state(state)
+    }
+
+    async fn send(app: &Router, method: &str, uri: &str) -> StatusCode {
+        app.clone()
+            .oneshot(
+                HttpRequest::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("authorization", "Bearer admin-secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn test_record_classifies_statuses() {
+        let metrics = RequestMetrics::default();
+        for status in [StatusCode::OK, StatusCode::NOT_FOUND, StatusCode::BAD_GATEWAY] {
+            metrics.record_request("GET /x");
+            metrics.record_response("GET /x", status);
+        }
+        metrics.record_request("GET /x"); // still in flight
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(
+            snapshot["GET /x"],
+            PathMetrics {
+                total: 4,
+                ok: 1,
+                client_errors: 1,
+                server_errors: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_middleware_counts_by_route_pattern() {
+        let state = create_test_state();
+        let app = app(state.clone());
+
+        send(&app, "GET", "/items/1").await;
+        send(&app, "GET", "/items/2").await;
+        send(&app, "GET", "/items/3").await;
+        send(&app, "GET", "/broken").await;
+        send(&app, "GET", "/nope/a").await;
+        send(&app, "GET", "/nope/b").await;
+
+        let snapshot = state.request_metrics.snapshot();
+        assert_eq!(snapshot["GET /items/:id"].total, 3);
+        assert_eq!(snapshot["GET /items/:id"].ok, 3);
+        assert_eq!(snapshot["GET /broken"].server_errors, 1);
+        assert_eq!(snapshot["GET <unmatched>"].total, 2);
+        assert_eq!(snapshot["GET <unmatched>"].client_errors, 2);
+
+        // Made-up methods share one key
+        send(&app, "FROB", "/items/1").await;
+        send(&app, "ZAP", "/items/1").await;
+        let snapshot = state.request_metrics.snapshot();
+        assert_eq!(snapshot["OTHER /items/:id"].total, 2);
+        assert!(!snapshot.keys().any(|key| key.starts_with("FROB") || key.starts_with("ZAP")));
+    }
+
+    #[tokio::test]
+    async fn test_get_metrics_endpoint() {
+        let state = create_test_state();
+        let app = app(state);
+
+        send(&app, "GET", "/items/1").await;
+        send(&app, "GET", "/items/2").await;
+
+        let resp = app
+            .clone()
+            .oneshot(
+                HttpRequest::builder()
+                    .uri("/api/admin/metrics/requests")
+                    .header("authorization", "Bearer admin-secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let items = &json["paths"]["GET /items/:id"];
+        assert_eq!(items["total"], 2);
+        assert_eq!(items["ok"], 2);
+        assert_eq!(items["4xx"], 0);
+        assert_eq!(items["5xx"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_reset_endpoint_clears_counters() {
+        let state = create_test_state();
+        let app = app(state.clone());
+
+        send(&app, "GET", "/items/1").await;
+        assert_eq!(
+            send(&app, "DELETE", "/api/admin/metrics/requests").await,
+            StatusCode::NO_CONTENT
+        );
+
+        let snapshot = state.request_metrics.snapshot();
+        assert!(!snapshot.contains_key("GET /items/:id"));
+        // The reset request itself was in flight across the reset
+        assert!(!snapshot.contains_key("DELETE /api/admin/metrics/requests"));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoints_require_admin_token() {
+        let state = create_test_state();
+        let app = app(state.clone());
+        send(&app, "GET", "/items/1").await;
+
+        let resp = app
+            .clone()
+            .oneshot(
+                HttpRequest::builder()
+                    .method("DELETE")
+                    .uri("/api/admin/metrics/requests")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(state.request_metrics.snapshot()["GET /items/:id"].total, 1);
+    }
+}
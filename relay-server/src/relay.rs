@@ -1,1052 +1,6114 @@
 use axum::{
-    extract::{ws::WebSocket, Query, State, WebSocketUpgrade},
-    http::StatusCode,
-    response::{Html, IntoResponse, Json},
+    extract::{
+        ws::{rejection::WebSocketUpgradeRejection, CloseFrame, WebSocket},
+        Path, Query, State, WebSocketUpgrade,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Json, Response,
+    },
 };
+use chrono::{DateTime, Utc};
 use futures_util::{SinkExt, StreamExt};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, RwLock};
 use tokio::time::Instant;
 use validator::Validate;
 
+use crate::admin::require_admin;
+use crate::auth::{bearer_token, constant_time_eq, generate_session_token, HOSTNAME_REGEX};
+use crate::envelope::{self, EnvelopeError, Frame};
+use crate::web::{l10n, theme};
 use crate::AppState;
 
 // Characters for pairing codes — no ambiguous chars (0/O, 1/I/L excluded)
 const CODE_CHARS: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
 
-/// Room expiry: 10 minutes if unpaired.
-const ROOM_EXPIRY_SECS: u64 = 600;
+/// Default pairing code length, hyphen excluded (`PAIR_CODE_LENGTH`).
+pub const DEFAULT_CODE_LENGTH: usize = 8;
+
+/// Bounds accepted for `PAIR_CODE_LENGTH`.
+pub const MIN_CODE_LENGTH: usize = 6;
+pub const MAX_CODE_LENGTH: usize = 16;
+
+/// Default unpaired room expiry: 10 minutes (`PAIR_ROOM_TTL_SECS`).
+pub const DEFAULT_ROOM_TTL_SECS: u64 = 600;
+
+/// Bounds for a room's own `expires_in_seconds` on `POST /api/pair`.
+pub const MIN_ROOM_EXPIRY_SECS: u64 = 60;
+pub const MAX_ROOM_EXPIRY_SECS: u64 = 86_400;
+
+/// Renewal never keeps an unpaired room past this age.
+pub const MAX_RENEWED_ROOM_AGE_SECS: u64 = 3_600;
+
+/// Without the owner token, a room can only be renewed this close to expiry.
+pub const RENEW_WINDOW_SECS: u64 = 60;
+
+/// Default seconds a room is kept after both sides disconnect (`PAIR_DISCONNECT_GRACE_SECS`).
+pub const DEFAULT_DISCONNECT_GRACE_SECS: u64 = 120;
+
+/// Default number of messages held for a side that is not connected (`RELAY_BUFFER_SIZE`).
+pub const DEFAULT_BUFFER_SIZE: usize = 50;
+
+/// Byte cap on the messages held for one side.
+const MAX_BACKLOG_BYTES: usize = 64 * 1024;
+
+/// Largest serialized room metadata `set_metadata` may leave behind.
+pub const MAX_ROOM_METADATA_BYTES: usize = 4 * 1024;
+
+/// Default interval between server pings on relay WebSockets (`RELAY_PING_INTERVAL_SECS`).
+pub const DEFAULT_PING_INTERVAL_SECS: u64 = 30;
+
+/// Default silence after which a relay WebSocket is closed (`RELAY_IDLE_TIMEOUT_SECS`).
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 90;
+
+/// Ping intervals a connection may go without answering before status stops
+/// counting it as live. Well short of the idle timeout, so a dead astation is
+/// reported long before its socket is closed.
+const LIVENESS_PING_INTERVALS: u32 = 2;
+
+/// Default per-connection outgoing queue depth, in messages (`RELAY_CHANNEL_CAPACITY`).
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 256;
+
+/// Default time a connection's queue may stay full before it is disconnected
+/// (`RELAY_SATURATION_TIMEOUT_SECS`).
+pub const DEFAULT_SATURATION_TIMEOUT_SECS: u64 = 10;
+
+/// Default text frames per second a relay connection may send (`RELAY_MESSAGE_RATE`).
+pub const DEFAULT_MESSAGE_RATE: u32 = 100;
+
+/// Default frames a connection may send at once above the rate (`RELAY_MESSAGE_BURST`).
+pub const DEFAULT_MESSAGE_BURST: u32 = 200;
+
+/// Default time a connection may keep exceeding its rate before it is closed
+/// (`RELAY_RATE_LIMIT_CLOSE_SECS`).
+pub const DEFAULT_RATE_LIMIT_CLOSE_SECS: u64 = 10;
+
+/// Dropped frames less than this far apart belong to one run of flooding.
+const RATE_LIMIT_QUIET: Duration = Duration::from_secs(1);
+
+/// Least time between two `rate_limited` notices to one connection.
+const RATE_LIMIT_NOTICE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a forward waits for room in a full queue before dropping the message.
+const FORWARD_WAIT: Duration = Duration::from_millis(100);
+
+/// How long a closing connection's writer gets to flush its Close frame.
+const CLOSE_FLUSH_WAIT: Duration = Duration::from_secs(1);
+
+/// Relayed frames buffered per observer before it starts missing some.
+const OBSERVER_CAPACITY: usize = 64;
+
+/// Room events buffered per `/api/relay/rooms/events` subscriber before it lags.
+const ROOM_EVENT_CAPACITY: usize = 256;
+
+/// Default seconds between latency probes on each connection (`RELAY_LATENCY_PROBE_SECS`).
+pub const DEFAULT_LATENCY_PROBE_SECS: u64 = 30;
+
+/// Weight of the newest round trip in a role's smoothed latency.
+const LATENCY_EWMA_ALPHA: f64 = 0.2;
+
+/// Keepalive comment interval on the room event stream.
+const ROOM_EVENT_KEEPALIVE: Duration = Duration::from_secs(30);
+
+/// Entries kept in a room's debugging history; the oldest go first.
+const ROOM_HISTORY_CAPACITY: usize = 200;
+
+/// How long a counted history entry keeps absorbing repeats before a new one starts.
+const ROOM_HISTORY_TALLY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Codes tried before room creation gives up with 503.
+const MAX_CODE_ATTEMPTS: usize = 5;
 
 // --- Types ---
 
+/// One side of a pair room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Atem,
+    Astation,
+}
+
+impl Role {
+    pub const ALL: [Role; 2] = [Role::Atem, Role::Astation];
+
+    /// `atem` or `astation`; anything else (including `observer`) is None.
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "atem" => Some(Self::Atem),
+            "astation" => Some(Self::Astation),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Atem => "atem",
+            Self::Astation => "astation",
+        }
+    }
+
+    /// The side opposite this one.
+    pub fn peer(self) -> Self {
+        match self {
+            Self::Atem => Self::Astation,
+            Self::Astation => Self::Atem,
+        }
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 struct PairRoom {
-    #[allow(dead_code)]
     code: String,
     hostname: String,
-    atem_tx: Option<mpsc::UnboundedSender<String>>,
-    astation_tx: Option<mpsc::UnboundedSender<String>>,
+    atem: Option<Connection>,
+    /// Every connected astation (at most one with `multi_astation` off).
+    astations: Vec<Connection>,
     created_at: Instant,
+    /// Seconds the room lives unpaired: `PAIR_ROOM_TTL_SECS`, or the
+    /// creator's `expires_in_seconds`.
+    expiry_secs: u64,
+    /// Seconds added to `expiry_secs` by renewals.
+    extended_secs: u64,
+    /// When atem and an astation were first connected at the same time.
+    paired_at: Option<DateTime<Utc>>,
+    /// Free-form room metadata, set by `control` messages.
+    metadata: serde_json::Map<String, serde_json::Value>,
+    /// When either side last answered a ping.
+    last_pong_at: Option<Instant>,
+    /// Messages astation sent while atem was not connected.
+    atem_backlog: Backlog,
+    /// Messages atem sent while astation was not connected.
+    astation_backlog: Backlog,
+    /// Messages dropped because the receiving side's queue was full.
+    dropped_messages: u64,
+    /// Returned once at creation; required for room management endpoints.
+    owner_token: String,
+    /// SHA-256 of each role's reconnect token, issued on the first pairing.
+    /// Once set, connecting by code needs that role's token.
+    reconnect_token_hashes: HashMap<Role, [u8; 32]>,
+    /// When the last connection left; the room is removed once this is older
+    /// than the disconnect grace period. Cleared by any reconnect.
+    disconnected_at: Option<Instant>,
+    /// Copies of relayed frames for `role=observer` connections. Dropping the
+    /// room closes every observer.
+    observers: broadcast::Sender<String>,
+    /// Both sides sent `e2e_init`: from then on only envelopes and binary
+    /// frames are relayed, and observers see frame sizes instead of contents.
+    /// Never cleared for the life of the room.
+    e2e: bool,
+    /// Sides that have sent `e2e_init`.
+    e2e_confirmed: HashSet<Role>,
+    /// Connections, traffic counts and errors, for `GET /api/admin/pair-rooms/:code/events`.
+    history: RoomHistory,
+    /// Smoothed round-trip time per role in milliseconds, from latency probe acks.
+    rtt_ewma_ms: HashMap<Role, f64>,
 }
 
-#[derive(Clone)]
-pub struct RelayHub {
-    rooms: Arc<RwLock<HashMap<String, PairRoom>>>,
+/// Why the server closed a relay WebSocket, sent as the Close frame's code
+/// and reason. Codes are in the 4000–4999 range reserved for applications:
+///
+/// | Code | Reason                | Sent when                                                        |
+/// |------|-----------------------|------------------------------------------------------------------|
+/// | 4000 | `room_deleted`        | The owner deleted the room (`DELETE /api/pair/:code`)            |
+/// | 4001 | `room_expired`        | The cleanup task removed the room                                |
+/// | 4002 | `connection_replaced` | A newer connection for the same role took over                   |
+/// | 4003 | `idle_timeout`        | Nothing arrived from the client for `RELAY_IDLE_TIMEOUT_SECS`    |
+/// | 4004 | `rate_limited`        | Over `RELAY_MESSAGE_RATE` for `RELAY_RATE_LIMIT_CLOSE_SECS`      |
+/// | 4005 | `queue_saturated`     | Outgoing queue full for `RELAY_SATURATION_TIMEOUT_SECS`          |
+/// | 4006 | `server_shutdown`     | The server is shutting down                                      |
+///
+/// A close the client starts, or a socket that fails, gets no code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    RoomDeleted,
+    RoomExpired,
+    Replaced,
+    IdleTimeout,
+    RateLimited,
+    QueueSaturated,
+    ServerShutdown,
 }
 
-impl RelayHub {
-    pub fn new() -> Self {
-        Self {
-            rooms: Arc::new(RwLock::new(HashMap::new())),
+impl CloseReason {
+    pub const ALL: [CloseReason; 7] = [
+        CloseReason::RoomDeleted,
+        CloseReason::RoomExpired,
+        CloseReason::Replaced,
+        CloseReason::IdleTimeout,
+        CloseReason::RateLimited,
+        CloseReason::QueueSaturated,
+        CloseReason::ServerShutdown,
+    ];
+
+    pub fn code(self) -> u16 {
+        match self {
+            Self::RoomDeleted => 4000,
+            Self::RoomExpired => 4001,
+            Self::Replaced => 4002,
+            Self::IdleTimeout => 4003,
+            Self::RateLimited => 4004,
+            Self::QueueSaturated => 4005,
+            Self::ServerShutdown => 4006,
         }
     }
 
-    /// Remove rooms that are older than ROOM_EXPIRY_SECS and have no astation connected.
-    pub async fn cleanup_expired(&self) {
-        let now = Instant::now();
-        let mut rooms = self.rooms.write().await;
-        rooms.retain(|_, room| {
-            let age = now.duration_since(room.created_at).as_secs();
-            // Keep if not expired, or if astation is connected (actively paired)
-            age < ROOM_EXPIRY_SECS || room.astation_tx.is_some()
-        });
+    pub fn reason(self) -> &'static str {
+        match self {
+            Self::RoomDeleted => "room_deleted",
+            Self::RoomExpired => "room_expired",
+            Self::Replaced => "connection_replaced",
+            Self::IdleTimeout => "idle_timeout",
+            Self::RateLimited => "rate_limited",
+            Self::QueueSaturated => "queue_saturated",
+            Self::ServerShutdown => "server_shutdown",
+        }
     }
-}
 
-impl Default for RelayHub {
-    fn default() -> Self {
-        Self::new()
+    fn frame(self) -> CloseFrame<'static> {
+        CloseFrame {
+            code: self.code(),
+            reason: self.reason().into(),
+        }
     }
 }
 
-/// Generate an 8-char pairing code like "ABCD-EFGH" (no ambiguous chars).
-fn generate_pairing_code() -> String {
-    let mut rng = rand::thread_rng();
-    let chars: Vec<u8> = (0..8)
-        .map(|_| CODE_CHARS[rng.gen_range(0..CODE_CHARS.len())])
-        .collect();
-    let s = String::from_utf8(chars).unwrap();
-    format!("{}-{}", &s[..4], &s[4..])
-}
+/// Shared by a connection's room entry and its writer task. Whatever ends the
+/// connection records why before dropping the room's sender; the writer sends
+/// that as its Close frame once the channel drains. The first reason wins.
+#[derive(Clone, Default)]
+struct CloseSignal(Arc<OnceLock<CloseReason>>);
 
-// --- Request / Response types ---
+impl CloseSignal {
+    fn set(&self, reason: CloseReason) {
+        let _ = self.0.set(reason);
+    }
 
-#[derive(Deserialize, Validate)]
-pub struct CreatePairRequest {
-    #[validate(length(min = 1, max = 255))]
-    pub hostname: String,
-}
+    fn frame(&self) -> Option<CloseFrame<'static>> {
+        self.0.get().map(|reason| reason.frame())
+    }
 
-#[derive(Serialize, Deserialize)]
-pub struct CreatePairResponse {
-    pub code: String,
+    fn reason(&self) -> Option<CloseReason> {
+        self.0.get().copied()
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct PairStatusResponse {
-    pub paired: bool,
-    pub hostname: String,
-}
+/// When a connection's client last sent anything, pongs included. Updated by
+/// its WebSocket task and read by the room's status.
+#[derive(Clone)]
+struct LastSeen(Arc<std::sync::Mutex<Instant>>);
 
-#[derive(Deserialize)]
-pub struct WsQuery {
-    // Pairing-based auth (traditional)
-    pub role: Option<String>,
-    pub code: Option<String>,
-    // Session-based auth (after HTTP auth)
-    pub session: Option<String>,
+impl LastSeen {
+    fn now() -> Self {
+        Self(Arc::new(std::sync::Mutex::new(Instant::now())))
+    }
+
+    fn touch(&self) {
+        *self.0.lock().unwrap() = Instant::now();
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.0.lock().unwrap().elapsed()
+    }
 }
 
-#[derive(Deserialize)]
-pub struct PairPageQuery {
-    pub code: String,
+/// One WebSocket attached to a room. The room holds the only long-lived sender,
+/// so removing the connection ends its writer.
+struct Connection {
+    tx: mpsc::Sender<String>,
+    /// When its queue started refusing messages.
+    saturated_since: Option<Instant>,
+    client: ClientInfo,
+    close: CloseSignal,
+    last_seen: LastSeen,
+    /// Silence after which the connection no longer counts as live
+    /// ([`RelayConfig::stale_after`]).
+    stale_after: Duration,
+    /// Nonce of the latency probe awaiting its ack, and when it was sent.
+    probe: Option<(String, Instant)>,
 }
 
-// --- Handlers ---
+impl Connection {
+    fn new(tx: mpsc::Sender<String>, client: ClientInfo) -> Self {
+        Self {
+            tx,
+            saturated_since: None,
+            client,
+            close: CloseSignal::default(),
+            last_seen: LastSeen::now(),
+            stale_after: RelayConfig::default().stale_after(),
+            probe: None,
+        }
+    }
 
-/// POST /api/pair — Register for pairing, get a code back.
-pub async fn create_pair_handler(
-    State(state): State<AppState>,
-    Json(body): Json<CreatePairRequest>,
-) -> impl IntoResponse {
-    // Validate input
-    if let Err(e) = body.validate() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({"error": format!("Validation error: {}", e)})),
-        )
-            .into_response();
+    /// Why this connection cannot be relied on, if it can't: its receiving
+    /// task is gone, or its client has been silent past `stale_after`.
+    fn stale_reason(&self) -> Option<&'static str> {
+        if self.tx.is_closed() {
+            Some("channel_closed")
+        } else if self.last_seen.elapsed() > self.stale_after {
+            Some("silent")
+        } else {
+            None
+        }
     }
+}
 
-    let hub = &state.relay;
-    let code = generate_pairing_code();
-    let room = PairRoom {
-        code: code.clone(),
-        hostname: body.hostname,
-        atem_tx: None,
-        astation_tx: None,
-        created_at: Instant::now(),
-    };
+pub(crate) fn hash_token(token: &str) -> [u8; 32] {
+    Sha256::digest(token.as_bytes()).into()
+}
 
-    let mut rooms = hub.rooms.write().await;
-    rooms.insert(code.clone(), room);
-    drop(rooms);
+/// Longest `client_version` or `platform` kept from the handshake.
+const MAX_CLIENT_FIELD_CHARS: usize = 64;
 
-    tracing::info!("Pair room created: {}", code);
-    (StatusCode::CREATED, Json(CreatePairResponse { code })).into_response()
+/// What a client said about itself on the WebSocket handshake.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
 }
 
-/// GET /api/pair/:code — Check pairing status.
-pub async fn pair_status_handler(
-    State(state): State<AppState>,
-    axum::extract::Path(code): axum::extract::Path<String>,
-) -> impl IntoResponse {
-    let rooms = state.relay.rooms.read().await;
-    match rooms.get(&code) {
-        Some(room) => {
-            let paired = room.astation_tx.is_some();
-            Ok(Json(PairStatusResponse {
-                paired,
-                hostname: room.hostname.clone(),
-            }))
+impl ClientInfo {
+    fn from_query(params: &WsQuery) -> Self {
+        let clip = |value: &Option<String>| value.as_ref().map(|v| v.chars().take(MAX_CLIENT_FIELD_CHARS).collect());
+        Self {
+            version: clip(&params.client_version),
+            platform: clip(&params.platform),
         }
-        None => Err((
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({"error": "Room not found"})),
-        )),
     }
 }
 
-/// GET /ws — WebSocket upgrade for relay.
-/// Auth methods:
-///   1. Pairing: ?role=atem|astation&code=XXXX (short-lived, explicit approval)
-///   2. Session: ?session=<session_id> (after HTTP auth, longer-lived)
-pub async fn ws_handler(
-    State(state): State<AppState>,
-    Query(params): Query<WsQuery>,
-    ws: WebSocketUpgrade,
-) -> impl IntoResponse {
-    let hub = state.relay.clone();
-
-    // Session-based auth (hybrid flow)
-    if let Some(session_id) = params.session.clone() {
-        // Validate session
-        let session = state.sessions.get(&session_id).await;
-        match session {
-            Some(s) if s.status == crate::auth::SessionStatus::Granted => {
-                // Valid session - use session_id as room code, role defaults to "atem"
-                let code = format!("session-{}", session_id);
-                let role = params.role.clone().unwrap_or_else(|| "atem".to_string());
-
-                // Create room if it doesn't exist
-                {
-                    let mut rooms = hub.rooms.write().await;
-                    if !rooms.contains_key(&code) {
-                        rooms.insert(
-                            code.clone(),
-                            PairRoom {
-                                code: code.clone(),
-                                hostname: s.hostname.clone(),
-                                atem_tx: None,
-                                astation_tx: None,
-                                created_at: Instant::now(),
-                            },
-                        );
-                    }
-                }
+/// Messages held for a side that is not connected, oldest first, capped by
+/// count (`RELAY_BUFFER_SIZE`) and by `MAX_BACKLOG_BYTES`.
+#[derive(Default)]
+struct Backlog {
+    messages: VecDeque<String>,
+    bytes: usize,
+}
 
-                return ws.on_upgrade(move |socket| handle_ws(hub, code, role, socket))
-                    .into_response();
-            }
-            _ => {
-                return (StatusCode::UNAUTHORIZED, "Invalid or expired session").into_response();
-            }
+impl Backlog {
+    /// Append `message`, evicting the oldest messages beyond either cap.
+    fn push(&mut self, message: String, max_messages: usize) {
+        self.bytes += message.len();
+        self.messages.push_back(message);
+        while self.messages.len() > max_messages || self.bytes > MAX_BACKLOG_BYTES {
+            let Some(evicted) = self.messages.pop_front() else {
+                break;
+            };
+            self.bytes -= evicted.len();
         }
     }
 
-    // Pairing-based auth (traditional flow)
-    let code = match params.code.clone() {
-        Some(c) => c,
-        None => return (StatusCode::BAD_REQUEST, "Missing code or session parameter").into_response(),
-    };
-    let role = match params.role.clone() {
-        Some(r) => r,
-        None => return (StatusCode::BAD_REQUEST, "Missing role parameter").into_response(),
-    };
+    fn take(&mut self) -> VecDeque<String> {
+        self.bytes = 0;
+        std::mem::take(&mut self.messages)
+    }
 
-    // Verify room exists
-    {
-        let rooms = hub.rooms.read().await;
-        if !rooms.contains_key(&code) {
-            return (
-                StatusCode::NOT_FOUND,
-                "Room not found",
-            )
-                .into_response();
-        }
+    fn len(&self) -> usize {
+        self.messages.len()
     }
+}
 
-    ws.on_upgrade(move |socket| handle_ws(hub, code, role, socket))
-        .into_response()
+/// Relay envelope, tagged by a top-level `"relay"` key.
+///
+/// `text` and `binary` are forwarded to the other side; `control`, `ping` and
+/// `pong` are handled by the relay itself. Messages without a `"relay"` key
+/// are application messages and are forwarded untouched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "relay", rename_all = "snake_case")]
+pub enum RelayMessage {
+    Text {
+        content: String,
+    },
+    Control {
+        action: String,
+        #[serde(default)]
+        payload: serde_json::Value,
+    },
+    Binary {
+        base64: String,
+    },
+    Ping {
+        id: u64,
+    },
+    Pong {
+        id: u64,
+    },
 }
 
-async fn handle_ws(hub: RelayHub, code: String, role: String, socket: WebSocket) {
-    let (mut ws_sink, mut ws_stream) = socket.split();
-    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+/// How an incoming frame should be handled.
+#[derive(Debug, PartialEq)]
+enum Incoming {
+    Envelope(RelayMessage),
+    /// Has a `"relay"` tag the server does not understand.
+    Unrecognized,
+    /// Plain application message (no envelope).
+    Passthrough,
+    /// `{"type":"e2e_init"}`: end-to-end key exchange, relayed but never
+    /// logged or shown to observers.
+    E2eInit,
+    /// `{"type":"latency_probe_ack"}`: answers the server's probe, never relayed.
+    ProbeAck { nonce: Option<String> },
+}
 
-    // Register this side's sender in the room
-    let _other_tx = {
-        let mut rooms = hub.rooms.write().await;
-        let room = match rooms.get_mut(&code) {
-            Some(r) => r,
-            None => {
-                tracing::warn!("Room {} disappeared before WS setup", code);
-                return;
-            }
+impl Incoming {
+    fn parse(text: &str) -> Self {
+        let value: serde_json::Value = match serde_json::from_str(text) {
+            Ok(value) => value,
+            Err(_) => return Incoming::Passthrough,
         };
-
-        match role.as_str() {
-            "atem" => {
-                room.atem_tx = Some(tx.clone());
-                room.astation_tx.clone()
-            }
-            "astation" => {
-                room.astation_tx = Some(tx.clone());
-                room.atem_tx.clone()
-            }
-            _ => {
-                tracing::warn!("Unknown role: {}", role);
-                return;
+        if value.get("relay").is_none() {
+            match value.get("type").and_then(serde_json::Value::as_str) {
+                Some(envelope::E2E_INIT) => return Incoming::E2eInit,
+                Some(envelope::LATENCY_PROBE_ACK) => {
+                    let nonce = value.pointer("/payload/nonce").and_then(serde_json::Value::as_str);
+                    return Incoming::ProbeAck { nonce: nonce.map(str::to_string) };
+                }
+                _ => return Incoming::Passthrough,
             }
         }
-    };
-
-    tracing::info!("WS connected: role={} code={}", role, code);
-
-    // Task: forward messages from our channel to the WS sink
-    let code_for_writer = code.clone();
-    let write_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            if ws_sink
-                .send(axum::extract::ws::Message::Text(msg.into()))
-                .await
-                .is_err()
-            {
-                tracing::debug!("WS write failed for {}", code_for_writer);
-                break;
-            }
+        match serde_json::from_value(value) {
+            Ok(message) => Incoming::Envelope(message),
+            Err(_) => Incoming::Unrecognized,
         }
-    });
+    }
+}
 
-    // Read incoming frames and forward to the other side
-    let hub_for_read = hub.clone();
-    let role_for_read = role.clone();
-    let code_for_read = code.clone();
-    while let Some(msg_result) = ws_stream.next().await {
-        match msg_result {
-            Ok(axum::extract::ws::Message::Text(text)) => {
-                // Get the other side's sender from the room (it may have connected since we started)
-                let other = {
-                    let rooms = hub_for_read.rooms.read().await;
-                    rooms.get(&code_for_read).and_then(|room| {
-                        match role_for_read.as_str() {
-                            "atem" => room.astation_tx.clone(),
-                            "astation" => room.atem_tx.clone(),
-                            _ => None,
-                        }
-                    })
-                };
+/// Pairing settings, read once at startup.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RelayConfig {
+    /// Characters in a pairing code, hyphen excluded.
+    pub code_length: usize,
+    /// Seconds an unpaired room lives before `cleanup_expired` removes it.
+    pub room_ttl_secs: u64,
+    /// Refuse a second connection for a connected role with 409 instead of
+    /// letting it take over (`RELAY_REJECT_DUPLICATE_ROLES`).
+    pub reject_duplicate_roles: bool,
+    /// How often the server pings each relay WebSocket.
+    pub ping_interval: Duration,
+    /// A connection that sends nothing (not even a pong) for this long is closed
+    /// and removed from its room.
+    pub idle_timeout: Duration,
+    /// Messages queued per connection before forwards start waiting and dropping.
+    pub channel_capacity: usize,
+    /// A connection whose queue stays full this long is disconnected.
+    pub saturation_timeout: Duration,
+    /// Let several astations share a room, each receiving every atem message
+    /// (`PAIR_MULTI_ASTATION`). Off restores one connection per role.
+    pub multi_astation: bool,
+    /// How long a room outlives the moment both sides disconnected
+    /// (`PAIR_DISCONNECT_GRACE_SECS`).
+    pub disconnect_grace: Duration,
+    /// Text frames per second each connection may send; 0 disables the limit.
+    pub message_rate: u32,
+    /// Frames a connection may send in a burst before the rate applies.
+    pub message_burst: u32,
+    /// A connection still over its rate after this long is closed.
+    pub rate_limit_close_after: Duration,
+    /// How often each enveloped connection is sent a `latency_probe`; zero disables.
+    pub latency_probe_interval: Duration,
+}
 
-                if let Some(other_tx) = other {
-                    let _ = other_tx.send(text.to_string());
-                }
-            }
-            Ok(axum::extract::ws::Message::Close(_)) => break,
-            Err(e) => {
-                tracing::debug!("WS read error for {} {}: {}", role, code_for_read, e);
-                break;
-            }
-            _ => {}
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            code_length: DEFAULT_CODE_LENGTH,
+            room_ttl_secs: DEFAULT_ROOM_TTL_SECS,
+            reject_duplicate_roles: false,
+            ping_interval: Duration::from_secs(DEFAULT_PING_INTERVAL_SECS),
+            idle_timeout: Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECS),
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            saturation_timeout: Duration::from_secs(DEFAULT_SATURATION_TIMEOUT_SECS),
+            multi_astation: true,
+            disconnect_grace: Duration::from_secs(DEFAULT_DISCONNECT_GRACE_SECS),
+            message_rate: DEFAULT_MESSAGE_RATE,
+            message_burst: DEFAULT_MESSAGE_BURST,
+            rate_limit_close_after: Duration::from_secs(DEFAULT_RATE_LIMIT_CLOSE_SECS),
+            latency_probe_interval: Duration::from_secs(DEFAULT_LATENCY_PROBE_SECS),
         }
     }
+}
 
-    // Cleanup: remove our sender from the room
-    {
-        let mut rooms = hub_for_read.rooms.write().await;
-        if let Some(room) = rooms.get_mut(&code) {
-            match role.as_str() {
-                "atem" => room.atem_tx = None,
-                "astation" => room.astation_tx = None,
-                _ => {}
-            }
-            // If both sides disconnected, remove the room
-            if room.atem_tx.is_none() && room.astation_tx.is_none() {
-                rooms.remove(&code);
-                tracing::info!("Room {} removed (both sides disconnected)", code);
-            }
+impl RelayConfig {
+    /// Silence after which a connection no longer counts as live: a couple of
+    /// missed pings, capped at the idle timeout that closes it anyway.
+    pub fn stale_after(&self) -> Duration {
+        (self.ping_interval * LIVENESS_PING_INTERVALS).min(self.idle_timeout)
+    }
+
+    /// Reject settings the relay cannot work with.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(MIN_CODE_LENGTH..=MAX_CODE_LENGTH).contains(&self.code_length) {
+            return Err(format!(
+                "PAIR_CODE_LENGTH must be between {} and {}, got {}",
+                MIN_CODE_LENGTH, MAX_CODE_LENGTH, self.code_length
+            ));
+        }
+        if self.room_ttl_secs == 0 {
+            return Err("PAIR_ROOM_TTL_SECS must be at least 1".to_string());
+        }
+        if self.ping_interval.is_zero() {
+            return Err("RELAY_PING_INTERVAL_SECS must be at least 1".to_string());
+        }
+        if self.idle_timeout <= self.ping_interval {
+            return Err(format!(
+                "RELAY_IDLE_TIMEOUT_SECS must be longer than the ping interval ({}s)",
+                self.ping_interval.as_secs()
+            ));
         }
+        if self.channel_capacity == 0 {
+            return Err("RELAY_CHANNEL_CAPACITY must be at least 1".to_string());
+        }
+        if self.message_rate > 0 && self.message_burst == 0 {
+            return Err("RELAY_MESSAGE_BURST must be at least 1".to_string());
+        }
+        if self.message_rate > 0 && self.rate_limit_close_after.is_zero() {
+            return Err("RELAY_RATE_LIMIT_CLOSE_SECS must be at least 1".to_string());
+        }
+        Ok(())
     }
+}
 
-    write_task.abort();
-    tracing::info!("WS disconnected: role={} code={}", role, code);
+#[derive(Clone)]
+pub struct RelayHub {
+    rooms: Arc<RwLock<HashMap<String, PairRoom>>>,
+    config: RelayConfig,
+    /// Per-direction cap on a room's backlog; the oldest message is dropped when full.
+    buffer_size: usize,
+    /// Replaces `generate_pairing_code` in tests.
+    code_generator: Option<Arc<dyn Fn() -> String + Send + Sync>>,
+    /// Generated codes that were already taken.
+    code_collisions: Arc<AtomicU64>,
+    counters: Arc<RelayCounters>,
+    /// Room lifecycle events for `/api/relay/rooms/events`.
+    events: broadcast::Sender<RoomEvent>,
 }
 
-/// GET /pair?code=XXXX — HTML landing page for pairing.
-pub async fn pair_page_handler(
-    State(state): State<AppState>,
-    Query(params): Query<PairPageQuery>,
-) -> impl IntoResponse {
-    let rooms = state.relay.rooms.read().await;
-    match rooms.get(&params.code) {
-        Some(room) => {
-            let html = render_pair_page(&params.code, &room.hostname);
-            Ok(Html(html))
+/// What happened to a room, as the SSE event name.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomEventType {
+    RoomCreated,
+    AtemConnected,
+    AstationConnected,
+    RoomRemoved,
+}
+
+impl RoomEventType {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::RoomCreated => "room_created",
+            Self::AtemConnected => "atem_connected",
+            Self::AstationConnected => "astation_connected",
+            Self::RoomRemoved => "room_removed",
+        }
+    }
+
+    fn connected(role: Role) -> Self {
+        match role {
+            Role::Atem => Self::AtemConnected,
+            Role::Astation => Self::AstationConnected,
         }
-        None => Err((
-            StatusCode::NOT_FOUND,
-            Html("<h1>Pairing code not found</h1><p>The code may have expired.</p>".to_string()),
-        )),
     }
 }
 
-/// HTML-escape a string to prevent XSS attacks
-fn html_escape(s: &str) -> String {
-    s.chars()
-        .map(|c| match c {
-            '&' => "&amp;".to_string(),
-            '<' => "&lt;".to_string(),
-            '>' => "&gt;".to_string(),
-            '"' => "&quot;".to_string(),
-            '\'' => "&#x27;".to_string(),
-            '/' => "&#x2F;".to_string(),
-            _ => c.to_string(),
-        })
-        .collect()
+/// One room lifecycle event, broadcast to every event stream subscriber.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct RoomEvent {
+    #[serde(skip)]
+    pub event_type: RoomEventType,
+    pub code: String,
+    pub hostname: String,
+    pub timestamp: DateTime<Utc>,
 }
 
-fn render_pair_page(code: &str, hostname: &str) -> String {
-    let code_escaped = html_escape(code);
-    let hostname_escaped = html_escape(hostname);
+impl RoomEvent {
+    fn to_sse(&self) -> Event {
+        Event::default()
+            .event(self.event_type.as_str())
+            .data(serde_json::to_string(self).unwrap_or_default())
+    }
+}
 
-    format!(
-        r#"<!DOCTYPE html>
-<html lang="en">
-<head>
-  <meta charset="UTF-8">
-  <meta name="viewport" content="width=device-width, initial-scale=1.0">
-  <title>Atem Pairing — {code}</title>
-  <style>
-    body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; display: flex; justify-content: center; align-items: center; min-height: 100vh; margin: 0; background: #0a0a0a; color: #e0e0e0; }}
-    .card {{ background: #1a1a2e; border-radius: 16px; padding: 48px; text-align: center; max-width: 420px; box-shadow: 0 8px 32px rgba(0,0,0,0.4); }}
-    .code {{ font-size: 48px; font-weight: 700; letter-spacing: 4px; color: #00d4aa; margin: 24px 0; font-family: 'SF Mono', monospace; }}
-    .hostname {{ color: #888; font-size: 14px; margin-bottom: 32px; }}
-    .btn {{ display: inline-block; padding: 12px 32px; background: #00d4aa; color: #0a0a0a; border-radius: 8px; text-decoration: none; font-weight: 600; font-size: 16px; transition: background 0.2s; }}
-    .btn:hover {{ background: #00f5c4; }}
-    .download {{ margin-top: 24px; font-size: 13px; color: #666; }}
-    .download a {{ color: #00d4aa; }}
-    h2 {{ margin: 0 0 8px; font-size: 20px; color: #fff; }}
-    p {{ margin: 4px 0; font-size: 14px; color: #aaa; }}
-  </style>
-</head>
-<body>
-  <div class="card">
-    <h2>Atem Pairing</h2>
-    <p>Enter this code in Astation to connect</p>
-    <div class="code">{code}</div>
-    <div class="hostname">Host: {hostname}</div>
-    <a class="btn" href="astation://pair?code={code_url}">Open in Astation</a>
-    <div class="download">
-      <p>Don't have Astation? <a href="https://github.com/AgoraIO-Community/astation/releases">Download</a></p>
-    </div>
-  </div>
-</body>
-</html>"#,
-        code = code_escaped,
-        hostname = hostname_escaped,
-        code_url = urlencoding::encode(code),
-    )
+/// What a room history entry records.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomHistoryKind {
+    Connected,
+    /// `detail` is the close reason, when the server ended the connection.
+    Disconnected,
+    /// Frames the side sent that were relayed or held for the other side.
+    Messages,
+    /// Frames lost over the side's message rate (`rate_limited`) or to its
+    /// full queue (`queue_full`).
+    Dropped,
+    /// Frames from the side bounced with an error envelope; `detail` is its code.
+    Error,
+    /// A connection whose channel had closed, removed by the cleanup sweep.
+    Reaped,
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::voice_session::VoiceSessionStore;
-    use super::*;
-    use crate::session_verify::SessionVerifyCache;
+/// One entry in a room's history. Never holds frame contents.
+#[derive(Debug, Clone, Serialize)]
+pub struct RoomHistoryEntry {
+    /// When it happened; for counted entries, the first occurrence.
+    pub at: DateTime<Utc>,
+    pub kind: RoomHistoryKind,
+    pub role: Role,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<&'static str>,
+    /// Occurrences folded into this entry; 1 for connects and disconnects.
+    pub count: u64,
+}
 
-    #[test]
-    fn pairing_code_format() {
-        let code = generate_pairing_code();
-        assert_eq!(code.len(), 9); // 4 + '-' + 4
-        assert_eq!(&code[4..5], "-");
+/// A room's last `ROOM_HISTORY_CAPACITY` entries, oldest first. Behind its own
+/// lock so traffic can be counted while the rooms are only read-locked.
+#[derive(Default)]
+struct RoomHistory(std::sync::Mutex<VecDeque<RoomHistoryEntry>>);
 
-        // No ambiguous characters
-        let no_hyphen = code.replace('-', "");
-        for ch in no_hyphen.chars() {
-            assert!(
-                CODE_CHARS.contains(&(ch as u8)),
-                "Character '{}' should be in CODE_CHARS",
-                ch
-            );
+impl RoomHistory {
+    fn push(entries: &mut VecDeque<RoomHistoryEntry>, entry: RoomHistoryEntry) {
+        if entries.len() == ROOM_HISTORY_CAPACITY {
+            entries.pop_front();
         }
+        entries.push_back(entry);
     }
 
-    #[test]
-    fn pairing_code_uniqueness() {
-        let codes: Vec<String> = (0..20).map(|_| generate_pairing_code()).collect();
-        let unique: std::collections::HashSet<&String> = codes.iter().collect();
-        assert!(unique.len() > 1, "Pairing codes should vary");
+    /// Append one entry.
+    fn note(&self, kind: RoomHistoryKind, role: Role, detail: Option<&'static str>) {
+        let entry = RoomHistoryEntry { at: Utc::now(), kind, role, detail, count: 1 };
+        Self::push(&mut self.0.lock().unwrap(), entry);
     }
 
-    #[test]
-    fn pairing_code_no_ambiguous_chars() {
-        for _ in 0..100 {
-            let code = generate_pairing_code();
-            let no_hyphen = code.replace('-', "");
-            assert!(!no_hyphen.contains('0'), "Should not contain 0");
-            assert!(!no_hyphen.contains('O'), "Should not contain O");
-            assert!(!no_hyphen.contains('1'), "Should not contain 1");
-            assert!(!no_hyphen.contains('I'), "Should not contain I");
-            assert!(!no_hyphen.contains('L'), "Should not contain L");
+    /// Count one occurrence, folded into the matching entry started within the
+    /// tally window, so a flood costs one entry per minute instead of the history.
+    fn tally(&self, kind: RoomHistoryKind, role: Role, detail: Option<&'static str>) {
+        let now = Utc::now();
+        let window = chrono::Duration::from_std(ROOM_HISTORY_TALLY_WINDOW).unwrap_or_default();
+        let mut entries = self.0.lock().unwrap();
+        let current = entries
+            .iter_mut()
+            .rev()
+            .take_while(|entry| now - entry.at < window)
+            .find(|entry| entry.kind == kind && entry.role == role && entry.detail == detail);
+        match current {
+            Some(entry) => entry.count += 1,
+            None => Self::push(&mut entries, RoomHistoryEntry { at: now, kind, role, detail, count: 1 }),
         }
     }
 
-    #[tokio::test]
-    async fn relay_hub_create_and_lookup() {
-        let hub = RelayHub::new();
-
-        let room = PairRoom {
-            code: "ABCD-EFGH".to_string(),
-            hostname: "test-host".to_string(),
-            atem_tx: None,
-            astation_tx: None,
-            created_at: Instant::now(),
-        };
+    fn entries(&self) -> Vec<RoomHistoryEntry> {
+        self.0.lock().unwrap().iter().cloned().collect()
+    }
+}
 
-        hub.rooms
-            .write()
-            .await
-            .insert("ABCD-EFGH".to_string(), room);
+/// Relay load counters, updated without taking the rooms lock.
+#[derive(Default)]
+struct RelayCounters {
+    rooms_created: AtomicU64,
+    rooms_expired: AtomicU64,
+    atem_connections: AtomicU64,
+    astation_connections: AtomicU64,
+    messages_relayed: AtomicU64,
+    bytes_relayed: AtomicU64,
+    messages_dropped: AtomicU64,
+    dead_senders_reaped: AtomicU64,
+}
 
-        let rooms = hub.rooms.read().await;
-        assert!(rooms.contains_key("ABCD-EFGH"));
-        assert_eq!(rooms["ABCD-EFGH"].hostname, "test-host");
+impl RelayCounters {
+    fn connections(&self, role: Role) -> &AtomicU64 {
+        match role {
+            Role::Atem => &self.atem_connections,
+            Role::Astation => &self.astation_connections,
+        }
     }
 
-    #[tokio::test]
-    async fn relay_hub_cleanup_expired() {
-        let hub = RelayHub::new();
+    /// Count one message of `len` bytes handed to a peer.
+    fn delivered_bytes(&self, len: usize) {
+        self.messages_relayed.fetch_add(1, Ordering::Relaxed);
+        self.bytes_relayed.fetch_add(len as u64, Ordering::Relaxed);
+    }
 
-        // Create a room with a very old timestamp
-        let room = PairRoom {
-            code: "OLD1-CODE".to_string(),
-            hostname: "old-host".to_string(),
-            atem_tx: None,
-            astation_tx: None,
-            created_at: Instant::now() - std::time::Duration::from_secs(ROOM_EXPIRY_SECS + 10),
-        };
-        hub.rooms
-            .write()
-            .await
-            .insert("OLD1-CODE".to_string(), room);
+    fn dropped(&self) {
+        self.messages_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
 
-        // Create a fresh room
-        let fresh = PairRoom {
-            code: "NEW1-CODE".to_string(),
-            hostname: "new-host".to_string(),
-            atem_tx: None,
-            astation_tx: None,
-            created_at: Instant::now(),
-        };
-        hub.rooms
-            .write()
-            .await
-            .insert("NEW1-CODE".to_string(), fresh);
+/// Relay load since startup, from `GET /api/admin/relay/stats`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RelayStats {
+    pub active_rooms: usize,
+    pub rooms_created: u64,
+    /// Unpaired rooms removed by the TTL sweep.
+    pub rooms_expired: u64,
+    /// Open WebSocket connections per role.
+    pub atem_connections: u64,
+    pub astation_connections: u64,
+    /// Messages delivered to a peer's queue; a message fanned out to several
+    /// astations counts once per astation.
+    pub messages_relayed: u64,
+    pub bytes_relayed: u64,
+    /// Messages lost to a full queue.
+    pub messages_dropped: u64,
+    /// Connections found with a closed channel by the cleanup sweep.
+    pub dead_senders_reaped: u64,
+}
 
-        hub.cleanup_expired().await;
+/// One room in `GET /api/admin/pair-rooms`. Only copied plain data: no
+/// senders, tokens or buffered messages.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PairRoomSummary {
+    /// Full code from [`RelayHub::list_summaries`]; masked in the listing.
+    pub code: String,
+    pub hostname: String,
+    pub created_at: DateTime<Utc>,
+    pub age_secs: u64,
+    pub atem_connected: bool,
+    pub astation_connected: bool,
+    /// Messages waiting for a side that is not connected, both directions.
+    pub backlog: usize,
+    /// Smoothed latency probe round trip per side, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub atem_rtt_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub astation_rtt_ms: Option<f64>,
+}
 
-        let rooms = hub.rooms.read().await;
-        assert!(!rooms.contains_key("OLD1-CODE"), "Expired room should be removed");
-        assert!(rooms.contains_key("NEW1-CODE"), "Fresh room should remain");
+/// `AB*****YZ`: a pairing code reduced to its first and last two characters.
+fn mask_code(code: &str) -> String {
+    let len = code.chars().count();
+    if len <= 4 {
+        return "*".repeat(len);
     }
+    code.chars()
+        .enumerate()
+        .map(|(i, c)| if i < 2 || i >= len - 2 { c } else { '*' })
+        .collect()
+}
 
-    #[tokio::test]
-    async fn relay_hub_cleanup_keeps_paired() {
-        let hub = RelayHub::new();
+impl PairRoom {
+    /// A fresh, unpaired room with nobody connected, created now.
+    fn new(code: String, hostname: String, owner_token: String, expiry_secs: u64) -> Self {
+        PairRoom {
+            code,
+            hostname,
+            atem: None,
+            astations: Vec::new(),
+            created_at: Instant::now(),
+            expiry_secs,
+            extended_secs: 0,
+            paired_at: None,
+            metadata: serde_json::Map::new(),
+            last_pong_at: None,
+            atem_backlog: Backlog::default(),
+            astation_backlog: Backlog::default(),
+            dropped_messages: 0,
+            owner_token,
+            reconnect_token_hashes: HashMap::new(),
+            disconnected_at: None,
+            observers: broadcast::channel(OBSERVER_CAPACITY).0,
+            e2e: false,
+            e2e_confirmed: HashSet::new(),
+            history: RoomHistory::default(),
+            rtt_ewma_ms: HashMap::new(),
+        }
+    }
 
-        // Create an old but paired room (astation_tx is Some)
-        let (tx, _rx) = mpsc::unbounded_channel::<String>();
-        let room = PairRoom {
-            code: "PAIR-CODE".to_string(),
-            hostname: "paired-host".to_string(),
-            atem_tx: None,
-            astation_tx: Some(tx),
-            created_at: Instant::now() - std::time::Duration::from_secs(ROOM_EXPIRY_SECS + 10),
-        };
-        hub.rooms
-            .write()
-            .await
-            .insert("PAIR-CODE".to_string(), room);
+    fn summary(&self, now: DateTime<Utc>) -> PairRoomSummary {
+        let age = self.created_at.elapsed();
+        PairRoomSummary {
+            code: self.code.clone(),
+            hostname: self.hostname.clone(),
+            created_at: now - chrono::Duration::from_std(age).unwrap_or_default(),
+            age_secs: age.as_secs(),
+            atem_connected: self.atem.is_some(),
+            astation_connected: !self.astations.is_empty(),
+            backlog: self.atem_backlog.len() + self.astation_backlog.len(),
+            atem_rtt_ms: self.rtt_ms(Role::Atem),
+            astation_rtt_ms: self.rtt_ms(Role::Astation),
+        }
+    }
 
-        hub.cleanup_expired().await;
+    fn status(&self) -> PairStatusResponse {
+        let age = self.created_at.elapsed().as_secs();
+        let atem_connected = self.atem.is_some();
+        let astation_connected = !self.astations.is_empty();
+        let astation_live = self.astations.iter().any(|c| c.stale_reason().is_none());
+        PairStatusResponse {
+            paired: atem_connected && astation_live,
+            astation_stale: astation_connected && !astation_live,
+            hostname: self.hostname.clone(),
+            created_secs_ago: age,
+            atem_connected,
+            astation_connected,
+            astation_count: self.astations.len(),
+            paired_at: self.paired_at,
+            // Rooms with astation attached never expire
+            expires_in_secs: (!astation_connected).then(|| self.lifetime_secs() as i64 - age as i64),
+            metadata: self.metadata.clone(),
+            last_pong_secs_ago: self.last_pong_at.map(|at| at.elapsed().as_secs()),
+            dropped_messages: self.dropped_messages,
+            atem_backlog: self.atem_backlog.len(),
+            astation_backlog: self.astation_backlog.len(),
+            atem: SideStatus::of(self.atem.as_ref()),
+            astation: SideStatus::of(self.astations.last()),
+            e2e: self.e2e,
+            atem_rtt_ms: self.rtt_ms(Role::Atem),
+            astation_rtt_ms: self.rtt_ms(Role::Astation),
+        }
+    }
 
-        let rooms = hub.rooms.read().await;
-        assert!(
-            rooms.contains_key("PAIR-CODE"),
-            "Paired room should not be cleaned up"
-        );
+    /// `role`'s smoothed round trip, to a tenth of a millisecond.
+    fn rtt_ms(&self, role: Role) -> Option<f64> {
+        self.rtt_ewma_ms.get(&role).map(|ms| (ms * 10.0).round() / 10.0)
     }
 
-    #[test]
-    fn render_pair_page_contains_code() {
-        let html = render_pair_page("TEST-CODE", "my-host");
-        assert!(html.contains("TEST-CODE"));
-        assert!(html.contains("my-host"));
-        assert!(html.contains("astation://pair?code=TEST-CODE"));
+    /// Seconds after creation the room expires unless an astation is connected.
+    fn lifetime_secs(&self) -> u64 {
+        self.expiry_secs + self.extended_secs
     }
 
-    // --- Integration tests (HTTP endpoint tests) ---
+    /// Push the deadline to a full `expiry_secs` from now, capped at
+    /// `MAX_RENEWED_ROOM_AGE_SECS`. False once the deadline is at the cap.
+    fn renew(&mut self) -> bool {
+        if self.lifetime_secs() >= MAX_RENEWED_ROOM_AGE_SECS {
+            return false;
+        }
+        let age = self.created_at.elapsed().as_secs();
+        let target = (age + self.expiry_secs).min(MAX_RENEWED_ROOM_AGE_SECS);
+        self.extended_secs = self.extended_secs.max(target.saturating_sub(self.expiry_secs));
+        true
+    }
 
-    use axum::{
-        body::Body,
-        http::{Request, StatusCode as HttpStatusCode},
-        Router,
-    };
-    use tower::ServiceExt;
+    /// Senders for every connection of `role`.
+    fn senders(&self, role: Role) -> Vec<mpsc::Sender<String>> {
+        match role {
+            Role::Atem => self.atem.iter().map(|c| c.tx.clone()).collect(),
+            Role::Astation => self.astations.iter().map(|c| c.tx.clone()).collect(),
+        }
+    }
 
-    fn create_relay_app() -> Router {
-        let state = crate::AppState {
-            sessions: crate::session_store::SessionStore::new(),
-            relay: RelayHub::new(),
-            rtc_sessions: crate::rtc_session::RtcSessionStore::new(),
-            session_verify_cache: SessionVerifyCache::new(),
-            voice_sessions: VoiceSessionStore::new(),
-        };
-        Router::new()
-            .route("/api/pair", axum::routing::post(create_pair_handler))
-            .route("/api/pair/:code", axum::routing::get(pair_status_handler))
-            .route("/ws", axum::routing::get(ws_handler))
-            .route("/pair", axum::routing::get(pair_page_handler))
-            .with_state(state)
+    fn is_connected(&self, role: Role) -> bool {
+        match role {
+            Role::Atem => self.atem.is_some(),
+            Role::Astation => !self.astations.is_empty(),
+        }
     }
 
-    /// Helper: POST /api/pair with given hostname, returns the response body as CreatePairResponse.
-    async fn post_create_pair(app: Router, hostname: &str) -> (HttpStatusCode, String) {
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/pair")
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(format!(r#"{{"hostname": "{}"}}"#, hostname)))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-        let status = response.status();
-        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-            .await
-            .unwrap();
-        let body_str = String::from_utf8(body.to_vec()).unwrap();
-        (status, body_str)
+    /// The connection of `role` whose sender is `tx`.
+    fn connection_mut(&mut self, role: Role, tx: &mpsc::Sender<String>) -> Option<&mut Connection> {
+        match role {
+            Role::Atem => self.atem.as_mut().filter(|c| c.tx.same_channel(tx)),
+            Role::Astation => self.astations.iter_mut().find(|c| c.tx.same_channel(tx)),
+        }
     }
 
-    #[tokio::test]
-    async fn test_create_pair_endpoint() {
-        let app = create_relay_app();
-        let (status, body_str) = post_create_pair(app, "my-machine").await;
+    /// Add a connection for `role` (replacing atem's).
+    fn add_connection(&mut self, role: Role, connection: Connection) {
+        match role {
+            Role::Atem => self.atem = Some(connection),
+            Role::Astation => self.astations.push(connection),
+        }
+        self.disconnected_at = None;
+        if self.paired_at.is_none() && self.atem.is_some() && !self.astations.is_empty() {
+            self.paired_at = Some(Utc::now());
+        }
+    }
 
-        assert_eq!(status, HttpStatusCode::CREATED);
+    /// Give each role a fresh reconnect token, sending it to that role's
+    /// connections as `{"type":"pair_established","token":...}`.
+    fn issue_reconnect_tokens(&mut self) {
+        for role in Role::ALL {
+            let token = generate_session_token();
+            self.reconnect_token_hashes.insert(role, hash_token(&token));
+            let frame = serde_json::json!({ "type": "pair_established", "token": token }).to_string();
+            for tx in self.senders(role) {
+                let _ = tx.try_send(frame.clone());
+            }
+        }
+    }
 
-        let resp: CreatePairResponse = serde_json::from_str(&body_str).unwrap();
-        // Code should be in XXXX-XXXX format (9 chars total)
-        assert_eq!(resp.code.len(), 9, "Code should be 9 characters (XXXX-XXXX)");
-        assert_eq!(
-            &resp.code[4..5],
-            "-",
-            "Code should have hyphen at position 4"
-        );
-        // Each half should be 4 alphanumeric chars
-        let left = &resp.code[..4];
-        let right = &resp.code[5..];
-        assert!(
-            left.chars().all(|c| c.is_ascii_alphanumeric()),
-            "Left half should be alphanumeric"
-        );
-        assert!(
-            right.chars().all(|c| c.is_ascii_alphanumeric()),
-            "Right half should be alphanumeric"
-        );
+    /// True if `role` may connect by code with `token`: always before the first
+    /// pairing, afterwards only with the role's reconnect token.
+    fn accepts_reconnect(&self, role: Role, token: Option<&str>) -> bool {
+        match self.reconnect_token_hashes.get(&role) {
+            None => true,
+            Some(expected) => token.is_some_and(|token| hash_token(token) == *expected),
+        }
     }
 
-    #[tokio::test]
-    async fn test_create_pair_code_no_ambiguous_chars() {
-        // Create several pairs and verify none contain ambiguous characters
-        for _ in 0..20 {
-            let app = create_relay_app();
-            let (status, body_str) = post_create_pair(app, "test-host").await;
-            assert_eq!(status, HttpStatusCode::CREATED);
+    /// Record `reason` as the close reason of every connection, ahead of the
+    /// room dropping their senders.
+    fn close_connections(&self, reason: CloseReason) {
+        for connection in self.atem.iter().chain(&self.astations) {
+            connection.close.set(reason);
+        }
+    }
 
-            let resp: CreatePairResponse = serde_json::from_str(&body_str).unwrap();
-            let code_no_hyphen = resp.code.replace('-', "");
-            assert!(
-                !code_no_hyphen.contains('0'),
-                "Code should not contain '0': {}",
-                resp.code
-            );
-            assert!(
-                !code_no_hyphen.contains('O'),
-                "Code should not contain 'O': {}",
-                resp.code
-            );
-            assert!(
-                !code_no_hyphen.contains('1'),
-                "Code should not contain '1': {}",
-                resp.code
-            );
-            assert!(
-                !code_no_hyphen.contains('I'),
-                "Code should not contain 'I': {}",
-                resp.code
-            );
-            assert!(
-                !code_no_hyphen.contains('L'),
-                "Code should not contain 'L': {}",
-                resp.code
-            );
+    /// Remove every connection of `role`.
+    fn take_connections(&mut self, role: Role) -> Vec<Connection> {
+        match role {
+            Role::Atem => self.atem.take().into_iter().collect(),
+            Role::Astation => std::mem::take(&mut self.astations),
         }
     }
 
-    #[tokio::test]
+    /// Remove the connection of `role` whose sender is `tx`; false if it is not there.
+    fn remove_connection(&mut self, role: Role, tx: &mpsc::Sender<String>) -> bool {
+        let removed = match role {
+            Role::Atem if self.atem.as_ref().is_some_and(|c| c.tx.same_channel(tx)) => {
+                self.atem = None;
+                true
+            }
+            Role::Atem => false,
+            Role::Astation => {
+                let before = self.astations.len();
+                self.astations.retain(|c| !c.tx.same_channel(tx));
+                self.astations.len() < before
+            }
+        };
+        if removed && self.atem.is_none() && self.astations.is_empty() {
+            self.disconnected_at = Some(Instant::now());
+        }
+        removed
+    }
+
+    /// Remove connections whose channel is closed (their writer task is gone),
+    /// returning how many. A room left with no one starts its grace period.
+    fn reap_dead_connections(&mut self) -> usize {
+        let had_connections = self.atem.is_some() || !self.astations.is_empty();
+        let mut reaped = 0;
+        if self.atem.as_ref().is_some_and(|c| c.tx.is_closed()) {
+            self.atem = None;
+            self.history.note(RoomHistoryKind::Reaped, Role::Atem, None);
+            reaped += 1;
+        }
+        let before = self.astations.len();
+        self.astations.retain(|c| !c.tx.is_closed());
+        for _ in self.astations.len()..before {
+            self.history.note(RoomHistoryKind::Reaped, Role::Astation, None);
+        }
+        reaped += before - self.astations.len();
+        if had_connections && self.atem.is_none() && self.astations.is_empty() {
+            self.disconnected_at = Some(Instant::now());
+        }
+        reaped
+    }
+
+    /// Messages held for `role` while it is not connected.
+    fn backlog_mut(&mut self, role: Role) -> &mut Backlog {
+        match role {
+            Role::Atem => &mut self.atem_backlog,
+            Role::Astation => &mut self.astation_backlog,
+        }
+    }
+
+    /// Copy a frame `from_role` sent towards `to_role` to every observer.
+    /// Once the room is end-to-end encrypted, or for `opaque` frames, only the
+    /// frame's size is copied.
+    fn observe(&self, from_role: Role, to_role: Role, text: &str, opaque: bool) {
+        if self.observers.receiver_count() == 0 {
+            return;
+        }
+        let mut frame = serde_json::json!({
+            "type": "observed",
+            "direction": format!("{}_to_{}", from_role, to_role),
+        });
+        if opaque || self.e2e {
+            frame["e2e"] = true.into();
+            frame["bytes"] = text.len().into();
+        } else {
+            frame["frame"] = text.into();
+        }
+        // Fails only if the last observer left meanwhile
+        let _ = self.observers.send(frame.to_string());
+    }
+
+    /// Fold one probe round trip into `role`'s smoothed latency; the first
+    /// sample is taken as it is.
+    fn record_rtt(&mut self, role: Role, rtt: Duration) {
+        let sample = rtt.as_secs_f64() * 1000.0;
+        let ewma = self.rtt_ewma_ms.entry(role).or_insert(sample);
+        *ewma += LATENCY_EWMA_ALPHA * (sample - *ewma);
+    }
+
+    /// Record that `role` sent `e2e_init`; true if that completed the exchange.
+    fn confirm_e2e(&mut self, role: Role) -> bool {
+        self.e2e_confirmed.insert(role);
+        if self.e2e || self.e2e_confirmed.len() < Role::ALL.len() {
+            return false;
+        }
+        self.e2e = true;
+        true
+    }
+
+    /// True if `token` lets an observer watch this room: the admin token, or
+    /// either side's reconnect token.
+    fn accepts_observer(&self, token: Option<&str>, admin_token: Option<&str>) -> bool {
+        let Some(token) = token else {
+            return false;
+        };
+        if admin_token.is_some_and(|expected| constant_time_eq(token, expected)) {
+            return true;
+        }
+        let hash = hash_token(token);
+        self.reconnect_token_hashes.values().any(|expected| *expected == hash)
+    }
+
+    /// Senders for the side opposite `role`.
+    fn peers_of(&self, role: Role) -> Vec<mpsc::Sender<String>> {
+        self.senders(role.peer())
+    }
+
+    /// Apply a `control` message to the room. A `set_metadata` that would grow
+    /// the metadata past [`MAX_ROOM_METADATA_BYTES`] changes nothing.
+    fn apply_control(&mut self, action: &str, payload: serde_json::Value) -> Result<(), EnvelopeError> {
+        match action {
+            "set_metadata" => match payload {
+                serde_json::Value::Object(fields) => {
+                    let mut metadata = self.metadata.clone();
+                    metadata.extend(fields);
+                    let size = serde_json::to_string(&metadata).map_or(usize::MAX, |json| json.len());
+                    if size > MAX_ROOM_METADATA_BYTES {
+                        return Err(EnvelopeError::metadata_too_large(MAX_ROOM_METADATA_BYTES));
+                    }
+                    self.metadata = metadata;
+                }
+                _ => tracing::warn!("Room {}: set_metadata payload must be an object", self.code),
+            },
+            "clear_metadata" => self.metadata.clear(),
+            _ => tracing::warn!("Room {}: unknown control action {:?}", self.code, action),
+        }
+        Ok(())
+    }
+}
+
+impl RelayHub {
+    pub fn new() -> Self {
+        Self {
+            rooms: Arc::new(RwLock::new(HashMap::new())),
+            config: RelayConfig::default(),
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            code_generator: None,
+            code_collisions: Arc::new(AtomicU64::new(0)),
+            counters: Arc::default(),
+            events: broadcast::channel(ROOM_EVENT_CAPACITY).0,
+        }
+    }
+
+    /// Use `config` for code generation and room expiry.
+    pub fn with_config(mut self, config: RelayConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn config(&self) -> RelayConfig {
+        self.config
+    }
+
+    /// [`normalize_code`] at the configured code length.
+    pub fn normalize_code(&self, raw: &str) -> Option<String> {
+        normalize_code(raw, self.config.code_length)
+    }
+
+    /// True if a new connection for `role` joins the existing ones instead of
+    /// taking over or being refused.
+    fn shares_role(&self, role: Role) -> bool {
+        role == Role::Astation && self.config.multi_astation
+    }
+
+    /// Receive every room event from now on.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<RoomEvent> {
+        self.events.subscribe()
+    }
+
+    fn emit(&self, event_type: RoomEventType, code: &str, hostname: &str) {
+        // Fails only when nobody is subscribed
+        let _ = self.events.send(RoomEvent {
+            event_type,
+            code: code.to_string(),
+            hostname: hostname.to_string(),
+            timestamp: Utc::now(),
+        });
+    }
+
+    /// Whether a room with this code is open.
+    pub async fn room_exists(&self, code: &str) -> bool {
+        self.rooms.read().await.contains_key(code)
+    }
+
+    /// Status of the room with this code, as `GET /api/pair/:code` reports it.
+    pub async fn room_status(&self, code: &str) -> Option<PairStatusResponse> {
+        let rooms = self.rooms.read().await;
+        rooms.get(code).map(|room| room.status())
+    }
+
+    /// Open a room for `hostname` under a fresh code, as `POST /api/pair` does.
+    pub async fn create_room(&self, hostname: String, owner_token: String) -> Option<String> {
+        self.insert_new_room(hostname, owner_token).await
+    }
+
+    /// Number of open pair rooms.
+    pub async fn room_count(&self) -> usize {
+        self.rooms.read().await.len()
+    }
+
+    /// Every room's summary, oldest first, copied under one read lock.
+    pub async fn list_summaries(&self) -> Vec<PairRoomSummary> {
+        let now = Utc::now();
+        let mut summaries: Vec<PairRoomSummary> =
+            self.rooms.read().await.values().map(|room| room.summary(now)).collect();
+        summaries.sort_by_key(|summary| std::cmp::Reverse(summary.age_secs));
+        summaries
+    }
+
+    /// Relay load counters plus the current room count.
+    pub async fn stats(&self) -> RelayStats {
+        let counters = &self.counters;
+        RelayStats {
+            active_rooms: self.room_count().await,
+            rooms_created: counters.rooms_created.load(Ordering::Relaxed),
+            rooms_expired: counters.rooms_expired.load(Ordering::Relaxed),
+            atem_connections: counters.atem_connections.load(Ordering::Relaxed),
+            astation_connections: counters.astation_connections.load(Ordering::Relaxed),
+            messages_relayed: counters.messages_relayed.load(Ordering::Relaxed),
+            bytes_relayed: counters.bytes_relayed.load(Ordering::Relaxed),
+            messages_dropped: counters.messages_dropped.load(Ordering::Relaxed),
+            dead_senders_reaped: counters.dead_senders_reaped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Number of generated pairing codes that collided with an existing room.
+    pub fn code_collisions(&self) -> u64 {
+        self.code_collisions.load(Ordering::Relaxed)
+    }
+
+    /// Insert a new room under an unused pairing code, regenerating on collision.
+    /// Returns None if `MAX_CODE_ATTEMPTS` codes in a row were taken.
+    async fn insert_new_room(&self, hostname: String, owner_token: String) -> Option<String> {
+        self.insert_expiring_room(hostname, owner_token, self.config.room_ttl_secs).await
+    }
+
+    /// [`Self::insert_new_room`] with the room's own unpaired lifetime.
+    async fn insert_expiring_room(&self, hostname: String, owner_token: String, expiry_secs: u64) -> Option<String> {
+        let mut rooms = self.rooms.write().await;
+        self.insert_room_locked(&mut rooms, hostname, owner_token, expiry_secs)
+    }
+
+    /// [`Self::insert_expiring_room`], unless `previous_owner_token` owns a
+    /// room for `hostname` nobody has paired with that is still within its
+    /// lifetime: that room's expiry clock restarts and it is handed back with
+    /// `owner_token` replacing the previous one. Returns `(code, owner_token, reused)`.
+    async fn insert_or_reuse_room(
+        &self,
+        hostname: String,
+        previous_owner_token: &str,
+        owner_token: String,
+        expiry_secs: u64,
+    ) -> Option<(String, String, bool)> {
+        let mut rooms = self.rooms.write().await;
+        let reusable = rooms.values_mut().find(|room| {
+            constant_time_eq(previous_owner_token, &room.owner_token)
+                && room.hostname == hostname
+                && room.paired_at.is_none()
+                && room.astations.is_empty()
+                && room.disconnected_at.is_none()
+                && room.created_at.elapsed().as_secs() < room.lifetime_secs()
+        });
+        if let Some(room) = reusable {
+            room.created_at = Instant::now();
+            room.expiry_secs = expiry_secs;
+            room.owner_token = owner_token.clone();
+            return Some((room.code.clone(), owner_token, true));
+        }
+        let code = self.insert_room_locked(&mut rooms, hostname, owner_token.clone(), expiry_secs)?;
+        Some((code, owner_token, false))
+    }
+
+    fn insert_room_locked(
+        &self,
+        rooms: &mut HashMap<String, PairRoom>,
+        hostname: String,
+        owner_token: String,
+        expiry_secs: u64,
+    ) -> Option<String> {
+        for attempt in 1..=MAX_CODE_ATTEMPTS {
+            let code = match &self.code_generator {
+                Some(generator) => generator(),
+                None => generate_pairing_code(self.config.code_length),
+            };
+            if rooms.contains_key(&code) {
+                self.code_collisions.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!("Pairing code collision (attempt {}/{})", attempt, MAX_CODE_ATTEMPTS);
+                continue;
+            }
+            rooms.insert(
+                code.clone(),
+                PairRoom::new(code.clone(), hostname, owner_token, expiry_secs),
+            );
+            self.counters.rooms_created.fetch_add(1, Ordering::Relaxed);
+            self.emit(RoomEventType::RoomCreated, &code, &rooms[&code].hostname);
+            return Some(code);
+        }
+        None
+    }
+
+    /// Cap the messages buffered per room for a late astation (0 disables buffering).
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Register `tx` as the sender for `role` in room `code`.
+    ///
+    /// Astations join alongside each other while `multi_astation` is on.
+    /// Otherwise, if `role` is already connected, the previous connection is sent a
+    /// `connection_replaced` notice and its sender dropped, which closes it with
+    /// [`CloseReason::Replaced`]; with `takeover` false the new connection is
+    /// refused instead.
+    /// When a side attaches with `replay_buffer`, messages the other side sent
+    /// while it was away are delivered first, oldest first; otherwise they are
+    /// discarded.
+    /// Returns the new connection's close signal and last-seen clock, or None if
+    /// the room is gone or the role is taken.
+    async fn attach(
+        &self,
+        code: &str,
+        role: Role,
+        tx: &mpsc::Sender<String>,
+        client: ClientInfo,
+        replay_buffer: bool,
+        takeover: bool,
+    ) -> Option<(CloseSignal, LastSeen)> {
+        let mut rooms = self.rooms.write().await;
+        let Some(room) = rooms.get_mut(code) else {
+            tracing::warn!("Room {} disappeared before WS setup", code);
+            return None;
+        };
+        if !self.shares_role(role) {
+            if room.is_connected(role) && !takeover {
+                tracing::info!("Room {}: {} already connected, refusing new connection", code, role);
+                return None;
+            }
+            for previous in room.take_connections(role) {
+                let notice = serde_json::json!({ "type": "connection_replaced", "role": role.as_str() });
+                let _ = previous.tx.try_send(notice.to_string());
+                previous.close.set(CloseReason::Replaced);
+                tracing::info!("Room {}: new {} connection replaced the previous one", code, role);
+            }
+        }
+        let was_paired = room.paired_at.is_some();
+        let mut connection = Connection::new(tx.clone(), client);
+        connection.stale_after = self.config.stale_after();
+        let shared = (connection.close.clone(), connection.last_seen.clone());
+        room.add_connection(role, connection);
+        room.history.note(RoomHistoryKind::Connected, role, None);
+        if !was_paired && room.paired_at.is_some() {
+            room.issue_reconnect_tokens();
+            tracing::info!("Room {} paired, reconnect tokens issued", code);
+        }
+        let buffered = room.backlog_mut(role).take();
+        if replay_buffer && !buffered.is_empty() {
+            tracing::info!("Replaying {} buffered messages to {} in {}", buffered.len(), role, code);
+            for message in buffered {
+                let len = message.len();
+                if tx.try_send(message).is_ok() {
+                    self.counters.delivered_bytes(len);
+                } else {
+                    room.dropped_messages += 1;
+                    room.history.tally(RoomHistoryKind::Dropped, role, Some("queue_full"));
+                    self.counters.dropped();
+                }
+            }
+        }
+        Some(shared)
+    }
+
+    /// Remove this connection from `role` if the room still holds it. A room
+    /// left with neither side is kept for the disconnect grace period so both
+    /// can come back after a network blip. After a takeover the room holds the
+    /// newer connection's sender, which a stale disconnect must leave alone.
+    /// Returns true if the connection was found and removed.
+    async fn detach(&self, code: &str, role: Role, own_tx: &mpsc::WeakSender<String>) -> bool {
+        let mut rooms = self.rooms.write().await;
+        let Some(room) = rooms.get_mut(code) else {
+            return false;
+        };
+        // Our sender only survives while the room holds it, so a failed upgrade
+        // means it was replaced
+        let Some(own) = own_tx.upgrade() else {
+            return false;
+        };
+        if !room.remove_connection(role, &own) {
+            return false;
+        }
+        if room.disconnected_at.is_some() {
+            tracing::info!(
+                "Room {}: both sides disconnected, keeping it for {}s",
+                code,
+                self.config.disconnect_grace.as_secs()
+            );
+        }
+        true
+    }
+
+    /// Tell the side still connected to a room that `disconnected_role` left,
+    /// so it stops sending into a channel nobody reads. Nothing is sent while
+    /// another connection of that role remains.
+    pub async fn notify_peer_disconnected(&self, code: &str, disconnected_role: Role) {
+        let peers = {
+            let rooms = self.rooms.read().await;
+            match rooms.get(code) {
+                Some(room) if !room.is_connected(disconnected_role) => room.peers_of(disconnected_role),
+                _ => Vec::new(),
+            }
+        };
+        let notice = serde_json::json!({ "type": "peer_disconnected", "role": disconnected_role.as_str() });
+        for peer in peers {
+            let _ = peer.try_send(notice.to_string());
+        }
+    }
+
+    /// Queue `text` for the `peer_role` side, waiting up to `FORWARD_WAIT` while
+    /// its queue is full. A message that still does not fit is dropped and
+    /// counted; once the queue has stayed full for `saturation_timeout` the peer
+    /// is disconnected and the sending side told so with `peer_disconnected`.
+    async fn forward(&self, code: &str, peer_role: Role, peer: &mpsc::Sender<String>, text: String) {
+        use tokio::sync::mpsc::error::{SendTimeoutError, TrySendError};
+
+        let len = text.len();
+        let text = match peer.try_send(text) {
+            Ok(()) => return self.counters.delivered_bytes(len),
+            Err(TrySendError::Closed(_)) => return,
+            Err(TrySendError::Full(text)) => text,
+        };
+        let delivered = match peer.send_timeout(text, FORWARD_WAIT).await {
+            Ok(()) => true,
+            Err(SendTimeoutError::Closed(_)) => return,
+            Err(SendTimeoutError::Timeout(_)) => false,
+        };
+        if delivered {
+            self.counters.delivered_bytes(len);
+        } else {
+            self.counters.dropped();
+        }
+
+        let mut rooms = self.rooms.write().await;
+        let Some(room) = rooms.get_mut(code) else {
+            return;
+        };
+        if !delivered {
+            room.dropped_messages += 1;
+            room.history.tally(RoomHistoryKind::Dropped, peer_role, Some("queue_full"));
+        }
+        let Some(connection) = room.connection_mut(peer_role, peer) else {
+            return;
+        };
+        if delivered {
+            connection.saturated_since = None;
+            return;
+        }
+        let since = *connection.saturated_since.get_or_insert_with(Instant::now);
+        if since.elapsed() < self.config.saturation_timeout {
+            return;
+        }
+        // Dropping the room's sender ends that connection
+        connection.close.set(CloseReason::QueueSaturated);
+        room.remove_connection(peer_role, peer);
+        tracing::warn!(
+            "Room {}: {} queue full for {}s, disconnecting it",
+            code,
+            peer_role,
+            since.elapsed().as_secs()
+        );
+        drop(rooms);
+        self.notify_peer_disconnected(code, peer_role).await;
+    }
+
+    /// Send a `latency_probe` to the connection of `role` whose sender is `tx`,
+    /// replacing any probe still unanswered. False if the connection is gone
+    /// or its queue is full.
+    async fn send_latency_probe(&self, code: &str, role: Role, tx: &mpsc::Sender<String>) -> bool {
+        let mut rooms = self.rooms.write().await;
+        let Some(connection) = rooms.get_mut(code).and_then(|room| room.connection_mut(role, tx)) else {
+            return false;
+        };
+        let nonce = format!("{:016x}", rand::thread_rng().gen::<u64>());
+        connection.probe = Some((nonce.clone(), Instant::now()));
+        tx.try_send(envelope::latency_probe(&nonce)).is_ok()
+    }
+
+    /// Time the ack of `role`'s outstanding probe and fold it into the role's
+    /// smoothed latency. Acks with a stale or unknown nonce are ignored.
+    /// Returns the measured round trip.
+    async fn ack_latency_probe(&self, code: &str, role: Role, tx: &mpsc::Sender<String>, nonce: &str) -> Option<Duration> {
+        let mut rooms = self.rooms.write().await;
+        let room = rooms.get_mut(code)?;
+        let connection = room.connection_mut(role, tx)?;
+        let sent_at = match connection.probe.take() {
+            Some((expected, sent_at)) if expected == nonce => sent_at,
+            other => {
+                connection.probe = other;
+                return None;
+            }
+        };
+        let rtt = sent_at.elapsed();
+        room.record_rtt(role, rtt);
+        Some(rtt)
+    }
+
+    /// Count one occurrence in room `code`'s history, if the room still exists.
+    async fn tally_history(&self, code: &str, kind: RoomHistoryKind, role: Role, detail: Option<&'static str>) {
+        if let Some(room) = self.rooms.read().await.get(code) {
+            room.history.tally(kind, role, detail);
+        }
+    }
+
+    /// Room `code`'s history, oldest first; None if the room does not exist.
+    pub async fn room_history(&self, code: &str) -> Option<Vec<RoomHistoryEntry>> {
+        self.rooms.read().await.get(code).map(|room| room.history.entries())
+    }
+
+    /// Push a server-originated message to every connection of one side of a room.
+    /// Returns false if the room does not exist or no connection took it.
+    pub async fn send_to(&self, code: &str, role: Role, message: String) -> bool {
+        let senders = {
+            let rooms = self.rooms.read().await;
+            rooms.get(code).map(|room| room.senders(role)).unwrap_or_default()
+        };
+        let mut delivered = false;
+        for tx in senders {
+            delivered |= tx.try_send(message.clone()).is_ok();
+        }
+        delivered
+    }
+
+    /// Disconnect every relay WebSocket with `reason`, leaving the rooms in place.
+    /// Returns how many connections were closed.
+    pub async fn close_all_connections(&self, reason: CloseReason) -> usize {
+        let mut rooms = self.rooms.write().await;
+        let mut closed = 0;
+        for room in rooms.values_mut() {
+            room.close_connections(reason);
+            for role in Role::ALL {
+                closed += room.take_connections(role).len();
+            }
+        }
+        closed
+    }
+
+    /// Remove rooms that are older than the configured TTL and have no astation
+    /// connected, and rooms both sides left more than the disconnect grace period ago.
+    /// Connections whose channel has closed are dropped first, so they no longer
+    /// count as connected.
+    pub async fn cleanup_expired(&self) {
+        let grace = self.config.disconnect_grace;
+        let now = Instant::now();
+        let mut rooms = self.rooms.write().await;
+        // A leaked or orphaned sender would otherwise keep its room alive forever
+        for room in rooms.values_mut() {
+            let reaped = room.reap_dead_connections();
+            if reaped > 0 {
+                tracing::warn!("Reaped {} dead connection(s) in room {}", reaped, room.code);
+                self.counters.dead_senders_reaped.fetch_add(reaped as u64, Ordering::Relaxed);
+            }
+        }
+        let before = rooms.len();
+        rooms.retain(|_, room| {
+            let keep = match room.disconnected_at {
+                // Both sides gone: only the grace period applies
+                Some(since) => now.duration_since(since) < grace,
+                // Keep if not expired, or if astation is connected (actively paired)
+                None => now.duration_since(room.created_at).as_secs() < room.lifetime_secs() || !room.astations.is_empty(),
+            };
+            if !keep {
+                room.close_connections(CloseReason::RoomExpired);
+                self.emit(RoomEventType::RoomRemoved, &room.code, &room.hostname);
+            }
+            keep
+        });
+        self.counters.rooms_expired.fetch_add((before - rooms.len()) as u64, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+impl RelayHub {
+    /// Replace the pairing code generator.
+    pub(crate) fn with_code_generator(mut self, generator: impl Fn() -> String + Send + Sync + 'static) -> Self {
+        self.code_generator = Some(Arc::new(generator));
+        self
+    }
+
+    /// The owner token of `code`'s room.
+    pub(crate) async fn owner_token(&self, code: &str) -> String {
+        self.rooms.read().await[code].owner_token.clone()
+    }
+
+    /// Insert a room whose `role` side is a channel the test reads from.
+    pub(crate) async fn connect_test_side(
+        &self,
+        code: &str,
+        role: Role,
+    ) -> mpsc::Receiver<String> {
+        let (tx, rx) = mpsc::channel::<String>(self.config.channel_capacity);
+        let mut rooms = self.rooms.write().await;
+        let room = rooms.entry(code.to_string()).or_insert_with(|| {
+            PairRoom::new(
+                code.to_string(),
+                "host".to_string(),
+                generate_session_token(),
+                self.config.room_ttl_secs,
+            )
+        });
+        room.add_connection(role, Connection::new(tx, ClientInfo::default()));
+        rx
+    }
+}
+
+impl Default for RelayHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Insert the hyphen halfway: "ABCDEFGH" → "ABCD-EFGH".
+fn hyphenate(code: &str) -> String {
+    let (head, tail) = code.split_at(code.len() / 2);
+    format!("{}-{}", head, tail)
+}
+
+/// Generate a `length`-char pairing code like "ABCD-EFGH" (no ambiguous chars).
+fn generate_pairing_code(length: usize) -> String {
+    let mut rng = rand::thread_rng();
+    let chars: Vec<u8> = (0..length)
+        .map(|_| CODE_CHARS[rng.gen_range(0..CODE_CHARS.len())])
+        .collect();
+    hyphenate(&String::from_utf8(chars).unwrap())
+}
+
+/// Canonical form of a hand-typed pairing code: case, hyphens and whitespace are
+/// ignored, so "abcd efgh" and "ABCDEFGH" both give "ABCD-EFGH". None unless the
+/// rest is exactly `length` characters from `CODE_CHARS`.
+pub fn normalize_code(raw: &str, length: usize) -> Option<String> {
+    let code: String = raw
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if code.len() != length || !code.bytes().all(|b| CODE_CHARS.contains(&b)) {
+        return None;
+    }
+    Some(hyphenate(&code))
+}
+
+/// Why `raw` was rejected by [`normalize_code`], worded for the person typing it.
+fn invalid_code_message(raw: &str, length: usize) -> String {
+    if raw
+        .chars()
+        .any(|c| matches!(c.to_ascii_uppercase(), '0' | 'O' | '1' | 'I' | 'L'))
+    {
+        "Pairing codes never contain 0, O, 1, I or L - check the code on the pairing page".to_string()
+    } else {
+        let example = hyphenate(&String::from_utf8_lossy(&CODE_CHARS[..length]));
+        format!("Pairing codes are {} letters and digits, like {}", length, example)
+    }
+}
+
+/// [`normalize_code`] for JSON endpoints: invalid codes → 400 with the reason.
+pub(crate) fn parse_code(relay: &RelayHub, raw: &str) -> Result<String, (StatusCode, Json<serde_json::Value>)> {
+    relay.normalize_code(raw).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": invalid_code_message(raw, relay.config.code_length)})),
+        )
+    })
+}
+
+// --- Request / Response types ---
+
+#[derive(Deserialize, Validate)]
+pub struct CreatePairRequest {
+    #[validate(length(min = 1, max = 255), regex(path = *HOSTNAME_REGEX))]
+    pub hostname: String,
+    /// This room's unpaired lifetime instead of `PAIR_ROOM_TTL_SECS`.
+    #[validate(range(min = MIN_ROOM_EXPIRY_SECS, max = MAX_ROOM_EXPIRY_SECS))]
+    pub expires_in_seconds: Option<u64>,
+    /// Hand back this hostname's unpaired room, if `owner_token` owns one, instead of a new code.
+    #[serde(default = "default_reuse_existing")]
+    pub reuse_existing: bool,
+    /// Owner token of the room to reuse, from an earlier create.
+    pub owner_token: Option<String>,
+}
+
+fn default_reuse_existing() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreatePairResponse {
+    pub code: String,
+    /// Bearer token for room management (`DELETE /api/pair/:code`).
+    pub owner_token: String,
+    /// Seconds the room lives unpaired.
+    pub expires_in_seconds: u64,
+    /// True when an existing unpaired room was handed back (200 instead of 201).
+    #[serde(default)]
+    pub reused: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PairStatusResponse {
+    /// True only when atem is connected and at least one astation is
+    /// connected and live: its channel is open and it has sent something,
+    /// pongs included, within two ping intervals (capped at
+    /// `RELAY_IDLE_TIMEOUT_SECS`).
+    pub paired: bool,
+    /// Astation is attached but none of its connections is live, e.g. the
+    /// process died without closing its socket. `astation.stale_reason` says why.
+    #[serde(default)]
+    pub astation_stale: bool,
+    pub hostname: String,
+    pub created_secs_ago: u64,
+    pub atem_connected: bool,
+    pub astation_connected: bool,
+    /// Astation clients connected to the room.
+    #[serde(default)]
+    pub astation_count: usize,
+    /// When both sides were first connected together.
+    #[serde(default)]
+    pub paired_at: Option<DateTime<Utc>>,
+    /// Seconds until the unpaired-room expiry, negative once past it; null
+    /// while an astation is connected, which exempts the room.
+    pub expires_in_secs: Option<i64>,
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+    /// Seconds since a side last answered a relay ping, if ever.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_pong_secs_ago: Option<u64>,
+    /// Messages dropped because a side's queue was full.
+    #[serde(default)]
+    pub dropped_messages: u64,
+    /// Messages waiting for atem to connect.
+    #[serde(default)]
+    pub atem_backlog: usize,
+    /// Messages waiting for astation to connect.
+    #[serde(default)]
+    pub astation_backlog: usize,
+    #[serde(default)]
+    pub atem: SideStatus,
+    /// The most recently connected astation.
+    #[serde(default)]
+    pub astation: SideStatus,
+    /// Both sides completed the `e2e_init` exchange.
+    #[serde(default)]
+    pub e2e: bool,
+    /// Smoothed round trip of the server's latency probes to atem, in
+    /// milliseconds; absent until atem has answered one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub atem_rtt_ms: Option<f64>,
+    /// The same for astation, across all of its connections.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub astation_rtt_ms: Option<f64>,
+}
+
+/// One side of a pair room, with the client details it connected with.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SideStatus {
+    pub connected: bool,
+    #[serde(flatten)]
+    pub client: ClientInfo,
+    /// Seconds since the client last sent anything, pongs included.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_secs_ago: Option<u64>,
+    /// `channel_closed` or `silent` when the connection is not live.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stale_reason: Option<String>,
+}
+
+impl SideStatus {
+    fn of(connection: Option<&Connection>) -> Self {
+        match connection {
+            Some(connection) => Self {
+                connected: true,
+                client: connection.client.clone(),
+                last_seen_secs_ago: Some(connection.last_seen.elapsed().as_secs()),
+                stale_reason: connection.stale_reason().map(str::to_string),
+            },
+            None => Self::default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct WsQuery {
+    // Pairing-based auth (traditional)
+    pub role: Option<String>,
+    pub code: Option<String>,
+    // Session-based auth (after HTTP auth)
+    pub session: Option<String>,
+    /// Receive messages the other side sent while this side was not connected.
+    #[serde(default)]
+    pub replay_buffer: bool,
+    /// Replace an existing connection for the same role (default); false gets 409
+    /// instead. Ignored when `RELAY_REJECT_DUPLICATE_ROLES` is set.
+    #[serde(default = "default_takeover")]
+    pub takeover: bool,
+    /// Client app version, reported in pair status.
+    pub client_version: Option<String>,
+    /// Client platform (e.g. `macos`), reported in pair status.
+    pub platform: Option<String>,
+    /// Reconnect token from `pair_established`; required by code once the room has paired.
+    /// With `role=observer`, either side's reconnect token or the admin token.
+    pub token: Option<String>,
+    /// `1` or `true` forwards frames without envelope checks, for clients that
+    /// predate the envelope protocol.
+    pub raw: Option<String>,
+}
+
+/// How a relay connection behaves, from the `/ws` query.
+#[derive(Clone, Copy)]
+struct ConnectOptions {
+    replay_buffer: bool,
+    takeover: bool,
+    raw: bool,
+}
+
+impl ConnectOptions {
+    fn new(params: &WsQuery, takeover: bool) -> Self {
+        Self {
+            replay_buffer: params.replay_buffer,
+            takeover,
+            raw: matches!(params.raw.as_deref(), Some("1" | "true")),
+        }
+    }
+}
+
+/// Verdict of [`MessageLimiter::check`] on one incoming frame.
+#[derive(Debug, PartialEq, Eq)]
+enum RateVerdict {
+    Allow,
+    /// Over the limit: drop the frame, telling the client when `notify` is set.
+    Drop { notify: bool },
+    /// Over the limit for longer than `rate_limit_close_after`.
+    Close,
+}
+
+/// Token bucket over one connection's text frames, so a client stuck in a
+/// send loop cannot flood its peer. Plain fields only; nothing is allocated
+/// per frame.
+struct MessageLimiter {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    refilled_at: Instant,
+    close_after: Duration,
+    /// Start of the current run of dropped frames
+    limited_since: Option<Instant>,
+    last_drop: Instant,
+    last_notice: Option<Instant>,
+}
+
+impl MessageLimiter {
+    /// `None` when the config disables rate limiting.
+    fn new(config: &RelayConfig, now: Instant) -> Option<Self> {
+        if config.message_rate == 0 {
+            return None;
+        }
+        let burst = f64::from(config.message_burst);
+        Some(Self {
+            rate: f64::from(config.message_rate),
+            burst,
+            tokens: burst,
+            refilled_at: now,
+            close_after: config.rate_limit_close_after,
+            limited_since: None,
+            last_drop: now,
+            last_notice: None,
+        })
+    }
+
+    fn check(&mut self, now: Instant) -> RateVerdict {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return RateVerdict::Allow;
+        }
+
+        // A pause in the drops ends the run; flooding that keeps going does not
+        let since = match self.limited_since {
+            Some(since) if now.saturating_duration_since(self.last_drop) < RATE_LIMIT_QUIET => since,
+            _ => now,
+        };
+        self.limited_since = Some(since);
+        self.last_drop = now;
+        if now.saturating_duration_since(since) >= self.close_after {
+            return RateVerdict::Close;
+        }
+        let notify = self
+            .last_notice
+            .is_none_or(|at| now.saturating_duration_since(at) >= RATE_LIMIT_NOTICE_INTERVAL);
+        if notify {
+            self.last_notice = Some(now);
+        }
+        RateVerdict::Drop { notify }
+    }
+}
+
+fn default_takeover() -> bool {
+    true
+}
+
+/// 409 for a connection that would replace a live one when takeover is off.
+fn role_taken(role: Role) -> axum::response::Response {
+    (
+        StatusCode::CONFLICT,
+        Json(serde_json::json!({
+            "error": format!("Role {} is already connected to this room", role),
+            "role": role.as_str(),
+        })),
+    )
+        .into_response()
+}
+
+/// The side named by `role`; 400 unless it is `atem` or `astation`.
+fn check_role(role: &str) -> Result<Role, (StatusCode, Json<serde_json::Value>)> {
+    Role::parse(role).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "role must be atem or astation" })),
+        )
+    })
+}
+
+#[derive(Deserialize)]
+pub struct PairPageQuery {
+    pub code: String,
+}
+
+// --- Handlers ---
+
+/// POST /api/pair — Register for pairing, get a code back.
+pub async fn create_pair_handler(
+    State(state): State<AppState>,
+    Json(body): Json<CreatePairRequest>,
+) -> impl IntoResponse {
+    if let Err(errors) = body.validate() {
+        let error = if errors.field_errors().contains_key("hostname") {
+            "invalid_hostname"
+        } else {
+            "invalid_expires_in_seconds"
+        };
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": error }))).into_response();
+    }
+
+    let expires_in_seconds = body.expires_in_seconds.unwrap_or(state.relay.config.room_ttl_secs);
+    let previous_owner_token = body.owner_token.filter(|_| body.reuse_existing);
+    let created = if let Some(previous_owner_token) = previous_owner_token {
+        state
+            .relay
+            .insert_or_reuse_room(body.hostname, &previous_owner_token, generate_session_token(), expires_in_seconds)
+            .await
+    } else {
+        let owner_token = generate_session_token();
+        state
+            .relay
+            .insert_expiring_room(body.hostname, owner_token.clone(), expires_in_seconds)
+            .await
+            .map(|code| (code, owner_token, false))
+    };
+    let Some((code, owner_token, reused)) = created else {
+        tracing::error!("No free pairing code after {} attempts", MAX_CODE_ATTEMPTS);
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({"error": "No free pairing code, try again"})),
+        )
+            .into_response();
+    };
+
+    let status = if reused {
+        tracing::info!("Pair room reused: {}", code);
+        StatusCode::OK
+    } else {
+        tracing::info!("Pair room created: {}", code);
+        StatusCode::CREATED
+    };
+    (
+        status,
+        Json(CreatePairResponse {
+            code,
+            owner_token,
+            expires_in_seconds,
+            reused,
+        }),
+    )
+        .into_response()
+}
+
+/// Require the room's owner token for a room management endpoint.
+/// Missing token → 401, unknown room → 404, wrong token → 403.
+pub(crate) async fn require_owner(
+    hub: &RelayHub,
+    code: &str,
+    headers: &HeaderMap,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let token = bearer_token(headers).ok_or((
+        StatusCode::UNAUTHORIZED,
+        Json(serde_json::json!({"error": "Missing owner token"})),
+    ))?;
+    let rooms = hub.rooms.read().await;
+    let room = rooms.get(code).ok_or((
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({"error": "Room not found"})),
+    ))?;
+    if !constant_time_eq(token, &room.owner_token) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({"error": "Invalid owner token"})),
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `code`'s room exists and `token` is its owner token.
+pub(crate) async fn is_room_owner(hub: &RelayHub, code: &str, token: &str) -> bool {
+    hub.rooms.read().await.get(code).is_some_and(|room| constant_time_eq(token, &room.owner_token))
+}
+
+/// DELETE /api/pair/:code — Tear down a room (owner token required).
+/// Dropping the room's senders closes both WebSocket connections with
+/// [`CloseReason::RoomDeleted`].
+pub async fn delete_pair_handler(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let code = parse_code(&state.relay, &code)?;
+    require_owner(&state.relay, &code, &headers).await?;
+    let Some(room) = state.relay.rooms.write().await.remove(&code) else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "Room not found"})),
+        ));
+    };
+    state.relay.emit(RoomEventType::RoomRemoved, &code, &room.hostname);
+    // Queued ahead of the close that dropping the room's senders causes
+    room.close_connections(CloseReason::RoomDeleted);
+    let notice = serde_json::json!({ "type": "room_deleted", "code": code }).to_string();
+    for tx in Role::ALL.into_iter().flat_map(|role| room.senders(role)) {
+        let _ = tx.try_send(notice.clone());
+    }
+    tracing::info!("Pair room deleted: {}", code);
+    Ok(StatusCode::OK)
+}
+
+/// POST /api/pair/:code/renew — Give an unpaired room another full lifetime,
+/// up to `MAX_RENEWED_ROOM_AGE_SECS` old. The owner token allows it any time;
+/// without one (the pair page) only within `RENEW_WINDOW_SECS` of expiry.
+pub async fn renew_pair_handler(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let code = parse_code(&state.relay, &code)?;
+    let is_owner = bearer_token(&headers).is_some();
+    if is_owner {
+        require_owner(&state.relay, &code, &headers).await?;
+    }
+    let mut rooms = state.relay.rooms.write().await;
+    let Some(room) = rooms.get_mut(&code) else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "Room not found"})),
+        ));
+    };
+    let expires_in = room.lifetime_secs().saturating_sub(room.created_at.elapsed().as_secs());
+    if !is_owner && expires_in > RENEW_WINDOW_SECS {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({"error": "Missing owner token"})),
+        ));
+    }
+    if !room.renew() {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({"error": "Room cannot be renewed any further"})),
+        ));
+    }
+    tracing::info!("Pair room renewed: {}", code);
+    Ok(Json(room.status()))
+}
+
+/// GET /api/admin/relay/stats — Relay load counters (admin token required).
+pub async fn relay_stats_handler(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    require_admin(&state, &headers)?;
+    Ok::<_, (StatusCode, Json<serde_json::Value>)>(Json(state.relay.stats().await))
+}
+
+/// GET /api/relay/rooms/events — Server-Sent Events for room creation, each
+/// side connecting and room removal (admin token required).
+pub async fn room_events_handler(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    require_admin(&state, &headers)?;
+    let events = futures_util::stream::unfold(state.relay.subscribe_events(), |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => return Some((Ok::<_, Infallible>(event.to_sse()), rx)),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Room event stream fell behind, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Ok::<_, (StatusCode, Json<serde_json::Value>)>(
+        Sse::new(events).keep_alive(KeepAlive::new().interval(ROOM_EVENT_KEEPALIVE)),
+    )
+}
+
+/// GET /api/admin/pair-rooms — Every open room with its code masked (admin token required).
+pub async fn list_pair_rooms_admin_handler(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    require_admin(&state, &headers)?;
+    let rooms: Vec<PairRoomSummary> = state
+        .relay
+        .list_summaries()
+        .await
+        .into_iter()
+        .map(|room| PairRoomSummary {
+            code: mask_code(&room.code),
+            ..room
+        })
+        .collect();
+    Ok::<_, (StatusCode, Json<serde_json::Value>)>(Json(serde_json::json!({
+        "count": rooms.len(),
+        "rooms": rooms,
+    })))
+}
+
+/// GET /api/admin/pair-rooms/:code — One room by its full code (admin token required).
+pub async fn get_pair_room_admin_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(code): Path<String>,
+) -> impl IntoResponse {
+    require_admin(&state, &headers)?;
+    // Session rooms (`session-<id>`) are not pairing codes; look those up as given
+    let code = state.relay.normalize_code(&code).unwrap_or(code);
+    let rooms = state.relay.rooms.read().await;
+    match rooms.get(&code) {
+        Some(room) => Ok(Json(room.summary(Utc::now()))),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "Room not found"})),
+        )),
+    }
+}
+
+/// GET /api/admin/pair-rooms/:code/events — The room's recent connections,
+/// message counts, drops and errors, oldest first (admin token required).
+pub async fn pair_room_history_admin_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(code): Path<String>,
+) -> impl IntoResponse {
+    require_admin(&state, &headers)?;
+    let code = state.relay.normalize_code(&code).unwrap_or(code);
+    match state.relay.room_history(&code).await {
+        Some(events) => Ok(Json(serde_json::json!({
+            "code": code,
+            "count": events.len(),
+            "events": events,
+        }))),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "Room not found"})),
+        )),
+    }
+}
+
+/// GET /api/pair/:code — Check pairing status. `metadata` is only shown to
+/// the room's owner token.
+pub async fn pair_status_handler(
+    State(state): State<AppState>,
+    axum::extract::Path(code): axum::extract::Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let code = parse_code(&state.relay, &code)?;
+    let rooms = state.relay.rooms.read().await;
+    match rooms.get(&code) {
+        Some(room) => {
+            let mut status = room.status();
+            // Metadata is the owner's; anyone else who knows the code sees the rest
+            if !bearer_token(&headers).is_some_and(|token| constant_time_eq(token, &room.owner_token)) {
+                status.metadata.clear();
+            }
+            Ok(Json(status))
+        }
+        None => Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "Room not found"})),
+        )),
+    }
+}
+
+/// GET /ws — WebSocket upgrade for relay.
+/// Auth methods:
+///   1. Pairing: ?role=atem|astation&code=XXXX (short-lived, explicit approval)
+///   2. Session: ?session=<session_id> (after HTTP auth, longer-lived)
+pub async fn ws_handler(
+    State(state): State<AppState>,
+    Query(params): Query<WsQuery>,
+    headers: HeaderMap,
+    // Checked after the query so bad requests get 400/404/409, not 426
+    ws: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+) -> impl IntoResponse {
+    let hub = state.relay.clone();
+    let takeover = params.takeover && !hub.config.reject_duplicate_roles;
+
+    // Session-based auth (hybrid flow)
+    if let Some(session_id) = params.session.clone() {
+        // Validate session
+        let session = state.sessions.get(&session_id).await;
+        match session {
+            Some(s) if s.status == crate::auth::SessionStatus::Granted => {
+                // Valid session - use session_id as room code, role defaults to "atem"
+                let code = format!("session-{}", session_id);
+                let role = match check_role(params.role.as_deref().unwrap_or("atem")) {
+                    Ok(role) => role,
+                    Err(error) => return error.into_response(),
+                };
+
+                // Create room if it doesn't exist
+                {
+                    let mut rooms = hub.rooms.write().await;
+                    if rooms
+                        .get(&code)
+                        .is_some_and(|room| !takeover && !hub.shares_role(role) && room.is_connected(role)) {
+                        return role_taken(role);
+                    }
+                    if !rooms.contains_key(&code) {
+                        rooms.insert(
+                            code.clone(),
+                            PairRoom::new(
+                                code.clone(),
+                                s.hostname.clone(),
+                                generate_session_token(),
+                                hub.config.room_ttl_secs,
+                            ),
+                        );
+                        hub.emit(RoomEventType::RoomCreated, &code, &s.hostname);
+                    }
+                }
+
+                let ws = match ws {
+                    Ok(ws) => ws,
+                    Err(rejection) => return rejection.into_response(),
+                };
+                let options = ConnectOptions::new(&params, takeover);
+                let client = ClientInfo::from_query(&params);
+                return ws
+                    .on_upgrade(move |socket| handle_ws(hub, code, role, client, options, socket))
+                    .into_response();
+            }
+            _ => {
+                return (StatusCode::UNAUTHORIZED, "Invalid or expired session").into_response();
+            }
+        }
+    }
+
+    // Pairing-based auth (traditional flow)
+    let code = match params.code.as_deref() {
+        Some(c) => match state.relay.normalize_code(c) {
+            Some(code) => code,
+            None => {
+                let message = invalid_code_message(c, state.relay.config.code_length);
+                return (StatusCode::BAD_REQUEST, message).into_response();
+            }
+        },
+        None => return (StatusCode::BAD_REQUEST, "Missing code or session parameter").into_response(),
+    };
+    let role = match params.role.as_deref() {
+        Some(r) => r,
+        None => return (StatusCode::BAD_REQUEST, "Missing role parameter").into_response(),
+    };
+    if role == "observer" {
+        // Admin token from the header (or the query, for browsers); reconnect tokens from the query
+        let token = bearer_token(&headers).or(params.token.as_deref());
+        let frames = {
+            let rooms = hub.rooms.read().await;
+            match rooms.get(&code) {
+                None => return (StatusCode::NOT_FOUND, "Room not found").into_response(),
+                Some(room) if !room.accepts_observer(token, state.admin_token.as_deref()) => {
+                    return (StatusCode::UNAUTHORIZED, "Observers need the admin token or a reconnect token")
+                        .into_response();
+                }
+                Some(room) => room.observers.subscribe(),
+            }
+        };
+        let ws = match ws {
+            Ok(ws) => ws,
+            Err(rejection) => return rejection.into_response(),
+        };
+        return ws
+            .on_upgrade(move |socket| handle_observer(code, frames, socket))
+            .into_response();
+    }
+    let role = match check_role(role) {
+        Ok(role) => role,
+        Err(error) => return error.into_response(),
+    };
+
+    // Verify room exists
+    {
+        let rooms = hub.rooms.read().await;
+        match rooms.get(&code) {
+            None => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    "Room not found",
+                )
+                    .into_response();
+            }
+            Some(room) if !room.accepts_reconnect(role, params.token.as_deref()) => {
+                tracing::warn!("Room {}: {} connection without a valid reconnect token", code, role);
+                return (StatusCode::UNAUTHORIZED, "Invalid or missing reconnect token").into_response();
+            }
+            Some(room) if !takeover && !hub.shares_role(role) && room.is_connected(role) => {
+                return role_taken(role)
+            }
+            Some(_) => {}
+        }
+    }
+
+    let ws = match ws {
+        Ok(ws) => ws,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let options = ConnectOptions::new(&params, takeover);
+    let client = ClientInfo::from_query(&params);
+    ws.on_upgrade(move |socket| handle_ws(hub, code, role, client, options, socket))
+        .into_response()
+}
+
+/// A read-only connection: copies of relayed frames go out, and anything the
+/// observer sends is answered with an error. Observers are not part of the
+/// room, so they never affect pairing, notices or expiry.
+async fn handle_observer(code: String, mut frames: broadcast::Receiver<String>, socket: WebSocket) {
+    let (mut ws_sink, mut ws_stream) = socket.split();
+    tracing::info!("WS observer connected: code={}", code);
+    loop {
+        let outgoing = tokio::select! {
+            frame = frames.recv() => match frame {
+                Ok(frame) => frame,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    serde_json::json!({ "type": "observer_lagged", "skipped": skipped }).to_string()
+                }
+                // Room removed
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            msg = ws_stream.next() => match msg {
+                Some(Ok(axum::extract::ws::Message::Text(_))) => EnvelopeError::read_only().to_frame(),
+                Some(Ok(axum::extract::ws::Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+        };
+        if ws_sink.send(axum::extract::ws::Message::Text(outgoing)).await.is_err() {
+            break;
+        }
+    }
+    let _ = ws_sink.send(axum::extract::ws::Message::Close(None)).await;
+    tracing::info!("WS observer disconnected: code={}", code);
+}
+
+async fn handle_ws(
+    hub: RelayHub,
+    code: String,
+    role: Role,
+    client: ClientInfo,
+    options: ConnectOptions,
+    socket: WebSocket,
+) {
+    let (mut ws_sink, mut ws_stream) = socket.split();
+    let (tx, mut rx) = mpsc::channel::<String>(hub.config.channel_capacity);
+
+    // Register this side's sender in the room
+    let ClientInfo { version, platform } = client.clone();
+    let Some((close, last_seen)) = hub.attach(&code, role, &tx, client, options.replay_buffer, options.takeover).await else {
+        return;
+    };
+    hub.counters.connections(role).fetch_add(1, Ordering::Relaxed);
+    if let Some(hostname) = hub.rooms.read().await.get(&code).map(|room| room.hostname.clone()) {
+        hub.emit(RoomEventType::connected(role), &code, &hostname);
+    }
+    // The room holds the only strong sender, so deleting the room ends the writer
+    let own_tx = tx.downgrade();
+    drop(tx);
+
+    tracing::info!(
+        "WS connected: role={} code={} version={} platform={}",
+        role,
+        code,
+        version.as_deref().unwrap_or("-"),
+        platform.as_deref().unwrap_or("-")
+    );
+
+    // Task: forward messages from our channel to the WS sink, pinging while quiet.
+    // A client silent past the idle timeout is closed (dead sockets from sleeping
+    // laptops or NAT timeouts never send a Close frame).
+    let code_for_writer = code.clone();
+    let role_for_writer = role;
+    let seen_by_writer = last_seen.clone();
+    let close_for_writer = close.clone();
+    let RelayConfig { ping_interval, idle_timeout, .. } = hub.config;
+    let mut write_task = tokio::spawn(async move {
+        let mut ping = tokio::time::interval_at(Instant::now() + ping_interval, ping_interval);
+        loop {
+            let frame = tokio::select! {
+                msg = rx.recv() => match msg {
+                    Some(msg) => axum::extract::ws::Message::Text(msg),
+                    // The room dropped our sender: close with its reason, if any
+                    None => break,
+                },
+                _ = ping.tick() => {
+                    let idle = seen_by_writer.elapsed();
+                    if idle > idle_timeout {
+                        tracing::info!(
+                            "WS idle for {}s, closing: role={} code={}",
+                            idle.as_secs(),
+                            role_for_writer,
+                            code_for_writer
+                        );
+                        close_for_writer.set(CloseReason::IdleTimeout);
+                        break;
+                    }
+                    axum::extract::ws::Message::Ping(Vec::new())
+                }
+            };
+            if ws_sink.send(frame).await.is_err() {
+                tracing::debug!("WS write failed for {}", code_for_writer);
+                return;
+            }
+        }
+        let _ = ws_sink
+            .send(axum::extract::ws::Message::Close(close_for_writer.frame()))
+            .await;
+    });
+
+    // Read incoming frames and forward to the other side
+    let hub_for_read = hub.clone();
+    let code_for_read = code.clone();
+    // A writer stuck on a stalled socket never sees its channel close, so also
+    // notice when the room has dropped our sender
+    let mut detached_check = tokio::time::interval(ping_interval);
+    let mut limiter = MessageLimiter::new(&hub.config, Instant::now());
+    // Raw clients predate envelopes, so they are not probed
+    let probe_interval = hub.config.latency_probe_interval;
+    let mut probe = (!probe_interval.is_zero() && !options.raw)
+        .then(|| tokio::time::interval_at(Instant::now() + probe_interval, probe_interval));
+    let mut writer_done = false;
+    loop {
+        let msg_result = tokio::select! {
+            msg = ws_stream.next() => match msg {
+                Some(msg) => msg,
+                None => break,
+            },
+            _ = &mut write_task => {
+                writer_done = true;
+                break;
+            }
+            _ = detached_check.tick() => {
+                if own_tx.strong_count() == 0 {
+                    break;
+                }
+                continue;
+            }
+            _ = async {
+                match probe.as_mut() {
+                    Some(probe) => probe.tick().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                if let Some(tx) = own_tx.upgrade() {
+                    hub_for_read.send_latency_probe(&code_for_read, role, &tx).await;
+                }
+                continue;
+            }
+        };
+        if msg_result.is_ok() {
+            last_seen.touch();
+        }
+        match msg_result {
+            Ok(axum::extract::ws::Message::Text(text)) => {
+                let Some(tx) = own_tx.upgrade() else {
+                    break;
+                };
+                match limiter.as_mut().map_or(RateVerdict::Allow, |limiter| limiter.check(Instant::now())) {
+                    RateVerdict::Allow => {}
+                    RateVerdict::Drop { notify } => {
+                        hub_for_read.counters.dropped();
+                        hub_for_read
+                            .tally_history(&code_for_read, RoomHistoryKind::Dropped, role, Some("rate_limited"))
+                            .await;
+                        if notify {
+                            tracing::debug!("Rate limiting {} in {}", role, code_for_read);
+                            let _ = tx.try_send(EnvelopeError::rate_limited().to_frame());
+                        }
+                        continue;
+                    }
+                    RateVerdict::Close => {
+                        tracing::info!("WS over its message rate for too long, closing: role={} code={}", role, code_for_read);
+                        close.set(CloseReason::RateLimited);
+                        break;
+                    }
+                }
+                if options.raw {
+                    route_incoming(&hub_for_read, &code_for_read, role, &tx, text).await;
+                    continue;
+                }
+                match envelope::check(&text) {
+                    Ok(Frame::Forward | Frame::Relay | Frame::ProbeAck) => {
+                        route_incoming(&hub_for_read, &code_for_read, role, &tx, text).await;
+                    }
+                    Ok(Frame::Ping { seq }) => {
+                        let _ = tx.try_send(envelope::pong(seq));
+                    }
+                    Err(error) => {
+                        tracing::debug!("Bouncing invalid frame from {} in {}: {}", role, code_for_read, error.message);
+                        hub_for_read
+                            .tally_history(&code_for_read, RoomHistoryKind::Error, role, Some(error.code))
+                            .await;
+                        let _ = tx.try_send(error.to_frame());
+                    }
+                }
+            }
+            Ok(axum::extract::ws::Message::Close(_)) => break,
+            // Pings are answered by axum; pings, pongs and binary frames only
+            // count as liveness
+            Ok(_) => {}
+            Err(e) => {
+                tracing::debug!("WS read error for {} {}: {}", role, code_for_read, e);
+                break;
+            }
+        }
+    }
+
+    // Cleanup: remove our sender from the room, unless we were replaced, and
+    // let the other side know we are gone
+    if hub_for_read.detach(&code, role, &own_tx).await {
+        hub_for_read.notify_peer_disconnected(&code, role).await;
+    }
+    if let Some(room) = hub_for_read.rooms.read().await.get(&code) {
+        let reason = close.reason().map(CloseReason::reason);
+        room.history.note(RoomHistoryKind::Disconnected, role, reason);
+    }
+
+    // With our sender gone the writer flushes what is queued and sends its
+    // Close frame; don't wait on a socket that has stopped draining
+    if !writer_done && tokio::time::timeout(CLOSE_FLUSH_WAIT, &mut write_task).await.is_err() {
+        write_task.abort();
+    }
+    hub_for_read.counters.connections(role).fetch_sub(1, Ordering::Relaxed);
+    tracing::info!("WS disconnected: role={} code={}", role, code);
+}
+
+/// Whether a frame may cross an end-to-end room: a `v1` envelope (its payload
+/// is the clients' ciphertext), a binary relay frame or the key exchange itself.
+/// Bare text and `{"relay":"text"}` frames are refused.
+fn is_opaque(incoming: &Incoming, text: &str) -> bool {
+    match incoming {
+        Incoming::E2eInit | Incoming::Envelope(RelayMessage::Binary { .. }) => true,
+        Incoming::Passthrough => envelope::check(text) == Ok(Frame::Forward),
+        _ => false,
+    }
+}
+
+/// Handle one text frame from `role`: forward it to the other side or handle it
+/// locally. `own_tx` is the sender's channel, used for replies such as pongs.
+async fn route_incoming(
+    hub: &RelayHub,
+    code: &str,
+    role: Role,
+    own_tx: &mpsc::Sender<String>,
+    text: String,
+) {
+    let incoming = Incoming::parse(&text);
+    match incoming {
+        Incoming::Passthrough
+        | Incoming::E2eInit
+        | Incoming::Envelope(RelayMessage::Text { .. })
+        | Incoming::Envelope(RelayMessage::Binary { .. }) => {
+            let e2e_init = incoming == Incoming::E2eInit;
+            if e2e_init {
+                let mut rooms = hub.rooms.write().await;
+                if rooms.get_mut(code).is_some_and(|room| room.confirm_e2e(role)) {
+                    tracing::info!("Room {}: end-to-end encryption established", code);
+                }
+            }
+            let peer_role = role.peer();
+            // Get the other side's senders from the room (it may have connected since we started)
+            let others = {
+                let rooms = hub.rooms.read().await;
+                let Some(room) = rooms.get(code) else {
+                    return;
+                };
+                if room.e2e && !is_opaque(&incoming, &text) {
+                    tracing::debug!("Refusing plaintext frame from {} in end-to-end room {}", role, code);
+                    let error = EnvelopeError::e2e_plaintext();
+                    room.history.tally(RoomHistoryKind::Error, role, Some(error.code));
+                    let _ = own_tx.try_send(error.to_frame());
+                    return;
+                }
+                room.history.tally(RoomHistoryKind::Messages, role, None);
+                room.observe(role, peer_role, &text, e2e_init);
+                room.senders(peer_role)
+            };
+            if !others.is_empty() {
+                for other_tx in &others {
+                    hub.forward(code, peer_role, other_tx, text.clone()).await;
+                }
+                return;
+            }
+            if hub.buffer_size == 0 {
+                return;
+            }
+            // Hold the message until the other side connects (re-checked under the write lock)
+            let mut rooms = hub.rooms.write().await;
+            let Some(room) = rooms.get_mut(code) else {
+                return;
+            };
+            let others = room.senders(peer_role);
+            if others.is_empty() {
+                room.backlog_mut(peer_role).push(text, hub.buffer_size);
+                return;
+            }
+            for other_tx in others {
+                // Connected just now, so its queue has room
+                if other_tx.try_send(text.clone()).is_ok() {
+                    hub.counters.delivered_bytes(text.len());
+                } else {
+                    room.dropped_messages += 1;
+                    room.history.tally(RoomHistoryKind::Dropped, peer_role, Some("queue_full"));
+                    hub.counters.dropped();
+                }
+            }
+        }
+        Incoming::Envelope(RelayMessage::Ping { id }) => {
+            if let Ok(pong) = serde_json::to_string(&RelayMessage::Pong { id }) {
+                let _ = own_tx.try_send(pong);
+            }
+        }
+        Incoming::Envelope(RelayMessage::Pong { .. }) => {
+            let mut rooms = hub.rooms.write().await;
+            if let Some(room) = rooms.get_mut(code) {
+                room.last_pong_at = Some(Instant::now());
+            }
+        }
+        Incoming::Envelope(RelayMessage::Control { action, payload }) => {
+            let mut rooms = hub.rooms.write().await;
+            if let Some(room) = rooms.get_mut(code) {
+                if let Err(error) = room.apply_control(&action, payload) {
+                    tracing::debug!("Refusing {} control from {} in {}: {}", action, role, code, error.code);
+                    room.history.tally(RoomHistoryKind::Error, role, Some(error.code));
+                    let _ = own_tx.try_send(error.to_frame());
+                }
+            }
+        }
+        Incoming::ProbeAck { nonce } => {
+            let rtt = match nonce {
+                Some(nonce) => hub.ack_latency_probe(code, role, own_tx, &nonce).await,
+                None => None,
+            };
+            match rtt {
+                Some(rtt) => tracing::debug!("Latency probe round trip to {} in {}: {:?}", role, code, rtt),
+                None => tracing::debug!("Ignoring unexpected latency probe ack from {} in {}", role, code),
+            }
+        }
+        Incoming::Unrecognized => {
+            tracing::warn!("Dropping unrecognized relay message from {} in {}", role, code);
+        }
+    }
+}
+
+/// GET /pair?code=XXXX — HTML landing page for pairing, in the best language
+/// the browser's `Accept-Language` asks for.
+pub async fn pair_page_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<PairPageQuery>,
+) -> impl IntoResponse {
+    let Some(code) = state.relay.normalize_code(&params.code) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Html(format!(
+                "<h1>Invalid pairing code</h1><p>{}</p>",
+                html_escape(&invalid_code_message(&params.code, state.relay.config.code_length))
+            )),
+        ));
+    };
+    let rooms = state.relay.rooms.read().await;
+    match rooms.get(&code) {
+        Some(room) => {
+            let expires_in_secs = room
+                .status()
+                .expires_in_secs
+                .map(|secs| secs.max(0));
+            let lang = l10n::negotiate_language(
+                headers
+                    .get(header::ACCEPT_LANGUAGE)
+                    .and_then(|value| value.to_str().ok()),
+            );
+            let html = render_pair_page_l10n(&code, &room.hostname, lang, expires_in_secs, "");
+            Ok(Html(html))
+        }
+        None => Err((
+            StatusCode::NOT_FOUND,
+            Html("<h1>Pairing code not found</h1><p>The code may have expired.</p>".to_string()),
+        )),
+    }
+}
+
+/// Pixel size of one QR module; the SVG scales, this only sets its intrinsic size.
+const QR_MODULE_PX: u32 = 8;
+
+/// GET /pair/qr?code=XXXX — SVG QR code of the room's `astation://` deep link.
+pub async fn pair_qr_handler(
+    State(state): State<AppState>,
+    Query(params): Query<PairPageQuery>,
+) -> Response {
+    let Some(code) = state.relay.normalize_code(&params.code) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            invalid_code_message(&params.code, state.relay.config.code_length),
+        )
+            .into_response();
+    };
+    if !state.relay.room_exists(&code).await {
+        return (StatusCode::NOT_FOUND, "Pairing code not found").into_response();
+    }
+    match render_pair_qr(&code) {
+        Ok(svg) => (
+            [
+                (header::CONTENT_TYPE, "image/svg+xml"),
+                // The image depends only on the code, so it never changes
+                (header::CACHE_CONTROL, "public, max-age=86400, immutable"),
+            ],
+            svg,
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Failed to encode QR code for {}: {}", code, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// The deep link the pair page's button and QR code open.
+fn pair_deep_link(code: &str) -> String {
+    format!("astation://pair?code={}", urlencoding::encode(code))
+}
+
+fn render_pair_qr(code: &str) -> Result<String, qrcode::types::QrError> {
+    let qr = qrcode::QrCode::new(pair_deep_link(code))?;
+    Ok(qr
+        .render::<qrcode::render::svg::Color>()
+        .module_dimensions(QR_MODULE_PX, QR_MODULE_PX)
+        .build())
+}
+
+/// HTML-escape a string to prevent XSS attacks
+fn html_escape(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&#x27;".to_string(),
+            '/' => "&#x2F;".to_string(),
+            _ => c.to_string(),
+        })
+        .collect()
+}
+
+/// Render the pair page in `lang` (see [`l10n::SUPPORTED_LANGUAGES`]; anything
+/// else renders in English). `expires_in_secs`, when set, drives a countdown
+/// on the page. `_theme_class` is reserved for server-side theming and
+/// currently ignored.
+fn render_pair_page_l10n(
+    code: &str,
+    hostname: &str,
+    lang: &str,
+    expires_in_secs: Option<i64>,
+    _theme_class: &str,
+) -> String {
+    let lang = if l10n::PAIR_PAGE_STRINGS.contains_key(lang) { lang } else { l10n::DEFAULT_LANGUAGE };
+    let t = |key| html_escape(l10n::text(lang, key));
+    // Countdown text is set from script, so it goes in as JS string literals
+    let js = |key| serde_json::to_string(l10n::text(lang, key)).unwrap_or_default();
+    let code_escaped = html_escape(code);
+    let hostname_escaped = html_escape(hostname);
+    let countdown = match expires_in_secs {
+        Some(secs) => format!(
+            r#"<div class="expires" id="expires" data-expires-in="{secs}" data-renew-url="/api/pair/{code_url}/renew">{expires_in} {mins}:{rem:02}</div>
+    <script>
+      (function () {{
+        const el = document.getElementById('expires');
+        let left = parseInt(el.dataset.expiresIn, 10);
+        let renewing = false;
+        // Still open near expiry: ask for more time (refused once the room is too old)
+        function renew() {{
+          renewing = true;
+          fetch(el.dataset.renewUrl, {{ method: 'POST' }})
+            .then(function (resp) {{ return resp.ok ? resp.json() : null; }})
+            .then(function (status) {{
+              if (status && status.expires_in_secs > left) {{
+                left = status.expires_in_secs;
+                renewing = false;
+              }}
+            }})
+            .catch(function () {{}});
+        }}
+        const timer = setInterval(function () {{
+          left -= 1;
+          if (left <= 0) {{
+            clearInterval(timer);
+            el.textContent = {expired_js};
+            return;
+          }}
+          if (left <= {window} && !renewing) renew();
+          el.textContent = {expires_in_js} + ' ' + Math.floor(left / 60) + ':' + String(left % 60).padStart(2, '0');
+        }}, 1000);
+      }})();
+    </script>"#,
+            secs = secs,
+            mins = secs / 60,
+            rem = secs % 60,
+            code_url = urlencoding::encode(code),
+            window = RENEW_WINDOW_SECS,
+            expires_in = t("expires_in"),
+            expires_in_js = js("expires_in"),
+            expired_js = js("expired"),
+        ),
+        None => String::new(),
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+  <meta charset="UTF-8">
+  <meta name="viewport" content="width=device-width, initial-scale=1.0">
+  <title>{title} — {code}</title>
+  {theme_init}
+  <style>
+    :root {{ --bg: #0a0a0a; --text: #e0e0e0; --card: #1a1a2e; --heading: #fff; --body: #aaa; --muted: #888; --faint: #666; --accent: #00d4aa; --accent-hover: #00f5c4; --shadow: rgba(0,0,0,0.4); }}
+    @media (prefers-color-scheme: light) {{
+      html:not([data-theme="dark"]) {{ --bg: #f5f5f7; --text: #1d1d1f; --card: #fff; --heading: #111; --body: #444; --muted: #666; --faint: #888; --accent: #00a383; --accent-hover: #00b893; --shadow: rgba(0,0,0,0.1); }}
+    }}
+    html[data-theme="light"] {{ --bg: #f5f5f7; --text: #1d1d1f; --card: #fff; --heading: #111; --body: #444; --muted: #666; --faint: #888; --accent: #00a383; --accent-hover: #00b893; --shadow: rgba(0,0,0,0.1); }}
+    body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', sans-serif; display: flex; justify-content: center; align-items: center; min-height: 100vh; margin: 0; background: var(--bg); color: var(--text); }}
+    .card {{ background: var(--card); border-radius: 16px; padding: 48px; text-align: center; max-width: 420px; box-shadow: 0 8px 32px var(--shadow); }}
+    .code {{ font-size: 48px; font-weight: 700; letter-spacing: 4px; color: var(--accent); margin: 24px 0; font-family: 'SF Mono', monospace; }}
+    .hostname {{ color: var(--muted); font-size: 14px; margin-bottom: 32px; }}
+    .qr {{ display: block; margin: 0 auto 24px; border-radius: 8px; }}
+    .expires {{ color: var(--muted); font-size: 13px; margin-top: 16px; }}
+    .btn {{ display: inline-block; padding: 12px 32px; background: var(--accent); color: #0a0a0a; border-radius: 8px; text-decoration: none; font-weight: 600; font-size: 16px; transition: background 0.2s; }}
+    .btn:hover {{ background: var(--accent-hover); }}
+    .download {{ margin-top: 24px; font-size: 13px; color: var(--faint); }}
+    .download a {{ color: var(--accent); }}
+    h2 {{ margin: 0 0 8px; font-size: 20px; color: var(--heading); }}
+    p {{ margin: 4px 0; font-size: 14px; color: var(--body); }}
+  </style>
+  {theme_css}
+</head>
+<body>
+  {theme_toggle}
+  <div class="card">
+    <h2>{title}</h2>
+    <p>{subtitle}</p>
+    <div class="code">{code}</div>
+    <div class="hostname">{host_label}: {hostname}</div>
+    <img class="qr" src="/pair/qr?code={code_url}" alt="QR code for {code}" width="200" height="200">
+    <a class="btn" href="{deep_link}">{button}</a>
+    {countdown}
+    <div class="download">
+      <p>{footer} <a href="https://github.com/AgoraIO-Community/astation/releases">{download}</a></p>
+    </div>
+  </div>
+</body>
+</html>"#,
+        code = code_escaped,
+        hostname = hostname_escaped,
+        code_url = urlencoding::encode(code),
+        deep_link = pair_deep_link(code),
+        countdown = countdown,
+        lang = lang,
+        title = t("title"),
+        subtitle = t("subtitle"),
+        host_label = t("host"),
+        button = t("button"),
+        footer = t("footer"),
+        download = t("download"),
+        theme_init = theme::INIT_SCRIPT,
+        theme_css = theme::TOGGLE_CSS,
+        theme_toggle = theme::TOGGLE_BUTTON,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::voice_session::VoiceSessionStore;
+    use super::*;
+    use crate::session_verify::SessionVerifyCache;
+
+    #[test]
+    fn pairing_code_format() {
+        let code = generate_pairing_code(DEFAULT_CODE_LENGTH);
+        assert_eq!(code.len(), 9); // 4 + '-' + 4
+        assert_eq!(&code[4..5], "-");
+
+        // No ambiguous characters
+        let no_hyphen = code.replace('-', "");
+        for ch in no_hyphen.chars() {
+            assert!(
+                CODE_CHARS.contains(&(ch as u8)),
+                "Character '{}' should be in CODE_CHARS",
+                ch
+            );
+        }
+    }
+
+    #[test]
+    fn normalize_code_accepts_hand_typed_forms() {
+        for raw in ["ABCD-EFGH", "abcd-efgh", "ABCDEFGH", "abcdefgh", " abcd efgh ", "Ab Cd-eF\tgH", "abcd--efgh"] {
+            assert_eq!(normalize_code(raw, DEFAULT_CODE_LENGTH).as_deref(), Some("ABCD-EFGH"), "{:?}", raw);
+        }
+        for _ in 0..20 {
+            let code = generate_pairing_code(DEFAULT_CODE_LENGTH);
+            assert_eq!(normalize_code(&code.to_lowercase(), DEFAULT_CODE_LENGTH), Some(code));
+        }
+    }
+
+    #[test]
+    fn normalize_code_rejects_invalid_codes() {
+        for raw in ["", "ABCD-EFG", "ABCD-EFGHJ", "ABCD-EF!H", "ABCD-ÉFGH", "session-123"] {
+            assert_eq!(normalize_code(raw, DEFAULT_CODE_LENGTH), None, "{:?}", raw);
+        }
+        for raw in ["ABCD-EFG0", "abcd-efgo", "1BCD-EFGH", "ABCD-EFGI", "ABCD-EFGL"] {
+            assert_eq!(normalize_code(raw, DEFAULT_CODE_LENGTH), None, "{:?}", raw);
+            assert!(invalid_code_message(raw, DEFAULT_CODE_LENGTH).contains("never contain"), "{:?}", raw);
+        }
+        assert!(invalid_code_message("ABC", DEFAULT_CODE_LENGTH).contains("8 letters"));
+    }
+
+    #[test]
+    fn pairing_code_uniqueness() {
+        let codes: Vec<String> = (0..20).map(|_| generate_pairing_code(DEFAULT_CODE_LENGTH)).collect();
+        let unique: std::collections::HashSet<&String> = codes.iter().collect();
+        assert!(unique.len() > 1, "Pairing codes should vary");
+    }
+
+    #[test]
+    fn pairing_code_no_ambiguous_chars() {
+        for _ in 0..100 {
+            let code = generate_pairing_code(DEFAULT_CODE_LENGTH);
+            let no_hyphen = code.replace('-', "");
+            assert!(!no_hyphen.contains('0'), "Should not contain 0");
+            assert!(!no_hyphen.contains('O'), "Should not contain O");
+            assert!(!no_hyphen.contains('1'), "Should not contain 1");
+            assert!(!no_hyphen.contains('I'), "Should not contain I");
+            assert!(!no_hyphen.contains('L'), "Should not contain L");
+        }
+    }
+
+    #[tokio::test]
+    async fn relay_hub_create_and_lookup() {
+        let hub = RelayHub::new();
+
+        let room = PairRoom::new(
+            "ABCD-EFGH".to_string(),
+            "test-host".to_string(),
+            generate_session_token(),
+            DEFAULT_ROOM_TTL_SECS,
+        );
+
+        hub.rooms
+            .write()
+            .await
+            .insert("ABCD-EFGH".to_string(), room);
+
+        let rooms = hub.rooms.read().await;
+        assert!(rooms.contains_key("ABCD-EFGH"));
+        assert_eq!(rooms["ABCD-EFGH"].hostname, "test-host");
+    }
+
+    #[tokio::test]
+    async fn relay_hub_cleanup_expired() {
+        let hub = RelayHub::new();
+
+        // Create a room with a very old timestamp
+        let room = PairRoom {
+            created_at: Instant::now() - std::time::Duration::from_secs(DEFAULT_ROOM_TTL_SECS + 10),
+            ..PairRoom::new(
+                "OLD1-CODE".to_string(),
+                "old-host".to_string(),
+                generate_session_token(),
+                DEFAULT_ROOM_TTL_SECS,
+            )
+        };
+        hub.rooms
+            .write()
+            .await
+            .insert("OLD1-CODE".to_string(), room);
+
+        // Create a fresh room
+        let fresh = PairRoom::new(
+            "NEW1-CODE".to_string(),
+            "new-host".to_string(),
+            generate_session_token(),
+            DEFAULT_ROOM_TTL_SECS,
+        );
+        hub.rooms
+            .write()
+            .await
+            .insert("NEW1-CODE".to_string(), fresh);
+
+        hub.cleanup_expired().await;
+
+        let rooms = hub.rooms.read().await;
+        assert!(!rooms.contains_key("OLD1-CODE"), "Expired room should be removed");
+        assert!(rooms.contains_key("NEW1-CODE"), "Fresh room should remain");
+    }
+
+    #[tokio::test]
+    async fn relay_hub_cleanup_keeps_paired() {
+        let hub = RelayHub::new();
+
+        // Create an old but paired room (astation_tx is Some)
+        let (tx, _rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        let room = PairRoom {
+            astations: vec![Connection::new(tx, ClientInfo::default())],
+            created_at: Instant::now() - std::time::Duration::from_secs(DEFAULT_ROOM_TTL_SECS + 10),
+            ..PairRoom::new(
+                "PAIR-CODE".to_string(),
+                "paired-host".to_string(),
+                generate_session_token(),
+                DEFAULT_ROOM_TTL_SECS,
+            )
+        };
+        hub.rooms
+            .write()
+            .await
+            .insert("PAIR-CODE".to_string(), room);
+
+        hub.cleanup_expired().await;
+
+        let rooms = hub.rooms.read().await;
+        assert!(
+            rooms.contains_key("PAIR-CODE"),
+            "Paired room should not be cleaned up"
+        );
+    }
+
+    #[test]
+    fn render_pair_page_contains_code() {
+        let html = render_pair_page_l10n("TEST-CODE", "my-host", "en", None, "");
+        assert!(html.contains("TEST-CODE"));
+        assert!(html.contains("my-host"));
+        assert!(html.contains("astation://pair?code=TEST-CODE"));
+    }
+
+    // --- Integration tests (HTTP endpoint tests) ---
+
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode as HttpStatusCode},
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn create_relay_app() -> Router {
+        let state = crate::AppState {
+            sessions: crate::session_store::SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: crate::rtc_session::RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        Router::new()
+            .route("/api/pair", axum::routing::post(create_pair_handler))
+            .route(
+                "/api/pair/:code",
+                axum::routing::get(pair_status_handler).delete(delete_pair_handler),
+            )
+            .route("/ws", axum::routing::get(ws_handler))
+            .route("/pair", axum::routing::get(pair_page_handler))
+            .route("/pair/qr", axum::routing::get(pair_qr_handler))
+            .with_state(state)
+    }
+
+    /// Helper: POST /api/pair with given hostname, returns the response body as CreatePairResponse.
+    async fn post_create_pair(app: Router, hostname: &str) -> (HttpStatusCode, String) {
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/pair")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(format!(r#"{{"hostname": "{}"}}"#, hostname)))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body_str = String::from_utf8(body.to_vec()).unwrap();
+        (status, body_str)
+    }
+
+    #[tokio::test]
+    async fn test_create_pair_validates_hostname() {
+        let app = create_relay_app();
+        let longest = "h".repeat(255);
+        for hostname in ["10.0.0.12", "studio_mac.local", "edit-bay-2", longest.as_str()] {
+            let (status, _) = post_create_pair(app.clone(), hostname).await;
+            assert_eq!(status, HttpStatusCode::CREATED, "should accept {:?}", hostname);
+        }
+
+        let too_long = "h".repeat(256);
+        for hostname in ["", "my machine", "host/../etc", "host:8080", "<script>", too_long.as_str()] {
+            let (status, body) = post_create_pair(app.clone(), hostname).await;
+            assert_eq!(status, HttpStatusCode::BAD_REQUEST, "should reject {:?}", hostname);
+            let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(json["error"], "invalid_hostname");
+        }
+    }
+
+    /// POST /api/pair with a raw JSON body against `hub`.
+    async fn post_pair_json(hub: RelayHub, body: serde_json::Value) -> (HttpStatusCode, serde_json::Value) {
+        let app = Router::new()
+            .route("/api/pair", axum::routing::post(create_pair_handler))
+            .with_state(ws_state(hub));
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/pair")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn create_pair_uses_the_global_expiry_by_default() {
+        let hub = RelayHub::new();
+        let (status, body) = post_pair_json(hub.clone(), serde_json::json!({ "hostname": "host" })).await;
+        assert_eq!(status, HttpStatusCode::CREATED);
+        assert_eq!(body["expires_in_seconds"], DEFAULT_ROOM_TTL_SECS);
+        let code = body["code"].as_str().unwrap();
+        assert_eq!(hub.rooms.read().await[code].expiry_secs, DEFAULT_ROOM_TTL_SECS);
+    }
+
+    #[tokio::test]
+    async fn create_pair_expiry_override_sets_the_room_lifetime() {
+        let hub = RelayHub::new();
+        let short = serde_json::json!({ "hostname": "demo", "expires_in_seconds": 60 });
+        let (status, body) = post_pair_json(hub.clone(), short).await;
+        assert_eq!(status, HttpStatusCode::CREATED);
+        assert_eq!(body["expires_in_seconds"], 60);
+        let short_code = body["code"].as_str().unwrap().to_string();
+        let (_, body) = post_pair_json(hub.clone(), serde_json::json!({ "hostname": "ci", "expires_in_seconds": 86_400 })).await;
+        let long_code = body["code"].as_str().unwrap().to_string();
+        let (_, body) = post_pair_json(hub.clone(), serde_json::json!({ "hostname": "plain" })).await;
+        let plain_code = body["code"].as_str().unwrap().to_string();
+
+        let expires_in = hub.room_status(&short_code).await.unwrap().expires_in_secs.unwrap();
+        assert!((59..=60).contains(&expires_in), "{}", expires_in);
+
+        // Past a minute, only the 60s room is due
+        for room in hub.rooms.write().await.values_mut() {
+            room.created_at -= Duration::from_secs(61);
+        }
+        hub.cleanup_expired().await;
+        let rooms = hub.rooms.read().await;
+        assert!(!rooms.contains_key(&short_code));
+        assert!(rooms.contains_key(&long_code) && rooms.contains_key(&plain_code));
+    }
+
+    #[tokio::test]
+    async fn create_pair_reuses_the_hostnames_unpaired_room() {
+        let hub = RelayHub::new();
+        let (status, first) = post_pair_json(hub.clone(), serde_json::json!({ "hostname": "retry-host" })).await;
+        assert_eq!(status, HttpStatusCode::CREATED);
+        assert_eq!(first["reused"], false);
+        let code = first["code"].as_str().unwrap().to_string();
+        age_room(&hub, &code, 120).await;
+        hub.rooms.write().await.get_mut(&code).unwrap().extended_secs = 300;
+
+        // Without the owner token, a retry gets a room of its own
+        let (status, stranger) = post_pair_json(hub.clone(), serde_json::json!({ "hostname": "retry-host" })).await;
+        assert_eq!(status, HttpStatusCode::CREATED);
+        assert_ne!(stranger["code"], first["code"]);
+        let forged = serde_json::json!({ "hostname": "retry-host", "owner_token": "guess" });
+        let (status, _) = post_pair_json(hub.clone(), forged).await;
+        assert_eq!(status, HttpStatusCode::CREATED);
+
+        // With it, the same code comes back under a fresh owner token, its clock
+        // restarted and its renewals kept
+        let retry = serde_json::json!({ "hostname": "retry-host", "owner_token": first["owner_token"] });
+        let (status, again) = post_pair_json(hub.clone(), retry.clone()).await;
+        assert_eq!(status, HttpStatusCode::OK);
+        assert_eq!(again["code"], first["code"]);
+        assert_ne!(again["owner_token"], first["owner_token"]);
+        assert_eq!(again["reused"], true);
+        {
+            let rooms = hub.rooms.read().await;
+            assert!(rooms[&code].created_at.elapsed().as_secs() < 5);
+            assert_eq!(rooms[&code].extended_secs, 300);
+            assert_eq!(rooms[&code].owner_token, again["owner_token"].as_str().unwrap());
+        }
+        assert_eq!(hub.room_count().await, 3);
+
+        // The replaced token no longer reuses it
+        let (status, _) = post_pair_json(hub.clone(), retry).await;
+        assert_eq!(status, HttpStatusCode::CREATED);
+
+        // Another hostname, or opting out, still gets a fresh room
+        let elsewhere = serde_json::json!({ "hostname": "other-host", "owner_token": again["owner_token"] });
+        let (_, other) = post_pair_json(hub.clone(), elsewhere).await;
+        assert_ne!(other["code"], first["code"]);
+        let opt_out = serde_json::json!({
+            "hostname": "retry-host",
+            "owner_token": again["owner_token"],
+            "reuse_existing": false,
+        });
+        let (status, fresh) = post_pair_json(hub.clone(), opt_out).await;
+        assert_eq!(status, HttpStatusCode::CREATED);
+        assert_ne!(fresh["code"], first["code"]);
+    }
+
+    #[tokio::test]
+    async fn create_pair_never_reuses_a_paired_or_expired_room() {
+        let hub = RelayHub::new();
+        let (_, first) = post_pair_json(hub.clone(), serde_json::json!({ "hostname": "paired-host" })).await;
+        let code = first["code"].as_str().unwrap().to_string();
+        let _atem_rx = hub.connect_test_side(&code, Role::Atem).await;
+        let _astation_rx = hub.connect_test_side(&code, Role::Astation).await;
+        assert!(hub.room_status(&code).await.unwrap().paired);
+
+        let retry = |previous: &serde_json::Value| {
+            serde_json::json!({ "hostname": "paired-host", "owner_token": previous["owner_token"] })
+        };
+        let (status, second) = post_pair_json(hub.clone(), retry(&first)).await;
+        assert_eq!(status, HttpStatusCode::CREATED);
+        assert_ne!(second["code"], first["code"]);
+
+        // Past its lifetime but not yet cleaned up: not handed out either
+        let stale = second["code"].as_str().unwrap().to_string();
+        age_room(&hub, &stale, DEFAULT_ROOM_TTL_SECS + 1).await;
+        let (status, third) = post_pair_json(hub.clone(), retry(&second)).await;
+        assert_eq!(status, HttpStatusCode::CREATED);
+        assert!(third["code"] != first["code"] && third["code"] != second["code"]);
+    }
+
+    #[tokio::test]
+    async fn create_pair_expiry_override_is_bounded() {
+        let hub = RelayHub::new();
+        for secs in [MIN_ROOM_EXPIRY_SECS, MAX_ROOM_EXPIRY_SECS] {
+            let hostname = format!("host-{}", secs);
+            let (status, body) =
+                post_pair_json(hub.clone(), serde_json::json!({ "hostname": hostname, "expires_in_seconds": secs })).await;
+            assert_eq!(status, HttpStatusCode::CREATED, "{}", secs);
+            assert_eq!(body["expires_in_seconds"], secs);
+        }
+        for secs in [0, MIN_ROOM_EXPIRY_SECS - 1, MAX_ROOM_EXPIRY_SECS + 1] {
+            let (status, body) =
+                post_pair_json(hub.clone(), serde_json::json!({ "hostname": "host", "expires_in_seconds": secs })).await;
+            assert_eq!(status, HttpStatusCode::BAD_REQUEST, "{}", secs);
+            assert_eq!(body["error"], "invalid_expires_in_seconds");
+        }
+        // A bad hostname is still reported as such
+        let (_, body) =
+            post_pair_json(hub.clone(), serde_json::json!({ "hostname": "", "expires_in_seconds": 1 })).await;
+        assert_eq!(body["error"], "invalid_hostname");
+        assert_eq!(hub.room_count().await, 2);
+    }
+
+    /// POST /api/pair/:code/renew against `hub`, with `token` as the bearer if set.
+    async fn post_renew(hub: RelayHub, code: &str, token: Option<&str>) -> (HttpStatusCode, serde_json::Value) {
+        let app = Router::new()
+            .route("/api/pair/:code/renew", axum::routing::post(renew_pair_handler))
+            .with_state(ws_state(hub));
+        let mut request = Request::builder().method("POST").uri(format!("/api/pair/{}/renew", code));
+        if let Some(token) = token {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let response = app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    async fn age_room(hub: &RelayHub, code: &str, secs: u64) {
+        hub.rooms.write().await.get_mut(code).unwrap().created_at -= Duration::from_secs(secs);
+    }
+
+    #[tokio::test]
+    async fn renewal_keeps_a_room_past_its_original_ttl() {
+        let hub = RelayHub::new().with_code_generator(|| "RENW-PAGE".to_string());
+        hub.insert_new_room("host".into(), "owner".into()).await.unwrap();
+
+        // Not near expiry yet: the pair page may not renew
+        let (status, _) = post_renew(hub.clone(), "RENW-PAGE", None).await;
+        assert_eq!(status, HttpStatusCode::UNAUTHORIZED);
+
+        age_room(&hub, "RENW-PAGE", DEFAULT_ROOM_TTL_SECS - 30).await;
+        let (status, body) = post_renew(hub.clone(), "renw-page", None).await;
+        assert_eq!(status, HttpStatusCode::OK);
+        let expires_in = body["expires_in_secs"].as_i64().unwrap();
+        assert!(expires_in > DEFAULT_ROOM_TTL_SECS as i64 - 2, "{}", body);
+        assert_eq!(hub.room_status("RENW-PAGE").await.unwrap().expires_in_secs, Some(expires_in));
+
+        age_room(&hub, "RENW-PAGE", 60).await;
+        hub.cleanup_expired().await;
+        assert!(hub.room_exists("RENW-PAGE").await, "renewed room should outlive the original TTL");
+
+        let (status, _) = post_renew(hub.clone(), "GHST-RMMS", None).await;
+        assert_eq!(status, HttpStatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn renewal_needs_the_owner_early_and_stops_at_the_cap() {
+        let hub = RelayHub::new().with_code_generator(|| "RENW-CAPS".to_string());
+        hub.insert_new_room("host".into(), "owner".into()).await.unwrap();
+
+        let (status, _) = post_renew(hub.clone(), "RENW-CAPS", Some("intruder")).await;
+        assert_eq!(status, HttpStatusCode::FORBIDDEN);
+        let (status, body) = post_renew(hub.clone(), "RENW-CAPS", Some("owner")).await;
+        assert_eq!(status, HttpStatusCode::OK, "{}", body);
+
+        // The deadline may not pass the absolute cap, and then no further
+        age_room(&hub, "RENW-CAPS", MAX_RENEWED_ROOM_AGE_SECS - 100).await;
+        let (status, body) = post_renew(hub.clone(), "RENW-CAPS", Some("owner")).await;
+        assert_eq!(status, HttpStatusCode::OK, "{}", body);
+        let expires_in = body["expires_in_secs"].as_i64().unwrap();
+        assert!((99..=100).contains(&expires_in), "{}", body);
+        let (status, _) = post_renew(hub.clone(), "RENW-CAPS", Some("owner")).await;
+        assert_eq!(status, HttpStatusCode::CONFLICT);
+
+        age_room(&hub, "RENW-CAPS", 101).await;
+        hub.cleanup_expired().await;
+        assert!(!hub.room_exists("RENW-CAPS").await);
+    }
+
+    #[tokio::test]
+    async fn test_create_pair_endpoint() {
+        let app = create_relay_app();
+        let (status, body_str) = post_create_pair(app, "my-machine").await;
+
+        assert_eq!(status, HttpStatusCode::CREATED);
+
+        let resp: CreatePairResponse = serde_json::from_str(&body_str).unwrap();
+        // Code should be in XXXX-XXXX format (9 chars total)
+        assert_eq!(resp.code.len(), 9, "Code should be 9 characters (XXXX-XXXX)");
+        assert_eq!(
+            &resp.code[4..5],
+            "-",
+            "Code should have hyphen at position 4"
+        );
+        // Each half should be 4 alphanumeric chars
+        let left = &resp.code[..4];
+        let right = &resp.code[5..];
+        assert!(
+            left.chars().all(|c| c.is_ascii_alphanumeric()),
+            "Left half should be alphanumeric"
+        );
+        assert!(
+            right.chars().all(|c| c.is_ascii_alphanumeric()),
+            "Right half should be alphanumeric"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_pair_code_no_ambiguous_chars() {
+        // Create several pairs and verify none contain ambiguous characters
+        for _ in 0..20 {
+            let app = create_relay_app();
+            let (status, body_str) = post_create_pair(app, "test-host").await;
+            assert_eq!(status, HttpStatusCode::CREATED);
+
+            let resp: CreatePairResponse = serde_json::from_str(&body_str).unwrap();
+            let code_no_hyphen = resp.code.replace('-', "");
+            assert!(
+                !code_no_hyphen.contains('0'),
+                "Code should not contain '0': {}",
+                resp.code
+            );
+            assert!(
+                !code_no_hyphen.contains('O'),
+                "Code should not contain 'O': {}",
+                resp.code
+            );
+            assert!(
+                !code_no_hyphen.contains('1'),
+                "Code should not contain '1': {}",
+                resp.code
+            );
+            assert!(
+                !code_no_hyphen.contains('I'),
+                "Code should not contain 'I': {}",
+                resp.code
+            );
+            assert!(
+                !code_no_hyphen.contains('L'),
+                "Code should not contain 'L': {}",
+                resp.code
+            );
+        }
+    }
+
+    #[tokio::test]
     async fn test_pair_status_exists() {
         let app = create_relay_app();
 
-        // Step 1: Create a pair
+        // Step 1: Create a pair
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/pair")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"hostname": "dev-machine"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), HttpStatusCode::CREATED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let created: CreatePairResponse = serde_json::from_slice(&body).unwrap();
+        let code = created.code;
+
+        // Step 2: GET /api/pair/:code should return paired=false
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/pair/{}", code))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), HttpStatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let status_resp: PairStatusResponse = serde_json::from_slice(&body).unwrap();
+        assert!(!status_resp.paired, "Newly created pair should not be paired yet");
+        assert_eq!(status_resp.hostname, "dev-machine");
+    }
+
+    #[tokio::test]
+    async fn test_pair_status_not_found() {
+        let app = create_relay_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/pair/ZZZZ-ZZZZ")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), HttpStatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_pair_page_exists() {
+        let app = create_relay_app();
+
+        // Step 1: Create a pair
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/pair")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"hostname": "page-test-host"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), HttpStatusCode::CREATED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let created: CreatePairResponse = serde_json::from_slice(&body).unwrap();
+        let code = created.code;
+
+        // Step 2: GET /pair?code=XXXX should return HTML containing the code
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/pair?code={}", code))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), HttpStatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(
+            html.contains(&code),
+            "HTML page should contain the pairing code"
+        );
+        assert!(
+            html.contains("page-test-host"),
+            "HTML page should contain the hostname"
+        );
+        assert!(
+            html.contains("Atem Pairing"),
+            "HTML page should contain the page title"
+        );
+        assert!(
+            html.contains(&format!("astation://pair?code={}", code)),
+            "HTML page should contain the deep link"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pair_page_not_found() {
+        let app = create_relay_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/pair?code=ZZZZ-ZZZZ")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), HttpStatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(
+            html.contains("not found"),
+            "404 page should indicate code not found"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ws_handler_room_not_found() {
+        let app = create_relay_app();
+
+        // Send a non-upgrade GET request to /ws with a nonexistent room code.
+        // Without the WebSocket upgrade headers, the handler should check the room
+        // and return 404 before attempting the upgrade.
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/ws?role=atem&code=ZZZZ-ZZZZ")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // The ws_handler checks room existence before upgrade, so a non-upgrade
+        // request to a nonexistent room should still fail. Axum returns 400 for
+        // missing upgrade headers on WebSocket routes when the handler calls
+        // ws.on_upgrade(), but our handler returns 404 before reaching that point
+        // for nonexistent rooms.
+        // Note: Since the request lacks upgrade headers, axum's WebSocketUpgrade
+        // extractor will reject it. The handler won't even run. Axum returns 400
+        // or the rejection status. We verify it does NOT return 200/101.
+        let status = response.status();
+        assert!(
+            status == HttpStatusCode::NOT_FOUND
+                || status == HttpStatusCode::BAD_REQUEST
+                || status == HttpStatusCode::UPGRADE_REQUIRED,
+            "Expected 404, 400, or 426 for non-upgrade WS request to nonexistent room, got {}",
+            status
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_multiple_pairs_unique_codes() {
+        let app = create_relay_app();
+        let mut codes = std::collections::HashSet::new();
+
+        for i in 0..10 {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/pair")
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(format!(r#"{{"hostname": "host-{}"}}"#, i)))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), HttpStatusCode::CREATED);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let resp: CreatePairResponse = serde_json::from_slice(&body).unwrap();
+            codes.insert(resp.code);
+        }
+
+        assert_eq!(
+            codes.len(),
+            10,
+            "All 10 pairing codes should be unique"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pairing_code_entropy() {
+        // Generate many codes to verify randomness distribution
+        let mut first_chars = std::collections::HashMap::new();
+        for _ in 0..100 {
+            let code = generate_pairing_code(DEFAULT_CODE_LENGTH);
+            let first_char = code.chars().next().unwrap();
+            *first_chars.entry(first_char).or_insert(0) += 1;
+        }
+
+        // Should have reasonable distribution (at least 5 different first characters out of 100 samples)
+        assert!(
+            first_chars.len() >= 5,
+            "Pairing codes should have good entropy, got {} unique first chars",
+            first_chars.len()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pair_page_xss_protection() {
+        // Test that hostname with HTML/JS is safely escaped
+        let html = render_pair_page_l10n("TEST-CODE", "<script>alert('xss')</script>", "en", None, "");
+        // If properly escaped, the literal string should appear, not executed
+        assert!(!html.contains("<script>alert"), "Script tags should be escaped or removed");
+
+        // Test with other XSS vectors
+        let html2 = render_pair_page_l10n("CODE-123", "' onload='alert(1)'", "en", None, "");
+        assert!(!html2.contains("onload='alert"), "Event handlers should be escaped");
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_expired_keeps_recently_paired() {
+        let hub = RelayHub::new();
+
+        // Create an old room but with atem connected (not astation)
+        let (tx_atem, _rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        let room = PairRoom {
+            atem: Some(Connection::new(tx_atem, ClientInfo::default())),
+            created_at: Instant::now() - std::time::Duration::from_secs(DEFAULT_ROOM_TTL_SECS + 10),
+            ..PairRoom::new(
+                "OLD-ATEM".to_string(),
+                "old-host".to_string(),
+                generate_session_token(),
+                DEFAULT_ROOM_TTL_SECS,
+            )
+        };
+        hub.rooms.write().await.insert("OLD-ATEM".to_string(), room);
+
+        hub.cleanup_expired().await;
+
+        let rooms = hub.rooms.read().await;
+        // Should be removed (only astation_tx prevents cleanup)
+        assert!(!rooms.contains_key("OLD-ATEM"), "Room with only atem connected should be cleaned up");
+    }
+
+    #[tokio::test]
+    async fn test_pair_status_shows_unpaired_then_paired() {
+        let state = crate::AppState {
+            sessions: crate::session_store::SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: crate::rtc_session::RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+
+        // Create pair
+        let code = generate_pairing_code(DEFAULT_CODE_LENGTH);
+        let room = PairRoom::new(
+            code.clone(),
+            "test-host".to_string(),
+            generate_session_token(),
+            DEFAULT_ROOM_TTL_SECS,
+        );
+        state.relay.rooms.write().await.insert(code.clone(), room);
+
+        let app = Router::new()
+            .route("/api/pair/:code", axum::routing::get(pair_status_handler))
+            .with_state(state.clone());
+
+        // Check status before pairing
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/pair/{}", code))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), HttpStatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let status: PairStatusResponse = serde_json::from_slice(&body).unwrap();
+        assert!(!status.paired, "Should not be paired initially");
+
+        // Simulate both sides connecting
+        let (atem_tx, _atem_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        let (tx, _rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        {
+            let mut rooms = state.relay.rooms.write().await;
+            if let Some(room) = rooms.get_mut(&code) {
+                room.atem = Some(Connection::new(atem_tx, ClientInfo::default()));
+                room.astations = vec![Connection::new(tx, ClientInfo::default())];
+            }
+        }
+
+        // Check status after pairing
+        let app2 = Router::new()
+            .route("/api/pair/:code", axum::routing::get(pair_status_handler))
+            .with_state(state);
+
+        let response = app2
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/pair/{}", code))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let status: PairStatusResponse = serde_json::from_slice(&body).unwrap();
+        assert!(status.paired, "Should be paired once both sides connect");
+        assert!(status.atem_connected);
+        assert!(status.astation_connected);
+    }
+
+    #[tokio::test]
+    async fn test_create_pair_regenerates_colliding_code() {
+        let codes = std::sync::Mutex::new(vec!["NEWC-ODE2", "TAKE-N234", "TAKE-N234"]);
+        let hub = RelayHub::new().with_code_generator(move || codes.lock().unwrap().pop().unwrap().to_string());
+        let mut astation_rx = hub.connect_test_side("TAKE-N234", Role::Astation).await;
+        let original_token = hub.rooms.read().await["TAKE-N234"].owner_token.clone();
+
+        let code = hub.insert_new_room("second-host".into(), "t".into()).await;
+        assert_eq!(code.as_deref(), Some("NEWC-ODE2"));
+        assert_eq!(hub.code_collisions(), 2);
+
+        // The original room is untouched and still connected
+        let rooms = hub.rooms.read().await;
+        assert_eq!(rooms["TAKE-N234"].owner_token, original_token);
+        assert_eq!(rooms["NEWC-ODE2"].hostname, "second-host");
+        assert!(rooms["TAKE-N234"].senders(Role::Astation)[0].clone().try_send("still here".into()).is_ok());
+        assert_eq!(astation_rx.try_recv().unwrap(), "still here");
+    }
+
+    #[tokio::test]
+    async fn test_create_pair_gives_up_with_503() {
+        let state = crate::AppState {
+            sessions: crate::session_store::SessionStore::new(),
+            relay: RelayHub::new().with_code_generator(|| "SAME-HASH".to_string()),
+            device_pairs: Default::default(),
+            rtc_sessions: crate::rtc_session::RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        let app = Router::new()
+            .route("/api/pair", axum::routing::post(create_pair_handler))
+            .with_state(state.clone());
+
+        let (status, body) = post_create_pair(app.clone(), "first").await;
+        assert_eq!(status, HttpStatusCode::CREATED);
+        let first: CreatePairResponse = serde_json::from_str(&body).unwrap();
+
+        let (status, _) = post_create_pair(app, "second").await;
+        assert_eq!(status, HttpStatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(state.relay.code_collisions(), MAX_CODE_ATTEMPTS as u64);
+        let rooms = state.relay.rooms.read().await;
+        assert_eq!(rooms.len(), 1);
+        assert_eq!(rooms["SAME-HASH"].hostname, "first");
+        assert_eq!(rooms["SAME-HASH"].owner_token, first.owner_token);
+    }
+
+    #[test]
+    fn relay_config_validates_code_length() {
+        assert!(RelayConfig::default().validate().is_ok());
+        for code_length in [MIN_CODE_LENGTH, MAX_CODE_LENGTH] {
+            assert!(RelayConfig { code_length, ..Default::default() }.validate().is_ok());
+        }
+        for code_length in [0, MIN_CODE_LENGTH - 1, MAX_CODE_LENGTH + 1] {
+            let error = RelayConfig { code_length, ..Default::default() }.validate().unwrap_err();
+            assert!(error.contains("PAIR_CODE_LENGTH"), "{}", error);
+        }
+        assert!(RelayConfig { room_ttl_secs: 0, ..Default::default() }.validate().is_err());
+        let idle_timeout = Duration::from_secs(DEFAULT_PING_INTERVAL_SECS);
+        assert!(RelayConfig { idle_timeout, ..Default::default() }.validate().is_err());
+        assert!(RelayConfig { ping_interval: Duration::ZERO, ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn stale_after_is_two_pings_capped_at_idle_timeout() {
+        assert_eq!(RelayConfig::default().stale_after(), Duration::from_secs(60));
+        let config = RelayConfig {
+            ping_interval: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(45),
+            ..Default::default()
+        };
+        assert_eq!(config.stale_after(), Duration::from_secs(45));
+    }
+
+    #[tokio::test]
+    async fn test_configured_code_length() {
+        let hub = RelayHub::new().with_config(RelayConfig { code_length: 12, ..Default::default() });
+        let code = hub.insert_new_room("long-host".into(), "t".into()).await.unwrap();
+        assert_eq!(code.len(), 13);
+        assert_eq!(&code[6..7], "-");
+        assert_eq!(hub.normalize_code(&code.replace('-', "").to_lowercase()), Some(code.clone()));
+        // Default-length codes no longer match
+        assert_eq!(hub.normalize_code("ABCD-EFGH"), None);
+        assert!(invalid_code_message("ABCD-EFGH", 12).contains("12 letters"));
+    }
+
+    #[tokio::test]
+    async fn test_short_room_ttl() {
+        let hub = RelayHub::new().with_config(RelayConfig { room_ttl_secs: 1, ..Default::default() });
+        let code = hub.insert_new_room("kiosk".into(), "t".into()).await.unwrap();
+        {
+            let rooms = hub.rooms.read().await;
+            let status = rooms[&code].status();
+            assert!(status.expires_in_secs.unwrap() <= 1);
+        }
+        hub.rooms.write().await.get_mut(&code).unwrap().created_at -= std::time::Duration::from_secs(2);
+
+        hub.cleanup_expired().await;
+        assert!(hub.rooms.read().await.is_empty(), "room past a 1s TTL should be removed");
+    }
+
+    #[test]
+    fn render_pair_page_shows_countdown() {
+        let html = render_pair_page_l10n("ABCD-EFGH", "host", "en", Some(125), "");
+        assert!(html.contains(r#"data-expires-in="125""#));
+        assert!(html.contains("Expires in 2:05"));
+        assert!(!render_pair_page_l10n("ABCD-EFGH", "host", "en", None, "").contains("data-expires-in"));
+        assert!(html.contains(r#"data-renew-url="/api/pair/ABCD-EFGH/renew""#));
+    }
+
+    #[test]
+    fn render_pair_page_supports_light_theme() {
+        let html = render_pair_page_l10n("ABCD-EFGH", "host", "en", None, "");
+        assert!(html.contains("@media (prefers-color-scheme: light)"));
+        assert!(html.contains(r#"html[data-theme="light"]"#));
+        assert!(html.contains(r#"id="theme-toggle""#));
+        assert!(html.contains("localStorage.setItem('astation-theme', next)"));
+    }
+
+    #[test]
+    fn render_pair_page_l10n_translates_the_page() {
+        let html = render_pair_page_l10n("ABCD-EFGH", "host", "ja", Some(125), "");
+        assert!(html.contains(r#"<html lang="ja">"#));
+        for key in ["title", "subtitle", "button", "footer", "download", "expires_in"] {
+            assert!(html.contains(&html_escape(l10n::text("ja", key))), "{}", key);
+        }
+        assert!(!html.contains("Open in Astation"));
+        assert!(html.contains(r#"el.textContent = "このコードは期限切れです";"#));
+
+        let html = render_pair_page_l10n("ABCD-EFGH", "host", "klingon", None, "");
+        assert!(html.contains(r#"<html lang="en">"#));
+        assert!(html.contains("Open in Astation"));
+    }
+
+    #[tokio::test]
+    async fn test_pair_page_negotiates_accept_language() {
+        let app = create_relay_app();
+        let (_, body) = post_create_pair(app.clone(), "l10n-host").await;
+        let created: CreatePairResponse = serde_json::from_str(&body).unwrap();
+
+        for (accept, button) in [
+            (Some("fr-FR,es;q=0.8,en;q=0.5"), "Abrir en Astation"),
+            (Some("zh-CN"), "在 Astation 中打开"),
+            (Some("fr"), "Open in Astation"),
+            (None, "Open in Astation"),
+        ] {
+            let mut request = Request::builder().uri(format!("/pair?code={}", created.code));
+            if let Some(accept) = accept {
+                request = request.header("Accept-Language", accept);
+            }
+            let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), HttpStatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let html = String::from_utf8(body.to_vec()).unwrap();
+            assert!(html.contains(button), "{:?}", accept);
+        }
+    }
+
+    #[test]
+    fn render_pair_page_embeds_qr_code() {
+        let html = render_pair_page_l10n("ABCD-EFGH", "host", "en", None, "");
+        assert!(html.contains(r#"<img class="qr" src="/pair/qr?code=ABCD-EFGH""#));
+        assert!(html.contains(r#"href="astation://pair?code=ABCD-EFGH""#));
+    }
+
+    #[tokio::test]
+    async fn test_pair_qr_serves_svg_for_known_codes() {
+        let app = create_relay_app();
+        let (_, body) = post_create_pair(app.clone(), "qr-host").await;
+        let created: CreatePairResponse = serde_json::from_str(&body).unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/pair/qr?code={}", created.code.to_lowercase()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), HttpStatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "image/svg+xml");
+        assert!(response.headers()["cache-control"].to_str().unwrap().contains("immutable"));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let svg = String::from_utf8(body.to_vec()).unwrap();
+
+        // A 30-byte link needs a version 3 symbol: 29 modules plus a 4-module quiet zone each side
+        let link = format!("astation://pair?code={}", created.code);
+        let modules = qrcode::QrCode::new(&link).unwrap().width() as u32;
+        assert_eq!(modules, 29);
+        let side = (modules + 8) * QR_MODULE_PX;
+        assert!(svg.contains(&format!(r#"viewBox="0 0 {} {}""#, side, side)), "{}", svg);
+
+        for (uri, expected) in [
+            ("/pair/qr?code=QRQR-NNNN", HttpStatusCode::NOT_FOUND),
+            ("/pair/qr?code=abc", HttpStatusCode::BAD_REQUEST),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), expected, "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_code_lookups_are_normalized() {
+        let hub = RelayHub::new().with_code_generator(|| "ABCD-EFGH".to_string());
+        let state = crate::AppState {
+            sessions: crate::session_store::SessionStore::new(),
+            relay: hub,
+            device_pairs: Default::default(),
+            rtc_sessions: crate::rtc_session::RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        let app = Router::new()
+            .route("/api/pair", axum::routing::post(create_pair_handler))
+            .route("/api/pair/:code", axum::routing::get(pair_status_handler))
+            .route("/pair", axum::routing::get(pair_page_handler))
+            .with_state(state);
+        assert_eq!(post_create_pair(app.clone(), "typed-host").await.0, HttpStatusCode::CREATED);
+
+        let get = |uri: String| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, String::from_utf8(body.to_vec()).unwrap())
+            }
+        };
+        for code in ["abcd-efgh", "ABCDEFGH", "abcd%20efgh"] {
+            let (status, _) = get(format!("/api/pair/{}", code)).await;
+            assert_eq!(status, HttpStatusCode::OK, "{}", code);
+            let (status, html) = get(format!("/pair?code={}", code)).await;
+            assert_eq!(status, HttpStatusCode::OK, "{}", code);
+            assert!(html.contains("ABCD-EFGH"));
+        }
+
+        let (status, body) = get("/api/pair/ABCD-EFG0".to_string()).await;
+        assert_eq!(status, HttpStatusCode::BAD_REQUEST);
+        assert!(body.contains("never contain"), "{}", body);
+        let (status, html) = get("/pair?code=abc".to_string()).await;
+        assert_eq!(status, HttpStatusCode::BAD_REQUEST);
+        assert!(html.contains("8 letters"), "{}", html);
+    }
+
+    /// Create a room over HTTP with both sides connected, then send
+    /// `DELETE /api/pair/:code` with the `Authorization` header `auth` picks.
+    async fn create_then_delete(
+        auth: impl FnOnce(&CreatePairResponse) -> Option<String>,
+    ) -> (HttpStatusCode, RelayHub, CreatePairResponse) {
+        let state = crate::AppState {
+            sessions: crate::session_store::SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: crate::rtc_session::RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        let app = Router::new()
+            .route("/api/pair", axum::routing::post(create_pair_handler))
+            .route("/api/pair/:code", axum::routing::delete(delete_pair_handler))
+            .with_state(state.clone());
+        let (status, body) = post_create_pair(app.clone(), "owner-host").await;
+        assert_eq!(status, HttpStatusCode::CREATED);
+        let created: CreatePairResponse = serde_json::from_str(&body).unwrap();
+        assert_eq!(created.owner_token.len(), 64);
+
+        let hub = state.relay.clone();
+        let mut atem_rx = hub.connect_test_side(&created.code, Role::Atem).await;
+        let mut astation_rx = hub.connect_test_side(&created.code, Role::Astation).await;
+
+        let mut request = Request::builder()
+            .method("DELETE")
+            .uri(format!("/api/pair/{}", created.code));
+        if let Some(auth) = auth(&created) {
+            request = request.header("Authorization", auth);
+        }
+        let response = app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        if response.status() == HttpStatusCode::OK {
+            // Each side is told, then its sender is dropped with the room, ending the WS writer
+            for rx in [&mut atem_rx, &mut astation_rx] {
+                let notice: serde_json::Value = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+                assert_eq!(notice, serde_json::json!({ "type": "room_deleted", "code": created.code }));
+            }
+            assert!(atem_rx.recv().await.is_none());
+            assert!(astation_rx.recv().await.is_none());
+        }
+        (response.status(), hub, created)
+    }
+
+    #[tokio::test]
+    async fn test_delete_pair_with_owner_token() {
+        let (status, hub, created) = create_then_delete(|created| Some(format!("Bearer {}", created.owner_token))).await;
+        assert_eq!(status, HttpStatusCode::OK);
+        assert!(!hub.rooms.read().await.contains_key(&created.code));
+    }
+
+    #[tokio::test]
+    async fn test_deleted_pair_is_gone_everywhere() {
+        let app = create_relay_app();
+        let (_, body) = post_create_pair(app.clone(), "cancel-host").await;
+        let created: CreatePairResponse = serde_json::from_str(&body).unwrap();
+        let request = |method: &str, uri: String| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("Authorization", format!("Bearer {}", created.owner_token))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let deleted = app.clone().oneshot(request("DELETE", format!("/api/pair/{}", created.code))).await.unwrap();
+        assert_eq!(deleted.status(), HttpStatusCode::OK);
+        for uri in [format!("/api/pair/{}", created.code), format!("/pair?code={}", created.code)] {
+            let response = app.clone().oneshot(request("GET", uri)).await.unwrap();
+            assert_eq!(response.status(), HttpStatusCode::NOT_FOUND);
+        }
+        let again = app.oneshot(request("DELETE", format!("/api/pair/{}", created.code))).await.unwrap();
+        assert_eq!(again.status(), HttpStatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_delete_pair_requires_owner_token() {
+        let (status, hub, created) = create_then_delete(|_| None).await;
+        assert_eq!(status, HttpStatusCode::UNAUTHORIZED);
+        assert!(hub.rooms.read().await.contains_key(&created.code));
+
+        let (status, hub, created) = create_then_delete(|_| Some("Bearer not-the-owner".into())).await;
+        assert_eq!(status, HttpStatusCode::FORBIDDEN);
+        assert!(hub.rooms.read().await.contains_key(&created.code));
+    }
+
+    #[tokio::test]
+    async fn test_pair_status_fields_after_creation() {
+        let app = create_relay_app();
+        let (_, body_str) = post_create_pair(app.clone(), "fields-host").await;
+        let created: CreatePairResponse = serde_json::from_str(&body_str).unwrap();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/pair/{}", created.code))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let status: PairStatusResponse = serde_json::from_slice(&body).unwrap();
+
+        assert!(!status.paired);
+        assert!(!status.atem_connected);
+        assert!(!status.astation_connected);
+        assert!(status.created_secs_ago <= 1);
+        let expires_in_secs = status.expires_in_secs.unwrap();
+        assert!(expires_in_secs <= DEFAULT_ROOM_TTL_SECS as i64);
+        assert!(expires_in_secs >= DEFAULT_ROOM_TTL_SECS as i64 - 1);
+        assert!(status.paired_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pair_status_partial_connection() {
+        let (atem_tx, _rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        let room = PairRoom {
+            atem: Some(Connection::new(atem_tx, ClientInfo::default())),
+            created_at: Instant::now() - std::time::Duration::from_secs(DEFAULT_ROOM_TTL_SECS + 30),
+            ..PairRoom::new(
+                "PART-CODE".to_string(),
+                "partial-host".to_string(),
+                generate_session_token(),
+                DEFAULT_ROOM_TTL_SECS,
+            )
+        };
+
+        let status = room.status();
+        assert!(!status.paired, "Only atem connected should not count as paired");
+        assert!(status.atem_connected);
+        assert!(!status.astation_connected);
+        assert!(status.created_secs_ago >= DEFAULT_ROOM_TTL_SECS + 30);
+        assert!(status.expires_in_secs.unwrap() <= -30, "Expired room should report negative expiry");
+        assert!(status.paired_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pair_status_tracks_pairing() {
+        let hub = RelayHub::new().with_code_generator(|| "PAIR-TIME".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let status = |hub: RelayHub| async move { hub.rooms.read().await["PAIR-TIME"].status() };
+
+        let _astation_rx = hub.connect_test_side("PAIR-TIME", Role::Astation).await;
+        let waiting = status(hub.clone()).await;
+        assert!(!waiting.atem_connected && waiting.astation_connected);
+        assert!(waiting.paired_at.is_none(), "one side is not a pairing");
+        assert_eq!(waiting.expires_in_secs, None, "astation exempts the room from expiry");
+
+        let before = Utc::now();
+        let _atem_rx = hub.connect_test_side("PAIR-TIME", Role::Atem).await;
+        let paired = status(hub.clone()).await;
+        assert!(paired.paired && paired.atem_connected && paired.astation_connected);
+        let paired_at = paired.paired_at.unwrap();
+        assert!(paired_at >= before && paired_at <= Utc::now());
+
+        // Losing astation brings back the expiry but keeps the first pairing time
+        let astation_tx = hub.rooms.read().await["PAIR-TIME"].senders(Role::Astation)[0].clone();
+        assert!(hub.detach("PAIR-TIME", Role::Astation, &astation_tx.downgrade()).await);
+        let _second_rx = hub.connect_test_side("PAIR-TIME", Role::Astation).await;
+        assert_eq!(status(hub.clone()).await.paired_at, Some(paired_at));
+        let astation_tx = hub.rooms.read().await["PAIR-TIME"].senders(Role::Astation)[0].clone();
+        assert!(hub.detach("PAIR-TIME", Role::Astation, &astation_tx.downgrade()).await);
+        let unpaired = status(hub.clone()).await;
+        assert!(!unpaired.paired);
+        assert_eq!(unpaired.paired_at, Some(paired_at));
+        assert!(unpaired.expires_in_secs.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_pair_creation() {
+        use std::sync::Arc;
+        use tokio::sync::Mutex;
+
+        let app = Arc::new(Mutex::new(create_relay_app()));
+        let mut handles = Vec::new();
+
+        for i in 0..10 {
+            let app_clone = app.clone();
+            handles.push(tokio::spawn(async move {
+                let app = app_clone.lock().await;
+                let response = app
+                    .clone()
+                    .oneshot(
+                        Request::builder()
+                            .method("POST")
+                            .uri("/api/pair")
+                            .header("Content-Type", "application/json")
+                            .body(Body::from(format!(r#"{{"hostname": "host-{}"}}"#, i)))
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<CreatePairResponse>(&body)
+                    .ok()
+                    .map(|r| r.code)
+            }));
+        }
+
+        let mut codes = std::collections::HashSet::new();
+        for handle in handles {
+            if let Some(code) = handle.await.unwrap() {
+                codes.insert(code);
+            }
+        }
+
+        assert_eq!(codes.len(), 10, "All concurrent pairs should have unique codes");
+    }
+
+    #[test]
+    fn test_code_chars_does_not_contain_ambiguous() {
+        let chars_str = String::from_utf8_lossy(CODE_CHARS);
+        assert!(!chars_str.contains('0'), "CODE_CHARS should not contain 0");
+        assert!(!chars_str.contains('O'), "CODE_CHARS should not contain O");
+        assert!(!chars_str.contains('1'), "CODE_CHARS should not contain 1");
+        assert!(!chars_str.contains('I'), "CODE_CHARS should not contain I");
+        assert!(!chars_str.contains('L'), "CODE_CHARS should not contain L");
+    }
+
+    // --- Relay envelope routing ---
+
+    struct ConnectedRoom {
+        hub: RelayHub,
+        atem_tx: mpsc::Sender<String>,
+        atem_rx: mpsc::Receiver<String>,
+        astation_rx: mpsc::Receiver<String>,
+    }
+
+    /// A room where only atem is connected.
+    async fn atem_only_room(hub: &RelayHub, code: &str) -> mpsc::Sender<String> {
+        let (atem_tx, _atem_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        hub.rooms.write().await.insert(
+            code.to_string(),
+            PairRoom {
+                atem: Some(Connection::new(atem_tx.clone(), ClientInfo::default())),
+                ..PairRoom::new(
+                    code.to_string(),
+                    "host".to_string(),
+                    generate_session_token(),
+                    DEFAULT_ROOM_TTL_SECS,
+                )
+            },
+        );
+        atem_tx
+    }
+
+    #[tokio::test]
+    async fn late_astation_receives_buffered_messages_in_order() {
+        let hub = RelayHub::new();
+        let atem_tx = atem_only_room(&hub, "LATE-JOIN").await;
+        for i in 0..5 {
+            route_incoming(&hub, "LATE-JOIN", Role::Atem, &atem_tx, format!(r#"{{"n":{}}}"#, i)).await;
+        }
+
+        let (astation_tx, mut astation_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        assert!(hub.attach("LATE-JOIN", Role::Astation, &astation_tx, ClientInfo::default(), true, true).await.is_some());
+        pair_token(&mut astation_rx);
+        route_incoming(&hub, "LATE-JOIN", Role::Atem, &atem_tx, r#"{"n":5}"#.to_string()).await;
+
+        for i in 0..6 {
+            assert_eq!(astation_rx.try_recv().unwrap(), format!(r#"{{"n":{}}}"#, i));
+        }
+        assert!(astation_rx.try_recv().is_err());
+        assert_eq!(hub.rooms.read().await["LATE-JOIN"].astation_backlog.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn buffer_is_capped_and_dropped_without_replay() {
+        let hub = RelayHub::new().with_buffer_size(3);
+        let atem_tx = atem_only_room(&hub, "CAPP-EDDD").await;
+        for i in 0..5 {
+            route_incoming(&hub, "CAPP-EDDD", Role::Atem, &atem_tx, format!("m{}", i)).await;
+        }
+        let buffered: Vec<String> =
+            hub.rooms.read().await["CAPP-EDDD"].astation_backlog.messages.iter().cloned().collect();
+        assert_eq!(buffered, vec!["m2", "m3", "m4"]);
+
+        // Without replay_buffer the backlog is discarded
+        let (astation_tx, mut astation_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        assert!(hub.attach("CAPP-EDDD", Role::Astation, &astation_tx, ClientInfo::default(), false, true).await.is_some());
+        pair_token(&mut astation_rx);
+        assert!(astation_rx.try_recv().is_err());
+        assert_eq!(hub.rooms.read().await["CAPP-EDDD"].astation_backlog.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn reconnecting_atem_receives_astation_messages_in_order() {
+        let hub = RelayHub::new();
+        let mut astation_rx = hub.connect_test_side("BACK-HAUL", Role::Astation).await;
+        let astation_tx = hub.rooms.read().await["BACK-HAUL"].senders(Role::Astation)[0].clone();
+        for text in ["first", "second", "third"] {
+            route_incoming(&hub, "BACK-HAUL", Role::Astation, &astation_tx, text.to_string()).await;
+        }
+        {
+            let rooms = hub.rooms.read().await;
+            let status = rooms["BACK-HAUL"].status();
+            assert_eq!((status.atem_backlog, status.astation_backlog), (3, 0));
+        }
+
+        let (atem_tx, mut atem_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        assert!(hub.attach("BACK-HAUL", Role::Atem, &atem_tx, ClientInfo::default(), true, true).await.is_some());
+        pair_token(&mut atem_rx);
+        pair_token(&mut astation_rx);
+        route_incoming(&hub, "BACK-HAUL", Role::Astation, &astation_tx, "live".to_string()).await;
+
+        for expected in ["first", "second", "third", "live"] {
+            assert_eq!(atem_rx.try_recv().unwrap(), expected);
+        }
+        assert!(astation_rx.try_recv().is_err());
+        assert_eq!(hub.rooms.read().await["BACK-HAUL"].status().atem_backlog, 0);
+    }
+
+    #[test]
+    fn backlog_evicts_oldest_past_either_cap() {
+        let mut backlog = Backlog::default();
+        for i in 0..5 {
+            backlog.push(format!("m{}", i), 3);
+        }
+        assert_eq!(backlog.messages, ["m2", "m3", "m4"]);
+
+        let mut backlog = Backlog::default();
+        let chunk = "x".repeat(MAX_BACKLOG_BYTES / 4);
+        for i in 0..5 {
+            backlog.push(format!("{}{}", i, chunk), 100);
+        }
+        assert_eq!(backlog.len(), 3);
+        assert!(backlog.messages[0].starts_with('2'));
+        assert!(backlog.bytes <= MAX_BACKLOG_BYTES);
+
+        // A single oversized message is not kept
+        backlog.push("y".repeat(MAX_BACKLOG_BYTES + 1), 100);
+        assert_eq!((backlog.len(), backlog.bytes), (0, 0));
+        assert!(backlog.take().is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_to_reaches_only_the_named_side() {
+        let mut room = connected_room("SEND-TOOO").await;
+        assert!(room.hub.send_to("SEND-TOOO", Role::Astation, "hello".into()).await);
+        assert_eq!(room.astation_rx.try_recv().unwrap(), "hello");
+        assert!(room.atem_rx.try_recv().is_err());
+
+        assert!(!room.hub.send_to("MISS-INGG", Role::Astation, "hello".into()).await);
+    }
+
+    #[tokio::test]
+    async fn reconnecting_atem_takes_over() {
+        let room = connected_room("TAKE-HAND").await;
+        let ConnectedRoom { hub, atem_tx: old_tx, mut atem_rx, .. } = room;
+        // Like handle_ws: the room holds the only strong sender
+        let old_weak = old_tx.downgrade();
+        drop(old_tx);
+
+        let (new_tx, mut new_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        assert!(hub.attach("TAKE-HAND", Role::Atem, &new_tx, ClientInfo::default(), false, true).await.is_some());
+        let new_weak = new_tx.downgrade();
+        drop(new_tx);
+
+        let notice: serde_json::Value = serde_json::from_str(&atem_rx.recv().await.unwrap()).unwrap();
+        assert_eq!(notice["type"], "connection_replaced");
+        assert_eq!(notice["role"], "atem");
+        assert!(atem_rx.recv().await.is_none(), "old connection's channel is closed");
+
+        // The stale disconnect leaves the new connection in place
+        hub.detach("TAKE-HAND", Role::Atem, &old_weak).await;
+        let (astation_tx, _astation_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        route_incoming(&hub, "TAKE-HAND", Role::Astation, &astation_tx, "to atem".into()).await;
+        assert_eq!(new_rx.try_recv().unwrap(), "to atem");
+
+        hub.detach("TAKE-HAND", Role::Atem, &new_weak).await;
+        assert!(hub.rooms.read().await["TAKE-HAND"].atem.is_none());
+    }
+
+    #[tokio::test]
+    async fn takeover_false_keeps_existing_connection() {
+        let mut room = connected_room("KEEP-FRST").await;
+        let (new_tx, mut new_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        assert!(room.hub.attach("KEEP-FRST", Role::Atem, &new_tx, ClientInfo::default(), false, false).await.is_none());
+        assert!(room.atem_rx.try_recv().is_err(), "no notice sent");
+
+        let (astation_tx, _astation_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        route_incoming(&room.hub, "KEEP-FRST", Role::Astation, &astation_tx, "hi".into()).await;
+        assert_eq!(room.atem_rx.try_recv().unwrap(), "hi");
+        assert!(new_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn relayed_and_dropped_messages_are_counted() {
+        let hub = RelayHub::new()
+            .with_config(RelayConfig {
+                channel_capacity: 1,
+                ..Default::default()
+            })
+            .with_code_generator(|| "CNTS-RELA".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let mut atem_rx = hub.connect_test_side("CNTS-RELA", Role::Atem).await;
+        let _astation_rx = hub.connect_test_side("CNTS-RELA", Role::Astation).await;
+        let atem_tx = hub.rooms.read().await["CNTS-RELA"].senders(Role::Atem)[0].clone();
+        let astation_tx = hub.rooms.read().await["CNTS-RELA"].senders(Role::Astation)[0].clone();
+
+        route_incoming(&hub, "CNTS-RELA", Role::Astation, &astation_tx, "hello".into()).await;
+        assert_eq!(atem_rx.try_recv().unwrap(), "hello");
+        route_incoming(&hub, "CNTS-RELA", Role::Atem, &atem_tx, "hi".into()).await;
+        // The astation queue holds one message and nobody reads it
+        route_incoming(&hub, "CNTS-RELA", Role::Atem, &atem_tx, "lost".into()).await;
+
+        assert_eq!(
+            hub.stats().await,
+            RelayStats {
+                active_rooms: 1,
+                rooms_created: 1,
+                rooms_expired: 0,
+                atem_connections: 0,
+                astation_connections: 0,
+                messages_relayed: 2,
+                bytes_relayed: 7,
+                messages_dropped: 1,
+                dead_senders_reaped: 0,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn room_outlives_both_sides_for_the_grace_period() {
+        let hub = RelayHub::new().with_config(RelayConfig {
+            disconnect_grace: Duration::from_secs(60),
+            ..Default::default()
+        });
+        let _atem_rx = hub.connect_test_side("GRCE-WNDW", Role::Atem).await;
+        let _astation_rx = hub.connect_test_side("GRCE-WNDW", Role::Astation).await;
+        let leave_all = |hub: RelayHub| async move {
+            for role in Role::ALL {
+                let own_tx = hub.rooms.read().await["GRCE-WNDW"].senders(role)[0].downgrade();
+                assert!(hub.detach("GRCE-WNDW", role, &own_tx).await);
+            }
+        };
+        leave_all(hub.clone()).await;
+
+        // Inside the window the room stays, even past the unpaired TTL
+        hub.rooms.write().await.get_mut("GRCE-WNDW").unwrap().created_at -=
+            Duration::from_secs(DEFAULT_ROOM_TTL_SECS + 1);
+        hub.cleanup_expired().await;
+        assert!(hub.rooms.read().await["GRCE-WNDW"].disconnected_at.is_some());
+
+        // Reconnecting clears the mark and the room relays again
+        let (atem_tx, _atem_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        let (astation_tx, mut astation_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        assert!(hub.attach("GRCE-WNDW", Role::Atem, &atem_tx, ClientInfo::default(), false, true).await.is_some());
+        assert!(hub.attach("GRCE-WNDW", Role::Astation, &astation_tx, ClientInfo::default(), false, true).await.is_some());
+        assert!(hub.rooms.read().await["GRCE-WNDW"].disconnected_at.is_none());
+        route_incoming(&hub, "GRCE-WNDW", Role::Atem, &atem_tx, "back".into()).await;
+        assert_eq!(astation_rx.try_recv().unwrap(), "back");
+        hub.cleanup_expired().await;
+        assert!(hub.rooms.read().await.contains_key("GRCE-WNDW"));
+
+        // Staying away past the window gets the room reaped
+        leave_all(hub.clone()).await;
+        *hub.rooms.write().await.get_mut("GRCE-WNDW").unwrap().disconnected_at.as_mut().unwrap() -=
+            Duration::from_secs(61);
+        hub.cleanup_expired().await;
+        assert!(!hub.rooms.read().await.contains_key("GRCE-WNDW"));
+        assert_eq!(hub.stats().await.rooms_expired, 1);
+    }
+
+    #[tokio::test]
+    async fn cleanup_reaps_dead_senders_then_expires_the_room() {
+        let hub = RelayHub::new().with_config(RelayConfig {
+            disconnect_grace: Duration::from_secs(60),
+            ..Default::default()
+        });
+        let atem_rx = hub.connect_test_side("DEAD-TXTX", Role::Atem).await;
+        let astation_rx = hub.connect_test_side("DEAD-TXTX", Role::Astation).await;
+        let live_rx = hub.connect_test_side("DEAD-TXTX", Role::Astation).await;
+        hub.rooms.write().await.get_mut("DEAD-TXTX").unwrap().created_at -=
+            Duration::from_secs(DEFAULT_ROOM_TTL_SECS + 1);
+
+        // One astation is still alive, so the room stays paired
+        drop(astation_rx);
+        hub.cleanup_expired().await;
+        assert_eq!(hub.rooms.read().await["DEAD-TXTX"].astations.len(), 1);
+        assert_eq!(hub.stats().await.dead_senders_reaped, 1);
+
+        // Once every receiver is gone, the room is empty and its grace period starts
+        drop(atem_rx);
+        drop(live_rx);
+        hub.cleanup_expired().await;
+        {
+            let rooms = hub.rooms.read().await;
+            let room = &rooms["DEAD-TXTX"];
+            assert!(room.atem.is_none() && room.astations.is_empty());
+            assert!(room.disconnected_at.is_some());
+        }
+        assert_eq!(hub.stats().await.dead_senders_reaped, 3);
+
+        *hub.rooms.write().await.get_mut("DEAD-TXTX").unwrap().disconnected_at.as_mut().unwrap() -=
+            Duration::from_secs(61);
+        hub.cleanup_expired().await;
+        assert!(!hub.rooms.read().await.contains_key("DEAD-TXTX"));
+        assert_eq!(hub.stats().await.rooms_expired, 1);
+    }
+
+    #[tokio::test]
+    async fn expired_rooms_are_counted() {
+        let hub = RelayHub::new();
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        for room in hub.rooms.write().await.values_mut() {
+            room.created_at -= Duration::from_secs(DEFAULT_ROOM_TTL_SECS + 1);
+        }
+        hub.cleanup_expired().await;
+        let stats = hub.stats().await;
+        assert_eq!((stats.active_rooms, stats.rooms_created, stats.rooms_expired), (0, 2, 2));
+    }
+
+    fn ws_state(relay: RelayHub) -> crate::AppState {
+        crate::AppState {
+            sessions: crate::session_store::SessionStore::new(),
+            relay,
+            device_pairs: Default::default(),
+            rtc_sessions: crate::rtc_session::RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        }
+    }
+
+    /// GET `uri` on the ws route without upgrade headers; checks that run
+    /// before the upgrade still answer.
+    async fn ws_get(relay: RelayHub, uri: &str) -> (HttpStatusCode, String) {
+        let app = Router::new()
+            .route("/ws", axum::routing::get(ws_handler))
+            .with_state(ws_state(relay));
         let response = app
-            .clone()
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/pair")
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(r#"{"hostname": "dev-machine"}"#))
-                    .unwrap(),
-            )
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
             .await
             .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8_lossy(&body).to_string())
+    }
 
-        assert_eq!(response.status(), HttpStatusCode::CREATED);
-        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+    #[tokio::test]
+    async fn ws_rejects_unknown_role_before_upgrade() {
+        let room = connected_room("WHAT-RXYZ").await;
+        for role in ["banana", "spectator", "ATEM", ""] {
+            let (status, body) = ws_get(room.hub.clone(), &format!("/ws?role={}&code=what-rxyz", role)).await;
+            assert_eq!(status, HttpStatusCode::BAD_REQUEST, "{:?}", role);
+            let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+            assert_eq!(json["error"], "role must be atem or astation");
+        }
+        assert_eq!(Role::parse("astation"), Some(Role::Astation));
+        assert_eq!(Role::Atem.peer(), Role::Astation);
+        // A valid role gets as far as the upgrade rejection
+        let (status, body) = ws_get(room.hub.clone(), "/ws?role=atem&code=WHAT-RXYZ").await;
+        assert!(status.is_client_error());
+        assert!(!body.contains("atem or astation"), "{}", body);
+    }
+
+    #[tokio::test]
+    async fn ws_with_takeover_false_gets_409() {
+        let room = connected_room("KEEP-SEAT").await;
+        let (status, body) = ws_get(room.hub.clone(), "/ws?role=atem&code=KEEP-SEAT&takeover=false").await;
+        assert_eq!(status, HttpStatusCode::CONFLICT);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["role"], "atem");
+    }
+
+    /// Serve the ws route on a bound listener; returns the ws base url.
+    async fn serve_ws(relay: RelayHub) -> String {
+        let app = Router::new()
+            .route("/ws", axum::routing::get(ws_handler))
+            .with_state(ws_state(relay));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("ws://{}/ws", addr)
+    }
+
+    #[tokio::test]
+    async fn ws_rejects_duplicate_role_when_configured() {
+        use tokio_tungstenite::tungstenite::Error as WsError;
+
+        let hub = RelayHub::new()
+            .with_config(RelayConfig {
+                reject_duplicate_roles: true,
+                multi_astation: false,
+                ..Default::default()
+            })
+            .with_code_generator(|| "DUPE-SEAT".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = format!("{}?role=astation&code=DUPE-SEAT", serve_ws(hub.clone()).await);
+
+        let (_first, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        // Wait for the first connection to register
+        for _ in 0..50 {
+            if !hub.rooms.read().await["DUPE-SEAT"].astations.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        match tokio_tungstenite::connect_async(&url).await {
+            Err(WsError::Http(response)) => {
+                assert_eq!(response.status(), HttpStatusCode::CONFLICT);
+                let json: serde_json::Value = serde_json::from_slice(response.body().as_deref().unwrap()).unwrap();
+                assert_eq!(json["role"], "astation");
+            }
+            other => panic!("expected 409, got {:?}", other.map(|(_, response)| response.status())),
+        }
+        // takeover=true cannot override the global setting
+        assert!(tokio_tungstenite::connect_async(format!("{}&takeover=true", url)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejected_duplicate_leaves_first_connection_intact() {
+        use tokio_tungstenite::tungstenite::{Error as WsError, Message as WsMessage};
+
+        let hub = RelayHub::new().with_code_generator(|| "KEEP-SEAT".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = serve_ws(hub.clone()).await;
+        let (mut atem, _) = tokio_tungstenite::connect_async(format!("{}?role=atem&code=KEEP-SEAT&raw=1", url))
             .await
             .unwrap();
-        let created: CreatePairResponse = serde_json::from_slice(&body).unwrap();
-        let code = created.code;
+        for _ in 0..50 {
+            if hub.rooms.read().await["KEEP-SEAT"].is_connected(Role::Atem) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Refused with 409 before the upgrade, so the room never sees it
+        match tokio_tungstenite::connect_async(format!("{}?role=atem&code=KEEP-SEAT&takeover=false", url)).await {
+            Err(WsError::Http(response)) => assert_eq!(response.status(), HttpStatusCode::CONFLICT),
+            other => panic!("expected 409, got {:?}", other.map(|(_, response)| response.status())),
+        }
+
+        let (mut astation, _) = tokio_tungstenite::connect_async(format!("{}?role=astation&code=KEEP-SEAT&raw=1", url))
+            .await
+            .unwrap();
+        // The first atem is still the one paired and relaying
+        next_notice(&mut atem, "pair_established").await;
+        next_notice(&mut astation, "pair_established").await;
+        atem.send(WsMessage::Text("still mine".into())).await.unwrap();
+        let relayed = tokio::time::timeout(Duration::from_secs(5), astation.next()).await.unwrap().unwrap().unwrap();
+        assert_eq!(relayed.into_text().unwrap(), "still mine");
+        assert_eq!(hub.stats().await.atem_connections, 1);
+    }
+
+    #[tokio::test]
+    async fn deflate_request_falls_back_to_uncompressed_frames() {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let hub = RelayHub::new().with_code_generator(|| "ZPPD-RAWS".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = serve_ws(hub.clone()).await;
+        let connect = |role: &'static str| {
+            let url = format!("{}?role={}&code=ZPPD-RAWS&raw=1", url, role);
+            async move {
+                let mut request = url.into_client_request().unwrap();
+                request.headers_mut().insert(
+                    "Sec-WebSocket-Extensions",
+                    "permessage-deflate; client_max_window_bits".parse().unwrap(),
+                );
+                let (socket, response) = tokio_tungstenite::connect_async(request).await.unwrap();
+                // Not negotiated: the upgrade response must not accept the extension
+                assert!(response.headers().get("sec-websocket-extensions").is_none());
+                socket
+            }
+        };
+        let mut atem = connect("atem").await;
+        let mut astation = connect("astation").await;
+        next_notice(&mut atem, "pair_established").await;
+        next_notice(&mut astation, "pair_established").await;
+
+        let blob = serde_json::json!({ "rows": vec!["a highly repetitive row of JSON"; 8_000] }).to_string();
+        atem.send(WsMessage::Text(blob.clone())).await.unwrap();
+        let relayed = tokio::time::timeout(Duration::from_secs(5), astation.next()).await.unwrap().unwrap().unwrap();
+        assert_eq!(relayed.into_text().unwrap(), blob);
+    }
+
+    /// Hub with a fast keepalive and one room, `code`, whose atem side is a test channel.
+    async fn keepalive_hub(code: &'static str) -> (RelayHub, mpsc::Receiver<String>) {
+        let hub = RelayHub::new()
+            .with_config(RelayConfig {
+                room_ttl_secs: 1,
+                ping_interval: Duration::from_millis(50),
+                idle_timeout: Duration::from_millis(150),
+                ..Default::default()
+            })
+            .with_code_generator(move || code.to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let atem_rx = hub.connect_test_side(code, Role::Atem).await;
+        (hub, atem_rx)
+    }
+
+    async fn astation_connected(hub: &RelayHub, code: &str) -> bool {
+        hub.rooms.read().await.get(code).is_some_and(|room| !room.astations.is_empty())
+    }
+
+    #[tokio::test]
+    async fn silent_connection_is_closed_and_room_can_expire() {
+        let (hub, _atem_rx) = keepalive_hub("QUET-SEAT").await;
+        let url = format!("{}?role=astation&code=QUET-SEAT", serve_ws(hub.clone()).await);
+
+        // Never read, so the client never answers the server's pings
+        let (_client, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let mut attached = false;
+        for _ in 0..100 {
+            attached |= astation_connected(&hub, "QUET-SEAT").await;
+            if attached && !astation_connected(&hub, "QUET-SEAT").await {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(attached);
+        assert!(!astation_connected(&hub, "QUET-SEAT").await, "silent astation was not dropped");
+
+        // With astation gone the room expires normally
+        hub.rooms.write().await.get_mut("QUET-SEAT").unwrap().created_at -= Duration::from_secs(2);
+        hub.cleanup_expired().await;
+        assert!(hub.rooms.read().await.get("QUET-SEAT").is_none());
+    }
+
+    #[tokio::test]
+    async fn astation_that_stops_answering_pings_is_stale_before_it_is_closed() {
+        let hub = RelayHub::new()
+            .with_config(RelayConfig {
+                ping_interval: Duration::from_millis(50),
+                idle_timeout: Duration::from_secs(5),
+                ..Default::default()
+            })
+            .with_code_generator(|| "HUSH-BEAT".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let _atem_rx = hub.connect_test_side("HUSH-BEAT", Role::Atem).await;
+        let url = format!("{}?role=astation&code=HUSH-BEAT", serve_ws(hub.clone()).await);
+
+        // Never read, so the client never answers the server's pings
+        let (_client, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let mut stale = None;
+        for _ in 0..100 {
+            let status = hub.room_status("HUSH-BEAT").await.unwrap();
+            if status.astation_stale {
+                stale = Some(status);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let status = stale.expect("astation never went stale");
+        assert!(!status.paired);
+        assert!(status.astation_connected, "still attached until the idle timeout");
+        assert_eq!(status.astation.stale_reason.as_deref(), Some("silent"));
+    }
+
+    #[tokio::test]
+    async fn connection_answering_pings_stays_connected() {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let (hub, _atem_rx) = keepalive_hub("HEAR-SEAT").await;
+        let url = format!("{}?role=astation&code=HEAR-SEAT", serve_ws(hub.clone()).await);
+
+        // Reading lets the client answer pings automatically
+        let (mut client, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let pings = tokio::spawn(async move {
+            let mut pings = 0;
+            let deadline = tokio::time::sleep(Duration::from_millis(500));
+            tokio::pin!(deadline);
+            loop {
+                tokio::select! {
+                    msg = client.next() => match msg {
+                        Some(Ok(WsMessage::Ping(_))) => pings += 1,
+                        Some(Ok(_)) => {}
+                        _ => return pings,
+                    },
+                    _ = &mut deadline => return pings,
+                }
+            }
+        });
+
+        let pings = pings.await.unwrap();
+        assert!(pings >= 3, "expected regular pings, got {}", pings);
+        assert!(astation_connected(&hub, "HEAR-SEAT").await);
+    }
+
+    /// Read text frames from `client` until one parses as JSON with `"type": kind`.
+    async fn next_notice<S>(client: &mut S, kind: &str) -> serde_json::Value
+    where
+        S: futures_util::Stream<Item = Result<tokio_tungstenite::tungstenite::Message, tokio_tungstenite::tungstenite::Error>>
+            + Unpin,
+    {
+        let read = async {
+            while let Some(Ok(msg)) = client.next().await {
+                if let Ok(text) = msg.into_text() {
+                    if let Ok(json) = serde_json::from_str::<serde_json::Value>(&text) {
+                        if json["type"] == kind {
+                            return json;
+                        }
+                    }
+                }
+            }
+            panic!("connection closed before {}", kind);
+        };
+        tokio::time::timeout(Duration::from_secs(5), read).await.expect("notice not received")
+    }
+
+    /// Skip to the server's Close frame and return its code and reason.
+    async fn next_close<S>(client: &mut S) -> (u16, String)
+    where
+        S: futures_util::Stream<Item = Result<tokio_tungstenite::tungstenite::Message, tokio_tungstenite::tungstenite::Error>>
+            + Unpin,
+    {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let read = async {
+            while let Some(Ok(msg)) = client.next().await {
+                if let WsMessage::Close(frame) = msg {
+                    let frame = frame.expect("close frame without a code");
+                    return (u16::from(frame.code), frame.reason.into_owned());
+                }
+            }
+            panic!("connection ended without a close frame");
+        };
+        tokio::time::timeout(Duration::from_secs(5), read).await.expect("close frame not received")
+    }
+
+    #[test]
+    fn close_reasons_have_distinct_application_codes() {
+        let codes: HashSet<u16> = CloseReason::ALL.iter().map(|reason| reason.code()).collect();
+        assert_eq!(codes.len(), CloseReason::ALL.len());
+        assert!(codes.iter().all(|code| (4000..5000).contains(code)));
+        assert_eq!(CloseReason::Replaced.reason(), "connection_replaced");
+    }
+
+    #[tokio::test]
+    async fn replaced_connection_gets_a_takeover_close_frame() {
+        let hub = RelayHub::new().with_code_generator(|| "SWAP-SCKT".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = format!("{}?role=atem&code=SWAP-SCKT&raw=1", serve_ws(hub.clone()).await);
+        let (mut first, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        for _ in 0..50 {
+            if hub.rooms.read().await["SWAP-SCKT"].is_connected(Role::Atem) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let (_second, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        // The notice is queued ahead of the close
+        next_notice(&mut first, "connection_replaced").await;
+        assert_eq!(next_close(&mut first).await, (4002, "connection_replaced".to_string()));
+    }
+
+    #[tokio::test]
+    async fn idle_connection_gets_an_idle_timeout_close_frame() {
+        // Timing out before the first ping leaves the client nothing to answer,
+        // so it can keep reading until the close arrives
+        let hub = RelayHub::new()
+            .with_config(RelayConfig {
+                ping_interval: Duration::from_millis(100),
+                idle_timeout: Duration::from_millis(50),
+                ..Default::default()
+            })
+            .with_code_generator(|| "MUTE-SCKT".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = format!("{}?role=astation&code=MUTE-SCKT", serve_ws(hub.clone()).await);
+        let (mut client, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+
+        assert_eq!(next_close(&mut client).await, (4003, "idle_timeout".to_string()));
+    }
+
+    #[tokio::test]
+    async fn expired_room_closes_with_room_expired() {
+        let hub = RelayHub::new()
+            .with_config(RelayConfig { room_ttl_secs: 1, ..Default::default() })
+            .with_code_generator(|| "PAST-SCKT".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = serve_ws(hub.clone()).await;
+        let (mut atem, _) = tokio_tungstenite::connect_async(format!("{}?role=atem&code=PAST-SCKT", url))
+            .await
+            .unwrap();
+        for _ in 0..50 {
+            if hub.rooms.read().await["PAST-SCKT"].is_connected(Role::Atem) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        hub.rooms.write().await.get_mut("PAST-SCKT").unwrap().created_at -= Duration::from_secs(2);
+        hub.cleanup_expired().await;
+        assert_eq!(next_close(&mut atem).await, (4001, "room_expired".to_string()));
+    }
+
+    #[tokio::test]
+    async fn shutdown_closes_every_relay_connection() {
+        let hub = RelayHub::new().with_code_generator(|| "ENDS-SCKT".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = serve_ws(hub.clone()).await;
+        let (mut atem, _) = tokio_tungstenite::connect_async(format!("{}?role=atem&code=ENDS-SCKT&raw=1", url))
+            .await
+            .unwrap();
+        let (mut astation, _) = tokio_tungstenite::connect_async(format!("{}?role=astation&code=ENDS-SCKT&raw=1", url))
+            .await
+            .unwrap();
+        next_notice(&mut atem, "pair_established").await;
+        next_notice(&mut astation, "pair_established").await;
+
+        assert_eq!(hub.close_all_connections(CloseReason::ServerShutdown).await, 2);
+        for client in [&mut atem, &mut astation] {
+            assert_eq!(next_close(client).await, (4006, "server_shutdown".to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn paired_room_requires_reconnect_token() {
+        use tokio_tungstenite::tungstenite::Error as WsError;
+
+        let hub = RelayHub::new().with_code_generator(|| "KEYS-GATE".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = serve_ws(hub.clone()).await;
+        let atem_url = format!("{}?role=atem&code=KEYS-GATE", url);
+
+        // Unpaired: the code alone is enough
+        let (mut atem, _) = tokio_tungstenite::connect_async(&atem_url).await.unwrap();
+        let (mut astation, _) = tokio_tungstenite::connect_async(format!("{}?role=astation&code=KEYS-GATE", url))
+            .await
+            .unwrap();
+        let atem_token = next_notice(&mut atem, "pair_established").await["token"].as_str().unwrap().to_string();
+        let astation_token =
+            next_notice(&mut astation, "pair_established").await["token"].as_str().unwrap().to_string();
+        assert_ne!(atem_token, astation_token);
+
+        atem.close(None).await.unwrap();
+        for _ in 0..100 {
+            if !hub.rooms.read().await["KEYS-GATE"].is_connected(Role::Atem) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let bad_urls = [
+            atem_url.clone(),
+            format!("{}&token=wrong", atem_url),
+            format!("{}&token={}", atem_url, astation_token),
+        ];
+        for bad in bad_urls {
+            match tokio_tungstenite::connect_async(&bad).await {
+                Err(WsError::Http(response)) => assert_eq!(response.status(), HttpStatusCode::UNAUTHORIZED),
+                other => panic!("expected 401 for {}, got {:?}", bad, other.map(|(_, response)| response.status())),
+            }
+        }
+        assert!(!hub.rooms.read().await["KEYS-GATE"].is_connected(Role::Atem));
+
+        tokio_tungstenite::connect_async(format!("{}&token={}", atem_url, atem_token)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn invalid_envelopes_bounce_and_valid_ones_relay() {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let hub = RelayHub::new().with_code_generator(|| "ENVE-PACK".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = serve_ws(hub.clone()).await;
+        let (mut atem, _) = tokio_tungstenite::connect_async(format!("{}?role=atem&code=ENVE-PACK", url))
+            .await
+            .unwrap();
+        // A legacy client opts out of envelope checks
+        let (mut astation, _) = tokio_tungstenite::connect_async(format!("{}?role=astation&code=ENVE-PACK&raw=1", url))
+            .await
+            .unwrap();
+        next_notice(&mut atem, "pair_established").await;
+        next_notice(&mut astation, "pair_established").await;
+
+        atem.send(WsMessage::Text("not json".into())).await.unwrap();
+        atem.send(WsMessage::Text(r#"{"v":1,"type":"teleport","seq":4}"#.into())).await.unwrap();
+        let error = next_notice(&mut atem, "error").await;
+        assert_eq!(error["payload"]["code"], "malformed_json");
+        let error = next_notice(&mut atem, "error").await;
+        assert_eq!((error["payload"]["code"].as_str(), error["seq"].as_u64()), (Some("unknown_type"), Some(4)));
+
+        atem.send(WsMessage::Text(r#"{"v":1,"type":"ping","seq":5}"#.into())).await.unwrap();
+        assert_eq!(next_notice(&mut atem, "pong").await["seq"], 5);
+
+        let valid = r#"{"v":1,"type":"message","payload":{"text":"hi"},"seq":6}"#;
+        atem.send(WsMessage::Text(valid.into())).await.unwrap();
+        let relayed = tokio::time::timeout(Duration::from_secs(5), astation.next()).await.unwrap().unwrap().unwrap();
+        // Forwarded byte-for-byte, and nothing bounced reached the other side first
+        assert_eq!(relayed.into_text().unwrap(), valid);
+
+        astation.send(WsMessage::Text("plain legacy text".into())).await.unwrap();
+        let relayed = tokio::time::timeout(Duration::from_secs(5), atem.next()).await.unwrap().unwrap().unwrap();
+        assert_eq!(relayed.into_text().unwrap(), "plain legacy text");
+    }
+
+    #[test]
+    fn message_limiter_drops_floods_and_closes_sustained_ones() {
+        let config = RelayConfig {
+            message_rate: 10,
+            message_burst: 20,
+            rate_limit_close_after: Duration::from_secs(3),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        assert!(MessageLimiter::new(&RelayConfig { message_rate: 0, ..config }, start).is_none());
+
+        // Steady traffic at the rate never trips the limit
+        let mut limiter = MessageLimiter::new(&config, start).unwrap();
+        for i in 0..100 {
+            assert_eq!(limiter.check(start + Duration::from_millis(100 * i)), RateVerdict::Allow);
+        }
+
+        // A burst passes up to its size, then frames drop with one notice
+        let mut limiter = MessageLimiter::new(&config, start).unwrap();
+        for _ in 0..20 {
+            assert_eq!(limiter.check(start), RateVerdict::Allow);
+        }
+        assert_eq!(limiter.check(start), RateVerdict::Drop { notify: true });
+        assert_eq!(limiter.check(start), RateVerdict::Drop { notify: false });
+
+        // Flooding at ten times the rate: refills let a few frames through, the
+        // notice repeats each second and the connection is closed after 3s
+        let mut verdicts = Vec::new();
+        for i in 1..=400 {
+            let verdict = limiter.check(start + Duration::from_millis(10 * i));
+            let closed = verdict == RateVerdict::Close;
+            verdicts.push(verdict);
+            if closed {
+                break;
+            }
+        }
+        assert_eq!(verdicts.last(), Some(&RateVerdict::Close));
+        assert_eq!(verdicts.len(), 300);
+        assert!(verdicts.contains(&RateVerdict::Allow));
+        assert_eq!(verdicts.iter().filter(|v| **v == RateVerdict::Drop { notify: true }).count(), 2);
+
+        // A pause ends the run: the next flood gets the full window again
+        let later = start + Duration::from_secs(60);
+        let mut limiter = MessageLimiter::new(&config, start).unwrap();
+        limiter.tokens = 0.0;
+        limiter.refilled_at = later;
+        assert_eq!(limiter.check(later), RateVerdict::Drop { notify: true });
+        let resumed = later + Duration::from_secs(2);
+        limiter.tokens = 0.0;
+        limiter.refilled_at = resumed;
+        assert_eq!(limiter.check(resumed), RateVerdict::Drop { notify: true });
+        assert_eq!(limiter.limited_since, Some(resumed));
+    }
 
-        // Step 2: GET /api/pair/:code should return paired=false
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .uri(format!("/api/pair/{}", code))
-                    .body(Body::empty())
-                    .unwrap(),
-            )
+    #[tokio::test]
+    async fn flooding_connection_is_rate_limited_then_closed() {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let hub = RelayHub::new()
+            .with_config(RelayConfig {
+                message_rate: 1,
+                message_burst: 3,
+                rate_limit_close_after: Duration::from_millis(500),
+                ..Default::default()
+            })
+            .with_code_generator(|| "SPAM-GATE".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = serve_ws(hub.clone()).await;
+        let (mut atem, _) = tokio_tungstenite::connect_async(format!("{}?role=atem&code=SPAM-GATE&raw=1", url))
             .await
             .unwrap();
-
-        assert_eq!(response.status(), HttpStatusCode::OK);
-        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        let (mut astation, _) = tokio_tungstenite::connect_async(format!("{}?role=astation&code=SPAM-GATE&raw=1", url))
             .await
             .unwrap();
-        let status_resp: PairStatusResponse = serde_json::from_slice(&body).unwrap();
-        assert!(!status_resp.paired, "Newly created pair should not be paired yet");
-        assert_eq!(status_resp.hostname, "dev-machine");
+        next_notice(&mut atem, "pair_established").await;
+        next_notice(&mut astation, "pair_established").await;
+
+        for i in 0..10 {
+            atem.send(WsMessage::Text(format!("frame {}", i))).await.unwrap();
+        }
+        let error = next_notice(&mut atem, "error").await;
+        assert_eq!(error["payload"]["code"], "rate_limited");
+        for i in 0..3 {
+            let relayed = tokio::time::timeout(Duration::from_secs(5), astation.next()).await.unwrap().unwrap().unwrap();
+            assert_eq!(relayed.into_text().unwrap(), format!("frame {}", i));
+        }
+
+        // Keep flooding past the close window: the relay hangs up on atem
+        for _ in 0..20 {
+            if atem.send(WsMessage::Text("more".into())).await.is_err() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let notice = next_notice(&mut astation, "peer_disconnected").await;
+        assert_eq!(notice["role"], "atem");
+        assert!(hub.stats().await.messages_dropped >= 7);
+        assert_eq!(next_close(&mut atem).await, (4004, "rate_limited".to_string()));
     }
 
     #[tokio::test]
-    async fn test_pair_status_not_found() {
-        let app = create_relay_app();
+    async fn relay_stats_track_open_connections() {
+        let hub = RelayHub::new().with_code_generator(|| "CNTS-WSKT".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = serve_ws(hub.clone()).await;
+        let mut state = ws_state(hub.clone());
+        state.admin_token = Some("admin".into());
+        let app = Router::new()
+            .route("/api/admin/relay/stats", axum::routing::get(relay_stats_handler))
+            .with_state(state);
+        let get_stats = |token: &'static str| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(
+                        Request::builder()
+                            .uri("/api/admin/relay/stats")
+                            .header("authorization", format!("Bearer {}", token))
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap())
+            }
+        };
+        assert_eq!(get_stats("wrong").await.0, HttpStatusCode::FORBIDDEN);
 
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .uri("/api/pair/NONEXIST")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
+        let (mut atem, _) = tokio_tungstenite::connect_async(format!("{}?role=atem&code=CNTS-WSKT", url))
+            .await
+            .unwrap();
+        let (_astation, _) = tokio_tungstenite::connect_async(format!("{}?role=astation&code=CNTS-WSKT", url))
             .await
             .unwrap();
+        next_notice(&mut atem, "pair_established").await;
+        let (status, stats) = get_stats("admin").await;
+        assert_eq!(status, HttpStatusCode::OK);
+        assert_eq!((stats["atem_connections"].as_u64(), stats["astation_connections"].as_u64()), (Some(1), Some(1)));
+        assert_eq!(stats["active_rooms"], 1);
 
-        assert_eq!(response.status(), HttpStatusCode::NOT_FOUND);
+        atem.close(None).await.unwrap();
+        for _ in 0..100 {
+            if hub.stats().await.atem_connections == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let stats = hub.stats().await;
+        assert_eq!((stats.atem_connections, stats.astation_connections), (0, 1));
     }
 
     #[tokio::test]
-    async fn test_pair_page_exists() {
-        let app = create_relay_app();
+    async fn observers_get_tagged_copies_of_relayed_frames() {
+        let mut room = connected_room("WTCH-TWAY").await;
+        let mut observer = room.hub.rooms.read().await["WTCH-TWAY"].observers.subscribe();
 
-        // Step 1: Create a pair
-        let response = app
-            .clone()
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/pair")
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(r#"{"hostname": "page-test-host"}"#))
-                    .unwrap(),
-            )
+        send_from_atem(&room, "WTCH-TWAY", "from atem").await;
+        assert_eq!(room.astation_rx.try_recv().unwrap(), "from atem");
+        let astation_tx = room.hub.rooms.read().await["WTCH-TWAY"].senders(Role::Astation)[0].clone();
+        route_incoming(&room.hub, "WTCH-TWAY", Role::Astation, &astation_tx, "from astation".to_string()).await;
+        assert_eq!(room.atem_rx.try_recv().unwrap(), "from astation");
+
+        let copies: Vec<serde_json::Value> = (0..2)
+            .map(|_| serde_json::from_str(&observer.try_recv().unwrap()).unwrap())
+            .collect();
+        assert_eq!(
+            copies,
+            [
+                serde_json::json!({ "type": "observed", "direction": "atem_to_astation", "frame": "from atem" }),
+                serde_json::json!({ "type": "observed", "direction": "astation_to_atem", "frame": "from astation" }),
+            ]
+        );
+        // Relay pings are answered, not relayed
+        send_from_atem(&room, "WTCH-TWAY", r#"{"relay":"ping","id":"p"}"#).await;
+        assert!(observer.try_recv().is_err());
+        assert_eq!(room.hub.rooms.read().await["WTCH-TWAY"].status().astation_count, 1);
+    }
+
+    #[tokio::test]
+    async fn e2e_handshake_locks_the_room_to_opaque_frames() {
+        let mut room = connected_room("ENCR-YPTD").await;
+        let mut observer = room.hub.rooms.read().await["ENCR-YPTD"].observers.subscribe();
+        let astation_tx = room.hub.rooms.read().await["ENCR-YPTD"].senders(Role::Astation)[0].clone();
+        let init = r#"{"v":1,"type":"e2e_init","payload":{"public_key":"AAAA"}}"#;
+        assert_eq!(Incoming::parse(init), Incoming::E2eInit);
+
+        // The key exchange is relayed, but observers only learn its size
+        send_from_atem(&room, "ENCR-YPTD", init).await;
+        assert_eq!(room.astation_rx.try_recv().unwrap(), init);
+        let copy: serde_json::Value = serde_json::from_str(&observer.try_recv().unwrap()).unwrap();
+        assert_eq!(
+            copy,
+            serde_json::json!({ "type": "observed", "direction": "atem_to_astation", "e2e": true, "bytes": init.len() })
+        );
+        assert!(!room.hub.room_status("ENCR-YPTD").await.unwrap().e2e);
+        // One side alone does not switch the room over
+        send_from_atem(&room, "ENCR-YPTD", "still plain").await;
+        assert_eq!(room.astation_rx.try_recv().unwrap(), "still plain");
+        let copy: serde_json::Value = serde_json::from_str(&observer.try_recv().unwrap()).unwrap();
+        assert_eq!(copy["frame"], "still plain");
+
+        route_incoming(&room.hub, "ENCR-YPTD", Role::Astation, &astation_tx, init.to_string()).await;
+        assert_eq!(room.atem_rx.try_recv().unwrap(), init);
+        let _ = observer.try_recv();
+        assert!(room.hub.room_status("ENCR-YPTD").await.unwrap().e2e);
+
+        // Envelopes and binary frames still flow; observers see metadata only
+        let sealed = r#"{"v":1,"type":"message","payload":{"ciphertext":"c2VjcmV0"}}"#;
+        let binary = r#"{"relay":"binary","base64":"AQID"}"#;
+        for frame in [sealed, binary] {
+            send_from_atem(&room, "ENCR-YPTD", frame).await;
+            assert_eq!(room.astation_rx.try_recv().unwrap(), frame);
+            let copy: serde_json::Value = serde_json::from_str(&observer.try_recv().unwrap()).unwrap();
+            assert!(copy.get("frame").is_none(), "{}", copy);
+            assert_eq!(copy["bytes"], frame.len());
+        }
+
+        // Plaintext is bounced back to the sender, and nobody else sees it
+        for frame in ["hello in the clear", r#"{"relay":"text","content":"hi"}"#] {
+            send_from_atem(&room, "ENCR-YPTD", frame).await;
+            assert!(room.astation_rx.try_recv().is_err(), "{}", frame);
+            assert!(observer.try_recv().is_err(), "{}", frame);
+            let error: serde_json::Value = serde_json::from_str(&room.atem_rx.try_recv().unwrap()).unwrap();
+            assert_eq!(error["payload"]["code"], "e2e_plaintext_refused");
+        }
+    }
+
+    #[tokio::test]
+    async fn observer_needs_a_token_and_cannot_send() {
+        use tokio_tungstenite::tungstenite::{Error as WsError, Message as WsMessage};
+
+        let hub = RelayHub::new().with_code_generator(|| "WTCH-WSKT".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let app = Router::new().route("/ws", axum::routing::get(ws_handler)).with_state(AppState {
+            admin_token: Some("admin".into()),
+            ..ws_state(hub.clone())
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        for query in ["", "&token=wrong"] {
+            match tokio_tungstenite::connect_async(format!("{}?role=observer&code=WTCH-WSKT{}", url, query)).await {
+                Err(WsError::Http(response)) => assert_eq!(response.status(), HttpStatusCode::UNAUTHORIZED),
+                other => panic!("expected 401, got {:?}", other.map(|(_, response)| response.status())),
+            }
+        }
+
+        let (mut atem, _) = tokio_tungstenite::connect_async(format!("{}?role=atem&code=WTCH-WSKT&raw=1", url))
             .await
             .unwrap();
-
-        assert_eq!(response.status(), HttpStatusCode::CREATED);
-        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        let (mut astation, _) = tokio_tungstenite::connect_async(format!("{}?role=astation&code=WTCH-WSKT&raw=1", url))
             .await
             .unwrap();
-        let created: CreatePairResponse = serde_json::from_slice(&body).unwrap();
-        let code = created.code;
+        let token = next_notice(&mut atem, "pair_established").await["token"].as_str().unwrap().to_string();
+        next_notice(&mut astation, "pair_established").await;
 
-        // Step 2: GET /pair?code=XXXX should return HTML containing the code
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .uri(format!("/pair?code={}", code))
-                    .body(Body::empty())
-                    .unwrap(),
-            )
+        // A reconnect token is enough; the admin token works too
+        let (mut observer, _) =
+            tokio_tungstenite::connect_async(format!("{}?role=observer&code=WTCH-WSKT&token={}", url, token))
+                .await
+                .unwrap();
+        let (_admin_observer, _) = tokio_tungstenite::connect_async(format!("{}?role=observer&code=WTCH-WSKT&token=admin", url))
             .await
             .unwrap();
 
-        assert_eq!(response.status(), HttpStatusCode::OK);
-        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        observer.send(WsMessage::Text("injected".into())).await.unwrap();
+        assert_eq!(next_notice(&mut observer, "error").await["payload"]["code"], "observer_read_only");
+
+        atem.send(WsMessage::Text("watched".into())).await.unwrap();
+        let relayed = tokio::time::timeout(Duration::from_secs(5), astation.next()).await.unwrap().unwrap().unwrap();
+        // The observer's frame never reached astation
+        assert_eq!(relayed.into_text().unwrap(), "watched");
+        let copy = next_notice(&mut observer, "observed").await;
+        assert_eq!((copy["direction"].as_str(), copy["frame"].as_str()), (Some("atem_to_astation"), Some("watched")));
+
+        let status = hub.rooms.read().await["WTCH-WSKT"].status();
+        assert!(status.paired);
+        assert_eq!(status.astation_count, 1);
+        assert_eq!(hub.stats().await.astation_connections, 1);
+    }
+
+    #[tokio::test]
+    async fn room_events_fire_in_lifecycle_order() {
+        let hub = RelayHub::new()
+            .with_config(RelayConfig { disconnect_grace: Duration::ZERO, ..Default::default() })
+            .with_code_generator(|| "EVNT-FEED".to_string());
+        let mut events = hub.subscribe_events();
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = serve_ws(hub.clone()).await;
+        let (mut atem, _) = tokio_tungstenite::connect_async(format!("{}?role=atem&code=EVNT-FEED", url))
             .await
             .unwrap();
-        let html = String::from_utf8(body.to_vec()).unwrap();
-        assert!(
-            html.contains(&code),
-            "HTML page should contain the pairing code"
+        for _ in 0..50 {
+            if hub.rooms.read().await["EVNT-FEED"].is_connected(Role::Atem) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let (astation, _) = tokio_tungstenite::connect_async(format!("{}?role=astation&code=EVNT-FEED", url))
+            .await
+            .unwrap();
+        next_notice(&mut atem, "pair_established").await;
+        drop((atem, astation));
+
+        // Once both sides are gone the sweep removes the room
+        for _ in 0..100 {
+            hub.cleanup_expired().await;
+            if hub.room_count().await == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let mut seen = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            assert_eq!((event.code.as_str(), event.hostname.as_str()), ("EVNT-FEED", "host"));
+            seen.push(event.event_type);
+        }
+        assert_eq!(
+            seen,
+            [
+                RoomEventType::RoomCreated,
+                RoomEventType::AtemConnected,
+                RoomEventType::AstationConnected,
+                RoomEventType::RoomRemoved,
+            ]
         );
-        assert!(
-            html.contains("page-test-host"),
-            "HTML page should contain the hostname"
+    }
+
+    #[tokio::test]
+    async fn room_events_stream_as_sse() {
+        let hub = RelayHub::new().with_code_generator(|| "EVNT-HTTP".to_string());
+        let mut state = ws_state(hub.clone());
+        state.admin_token = Some("admin".into());
+        let app = Router::new()
+            .route("/api/relay/rooms/events", axum::routing::get(room_events_handler))
+            .with_state(state);
+        let request = |token: &str| {
+            Request::builder()
+                .uri("/api/relay/rooms/events")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app.clone().oneshot(request("wrong")).await.unwrap();
+        assert_eq!(response.status(), HttpStatusCode::FORBIDDEN);
+
+        let response = app.oneshot(request("admin")).await.unwrap();
+        assert_eq!(response.status(), HttpStatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let mut body = response.into_body().into_data_stream();
+        let chunk = tokio::time::timeout(Duration::from_secs(5), body.next()).await.unwrap().unwrap().unwrap();
+        let text = String::from_utf8(chunk.to_vec()).unwrap();
+        let data = text
+            .strip_prefix("event: room_created\ndata: ")
+            .and_then(|rest| rest.strip_suffix("\n\n"))
+            .unwrap_or_else(|| panic!("unexpected frame {:?}", text));
+        let data: serde_json::Value = serde_json::from_str(data).unwrap();
+        assert_eq!((data["code"].as_str(), data["hostname"].as_str()), (Some("EVNT-HTTP"), Some("host")));
+        assert!(data["timestamp"].is_string());
+    }
+
+    #[test]
+    fn test_mask_code() {
+        assert_eq!(mask_code("ABCD-WXYZ"), "AB*****YZ");
+        assert_eq!(mask_code("ABC23"), "AB*23");
+        assert_eq!(mask_code("ABCD"), "****");
+    }
+
+    #[tokio::test]
+    async fn admin_pair_rooms_list_masked_summaries() {
+        let codes = std::sync::atomic::AtomicUsize::new(0);
+        let hub = RelayHub::new()
+            .with_code_generator(move || ["ADMN-AAAA", "ADMN-BBBB"][codes.fetch_add(1, Ordering::Relaxed)].to_string());
+        hub.insert_new_room("first-host".into(), "secret-owner-token".into()).await.unwrap();
+        hub.insert_new_room("second-host".into(), "t".into()).await.unwrap();
+        // atem waits alone with a message held for astation
+        let _atem_rx = hub.connect_test_side("ADMN-AAAA", Role::Atem).await;
+        let atem_tx = hub.rooms.read().await["ADMN-AAAA"].senders(Role::Atem)[0].clone();
+        route_incoming(&hub, "ADMN-AAAA", Role::Atem, &atem_tx, "private payload".to_string()).await;
+
+        let mut state = ws_state(hub.clone());
+        state.admin_token = Some("admin".into());
+        let app = Router::new()
+            .route("/api/admin/pair-rooms", axum::routing::get(list_pair_rooms_admin_handler))
+            .route("/api/admin/pair-rooms/:code", axum::routing::get(get_pair_room_admin_handler))
+            .with_state(state);
+        let get = |uri: &'static str, token: &'static str| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(
+                        Request::builder()
+                            .uri(uri)
+                            .header("authorization", format!("Bearer {}", token))
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, String::from_utf8(body.to_vec()).unwrap())
+            }
+        };
+        assert_eq!(get("/api/admin/pair-rooms", "wrong").await.0, HttpStatusCode::FORBIDDEN);
+        assert_eq!(get("/api/admin/pair-rooms/ADMN-AAAA", "wrong").await.0, HttpStatusCode::FORBIDDEN);
+
+        let (status, body) = get("/api/admin/pair-rooms", "admin").await;
+        assert_eq!(status, HttpStatusCode::OK);
+        for leaked in ["ADMN-AAAA", "ADMN-BBBB", "secret-owner-token", "private payload"] {
+            assert!(!body.contains(leaked), "{} in {}", leaked, body);
+        }
+        let list: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(list["count"], 2);
+        let first = list["rooms"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|room| room["hostname"] == "first-host")
+            .unwrap();
+        let mut fields: Vec<&str> = first.as_object().unwrap().keys().map(String::as_str).collect();
+        fields.sort_unstable();
+        assert_eq!(
+            fields,
+            ["age_secs", "astation_connected", "atem_connected", "backlog", "code", "created_at", "hostname"]
         );
-        assert!(
-            html.contains("Atem Pairing"),
-            "HTML page should contain the page title"
+        assert_eq!(first["code"], "AD*****AA");
+        assert_eq!((first["atem_connected"].as_bool(), first["astation_connected"].as_bool()), (Some(true), Some(false)));
+        assert_eq!(first["backlog"], 1);
+
+        // Exact lookup takes the full code, in any case
+        let (status, body) = get("/api/admin/pair-rooms/admn-bbbb", "admin").await;
+        assert_eq!(status, HttpStatusCode::OK);
+        let room: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!((room["code"].as_str(), room["hostname"].as_str()), (Some("ADMN-BBBB"), Some("second-host")));
+        assert!(!body.contains("\"t\""));
+        assert_eq!(get("/api/admin/pair-rooms/ADMN-CCCC", "admin").await.0, HttpStatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn room_history_folds_repeats_and_keeps_the_newest_entries() {
+        let history = RoomHistory::default();
+        for _ in 0..5 {
+            history.tally(RoomHistoryKind::Messages, Role::Atem, None);
+        }
+        history.tally(RoomHistoryKind::Messages, Role::Astation, None);
+        history.tally(RoomHistoryKind::Messages, Role::Atem, None);
+        let counts: Vec<(Role, u64)> = history.entries().iter().map(|e| (e.role, e.count)).collect();
+        assert_eq!(counts, [(Role::Atem, 6), (Role::Astation, 1)]);
+
+        // Past the tally window a new entry starts
+        history.0.lock().unwrap()[0].at -= chrono::Duration::minutes(2);
+        history.tally(RoomHistoryKind::Messages, Role::Atem, None);
+        assert_eq!(history.entries().len(), 3);
+
+        for _ in 0..ROOM_HISTORY_CAPACITY {
+            history.note(RoomHistoryKind::Connected, Role::Astation, None);
+        }
+        let entries = history.entries();
+        assert_eq!(entries.len(), ROOM_HISTORY_CAPACITY);
+        assert!(entries.iter().all(|e| e.kind == RoomHistoryKind::Connected));
+    }
+
+    #[tokio::test]
+    async fn room_history_records_a_session_without_payloads() {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let hub = RelayHub::new().with_code_generator(|| "TRAC-KMSG".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = serve_ws(hub.clone()).await;
+        let (mut atem, _) = tokio_tungstenite::connect_async(format!("{}?role=atem&code=TRAC-KMSG", url))
+            .await
+            .unwrap();
+        let (mut astation, _) = tokio_tungstenite::connect_async(format!("{}?role=astation&code=TRAC-KMSG", url))
+            .await
+            .unwrap();
+        next_notice(&mut atem, "pair_established").await;
+        next_notice(&mut astation, "pair_established").await;
+
+        for n in 0..3 {
+            let frame = serde_json::json!({ "v": 1, "type": "command", "payload": { "run": "secret-command" }, "seq": n });
+            atem.send(WsMessage::Text(frame.to_string())).await.unwrap();
+        }
+        atem.send(WsMessage::Text("secret-garbage".into())).await.unwrap();
+        assert_eq!(next_notice(&mut atem, "error").await["payload"]["code"], "malformed_json");
+        atem.close(None).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !hub
+                .room_history("TRAC-KMSG")
+                .await
+                .unwrap()
+                .iter()
+                .any(|e| e.kind == RoomHistoryKind::Disconnected)
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let mut state = ws_state(hub.clone());
+        state.admin_token = Some("admin".into());
+        let app = Router::new()
+            .route("/api/admin/pair-rooms/:code/events", axum::routing::get(pair_room_history_admin_handler))
+            .with_state(state);
+        let get = |uri: &'static str, token: &'static str| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(
+                        Request::builder()
+                            .uri(uri)
+                            .header("authorization", format!("Bearer {}", token))
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, String::from_utf8(body.to_vec()).unwrap())
+            }
+        };
+        assert_eq!(get("/api/admin/pair-rooms/TRAC-KMSG/events", "wrong").await.0, HttpStatusCode::FORBIDDEN);
+        assert_eq!(get("/api/admin/pair-rooms/TRAC-CCCC/events", "admin").await.0, HttpStatusCode::NOT_FOUND);
+
+        let (status, body) = get("/api/admin/pair-rooms/trac-kmsg/events", "admin").await;
+        assert_eq!(status, HttpStatusCode::OK);
+        for leaked in ["secret-command", "secret-garbage"] {
+            assert!(!body.contains(leaked), "{} in {}", leaked, body);
+        }
+        let history: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(history["code"], "TRAC-KMSG");
+        let events: Vec<(String, String, Option<String>, u64)> = history["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| {
+                (
+                    e["kind"].as_str().unwrap().to_string(),
+                    e["role"].as_str().unwrap().to_string(),
+                    e["detail"].as_str().map(str::to_string),
+                    e["count"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        let expected = [
+            ("connected", "atem", None, 1),
+            ("connected", "astation", None, 1),
+            ("messages", "atem", None, 3),
+            ("error", "atem", Some("malformed_json"), 1),
+            ("disconnected", "atem", None, 1),
+        ];
+        let expected: Vec<(String, String, Option<String>, u64)> = expected
+            .iter()
+            .map(|(kind, role, detail, count)| (kind.to_string(), role.to_string(), detail.map(str::to_string), *count))
+            .collect();
+        assert_eq!(events, expected);
+        assert_eq!(history["count"], 5);
+    }
+
+    #[tokio::test]
+    async fn dead_astation_channel_is_not_paired() {
+        let hub = RelayHub::new();
+        let _atem_rx = hub.connect_test_side("DEAD-PEER", Role::Atem).await;
+        let astation_rx = hub.connect_test_side("DEAD-PEER", Role::Astation).await;
+        assert!(hub.room_status("DEAD-PEER").await.unwrap().paired);
+
+        // The astation task died without its socket ever closing
+        drop(astation_rx);
+        let status = hub.room_status("DEAD-PEER").await.unwrap();
+        assert!(!status.paired);
+        assert!(status.astation_stale);
+        assert!(status.astation_connected);
+        assert_eq!(status.astation.stale_reason.as_deref(), Some("channel_closed"));
+        assert_eq!(status.atem.stale_reason, None);
+    }
+
+    #[tokio::test]
+    async fn silent_astation_is_stale_until_it_is_heard_from() {
+        let hub = RelayHub::new();
+        let _atem_rx = hub.connect_test_side("MUTE-PEER", Role::Atem).await;
+        let _astation_rx = hub.connect_test_side("MUTE-PEER", Role::Astation).await;
+        let last_seen = hub.rooms.read().await["MUTE-PEER"].astations[0].last_seen.clone();
+        *last_seen.0.lock().unwrap() -= Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECS + 5);
+
+        let status = hub.room_status("MUTE-PEER").await.unwrap();
+        assert!(!status.paired);
+        assert!(status.astation_stale);
+        assert_eq!(status.astation.stale_reason.as_deref(), Some("silent"));
+        assert!(status.astation.last_seen_secs_ago.unwrap() > DEFAULT_IDLE_TIMEOUT_SECS);
+
+        last_seen.touch();
+        let status = hub.room_status("MUTE-PEER").await.unwrap();
+        assert!(status.paired);
+        assert!(!status.astation_stale);
+        assert_eq!(status.astation.stale_reason, None);
+    }
+
+    #[tokio::test]
+    async fn handshake_client_details_show_in_status() {
+        let hub = RelayHub::new().with_code_generator(|| "VERS-SEEN".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = serve_ws(hub.clone()).await;
+        let status = |hub: RelayHub| async move { hub.rooms.read().await["VERS-SEEN"].status() };
+
+        let (mut atem, _) =
+            tokio_tungstenite::connect_async(format!("{}?role=atem&code=VERS-SEEN&client_version=1.4.2&platform=linux", url))
+                .await
+                .unwrap();
+        let long_platform = "x".repeat(MAX_CLIENT_FIELD_CHARS + 10);
+        let (_astation, _) = tokio_tungstenite::connect_async(format!(
+            "{}?role=astation&code=VERS-SEEN&client_version=2.0&platform={}",
+            url, long_platform
+        ))
+        .await
+        .unwrap();
+        for _ in 0..100 {
+            if status(hub.clone()).await.paired {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let paired = status(hub.clone()).await;
+        let mut atem_status = serde_json::to_value(&paired.atem).unwrap();
+        assert!(atem_status.as_object_mut().unwrap().remove("last_seen_secs_ago").is_some());
+        assert_eq!(
+            atem_status,
+            serde_json::json!({ "connected": true, "version": "1.4.2", "platform": "linux" })
         );
-        assert!(
-            html.contains(&format!("astation://pair?code={}", code)),
-            "HTML page should contain the deep link"
+        assert_eq!(paired.astation.client.version.as_deref(), Some("2.0"));
+        assert_eq!(paired.astation.client.platform.unwrap().len(), MAX_CLIENT_FIELD_CHARS);
+
+        // Disconnecting clears that side's details
+        atem.close(None).await.unwrap();
+        for _ in 0..100 {
+            if !status(hub.clone()).await.atem.connected {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let unpaired = status(hub.clone()).await;
+        assert_eq!(unpaired.atem, SideStatus::default());
+        assert_eq!(
+            serde_json::to_value(&unpaired.atem).unwrap(),
+            serde_json::json!({ "connected": false })
         );
+        assert!(unpaired.astation.connected);
+    }
+
+    #[tokio::test]
+    async fn disconnect_notifies_the_other_side() {
+        let hub = RelayHub::new().with_code_generator(|| "BYEB-YEXX".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = serve_ws(hub.clone()).await;
+
+        for leaving in ["atem", "astation"] {
+            let staying = if leaving == "atem" { "astation" } else { "atem" };
+            let (mut stay, _) = tokio_tungstenite::connect_async(format!("{}?role={}&code=BYEB-YEXX", url, staying))
+                .await
+                .unwrap();
+            let (mut leave, _) = tokio_tungstenite::connect_async(format!("{}?role={}&code=BYEB-YEXX", url, leaving))
+                .await
+                .unwrap();
+
+            // Wait for both connections to register
+            for _ in 0..100 {
+                let paired = hub.rooms.read().await["BYEB-YEXX"].status().paired;
+                if paired {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+
+            leave.close(None).await.unwrap();
+            let notice = next_notice(&mut stay, "peer_disconnected").await;
+            assert_eq!(notice["role"], leaving);
+
+            stay.close(None).await.unwrap();
+            // Both gone: the room waits out the grace period, so replace it with
+            // an unpaired one for the next round
+            for _ in 0..100 {
+                if hub.rooms.read().await["BYEB-YEXX"].disconnected_at.is_some() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            hub.rooms.write().await.remove("BYEB-YEXX");
+            hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn takeover_does_not_notify_the_other_side() {
+        let mut room = connected_room("SWAP-SEAT").await;
+        let (old_tx, _old_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        let (new_tx, _new_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        assert!(room.hub.attach("SWAP-SEAT", Role::Atem, &old_tx, ClientInfo::default(), false, true).await.is_some());
+        assert!(room.hub.attach("SWAP-SEAT", Role::Atem, &new_tx, ClientInfo::default(), false, true).await.is_some());
+
+        // The replaced connection's cleanup is not a disconnect
+        assert!(!room.hub.detach("SWAP-SEAT", Role::Atem, &old_tx.downgrade()).await);
+        assert!(room.astation_rx.try_recv().is_err());
+
+        assert!(room.hub.detach("SWAP-SEAT", Role::Atem, &new_tx.downgrade()).await);
+        room.hub.notify_peer_disconnected("SWAP-SEAT", Role::Atem).await;
+        let notice: serde_json::Value = serde_json::from_str(&room.astation_rx.try_recv().unwrap()).unwrap();
+        assert_eq!(notice, serde_json::json!({ "type": "peer_disconnected", "role": "atem" }));
+    }
+
+    #[tokio::test]
+    async fn full_queue_drops_messages_then_disconnects_the_peer() {
+        let hub = RelayHub::new().with_config(RelayConfig {
+            channel_capacity: 4,
+            saturation_timeout: Duration::from_millis(300),
+            ..Default::default()
+        });
+        // astation never drains its queue
+        let mut atem_rx = hub.connect_test_side("SLOW-PEER", Role::Atem).await;
+        let _astation_rx = hub.connect_test_side("SLOW-PEER", Role::Astation).await;
+        let atem_tx = hub.rooms.read().await["SLOW-PEER"].senders(Role::Atem)[0].clone();
+        let dropped = |hub: RelayHub| async move { hub.rooms.read().await["SLOW-PEER"].dropped_messages };
+
+        for i in 0..6 {
+            route_incoming(&hub, "SLOW-PEER", Role::Atem, &atem_tx, format!("msg {}", i)).await;
+        }
+        assert_eq!(dropped(hub.clone()).await, 2);
+        assert!(!hub.rooms.read().await["SLOW-PEER"].astations.is_empty());
+        let status = hub.rooms.read().await["SLOW-PEER"].status();
+        assert_eq!(status.dropped_messages, 2);
+
+        // Still full past the saturation timeout: astation is cut off
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        route_incoming(&hub, "SLOW-PEER", Role::Atem, &atem_tx, "one more".to_string()).await;
+        assert_eq!(dropped(hub.clone()).await, 3);
+        assert!(hub.rooms.read().await["SLOW-PEER"].astations.is_empty());
+        let notice: serde_json::Value = serde_json::from_str(&atem_rx.try_recv().unwrap()).unwrap();
+        assert_eq!(notice, serde_json::json!({ "type": "peer_disconnected", "role": "astation" }));
+
+        // Later messages are buffered for a reconnecting astation as usual
+        route_incoming(&hub, "SLOW-PEER", Role::Atem, &atem_tx, "buffered".to_string()).await;
+        assert_eq!(hub.rooms.read().await["SLOW-PEER"].astation_backlog.len(), 1);
     }
 
     #[tokio::test]
-    async fn test_pair_page_not_found() {
-        let app = create_relay_app();
+    async fn draining_queue_resets_saturation() {
+        let hub = RelayHub::new().with_config(RelayConfig {
+            channel_capacity: 1,
+            saturation_timeout: Duration::from_millis(200),
+            ..Default::default()
+        });
+        let _atem_rx = hub.connect_test_side("DRAN-PEER", Role::Atem).await;
+        let mut astation_rx = hub.connect_test_side("DRAN-PEER", Role::Astation).await;
+        let atem_tx = hub.rooms.read().await["DRAN-PEER"].senders(Role::Atem)[0].clone();
 
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .uri("/pair?code=NONEXIST")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+        route_incoming(&hub, "DRAN-PEER", Role::Atem, &atem_tx, "a".to_string()).await;
+        route_incoming(&hub, "DRAN-PEER", Role::Atem, &atem_tx, "dropped".to_string()).await;
+        assert!(hub.rooms.read().await["DRAN-PEER"].astations[0].saturated_since.is_some());
 
-        assert_eq!(response.status(), HttpStatusCode::NOT_FOUND);
-        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-            .await
-            .unwrap();
-        let html = String::from_utf8(body.to_vec()).unwrap();
-        assert!(
-            html.contains("not found"),
-            "404 page should indicate code not found"
-        );
+        // A forward that got through after waiting clears the saturation clock
+        let reader = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let first = astation_rx.recv().await.unwrap();
+            (first, astation_rx)
+        });
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        route_incoming(&hub, "DRAN-PEER", Role::Atem, &atem_tx, "b".to_string()).await;
+        let (first, _astation_rx) = reader.await.unwrap();
+        assert_eq!(first, "a");
+        let rooms = hub.rooms.read().await;
+        assert!(!rooms["DRAN-PEER"].astations.is_empty());
+        assert_eq!(rooms["DRAN-PEER"].dropped_messages, 1);
     }
 
     #[tokio::test]
-    async fn test_ws_handler_room_not_found() {
-        let app = create_relay_app();
+    async fn atem_messages_fan_out_to_every_astation() {
+        let hub = RelayHub::new();
+        let mut atem_rx = hub.connect_test_side("FANS-WIDE", Role::Atem).await;
+        let (first_tx, mut first_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        let (second_tx, mut second_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        assert!(hub.attach("FANS-WIDE", Role::Astation, &first_tx, ClientInfo::default(), false, true).await.is_some());
+        assert!(hub.attach("FANS-WIDE", Role::Astation, &second_tx, ClientInfo::default(), false, true).await.is_some());
+        pair_token(&mut atem_rx);
+        pair_token(&mut first_rx);
+        assert_eq!(hub.rooms.read().await["FANS-WIDE"].status().astation_count, 2);
 
-        // Send a non-upgrade GET request to /ws with a nonexistent room code.
-        // Without the WebSocket upgrade headers, the handler should check the room
-        // and return 404 before attempting the upgrade.
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .uri("/ws?role=atem&code=NONEXIST")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+        let atem_tx = hub.rooms.read().await["FANS-WIDE"].senders(Role::Atem)[0].clone();
+        route_incoming(&hub, "FANS-WIDE", Role::Atem, &atem_tx, "to all".to_string()).await;
+        assert_eq!(first_rx.try_recv().unwrap(), "to all");
+        assert_eq!(second_rx.try_recv().unwrap(), "to all");
 
-        // The ws_handler checks room existence before upgrade, so a non-upgrade
-        // request to a nonexistent room should still fail. Axum returns 400 for
-        // missing upgrade headers on WebSocket routes when the handler calls
-        // ws.on_upgrade(), but our handler returns 404 before reaching that point
-        // for nonexistent rooms.
-        // Note: Since the request lacks upgrade headers, axum's WebSocketUpgrade
-        // extractor will reject it. The handler won't even run. Axum returns 400
-        // or the rejection status. We verify it does NOT return 200/101.
-        let status = response.status();
-        assert!(
-            status == HttpStatusCode::NOT_FOUND
-                || status == HttpStatusCode::BAD_REQUEST
-                || status == HttpStatusCode::UPGRADE_REQUIRED,
-            "Expected 404, 400, or 426 for non-upgrade WS request to nonexistent room, got {}",
-            status
-        );
+        // One astation leaving keeps the room paired and atem is not told
+        assert!(hub.detach("FANS-WIDE", Role::Astation, &first_tx.downgrade()).await);
+        hub.notify_peer_disconnected("FANS-WIDE", Role::Astation).await;
+        assert!(atem_rx.try_recv().is_err());
+        let status = hub.rooms.read().await["FANS-WIDE"].status();
+        assert!(status.paired);
+        assert_eq!(status.astation_count, 1);
+
+        route_incoming(&hub, "FANS-WIDE", Role::Atem, &atem_tx, "still here".to_string()).await;
+        assert_eq!(second_rx.try_recv().unwrap(), "still here");
+        assert!(first_rx.try_recv().is_err());
+
+        // Astation messages still reach atem
+        route_incoming(&hub, "FANS-WIDE", Role::Astation, &second_tx, "reply".to_string()).await;
+        assert_eq!(atem_rx.try_recv().unwrap(), "reply");
     }
 
     #[tokio::test]
-    async fn test_create_multiple_pairs_unique_codes() {
-        let app = create_relay_app();
-        let mut codes = std::collections::HashSet::new();
+    async fn single_astation_mode_replaces_the_previous_one() {
+        let hub = RelayHub::new().with_config(RelayConfig {
+            multi_astation: false,
+            ..Default::default()
+        });
+        let mut first_rx = hub.connect_test_side("SNGL-SEAT", Role::Astation).await;
+        let (second_tx, _second_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        assert!(hub.attach("SNGL-SEAT", Role::Astation, &second_tx, ClientInfo::default(), false, true).await.is_some());
 
-        for i in 0..10 {
-            let response = app
-                .clone()
-                .oneshot(
-                    Request::builder()
-                        .method("POST")
-                        .uri("/api/pair")
-                        .header("Content-Type", "application/json")
-                        .body(Body::from(format!(r#"{{"hostname": "host-{}"}}"#, i)))
-                        .unwrap(),
-                )
-                .await
-                .unwrap();
+        let notice: serde_json::Value = serde_json::from_str(&first_rx.try_recv().unwrap()).unwrap();
+        assert_eq!(notice["type"], "connection_replaced");
+        assert_eq!(hub.rooms.read().await["SNGL-SEAT"].astations.len(), 1);
+    }
 
-            assert_eq!(response.status(), HttpStatusCode::CREATED);
-            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-                .await
-                .unwrap();
-            let resp: CreatePairResponse = serde_json::from_slice(&body).unwrap();
-            codes.insert(resp.code);
+    /// Consume the `pair_established` frame a first pairing sends, returning its token.
+    fn pair_token(rx: &mut mpsc::Receiver<String>) -> String {
+        let frame: serde_json::Value = serde_json::from_str(&rx.try_recv().unwrap()).unwrap();
+        assert_eq!(frame["type"], "pair_established");
+        frame["token"].as_str().unwrap().to_string()
+    }
+
+    /// A room with both sides connected through captured channels.
+    async fn connected_room(code: &str) -> ConnectedRoom {
+        let hub = RelayHub::new();
+        let (atem_tx, atem_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        let (astation_tx, astation_rx) = mpsc::channel::<String>(DEFAULT_CHANNEL_CAPACITY);
+        hub.rooms.write().await.insert(
+            code.to_string(),
+            PairRoom {
+                atem: Some(Connection::new(atem_tx.clone(), ClientInfo::default())),
+                astations: vec![Connection::new(astation_tx, ClientInfo::default())],
+                paired_at: Some(Utc::now()),
+                ..PairRoom::new(
+                    code.to_string(),
+                    "host".to_string(),
+                    generate_session_token(),
+                    DEFAULT_ROOM_TTL_SECS,
+                )
+            },
+        );
+        ConnectedRoom {
+            hub,
+            atem_tx,
+            atem_rx,
+            astation_rx,
         }
+    }
+
+    async fn send_from_atem(room: &ConnectedRoom, code: &str, text: &str) {
+        route_incoming(&room.hub, code, Role::Atem, &room.atem_tx, text.to_string()).await;
+    }
 
+    #[test]
+    fn relay_message_parsing() {
         assert_eq!(
-            codes.len(),
-            10,
-            "All 10 pairing codes should be unique"
+            Incoming::parse(r#"{"relay":"ping","id":7}"#),
+            Incoming::Envelope(RelayMessage::Ping { id: 7 })
+        );
+        assert_eq!(
+            Incoming::parse(r#"{"relay":"control","action":"clear_metadata"}"#),
+            Incoming::Envelope(RelayMessage::Control {
+                action: "clear_metadata".to_string(),
+                payload: serde_json::Value::Null,
+            })
+        );
+        assert_eq!(Incoming::parse(r#"{"relay":"teleport"}"#), Incoming::Unrecognized);
+        assert_eq!(Incoming::parse(r#"{"type":"heartbeat"}"#), Incoming::Passthrough);
+        assert_eq!(Incoming::parse("not json"), Incoming::Passthrough);
+        assert_eq!(
+            Incoming::parse(r#"{"v":1,"type":"latency_probe_ack","payload":{"nonce":"ab"}}"#),
+            Incoming::ProbeAck { nonce: Some("ab".to_string()) }
         );
+        assert_eq!(Incoming::parse(r#"{"type":"latency_probe_ack"}"#), Incoming::ProbeAck { nonce: None });
     }
 
     #[tokio::test]
-    async fn test_pairing_code_entropy() {
-        // Generate many codes to verify randomness distribution
-        let mut first_chars = std::collections::HashMap::new();
-        for _ in 0..100 {
-            let code = generate_pairing_code();
-            let first_char = code.chars().next().unwrap();
-            *first_chars.entry(first_char).or_insert(0) += 1;
-        }
+    async fn relay_forwards_text_and_binary() {
+        let mut room = connected_room("FWD1-CODE").await;
+        let text = r#"{"relay":"text","content":"hello"}"#;
+        let binary = r#"{"relay":"binary","base64":"AAEC"}"#;
 
-        // Should have reasonable distribution (at least 5 different first characters out of 100 samples)
-        assert!(
-            first_chars.len() >= 5,
-            "Pairing codes should have good entropy, got {} unique first chars",
-            first_chars.len()
-        );
+        send_from_atem(&room, "FWD1-CODE", text).await;
+        send_from_atem(&room, "FWD1-CODE", binary).await;
+
+        assert_eq!(room.astation_rx.try_recv().unwrap(), text);
+        assert_eq!(room.astation_rx.try_recv().unwrap(), binary);
+        assert!(room.atem_rx.try_recv().is_err(), "nothing echoed to sender");
     }
 
     #[tokio::test]
-    async fn test_pair_page_xss_protection() {
-        // Test that hostname with HTML/JS is safely escaped
-        let html = render_pair_page("TEST-CODE", "<script>alert('xss')</script>");
-        // If properly escaped, the literal string should appear, not executed
-        assert!(!html.contains("<script>alert"), "Script tags should be escaped or removed");
+    async fn relay_forwards_plain_application_messages() {
+        let mut room = connected_room("FWD2-CODE").await;
+        let legacy = r#"{"type":"statusUpdate","data":{"status":"idle"}}"#;
 
-        // Test with other XSS vectors
-        let html2 = render_pair_page("CODE-123", "' onload='alert(1)'");
-        assert!(!html2.contains("onload='alert"), "Event handlers should be escaped");
+        send_from_atem(&room, "FWD2-CODE", legacy).await;
+
+        assert_eq!(room.astation_rx.try_recv().unwrap(), legacy);
     }
 
     #[tokio::test]
-    async fn test_cleanup_expired_keeps_recently_paired() {
-        let hub = RelayHub::new();
-
-        // Create an old room but with atem connected (not astation)
-        let (tx_atem, _rx) = mpsc::unbounded_channel::<String>();
-        let room = PairRoom {
-            code: "OLD-ATEM".to_string(),
-            hostname: "old-host".to_string(),
-            atem_tx: Some(tx_atem),
-            astation_tx: None,
-            created_at: Instant::now() - std::time::Duration::from_secs(ROOM_EXPIRY_SECS + 10),
-        };
-        hub.rooms.write().await.insert("OLD-ATEM".to_string(), room);
+    async fn relay_answers_ping_locally() {
+        let mut room = connected_room("PING-CODE").await;
 
-        hub.cleanup_expired().await;
+        send_from_atem(&room, "PING-CODE", r#"{"relay":"ping","id":42}"#).await;
 
-        let rooms = hub.rooms.read().await;
-        // Should be removed (only astation_tx prevents cleanup)
-        assert!(!rooms.contains_key("OLD-ATEM"), "Room with only atem connected should be cleaned up");
+        let reply: RelayMessage = serde_json::from_str(&room.atem_rx.try_recv().unwrap()).unwrap();
+        assert_eq!(reply, RelayMessage::Pong { id: 42 });
+        assert!(room.astation_rx.try_recv().is_err(), "ping is not forwarded");
     }
 
     #[tokio::test]
-    async fn test_pair_status_shows_unpaired_then_paired() {
-        let state = crate::AppState {
-            sessions: crate::session_store::SessionStore::new(),
-            relay: RelayHub::new(),
-            rtc_sessions: crate::rtc_session::RtcSessionStore::new(),
-            session_verify_cache: SessionVerifyCache::new(),
-            voice_sessions: VoiceSessionStore::new(),
-        };
+    async fn relay_records_pong() {
+        let mut room = connected_room("PONG-CODE").await;
 
-        // Create pair
-        let code = generate_pairing_code();
-        let room = PairRoom {
-            code: code.clone(),
-            hostname: "test-host".to_string(),
-            atem_tx: None,
-            astation_tx: None,
-            created_at: Instant::now(),
-        };
-        state.relay.rooms.write().await.insert(code.clone(), room);
+        send_from_atem(&room, "PONG-CODE", r#"{"relay":"pong","id":1}"#).await;
 
-        let app = Router::new()
-            .route("/api/pair/:code", axum::routing::get(pair_status_handler))
-            .with_state(state.clone());
+        assert!(room.astation_rx.try_recv().is_err(), "pong is not forwarded");
+        let rooms = room.hub.rooms.read().await;
+        assert!(rooms["PONG-CODE"].status().last_pong_secs_ago.is_some());
+    }
 
-        // Check status before pairing
-        let response = app
-            .clone()
-            .oneshot(
-                Request::builder()
-                    .uri(format!("/api/pair/{}", code))
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+    #[tokio::test]
+    async fn relay_control_updates_room_metadata() {
+        let mut room = connected_room("CTRL-CODE").await;
 
-        assert_eq!(response.status(), HttpStatusCode::OK);
-        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
-        let status: PairStatusResponse = serde_json::from_slice(&body).unwrap();
-        assert!(!status.paired, "Should not be paired initially");
+        send_from_atem(
+            &room,
+            "CTRL-CODE",
+            r#"{"relay":"control","action":"set_metadata","payload":{"editor":"vim"}}"#,
+        )
+        .await;
+        assert!(room.astation_rx.try_recv().is_err(), "control is not forwarded");
+        {
+            let rooms = room.hub.rooms.read().await;
+            assert_eq!(rooms["CTRL-CODE"].status().metadata["editor"], "vim");
+        }
 
-        // Simulate astation connection
-        let (tx, _rx) = mpsc::unbounded_channel::<String>();
+        // Oversized metadata is refused whole, with an error back to the sender
+        let oversized = serde_json::json!({
+            "relay": "control",
+            "action": "set_metadata",
+            "payload": { "theme": "dark", "blob": "x".repeat(MAX_ROOM_METADATA_BYTES) },
+        });
+        send_from_atem(&room, "CTRL-CODE", &oversized.to_string()).await;
+        let error: serde_json::Value = serde_json::from_str(&room.atem_rx.try_recv().unwrap()).unwrap();
+        assert_eq!(error["payload"]["code"], "metadata_too_large");
         {
-            let mut rooms = state.relay.rooms.write().await;
-            if let Some(room) = rooms.get_mut(&code) {
-                room.astation_tx = Some(tx);
-            }
+            let rooms = room.hub.rooms.read().await;
+            assert_eq!(rooms["CTRL-CODE"].metadata.keys().collect::<Vec<_>>(), ["editor"]);
         }
 
-        // Check status after pairing
-        let app2 = Router::new()
+        send_from_atem(&room, "CTRL-CODE", r#"{"relay":"control","action":"clear_metadata"}"#).await;
+        let rooms = room.hub.rooms.read().await;
+        assert!(rooms["CTRL-CODE"].metadata.is_empty());
+    }
+
+    #[tokio::test]
+    async fn pair_status_shows_metadata_only_to_the_owner() {
+        let room = connected_room("META-DATA").await;
+        send_from_atem(
+            &room,
+            "META-DATA",
+            r#"{"relay":"control","action":"set_metadata","payload":{"editor":"vim"}}"#,
+        )
+        .await;
+        let owner_token = room.hub.owner_token("META-DATA").await;
+        let app = Router::new()
             .route("/api/pair/:code", axum::routing::get(pair_status_handler))
-            .with_state(state);
+            .with_state(ws_state(room.hub.clone()));
+        let status = |token: Option<&str>| {
+            let mut request = Request::builder().uri("/api/pair/META-DATA");
+            if let Some(token) = token {
+                request = request.header("Authorization", format!("Bearer {}", token));
+            }
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+        assert!(status(None).await.get("metadata").is_none());
+        assert!(status(Some("guess")).await.get("metadata").is_none());
+        assert_eq!(status(Some(&owner_token)).await["metadata"]["editor"], "vim");
+    }
 
-        let response = app2
-            .oneshot(
-                Request::builder()
-                    .uri(format!("/api/pair/{}", code))
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+    #[tokio::test]
+    async fn relay_drops_unrecognized_variants() {
+        let mut room = connected_room("DROP-CODE").await;
 
-        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
-        let status: PairStatusResponse = serde_json::from_slice(&body).unwrap();
-        assert!(status.paired, "Should be paired after astation connects");
+        send_from_atem(&room, "DROP-CODE", r#"{"relay":"teleport","to":"mars"}"#).await;
+        send_from_atem(&room, "DROP-CODE", r#"{"relay":"ping"}"#).await; // missing id
+
+        assert!(room.astation_rx.try_recv().is_err());
+        assert!(room.atem_rx.try_recv().is_err());
+    }
+
+    /// The nonce of the `latency_probe` waiting in `rx`.
+    fn probe_nonce(rx: &mut mpsc::Receiver<String>) -> String {
+        let probe: serde_json::Value = serde_json::from_str(&rx.try_recv().unwrap()).unwrap();
+        assert_eq!(probe["type"], "latency_probe");
+        probe["payload"]["nonce"].as_str().unwrap().to_string()
+    }
+
+    fn probe_ack(nonce: &str) -> String {
+        serde_json::json!({ "v": 1, "type": "latency_probe_ack", "payload": { "nonce": nonce } }).to_string()
     }
 
     #[tokio::test]
-    async fn test_concurrent_pair_creation() {
-        use std::sync::Arc;
-        use tokio::sync::Mutex;
+    async fn latency_probe_acks_update_the_ewma_and_never_reach_the_peer() {
+        let mut room = connected_room("PRBE-ACKS").await;
+        let hub = room.hub.clone();
+        let atem_tx = hub.rooms.read().await["PRBE-ACKS"].senders(Role::Atem)[0].clone();
+        let rtt_ms = |hub: RelayHub| async move { hub.rooms.read().await["PRBE-ACKS"].rtt_ewma_ms.get(&Role::Atem).copied() };
 
-        let app = Arc::new(Mutex::new(create_relay_app()));
-        let mut handles = Vec::new();
+        assert!(hub.send_latency_probe("PRBE-ACKS", Role::Atem, &atem_tx).await);
+        let nonce = probe_nonce(&mut room.atem_rx);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        route_incoming(&hub, "PRBE-ACKS", Role::Atem, &atem_tx, probe_ack(&nonce)).await;
+        let first = rtt_ms(hub.clone()).await.unwrap();
+        assert!(first >= 20.0, "{}", first);
 
-        for i in 0..10 {
-            let app_clone = app.clone();
-            handles.push(tokio::spawn(async move {
-                let app = app_clone.lock().await;
-                let response = app
-                    .clone()
-                    .oneshot(
-                        Request::builder()
-                            .method("POST")
-                            .uri("/api/pair")
-                            .header("Content-Type", "application/json")
-                            .body(Body::from(format!(r#"{{"hostname": "host-{}"}}"#, i)))
-                            .unwrap(),
-                    )
-                    .await
-                    .unwrap();
+        // An ack is only counted once, and a wrong nonce is ignored
+        route_incoming(&hub, "PRBE-ACKS", Role::Atem, &atem_tx, probe_ack(&nonce)).await;
+        assert!(hub.send_latency_probe("PRBE-ACKS", Role::Atem, &atem_tx).await);
+        let nonce = probe_nonce(&mut room.atem_rx);
+        route_incoming(&hub, "PRBE-ACKS", Role::Atem, &atem_tx, probe_ack("not-the-nonce")).await;
+        assert_eq!(rtt_ms(hub.clone()).await, Some(first));
 
-                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-                    .await
-                    .unwrap();
-                serde_json::from_slice::<CreatePairResponse>(&body)
-                    .ok()
-                    .map(|r| r.code)
-            }));
-        }
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        let second = hub
+            .ack_latency_probe("PRBE-ACKS", Role::Atem, &atem_tx, &nonce)
+            .await
+            .unwrap()
+            .as_secs_f64()
+            * 1000.0;
+        let smoothed = rtt_ms(hub.clone()).await.unwrap();
+        assert!((smoothed - (first + LATENCY_EWMA_ALPHA * (second - first))).abs() < 1e-9);
+        assert!(first < smoothed && smoothed < second, "{} {} {}", first, smoothed, second);
 
-        let mut codes = std::collections::HashSet::new();
-        for handle in handles {
-            if let Some(code) = handle.await.unwrap() {
-                codes.insert(code);
-            }
-        }
+        let status = hub.room_status("PRBE-ACKS").await.unwrap();
+        assert_eq!(status.atem_rtt_ms, Some((smoothed * 10.0).round() / 10.0));
+        assert_eq!(status.astation_rtt_ms, None);
+        let summary = hub.list_summaries().await.remove(0);
+        assert_eq!(summary.atem_rtt_ms, status.atem_rtt_ms);
 
-        assert_eq!(codes.len(), 10, "All concurrent pairs should have unique codes");
+        // Neither probes nor acks were relayed, or held for later
+        assert!(room.astation_rx.try_recv().is_err());
+        assert!(room.atem_rx.try_recv().is_err());
+        assert_eq!(hub.rooms.read().await["PRBE-ACKS"].astation_backlog.len(), 0);
     }
 
-    #[test]
-    fn test_code_chars_does_not_contain_ambiguous() {
-        let chars_str = String::from_utf8_lossy(CODE_CHARS);
-        assert!(!chars_str.contains('0'), "CODE_CHARS should not contain 0");
-        assert!(!chars_str.contains('O'), "CODE_CHARS should not contain O");
-        assert!(!chars_str.contains('1'), "CODE_CHARS should not contain 1");
-        assert!(!chars_str.contains('I'), "CODE_CHARS should not contain I");
-        assert!(!chars_str.contains('L'), "CODE_CHARS should not contain L");
+    #[tokio::test]
+    async fn connections_are_probed_and_acks_stay_on_the_server() {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let hub = RelayHub::new()
+            .with_config(RelayConfig {
+                latency_probe_interval: Duration::from_millis(50),
+                ..RelayConfig::default()
+            })
+            .with_code_generator(|| "PRBE-WAVE".to_string());
+        hub.insert_new_room("host".into(), "t".into()).await.unwrap();
+        let url = serve_ws(hub.clone()).await;
+        let (mut atem, _) = tokio_tungstenite::connect_async(format!("{}?role=atem&code=PRBE-WAVE", url))
+            .await
+            .unwrap();
+        let (mut astation, _) = tokio_tungstenite::connect_async(format!("{}?role=astation&code=PRBE-WAVE", url))
+            .await
+            .unwrap();
+        next_notice(&mut atem, "pair_established").await;
+        next_notice(&mut astation, "pair_established").await;
+
+        let probe = next_notice(&mut atem, "latency_probe").await;
+        let nonce = probe["payload"]["nonce"].as_str().unwrap();
+        atem.send(WsMessage::Text(probe_ack(nonce))).await.unwrap();
+        let marker = r#"{"v":1,"type":"event","payload":{"after":"ack"}}"#;
+        atem.send(WsMessage::Text(marker.into())).await.unwrap();
+
+        // The first frame astation gets from atem is the marker, not the ack
+        loop {
+            let frame = tokio::time::timeout(Duration::from_secs(5), astation.next()).await.unwrap().unwrap().unwrap();
+            let WsMessage::Text(text) = frame else { continue };
+            let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+            assert_ne!(value["type"], "latency_probe_ack");
+            if value["type"] != "latency_probe" {
+                assert_eq!(text, marker);
+                break;
+            }
+        }
+        assert!(hub.room_status("PRBE-WAVE").await.unwrap().atem_rtt_ms.is_some());
     }
 }
@@ -1,12 +1,21 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::{Html, IntoResponse, Json},
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Json,
+    },
 };
+use futures_util::stream;
 use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use tokio::sync::broadcast;
 use validator::Validate;
 
-use crate::auth::{self, SessionStatus};
+use crate::admin::require_admin;
+use crate::auth::{self, SessionStatus, HOSTNAME_REGEX};
+use crate::session_store::{DenyResult, GrantResult};
 use crate::web::auth_page;
 use crate::AppState;
 
@@ -14,7 +23,7 @@ use crate::AppState;
 
 #[derive(Deserialize, Validate)]
 pub struct CreateSessionRequest {
-    #[validate(length(min = 1, max = 255))]
+    #[validate(length(min = 1, max = 255), regex(path = *HOSTNAME_REGEX))]
     pub hostname: String,
 }
 
@@ -41,37 +50,109 @@ pub struct GrantRequest {
     pub otp: String,
 }
 
+/// Maximum length of the `X-Created-By` header.
+pub const MAX_CREATED_BY_LEN: usize = 255;
+
 #[derive(Serialize)]
 pub struct ErrorResponse {
     pub error: String,
 }
 
+/// Auth session as shown to admins (no OTP or token).
+#[derive(Serialize, Deserialize)]
+pub struct AdminSessionSummary {
+    pub id: String,
+    pub hostname: String,
+    pub status: SessionStatus,
+    pub client_ip: Option<IpAddr>,
+    pub created_by: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AdminSessionListResponse {
+    pub sessions: Vec<AdminSessionSummary>,
+    pub count: usize,
+}
+
 #[derive(Deserialize)]
 pub struct AuthPageQuery {
     pub id: String,
     pub tag: String,
 }
 
+/// Non-empty value of an optional identity header (`X-Created-By`, `X-Requester`).
+fn identity_header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
 // --- Route Handlers ---
 
 /// POST /api/sessions
 /// Creates a new auth session for the given hostname.
 pub async fn create_session_handler(
     State(state): State<AppState>,
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
     Json(body): Json<CreateSessionRequest>,
 ) -> impl IntoResponse {
-    // Validate input
-    if let Err(e) = body.validate() {
+    // Validate input (hostname is the only field)
+    if body.validate().is_err() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "invalid_hostname".to_string(),
+            }),
+        )
+            .into_response();
+    }
+    let created_by = identity_header(&headers, "x-created-by");
+    if created_by.is_some_and(|c| c.chars().count() > MAX_CREATED_BY_LEN) {
         return (
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
-                error: format!("Validation error: {}", e),
+                error: format!(
+                    "Validation error: X-Created-By must be at most {} characters",
+                    MAX_CREATED_BY_LEN
+                ),
             }),
         )
             .into_response();
     }
 
-    let session = auth::create_session(&body.hostname);
+    let limit = state.sessions.max_pending_per_hostname();
+    if state.sessions.count_pending_for_hostname(&body.hostname).await >= limit {
+        tracing::warn!("Too many pending auth sessions for hostname {}", body.hostname);
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(serde_json::json!({
+                "error": "too_many_pending_sessions",
+                "hostname": body.hostname,
+                "limit": limit,
+            })),
+        )
+            .into_response();
+    }
+
+    let client_ip = if state.sessions.records_client_ip() {
+        auth::client_ip(
+            &headers,
+            connect_info.map(|ConnectInfo(addr)| addr),
+            state.sessions.trusts_proxy(),
+        )
+    } else {
+        None
+    };
+    let mut session = auth::create_session_with_ip(&body.hostname, client_ip);
+    session.created_by = created_by.map(str::to_string);
+    if let Some(key) = state.sessions.otp_hmac_key() {
+        session.otp = auth::generate_signed_otp(&session, key);
+    }
     let response = CreateSessionResponse {
         id: session.id.clone(),
         otp: session.otp.clone(),
@@ -84,22 +165,47 @@ pub async fn create_session_handler(
     (StatusCode::CREATED, Json(response)).into_response()
 }
 
+/// The status as it appears in JSON, e.g. `granted`.
+fn status_name(status: &SessionStatus) -> String {
+    serde_json::to_string(status)
+        .unwrap_or_default()
+        .trim_matches('"')
+        .to_string()
+}
+
+/// Whether an `If-None-Match` header lists `etag` (weak comparison) or is `*`.
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let Some(value) = headers.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    let strip_weak = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    value
+        .split(',')
+        .any(|tag| tag.trim() == "*" || strip_weak(tag) == strip_weak(etag))
+}
+
 /// GET /api/sessions/:id/status
 /// Returns the current status of a session. Includes token if granted.
+/// Carries a weak `ETag` of the id and status; a poll whose `If-None-Match`
+/// still matches gets `304 Not Modified` with no body.
 pub async fn get_session_status_handler(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     match state.sessions.get(&id).await {
         Some(session) => {
-            // Check if session has expired
-            let status = if session.status == SessionStatus::Pending
-                && chrono::Utc::now() > session.expires_at
-            {
-                SessionStatus::Expired
-            } else {
-                session.status.clone()
-            };
+            // `get` has already marked a lapsed session expired
+            let status = session.status.clone();
+            let etag = format!("W/\"{}:{}\"", session.id, status_name(&status));
+            // Conditional polls stay possible, but no shared cache may keep a token
+            let cache_headers = [
+                (header::ETAG, etag.clone()),
+                (header::CACHE_CONTROL, "no-store".to_string()),
+            ];
+            if etag_matches(&headers, &etag) {
+                return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+            }
 
             let token = if status == SessionStatus::Granted {
                 session.token.clone()
@@ -107,11 +213,15 @@ pub async fn get_session_status_handler(
                 None
             };
 
-            Ok(Json(SessionStatusResponse {
-                id: session.id,
-                status,
-                token,
-            }))
+            Ok((
+                cache_headers,
+                Json(SessionStatusResponse {
+                    id: session.id,
+                    status,
+                    token,
+                }),
+            )
+                .into_response())
         }
         None => Err((
             StatusCode::NOT_FOUND,
@@ -122,67 +232,120 @@ pub async fn get_session_status_handler(
     }
 }
 
+/// GET /api/sessions/:id/events
+/// Server-Sent Events stream for the auth page. Sends one `granted`, `denied`
+/// or `expired` event (data `{"status": ...}`, never the token) once the
+/// session leaves pending, then ends.
+pub async fn session_events_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    // Subscribe before reading so a change in between is not missed
+    let changes = state.sessions.subscribe_status();
+    let Some(session) = state.sessions.get(&id).await else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Session not found".to_string(),
+            }),
+        ));
+    };
+    let outcome = wait_for_outcome(state.sessions.clone(), changes, session);
+    let events = stream::once(async move {
+        let status = outcome.await;
+        Ok::<_, Infallible>(
+            Event::default()
+                .event(status_name(&status))
+                .data(serde_json::json!({ "status": status }).to_string()),
+        )
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Resolve to the session's first non-pending status; pending past its expiry is `Expired`.
+async fn wait_for_outcome(
+    sessions: crate::session_store::SessionStore,
+    mut changes: broadcast::Receiver<(String, SessionStatus)>,
+    session: auth::Session,
+) -> SessionStatus {
+    if session.status != SessionStatus::Pending {
+        return session.status;
+    }
+    let until_expiry = (session.expires_at - chrono::Utc::now())
+        .to_std()
+        .unwrap_or_default();
+    let expiry = tokio::time::sleep(until_expiry);
+    tokio::pin!(expiry);
+    loop {
+        tokio::select! {
+            _ = &mut expiry => return SessionStatus::Expired,
+            change = changes.recv() => match change {
+                Ok((id, status)) if id == session.id && status != SessionStatus::Pending => {
+                    return status;
+                }
+                Ok(_) => {}
+                // Missed some changes: re-read this one
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    match sessions.get(&session.id).await {
+                        Some(current) if current.status != SessionStatus::Pending => return current.status,
+                        Some(_) => {}
+                        None => return SessionStatus::Expired,
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    expiry.as_mut().await;
+                    return SessionStatus::Expired;
+                }
+            },
+        }
+    }
+}
+
 /// POST /api/sessions/:id/grant
 /// Validates the OTP, sets status to Granted, and generates a session token.
+/// When both the session's `created_by` and an `X-Requester` header are present
+/// they must match.
 pub async fn grant_session_handler(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    headers: HeaderMap,
     Json(body): Json<GrantRequest>,
 ) -> impl IntoResponse {
-    match state.sessions.get(&id).await {
-        Some(mut session) => {
-            // Check if already processed
-            if session.status != SessionStatus::Pending {
-                return Err((
-                    StatusCode::CONFLICT,
-                    Json(ErrorResponse {
-                        error: format!(
-                            "Session is already {}",
-                            serde_json::to_string(&session.status)
-                                .unwrap_or_default()
-                                .trim_matches('"')
-                        ),
-                    }),
-                ));
-            }
-
-            // Validate OTP
-            if !auth::validate_otp(&session, &body.otp) {
-                // Check if expired
-                if chrono::Utc::now() > session.expires_at {
-                    return Err((
-                        StatusCode::GONE,
-                        Json(ErrorResponse {
-                            error: "Session has expired".to_string(),
-                        }),
-                    ));
-                }
-                return Err((
-                    StatusCode::UNAUTHORIZED,
-                    Json(ErrorResponse {
-                        error: "Invalid OTP".to_string(),
-                    }),
-                ));
-            }
-
-            session.status = SessionStatus::Granted;
-            session.token = Some(auth::generate_session_token());
-            let response = SessionStatusResponse {
-                id: session.id.clone(),
-                status: session.status.clone(),
-                token: session.token.clone(),
-            };
-            state.sessions.update(&id, session).await;
-
-            Ok(Json(response))
+    // The creator never changes, so this check can run before the atomic grant
+    if let Some(requester) = identity_header(&headers, "x-requester") {
+        let created_by = state.sessions.get(&id).await.and_then(|session| session.created_by);
+        if created_by.is_some_and(|created_by| created_by != requester) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: "Requester does not match session creator".to_string(),
+                }),
+            ));
         }
-        None => Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Session not found".to_string(),
-            }),
-        )),
     }
+
+    let (status, error) = match state.sessions.grant_if_pending(&id, &body.otp).await {
+        GrantResult::Granted(token) => {
+            return Ok(Json(SessionStatusResponse {
+                id,
+                status: SessionStatus::Granted,
+                token: Some(token),
+            }));
+        }
+        GrantResult::AlreadyProcessed(current) => (
+            StatusCode::CONFLICT,
+            format!(
+                "Session is already {}",
+                serde_json::to_string(&current)
+                    .unwrap_or_default()
+                    .trim_matches('"')
+            ),
+        ),
+        GrantResult::Expired => (StatusCode::GONE, "Session has expired".to_string()),
+        GrantResult::InvalidOtp => (StatusCode::UNAUTHORIZED, "Invalid OTP".to_string()),
+        GrantResult::NotFound => (StatusCode::NOT_FOUND, "Session not found".to_string()),
+    };
+    Err((status, Json(ErrorResponse { error })))
 }
 
 /// POST /api/sessions/:id/deny
@@ -191,39 +354,55 @@ pub async fn deny_session_handler(
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
-    match state.sessions.get(&id).await {
-        Some(mut session) => {
-            if session.status != SessionStatus::Pending {
-                return Err((
-                    StatusCode::CONFLICT,
-                    Json(ErrorResponse {
-                        error: format!(
-                            "Session is already {}",
-                            serde_json::to_string(&session.status)
-                                .unwrap_or_default()
-                                .trim_matches('"')
-                        ),
-                    }),
-                ));
-            }
-
-            session.status = SessionStatus::Denied;
-            let response = SessionStatusResponse {
-                id: session.id.clone(),
-                status: session.status.clone(),
+    let (status, error) = match state.sessions.deny_if_pending(&id).await {
+        DenyResult::Denied => {
+            return Ok(Json(SessionStatusResponse {
+                id,
+                status: SessionStatus::Denied,
                 token: None,
-            };
-            state.sessions.update(&id, session).await;
-
-            Ok(Json(response))
+            }));
         }
-        None => Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Session not found".to_string(),
-            }),
-        )),
-    }
+        DenyResult::AlreadyProcessed(current) => (
+            StatusCode::CONFLICT,
+            format!(
+                "Session is already {}",
+                serde_json::to_string(&current)
+                    .unwrap_or_default()
+                    .trim_matches('"')
+            ),
+        ),
+        DenyResult::NotFound => (StatusCode::NOT_FOUND, "Session not found".to_string()),
+    };
+    Err((status, Json(ErrorResponse { error })))
+}
+
+/// GET /api/admin/sessions
+/// Lists all auth sessions, including the requesting client IP (admin token required).
+pub async fn list_sessions_admin_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    require_admin(&state, &headers)?;
+
+    let sessions: Vec<AdminSessionSummary> = state
+        .sessions
+        .list()
+        .await
+        .into_iter()
+        .map(|s| AdminSessionSummary {
+            id: s.id,
+            hostname: s.hostname,
+            status: s.status,
+            client_ip: s.client_ip,
+            created_by: s.created_by,
+            created_at: s.created_at,
+            expires_at: s.expires_at,
+        })
+        .collect();
+    Ok::<_, (StatusCode, Json<serde_json::Value>)>(Json(AdminSessionListResponse {
+        count: sessions.len(),
+        sessions,
+    }))
 }
 
 /// GET /auth?id=...&tag=...
@@ -237,6 +416,7 @@ pub async fn auth_page_handler(
             &session.id,
             &params.tag,
             &session.otp,
+            "",
         ))),
         None => Err((
             StatusCode::NOT_FOUND,
@@ -269,9 +449,13 @@ mod tests {
         let state = AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         };
         Router::new()
             .route("/api/sessions", post(create_session_handler))
@@ -326,14 +510,82 @@ mod tests {
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 
+    #[tokio::test]
+    async fn test_session_status_etag_and_not_modified() {
+        let app = create_app();
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/sessions")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"hostname": "poll-machine"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let created: CreateSessionResponse = serde_json::from_slice(&body).unwrap();
+
+        let poll = |etag: Option<String>| {
+            let app = app.clone();
+            let uri = format!("/api/sessions/{}/status", created.id);
+            async move {
+                let mut request = Request::builder().uri(uri);
+                if let Some(etag) = etag {
+                    request = request.header("If-None-Match", etag);
+                }
+                let response = app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+                let status = response.status();
+                let etag = response.headers()["etag"].to_str().unwrap().to_string();
+                assert_eq!(response.headers()["cache-control"], "no-store");
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, etag, body)
+            }
+        };
+
+        let (status, etag, _) = poll(None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(etag, format!("W/\"{}:pending\"", created.id));
+
+        // Unchanged: 304 with no body
+        let (status, same, body) = poll(Some(etag.clone())).await;
+        assert_eq!(status, StatusCode::NOT_MODIFIED);
+        assert_eq!(same, etag);
+        assert!(body.is_empty());
+
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/api/sessions/{}/deny", created.id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Changed: the stale tag gets the full response and a new tag
+        let (status, etag, body) = poll(Some(etag)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(etag, format!("W/\"{}:denied\"", created.id));
+        let resp: SessionStatusResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(resp.status, SessionStatus::Denied);
+    }
+
     #[tokio::test]
     async fn test_full_grant_lifecycle() {
         let state = AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         };
         let app = Router::new()
             .route("/api/sessions", post(create_session_handler))
@@ -431,9 +683,13 @@ mod tests {
         let state = AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         };
         let app = Router::new()
             .route("/api/sessions", post(create_session_handler))
@@ -501,9 +757,13 @@ mod tests {
         let state = AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         };
         let app = Router::new()
             .route("/api/sessions", post(create_session_handler))
@@ -589,9 +849,13 @@ mod tests {
         let state = AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         };
         let session = create_session("my-machine");
         let session_id = session.id.clone();
@@ -642,9 +906,13 @@ mod tests {
         let state = AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         };
         let app = Router::new()
             .route("/api/sessions", post(create_session_handler))
@@ -707,9 +975,13 @@ mod tests {
         let state = AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         };
         let app = Router::new()
             .route("/api/sessions", post(create_session_handler))
@@ -771,9 +1043,13 @@ mod tests {
         let state = AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         };
         let app = Router::new()
             .route("/api/sessions", post(create_session_handler))
@@ -879,9 +1155,13 @@ mod tests {
         let state = AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         };
         let app = Router::new()
             .route("/api/sessions", post(create_session_handler))
@@ -944,9 +1224,13 @@ mod tests {
         let state = AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         };
 
         // Create an expired session manually
@@ -959,6 +1243,8 @@ mod tests {
             token: None,
             created_at: now - Duration::minutes(10),
             expires_at: now - Duration::minutes(5),
+            client_ip: None,
+            created_by: None,
         };
         let session_id = expired_session.id.clone();
         state.sessions.create(expired_session).await;
@@ -1026,4 +1312,401 @@ mod tests {
             assert_eq!(resp.hostname, hostname);
         }
     }
+
+    #[tokio::test]
+    async fn test_invalid_hostnames_are_rejected() {
+        let app = create_app();
+        let post = |hostname: String| {
+            let app = app.clone();
+            async move {
+                let body = serde_json::json!({ "hostname": hostname }).to_string();
+                let response = app
+                    .oneshot(
+                        Request::builder()
+                            .method("POST")
+                            .uri("/api/sessions")
+                            .header("Content-Type", "application/json")
+                            .body(Body::from(body))
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap())
+            }
+        };
+
+        for hostname in ["my machine", "host/name", "back\\slash", "tab\there", "émoji", ""] {
+            let (status, body) = post(hostname.to_string()).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "should reject {:?}", hostname);
+            assert_eq!(body["error"], "invalid_hostname");
+        }
+        assert_eq!(post("a".repeat(256)).await.0, StatusCode::BAD_REQUEST);
+        assert_eq!(post("a".repeat(255)).await.0, StatusCode::CREATED);
+    }
+
+    fn admin_state(sessions: SessionStore) -> AppState {
+        AppState {
+            sessions,
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: Some("admin-secret".into()),
+        }
+    }
+
+    async fn create_with(app: &Router, req: axum::http::request::Builder) -> CreateSessionResponse {
+        let response = app
+            .clone()
+            .oneshot(
+                req.method("POST")
+                    .uri("/api/sessions")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"hostname": "ip-host"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pending_sessions_limited_per_hostname() {
+        let app = create_app();
+        let create = |hostname: &'static str| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(
+                        Request::builder()
+                            .method("POST")
+                            .uri("/api/sessions")
+                            .header("Content-Type", "application/json")
+                            .body(Body::from(format!(r#"{{"hostname": "{}"}}"#, hostname)))
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap())
+            }
+        };
+        let resolve = |action: &'static str, created: serde_json::Value| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(
+                        Request::builder()
+                            .method("POST")
+                            .uri(format!("/api/sessions/{}/{}", created["id"].as_str().unwrap(), action))
+                            .header("Content-Type", "application/json")
+                            .body(Body::from(format!(r#"{{"otp": "{}"}}"#, created["otp"].as_str().unwrap())))
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+            }
+        };
+
+        let mut pending = Vec::new();
+        for _ in 0..crate::session_store::DEFAULT_MAX_PENDING_PER_HOSTNAME {
+            let (status, created) = create("retry-host").await;
+            assert_eq!(status, StatusCode::CREATED);
+            pending.push(created);
+        }
+
+        let (status, error) = create("retry-host").await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(error["error"], "too_many_pending_sessions");
+        assert_eq!(error["hostname"], "retry-host");
+        assert_eq!(error["limit"], 3);
+
+        // Other hostnames are unaffected
+        assert_eq!(create("other-host").await.0, StatusCode::CREATED);
+
+        // Granted and denied sessions free their slot
+        resolve("grant", pending.pop().unwrap()).await;
+        assert_eq!(create("retry-host").await.0, StatusCode::CREATED);
+        resolve("deny", pending.pop().unwrap()).await;
+        assert_eq!(create("retry-host").await.0, StatusCode::CREATED);
+        assert_eq!(create("retry-host").await.0, StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_create_session_records_client_ip() {
+        let state = admin_state(SessionStore::new().with_trust_proxy(true));
+        let app = Router::new()
+            .route("/api/sessions", post(create_session_handler))
+            .with_state(state.clone());
+
+        let peer: SocketAddr = "192.0.2.10:4000".parse().unwrap();
+        let from_xff = create_with(&app, Request::builder().header("X-Forwarded-For", "203.0.113.5")).await;
+        let from_real_ip = create_with(&app, Request::builder().header("X-Real-IP", "203.0.113.6")).await;
+        let from_peer = create_with(&app, Request::builder().extension(ConnectInfo(peer))).await;
+
+        let ip = |id: String| {
+            let sessions = state.sessions.clone();
+            async move { sessions.get(&id).await.unwrap().client_ip.map(|ip| ip.to_string()) }
+        };
+        assert_eq!(ip(from_xff.id).await.as_deref(), Some("203.0.113.5"));
+        assert_eq!(ip(from_real_ip.id).await.as_deref(), Some("203.0.113.6"));
+        assert_eq!(ip(from_peer.id).await.as_deref(), Some("192.0.2.10"));
+    }
+
+    #[tokio::test]
+    async fn test_create_session_ignores_forwarded_for_without_trust_proxy() {
+        let state = admin_state(SessionStore::new());
+        let app = Router::new()
+            .route("/api/sessions", post(create_session_handler))
+            .with_state(state.clone());
+
+        let peer: SocketAddr = "192.0.2.10:4000".parse().unwrap();
+        let spoofed = create_with(
+            &app,
+            Request::builder().header("X-Forwarded-For", "203.0.113.5").extension(ConnectInfo(peer)),
+        )
+        .await;
+        let session = state.sessions.get(&spoofed.id).await.unwrap();
+        assert_eq!(session.client_ip, Some("192.0.2.10".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_create_session_signs_otp_when_keyed() {
+        let state = admin_state(SessionStore::new().with_otp_hmac_key(Some("shared-key")));
+        let app = Router::new()
+            .route("/api/sessions", post(create_session_handler))
+            .with_state(state.clone());
+
+        let created = create_with(&app, Request::builder()).await;
+        let session = state.sessions.get(&created.id).await.unwrap();
+        assert_eq!(created.otp, auth::generate_signed_otp(&session, b"shared-key"));
+        assert!(auth::verify_signed_otp(&created.id, "ip-host", created.expires_at, &created.otp, b"shared-key"));
+        assert_eq!(
+            state.sessions.grant_if_pending(&created.id, "not-it").await,
+            crate::session_store::GrantResult::InvalidOtp
+        );
+        assert!(matches!(
+            state.sessions.grant_if_pending(&created.id, &created.otp).await,
+            crate::session_store::GrantResult::Granted(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_create_session_client_ip_recording_disabled() {
+        let state = admin_state(SessionStore::new().with_record_client_ip(false));
+        let app = Router::new()
+            .route("/api/sessions", post(create_session_handler))
+            .with_state(state.clone());
+
+        let created = create_with(&app, Request::builder().header("X-Real-IP", "203.0.113.6")).await;
+        assert!(state.sessions.get(&created.id).await.unwrap().client_ip.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_admin_sessions_listing_exposes_client_ip() {
+        let state = admin_state(SessionStore::new().with_trust_proxy(true));
+        let app = Router::new()
+            .route("/api/sessions", post(create_session_handler))
+            .route("/api/admin/sessions", get(list_sessions_admin_handler))
+            .with_state(state);
+
+        let created = create_with(&app, Request::builder().header("X-Real-IP", "203.0.113.9")).await;
+        let public = serde_json::to_value(&created).unwrap();
+        assert!(public.get("client_ip").is_none(), "public response must not carry the IP");
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/admin/sessions")
+                    .header("Authorization", "Bearer admin-secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let listing: AdminSessionListResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(listing.count, 1);
+        assert_eq!(listing.sessions[0].id, created.id);
+        assert_eq!(
+            listing.sessions[0].client_ip,
+            Some("203.0.113.9".parse().unwrap())
+        );
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/admin/sessions")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    /// Create a session with an optional `X-Created-By`, then try to grant it
+    /// with an optional `X-Requester`. Returns the grant status.
+    async fn grant_as(created_by: Option<&str>, requester: Option<&str>) -> StatusCode {
+        let state = admin_state(SessionStore::new());
+        let app = Router::new()
+            .route("/api/sessions", post(create_session_handler))
+            .route("/api/sessions/:id/grant", post(grant_session_handler))
+            .with_state(state.clone());
+
+        let mut req = Request::builder();
+        if let Some(created_by) = created_by {
+            req = req.header("X-Created-By", created_by);
+        }
+        let created = create_with(&app, req).await;
+        assert_eq!(
+            state.sessions.get(&created.id).await.unwrap().created_by.as_deref(),
+            created_by
+        );
+
+        let mut req = Request::builder()
+            .method("POST")
+            .uri(format!("/api/sessions/{}/grant", created.id))
+            .header("Content-Type", "application/json");
+        if let Some(requester) = requester {
+            req = req.header("X-Requester", requester);
+        }
+        let body = Body::from(format!(r#"{{"otp": "{}"}}"#, created.otp));
+        app.oneshot(req.body(body).unwrap()).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_grant_requires_matching_requester() {
+        assert_eq!(grant_as(Some("alice@example.com"), Some("alice@example.com")).await, StatusCode::OK);
+        assert_eq!(grant_as(Some("alice@example.com"), Some("bob@example.com")).await, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_grant_requester_check_is_opt_in() {
+        assert_eq!(grant_as(None, None).await, StatusCode::OK);
+        assert_eq!(grant_as(Some("alice@example.com"), None).await, StatusCode::OK);
+        assert_eq!(grant_as(None, Some("bob@example.com")).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_created_by_is_validated_and_listed() {
+        let state = admin_state(SessionStore::new());
+        let app = Router::new()
+            .route("/api/sessions", post(create_session_handler))
+            .route("/api/admin/sessions", get(list_sessions_admin_handler))
+            .with_state(state);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/sessions")
+                    .header("Content-Type", "application/json")
+                    .header("X-Created-By", "x".repeat(MAX_CREATED_BY_LEN + 1))
+                    .body(Body::from(r#"{"hostname": "ip-host"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let created_by = "x".repeat(MAX_CREATED_BY_LEN);
+        create_with(&app, Request::builder().header("X-Created-By", created_by.as_str())).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/admin/sessions")
+                    .header("Authorization", "Bearer admin-secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let listing: AdminSessionListResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(listing.count, 1);
+        assert_eq!(listing.sessions[0].created_by.as_deref(), Some(created_by.as_str()));
+    }
+
+    async fn read_events(app: Router, id: &str) -> (StatusCode, String) {
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/sessions/{}/events", id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            axum::body::to_bytes(response.into_body(), usize::MAX),
+        )
+        .await
+        .expect("event stream did not end")
+        .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_session_events_stream_outcome() {
+        let state = admin_state(SessionStore::new());
+        let app = Router::new()
+            .route("/api/sessions/:id/events", get(session_events_handler))
+            .with_state(state.clone());
+
+        // Granted while the stream is open
+        let session = crate::auth::create_session("sse-host");
+        let id = session.id.clone();
+        state.sessions.create(session.clone()).await;
+        let sessions = state.sessions.clone();
+        let granted = session.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            let mut granted = granted;
+            granted.status = SessionStatus::Granted;
+            granted.token = Some("secret-token".into());
+            sessions.update(&granted.id.clone(), granted).await;
+        });
+        let (status, body) = read_events(app.clone(), &id).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("event: granted"), "{}", body);
+        assert!(body.contains(r#"data: {"status":"granted"}"#), "{}", body);
+        assert!(!body.contains("secret-token"));
+
+        // Already resolved: answered immediately
+        let mut denied = crate::auth::create_session("sse-host");
+        denied.status = SessionStatus::Denied;
+        state.sessions.create(denied.clone()).await;
+        assert!(read_events(app.clone(), &denied.id).await.1.contains("event: denied"));
+
+        // Pending past its expiry
+        let mut expired = crate::auth::create_session("sse-host");
+        expired.expires_at = chrono::Utc::now() + chrono::Duration::milliseconds(50);
+        state.sessions.create(expired.clone()).await;
+        assert!(read_events(app.clone(), &expired.id).await.1.contains("event: expired"));
+
+        assert_eq!(read_events(app, "missing").await.0, StatusCode::NOT_FOUND);
+    }
 }
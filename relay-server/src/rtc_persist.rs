@@ -0,0 +1,218 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::rtc_session::{RtcSession, RtcSessionStore};
+use crate::supervisor::{spawn_supervised, TaskRestarts};
+
+// Best-effort persistence of RTC sessions across restarts.
+//
+// Request handlers only flip the store's dirty flag; a background task writes
+// a JSON snapshot when the flag is set. Writes go to a temp file that is then
+// renamed over the target, so a crash mid-write leaves the previous snapshot.
+// Snapshots hold host tokens, so the file is only readable by its owner.
+
+/// Bumped if the on-disk format changes incompatibly.
+const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    saved_at: DateTime<Utc>,
+    sessions: Vec<RtcSession>,
+}
+
+/// Write all sessions to `path`.
+pub async fn save(store: &RtcSessionStore, path: &Path) -> io::Result<()> {
+    let snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        saved_at: Utc::now(),
+        sessions: store.export().await,
+    };
+    let json = serde_json::to_vec(&snapshot)?;
+    write_snapshot(path, &json).await
+}
+
+/// Replace `path` with `bytes` through an owner-only temp file and a rename.
+pub(crate) async fn write_snapshot(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp = tmp_path(path);
+    write_private(&tmp, bytes).await?;
+    tokio::fs::rename(&tmp, path).await
+}
+
+/// Write `bytes` to a fresh file at `path` with mode 0600. A leftover temp
+/// file is removed first so its permissions are not inherited.
+async fn write_private(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path).await?;
+    file.write_all(bytes).await?;
+    file.sync_all().await
+}
+
+/// Restore non-expired sessions from `path`. A missing file restores nothing.
+pub async fn load(store: &RtcSessionStore, path: &Path) -> io::Result<usize> {
+    let bytes = match tokio::fs::read(path).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let snapshot: Snapshot = serde_json::from_slice(&bytes)?;
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported snapshot version {}", snapshot.version),
+        ));
+    }
+    Ok(store.import(snapshot.sessions).await)
+}
+
+/// Periodically write a snapshot whenever the store has changed. The task is
+/// supervised, so a panic mid-save does not stop persistence for good.
+pub fn spawn_persister(restarts: &Arc<TaskRestarts>, store: RtcSessionStore, path: PathBuf, every: Duration) {
+    spawn_supervised(restarts, "rtc_persister", move || {
+        let store = store.clone();
+        let path = path.clone();
+        async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                if !store.take_dirty() {
+                    continue;
+                }
+                if let Err(e) = save(&store, &path).await {
+                    tracing::warn!("Failed to persist RTC sessions to {}: {}", path.display(), e);
+                    // Retry on the next tick
+                    store.mark_dirty();
+                }
+            }
+        }
+    });
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rtc-persist-{}-{}.json", name, uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_resumes_uid_counter() {
+        let path = temp_file("roundtrip");
+        let store = RtcSessionStore::new();
+        let created = store
+            .create("persist-1".into(), "app".into(), "ch".into(), "tok".into(), 7)
+            .await.unwrap();
+        store.join("persist-1", "Alice".into()).await.unwrap();
+        store.join("persist-1", "Bob".into()).await.unwrap();
+        save(&store, &path).await.unwrap();
+
+        let restored = RtcSessionStore::new();
+        assert_eq!(load(&restored, &path).await.unwrap(), 1);
+
+        let session = restored.get("persist-1").await.unwrap();
+        assert_eq!(session.channel, "ch");
+        assert_eq!(session.host_uid, 7);
+        assert_eq!(session.uid_counter_value, 1002);
+        assert_eq!(session.participants.len(), 2);
+        assert_eq!(session.participants[0].display_name.as_deref(), Some("Alice"));
+        assert!(restored.verify_host_token("persist-1", &created.host_token).await);
+
+        let carol = restored.join("persist-1", "Carol".into()).await.unwrap();
+        assert_eq!(carol.uid, 1002, "uid must not repeat after restore");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_load_skips_expired_sessions() {
+        let path = temp_file("expired");
+        let store = RtcSessionStore::new();
+        store.create("live".into(), "app".into(), "ch".into(), "tok".into(), 1).await.unwrap();
+        let mut snapshots = store.export().await;
+        let mut expired = snapshots[0].clone();
+        expired.id = "stale".into();
+        expired.expires_at = Utc::now() - chrono::Duration::minutes(1);
+        expired.last_activity = Utc::now() - chrono::Duration::hours(3);
+        snapshots.push(expired);
+
+        let json = serde_json::to_vec(&Snapshot {
+            version: SNAPSHOT_VERSION,
+            saved_at: Utc::now(),
+            sessions: snapshots,
+        })
+        .unwrap();
+        std::fs::write(&path, json).unwrap();
+
+        let restored = RtcSessionStore::new();
+        assert_eq!(load(&restored, &path).await.unwrap(), 1);
+        assert!(restored.get("live").await.is_some());
+        assert!(restored.get("stale").await.is_none());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_snapshot_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_file("mode");
+        // A stale temp file with loose permissions must not leak its mode
+        std::fs::write(tmp_path(&path), b"stale").unwrap();
+        std::fs::set_permissions(tmp_path(&path), std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let store = RtcSessionStore::new();
+        store.create("private".into(), "app".into(), "ch".into(), "tok".into(), 1).await.unwrap();
+        save(&store, &path).await.unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!tmp_path(&path).exists());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_load_missing_file_restores_nothing() {
+        let store = RtcSessionStore::new();
+        assert_eq!(load(&store, &temp_file("missing")).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_mutations_mark_store_dirty() {
+        let store = RtcSessionStore::new();
+        assert!(!store.take_dirty());
+
+        store.create("dirty".into(), "app".into(), "ch".into(), "tok".into(), 1).await.unwrap();
+        assert!(store.take_dirty());
+        assert!(!store.take_dirty(), "flag is cleared once taken");
+
+        store.join("dirty", "Alice".into()).await.unwrap();
+        assert!(store.take_dirty());
+
+        store.delete("dirty").await;
+        assert!(store.take_dirty());
+    }
+}
@@ -1,28 +1,462 @@
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
 use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::admin::require_admin;
+use crate::agora_token::{RtcTokenRequest, RtcTokenRole};
+use crate::auth::{bearer_token, constant_time_eq};
+use crate::relay::{is_room_owner, RelayHub, Role};
+use crate::webhook::{self, WebhookEvent, WebhookEventKind, WebhookPolicy, WebhookUrlError};
 use crate::AppState;
 
 // --- Data Models ---
 
+/// Role of a participant within an RTC session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParticipantRole {
+    /// Session creator; holds the host token.
+    Host,
+    /// Promoted by the host; may moderate the session.
+    Cohost,
+    #[default]
+    Attendee,
+}
+
+/// How attendee uids are chosen at join time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UidAllocation {
+    /// 1000, 1001, ... never reusing a uid.
+    #[default]
+    Sequential,
+    /// Hand out the lowest uid freed by a removed participant before counting up.
+    ReuseFreed,
+    /// Random uid above 1000, re-rolled on collision with a present participant.
+    Random,
+}
+
+/// What a participant intends to publish, so the host layout can prepare tiles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MediaCapabilities {
+    pub audio: bool,
+    pub video: bool,
+    pub screen: bool,
+}
+
+impl MediaCapabilities {
+    /// Whether the participant intends to publish any media at all.
+    pub fn publishes(&self) -> bool {
+        self.audio || self.video || self.screen
+    }
+}
+
+impl Default for MediaCapabilities {
+    fn default() -> Self {
+        MediaCapabilities {
+            audio: true,
+            video: true,
+            screen: false,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Participant {
     pub uid: u32,
     pub display_name: Option<String>,
     pub joined_at: DateTime<Utc>,
+    #[serde(default)]
+    pub role: ParticipantRole,
+    /// Client-supplied JSON object (avatar URL, pronouns, client version, ...).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+    #[serde(default)]
+    pub capabilities: MediaCapabilities,
+}
+
+/// Maximum serialized size of participant metadata.
+pub const MAX_METADATA_BYTES: usize = 2048;
+
+/// Why participant metadata was rejected.
+#[derive(Debug, PartialEq, Eq)]
+pub enum MetadataError {
+    NotAnObject,
+    TooLarge,
+}
+
+impl MetadataError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            MetadataError::NotAnObject => "metadata_not_object",
+            MetadataError::TooLarge => "metadata_too_large",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            MetadataError::NotAnObject => "Metadata must be a JSON object".to_string(),
+            MetadataError::TooLarge => {
+                format!("Metadata must be at most {} bytes", MAX_METADATA_BYTES)
+            }
+        }
+    }
+}
+
+/// Check that metadata is a JSON object no larger than [`MAX_METADATA_BYTES`].
+pub fn validate_metadata(metadata: &serde_json::Value) -> Result<(), MetadataError> {
+    if !metadata.is_object() {
+        return Err(MetadataError::NotAnObject);
+    }
+    let size = serde_json::to_vec(metadata).map(|v| v.len()).unwrap_or(usize::MAX);
+    if size > MAX_METADATA_BYTES {
+        return Err(MetadataError::TooLarge);
+    }
+    Ok(())
+}
+
+/// Token privileges for a joining participant. Attendees that publish no
+/// media get a join-only token; hosts and co-hosts can always publish.
+fn token_role(role: ParticipantRole, capabilities: MediaCapabilities) -> RtcTokenRole {
+    match role {
+        ParticipantRole::Host | ParticipantRole::Cohost => RtcTokenRole::Publisher,
+        ParticipantRole::Attendee if capabilities.publishes() => RtcTokenRole::Publisher,
+        ParticipantRole::Attendee => RtcTokenRole::Subscriber,
+    }
+}
+
+/// Maximum participants per session, host included.
+pub const MAX_PARTICIPANTS: usize = 8;
+
+/// Why a join was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JoinError {
+    NotFound,
+    Full { max: usize },
+    Expired,
+    /// The host or a co-host has locked the session against new joins.
+    Locked,
+    /// The session has a passcode and none was given.
+    PasscodeRequired,
+    PasscodeInvalid,
+    /// [`PASSCODE_MAX_FAILURES`] wrong passcodes within the window.
+    PasscodeAttemptsExceeded,
+    /// Another participant already uses this display name (unique names only).
+    NameTaken,
+}
+
+impl JoinError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            JoinError::NotFound => "session_not_found",
+            JoinError::Full { .. } => "session_full",
+            JoinError::Expired => "session_expired",
+            JoinError::Locked => "session_locked",
+            JoinError::PasscodeRequired => "passcode_required",
+            JoinError::PasscodeInvalid => "passcode_invalid",
+            JoinError::PasscodeAttemptsExceeded => "passcode_attempts_exceeded",
+            JoinError::NameTaken => "display_name_taken",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            JoinError::NotFound => "Session not found".to_string(),
+            JoinError::Full { max } => format!("Session is full (maximum {} participants)", max),
+            JoinError::Expired => "Session has expired".to_string(),
+            JoinError::Locked => "Session is locked".to_string(),
+            JoinError::PasscodeRequired => "Passcode required".to_string(),
+            JoinError::PasscodeInvalid => "Invalid passcode".to_string(),
+            JoinError::PasscodeAttemptsExceeded => {
+                "Too many passcode attempts, try again later".to_string()
+            }
+            JoinError::NameTaken => "Display name already in use".to_string(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            JoinError::NotFound => StatusCode::NOT_FOUND,
+            JoinError::Full { .. } | JoinError::Locked | JoinError::NameTaken => StatusCode::CONFLICT,
+            JoinError::Expired => StatusCode::GONE,
+            JoinError::PasscodeRequired | JoinError::PasscodeInvalid => StatusCode::UNAUTHORIZED,
+            JoinError::PasscodeAttemptsExceeded => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+}
+
+/// Wrong passcodes allowed per session within [`PASSCODE_FAILURE_WINDOW_SECS`].
+pub const PASSCODE_MAX_FAILURES: usize = 5;
+
+pub const PASSCODE_FAILURE_WINDOW_SECS: i64 = 60;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Salted HMAC-SHA256 of a session passcode; the passcode itself is never stored.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PasscodeHash {
+    salt: String,
+    hash: String,
+}
+
+impl PasscodeHash {
+    pub fn new(passcode: &str) -> Self {
+        let salt = crate::auth::generate_session_token();
+        let hash = Self::mac(&salt, passcode)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        PasscodeHash { salt, hash }
+    }
+
+    /// Constant-time comparison against a presented passcode.
+    pub fn verify(&self, passcode: &str) -> bool {
+        let expected: Option<Vec<u8>> = (0..self.hash.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(self.hash.get(i..i + 2)?, 16).ok())
+            .collect();
+        match expected {
+            Some(expected) => Self::mac(&self.salt, passcode).verify_slice(&expected).is_ok(),
+            None => false,
+        }
+    }
+
+    fn mac(salt: &str, passcode: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(salt.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(passcode.as_bytes());
+        mac
+    }
+}
+
+/// Default cap on concurrent sessions held in memory (`RTC_MAX_SESSIONS`).
+pub const DEFAULT_MAX_SESSIONS: usize = 1000;
+
+/// Default cap on concurrent sessions per Agora app id (`MAX_SESSIONS_PER_APP_ID`).
+pub const DEFAULT_MAX_SESSIONS_PER_APP_ID: usize = 100;
+
+/// Default time a session outlives its last activity (`RTC_MAX_IDLE_HOURS`).
+pub const DEFAULT_MAX_IDLE_HOURS: i64 = 2;
+
+/// Why a session could not be created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CreateError {
+    /// The store already holds `max` sessions.
+    AtCapacity { max: usize },
+    /// The app id already has `count` live sessions, at or over its quota of `max`.
+    AppQuotaExceeded { count: usize, max: usize },
+}
+
+impl CreateError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            CreateError::AtCapacity { .. } => "server_at_capacity",
+            CreateError::AppQuotaExceeded { .. } => "app_quota_exceeded",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            CreateError::AtCapacity { .. } => "Server at capacity".to_string(),
+            CreateError::AppQuotaExceeded { max, .. } => {
+                format!("Too many sessions for this app id (maximum {})", max)
+            }
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            CreateError::AtCapacity { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            CreateError::AppQuotaExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+}
+
+/// A co-host may claim host once the host has been silent this long.
+pub const HOST_STALE_AFTER_MINS: i64 = 5;
+
+/// Why a host transfer or claim was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferHostError {
+    NotFound,
+    ParticipantNotFound,
+    AlreadyHost,
+    /// Claims are only honoured after [`HOST_STALE_AFTER_MINS`] without a host heartbeat.
+    HostActive,
+    /// Only the longest-present co-host may claim.
+    NotEligible,
+    /// The presented host token is not (or no longer) the session's.
+    InvalidToken,
+    /// A claim's `client_id` is not the one issued to `uid` at join.
+    InvalidClientId,
+}
+
+/// How a host transfer is authorised.
+#[derive(Debug, Clone, Copy)]
+pub enum HostAuth<'a> {
+    /// The current host hands over with its host token.
+    HostToken(&'a str),
+    /// A co-host claims host for itself, proven by the `client_id` it got at join.
+    Claim { client_id: &'a str },
+}
+
+impl TransferHostError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TransferHostError::NotFound => "session_not_found",
+            TransferHostError::ParticipantNotFound => "participant_not_found",
+            TransferHostError::AlreadyHost => "already_host",
+            TransferHostError::HostActive => "host_active",
+            TransferHostError::NotEligible => "not_eligible",
+            TransferHostError::InvalidToken => "invalid_host_token",
+            TransferHostError::InvalidClientId => "invalid_client_id",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            TransferHostError::NotFound => "Session not found".to_string(),
+            TransferHostError::ParticipantNotFound => "Participant not found".to_string(),
+            TransferHostError::AlreadyHost => "Participant is already the host".to_string(),
+            TransferHostError::HostActive => "Host is still active".to_string(),
+            TransferHostError::NotEligible => {
+                "Only the longest-present co-host may claim host".to_string()
+            }
+            TransferHostError::InvalidToken => "Invalid host token".to_string(),
+            TransferHostError::InvalidClientId => "Invalid client id".to_string(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            TransferHostError::NotFound => StatusCode::NOT_FOUND,
+            TransferHostError::ParticipantNotFound | TransferHostError::AlreadyHost => {
+                StatusCode::BAD_REQUEST
+            }
+            TransferHostError::HostActive => StatusCode::CONFLICT,
+            TransferHostError::NotEligible
+            | TransferHostError::InvalidToken
+            | TransferHostError::InvalidClientId => StatusCode::FORBIDDEN,
+        }
+    }
+}
+
+/// Cloud recording marker kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingEventKind {
+    Started,
+    Stopped,
+}
+
+/// A recording start/stop marker, kept so transcripts can be aligned afterwards.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordingEvent {
+    pub event: RecordingEventKind,
+    pub at: DateTime<Utc>,
+    pub initiated_by_uid: u32,
+    /// Agora cloud recording resource id, when the host passes one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_id: Option<String>,
+}
+
+/// Why a recording marker was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordingError {
+    NotFound,
+    AlreadyRecording,
+    NotRecording,
+}
+
+impl RecordingError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RecordingError::NotFound => "session_not_found",
+            RecordingError::AlreadyRecording => "already_recording",
+            RecordingError::NotRecording => "not_recording",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            RecordingError::NotFound => "Session not found".to_string(),
+            RecordingError::AlreadyRecording => "Recording already started".to_string(),
+            RecordingError::NotRecording => "Recording not started".to_string(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            RecordingError::NotFound => StatusCode::NOT_FOUND,
+            RecordingError::AlreadyRecording | RecordingError::NotRecording => StatusCode::CONFLICT,
+        }
+    }
+}
+
+/// Why a change to a participant (removal, role or capabilities) was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParticipantError {
+    NotFound,
+    ParticipantNotFound,
+    /// The host cannot be removed; host changes hands only by transfer.
+    HostNotRemovable,
+    /// Host is only ever taken through a transfer.
+    HostRoleNotAssignable,
+    /// The host's own role cannot be changed.
+    HostRoleImmutable,
+    /// The `client_id` is not the one issued to the participant at join.
+    InvalidClientId,
+}
+
+impl ParticipantError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParticipantError::NotFound => "session_not_found",
+            ParticipantError::ParticipantNotFound => "participant_not_found",
+            ParticipantError::HostNotRemovable => "host_not_removable",
+            ParticipantError::HostRoleNotAssignable => "host_role_not_assignable",
+            ParticipantError::HostRoleImmutable => "host_role_immutable",
+            ParticipantError::InvalidClientId => "invalid_client_id",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ParticipantError::NotFound => "Session not found".to_string(),
+            ParticipantError::ParticipantNotFound => "Participant not found".to_string(),
+            ParticipantError::HostNotRemovable => "Host cannot be removed".to_string(),
+            ParticipantError::HostRoleNotAssignable => "Host role cannot be assigned".to_string(),
+            ParticipantError::HostRoleImmutable => "Host role cannot be changed".to_string(),
+            ParticipantError::InvalidClientId => "Invalid client id".to_string(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ParticipantError::NotFound | ParticipantError::ParticipantNotFound => StatusCode::NOT_FOUND,
+            ParticipantError::HostNotRemovable
+            | ParticipantError::HostRoleNotAssignable
+            | ParticipantError::HostRoleImmutable => StatusCode::BAD_REQUEST,
+            ParticipantError::InvalidClientId => StatusCode::FORBIDDEN,
+        }
+    }
 }
 
 /// Internal session data (uid_counter is atomic and not directly clonable).
@@ -31,43 +465,276 @@ pub struct RtcSessionInner {
     pub app_id: String,
     pub channel: String,
     pub token: String,
+    pub host_token: String,
     pub uid_counter: AtomicU32,
     pub host_uid: u32,
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
     pub participants: Vec<Participant>,
+    /// Mint a per-uid token at join time instead of handing out `token`.
+    pub generate_tokens: bool,
+    /// Relay pairing code of the creating Atem, notified when the session is deleted.
+    pub pair_code: Option<String>,
+    pub uid_allocation: UidAllocation,
+    /// Uids released by removed participants (`ReuseFreed` only).
+    pub free_uids: BTreeSet<u32>,
+    /// Last time the host token was used (any host action or heartbeat).
+    pub host_seen_at: DateTime<Utc>,
+    /// Last join, kick or host transfer. Keeps the session alive past
+    /// `expires_at`; see [`RtcSessionStore::expiry`].
+    pub last_activity: DateTime<Utc>,
+    /// Per-participant secret returned at join, keyed by uid; lets a participant
+    /// update its own state.
+    pub client_ids: HashMap<u32, String>,
+    /// Attendees must present this passcode to join.
+    pub passcode: Option<PasscodeHash>,
+    /// Times of recent wrong passcodes (not persisted).
+    pub passcode_failures: Vec<DateTime<Utc>>,
+    /// Recording markers, oldest first.
+    pub recording_events: Vec<RecordingEvent>,
+    /// Receives this session's lifecycle events, in addition to `RTC_WEBHOOK_URL`.
+    pub webhook_url: Option<String>,
+    /// Refuse joins whose display name a participant already has.
+    pub enforce_unique_names: bool,
+    /// Refuse new joins other than the host's.
+    pub locked: bool,
 }
 
-/// Snapshot of an RTC session (returned by store operations).
-#[derive(Clone, Debug)]
+/// Snapshot of an RTC session (returned by store operations and persisted to disk).
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RtcSession {
     pub id: String,
     pub app_id: String,
     pub channel: String,
     pub token: String,
+    pub host_token: String,
     pub uid_counter_value: u32,
     pub host_uid: u32,
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
     pub participants: Vec<Participant>,
+    pub generate_tokens: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pair_code: Option<String>,
+    #[serde(default)]
+    pub uid_allocation: UidAllocation,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub free_uids: BTreeSet<u32>,
+    #[serde(default = "Utc::now")]
+    pub host_seen_at: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub last_activity: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub client_ids: HashMap<u32, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passcode: Option<PasscodeHash>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recording_events: Vec<RecordingEvent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    #[serde(default)]
+    pub enforce_unique_names: bool,
+    #[serde(default)]
+    pub locked: bool,
+}
+
+impl RtcSession {
+    /// Whether the last recording marker is a start.
+    pub fn is_recording(&self) -> bool {
+        self.recording_events
+            .last()
+            .is_some_and(|e| e.event == RecordingEventKind::Started)
+    }
+}
+
+/// Stands in for credentials in [`RtcSessionSnapshot`].
+const REDACTED: &str = "<redacted>";
+
+/// One session as served by `GET /api/admin/rtc-sessions/snapshot`, for
+/// offline debugging. Tokens and client ids are replaced by `<redacted>` and the
+/// passcode hash is left out.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RtcSessionSnapshot {
+    #[serde(flatten)]
+    pub session: RtcSession,
+    pub participant_count: usize,
+    pub passcode_required: bool,
+}
+
+/// Scalar view of a session: no roster, tokens or recording history are copied.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RtcSessionMeta {
+    pub id: String,
+    pub app_id: String,
+    pub channel: String,
+    pub host_uid: u32,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub participant_count: usize,
+    pub passcode_required: bool,
+    pub generate_tokens: bool,
 }
 
 impl RtcSessionInner {
+    fn meta(&self) -> RtcSessionMeta {
+        RtcSessionMeta {
+            id: self.id.clone(),
+            app_id: self.app_id.clone(),
+            channel: self.channel.clone(),
+            host_uid: self.host_uid,
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+            participant_count: self.participants.len(),
+            passcode_required: self.passcode.is_some(),
+            generate_tokens: self.generate_tokens,
+        }
+    }
+
     fn snapshot(&self) -> RtcSession {
         RtcSession {
             id: self.id.clone(),
             app_id: self.app_id.clone(),
             channel: self.channel.clone(),
             token: self.token.clone(),
+            host_token: self.host_token.clone(),
             uid_counter_value: self.uid_counter.load(Ordering::SeqCst),
             host_uid: self.host_uid,
             created_at: self.created_at,
             expires_at: self.expires_at,
             participants: self.participants.clone(),
+            generate_tokens: self.generate_tokens,
+            pair_code: self.pair_code.clone(),
+            uid_allocation: self.uid_allocation,
+            free_uids: self.free_uids.clone(),
+            host_seen_at: self.host_seen_at,
+            last_activity: self.last_activity,
+            client_ids: self.client_ids.clone(),
+            passcode: self.passcode.clone(),
+            recording_events: self.recording_events.clone(),
+            webhook_url: self.webhook_url.clone(),
+            enforce_unique_names: self.enforce_unique_names,
+            locked: self.locked,
+        }
+    }
+
+    fn debug_snapshot(&self) -> RtcSessionSnapshot {
+        let mut session = self.snapshot();
+        session.token = REDACTED.to_string();
+        session.host_token = REDACTED.to_string();
+        for client_id in session.client_ids.values_mut() {
+            *client_id = REDACTED.to_string();
+        }
+        session.passcode = None;
+        RtcSessionSnapshot {
+            participant_count: session.participants.len(),
+            passcode_required: self.passcode.is_some(),
+            session,
+        }
+    }
+
+    /// Rebuild live session state from a snapshot, resuming the uid counter.
+    fn from_snapshot(session: RtcSession) -> Self {
+        RtcSessionInner {
+            id: session.id,
+            app_id: session.app_id,
+            channel: session.channel,
+            token: session.token,
+            host_token: session.host_token,
+            uid_counter: AtomicU32::new(session.uid_counter_value),
+            host_uid: session.host_uid,
+            created_at: session.created_at,
+            expires_at: session.expires_at,
+            participants: session.participants,
+            generate_tokens: session.generate_tokens,
+            pair_code: session.pair_code,
+            uid_allocation: session.uid_allocation,
+            free_uids: session.free_uids,
+            host_seen_at: session.host_seen_at,
+            last_activity: session.last_activity,
+            client_ids: session.client_ids,
+            passcode: session.passcode,
+            passcode_failures: Vec::new(),
+            recording_events: session.recording_events,
+            webhook_url: session.webhook_url,
+            enforce_unique_names: session.enforce_unique_names,
+            locked: session.locked,
+        }
+    }
+
+    /// Pick the uid for a new attendee. Callers hold the session write lock.
+    fn allocate_uid(&mut self) -> u32 {
+        match self.uid_allocation {
+            UidAllocation::Sequential => self.uid_counter.fetch_add(1, Ordering::SeqCst),
+            UidAllocation::ReuseFreed => match self.free_uids.pop_first() {
+                Some(uid) => uid,
+                None => self.uid_counter.fetch_add(1, Ordering::SeqCst),
+            },
+            UidAllocation::Random => {
+                let mut rng = rand::thread_rng();
+                loop {
+                    let uid = rng.gen_range(1001..=u32::MAX);
+                    if uid != self.host_uid && !self.participants.iter().any(|p| p.uid == uid) {
+                        return uid;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Return a departed attendee's uid to the pool.
+    fn release_uid(&mut self, uid: u32) {
+        if self.uid_allocation == UidAllocation::ReuseFreed && uid != self.host_uid {
+            self.free_uids.insert(uid);
+        }
+    }
+
+    /// Check an attendee's passcode, recording failures for rate limiting.
+    fn check_passcode(&mut self, passcode: Option<&str>) -> Result<(), JoinError> {
+        let Some(hash) = &self.passcode else {
+            return Ok(());
+        };
+        let now = Utc::now();
+        let window_start = now - Duration::seconds(PASSCODE_FAILURE_WINDOW_SECS);
+        self.passcode_failures.retain(|&at| at > window_start);
+        if self.passcode_failures.len() >= PASSCODE_MAX_FAILURES {
+            return Err(JoinError::PasscodeAttemptsExceeded);
+        }
+        let passcode = passcode.ok_or(JoinError::PasscodeRequired)?;
+        if !hash.verify(passcode) {
+            self.passcode_failures.push(now);
+            return Err(JoinError::PasscodeInvalid);
         }
+        Ok(())
     }
 }
 
+/// Per-join settings.
+#[derive(Clone, Debug, Default)]
+pub struct JoinOptions {
+    /// Join as host (uid = `host_uid`, role = host). Callers must check the host token.
+    pub as_host: bool,
+    /// Already validated with [`validate_metadata`].
+    pub metadata: Option<serde_json::Value>,
+    pub capabilities: MediaCapabilities,
+    /// Checked against the session passcode; ignored for host joins.
+    pub passcode: Option<String>,
+}
+
+/// Optional per-session settings chosen at creation.
+#[derive(Clone, Debug, Default)]
+pub struct RtcSessionOptions {
+    /// Generate per-participant tokens server-side (requires an app certificate).
+    pub generate_tokens: bool,
+    /// Relay room to notify on deletion.
+    pub pair_code: Option<String>,
+    pub uid_allocation: UidAllocation,
+    /// Plaintext passcode; hashed before it is stored.
+    pub passcode: Option<String>,
+    pub webhook_url: Option<String>,
+    /// Overrides the store's `RTC_ENFORCE_UNIQUE_NAMES` default.
+    pub enforce_unique_names: Option<bool>,
+}
+
 // --- Request / Response types ---
 
 #[derive(Deserialize, Validate)]
@@ -76,15 +743,37 @@ pub struct CreateRtcSessionRequest {
     pub app_id: String,
     #[validate(length(min = 1, max = 64))]
     pub channel: String,
-    #[validate(length(min = 1, max = 4096))]
+    /// Shared token handed to every joiner; may be omitted when `generate_tokens` is set.
+    #[serde(default)]
+    #[validate(length(max = 4096))]
     pub token: String,
     pub host_uid: u32,
+    #[serde(default)]
+    pub generate_tokens: bool,
+    /// Pairing code of the creator's relay room; its astation side is told when the session is deleted.
+    #[validate(length(min = 1, max = 64))]
+    pub pair_code: Option<String>,
+    /// Owner token of `pair_code`'s room; required with `pair_code`.
+    pub pair_owner_token: Option<String>,
+    #[serde(default)]
+    pub uid_allocation: UidAllocation,
+    /// Attendees must send this passcode to join.
+    #[validate(length(min = 4, max = 32))]
+    pub passcode: Option<String>,
+    /// http(s) URL that receives this session's lifecycle events.
+    #[validate(length(min = 1, max = 2048))]
+    pub webhook_url: Option<String>,
+    /// Refuse a join whose name is already taken; defaults to `RTC_ENFORCE_UNIQUE_NAMES`.
+    pub enforce_unique_names: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct CreateRtcSessionResponse {
     pub id: String,
     pub url: String,
+    /// Secret for privileged operations; only ever returned here.
+    #[serde(default)]
+    pub host_token: String,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -93,12 +782,45 @@ pub struct GetRtcSessionResponse {
     pub channel: String,
     pub host_uid: u32,
     pub created_at: DateTime<Utc>,
+    /// Joiners must send `passcode`.
+    #[serde(default)]
+    pub passcode_required: bool,
+    /// Present with `?include=participants`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub participants: Option<Vec<Participant>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub participant_count: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_participants: Option<usize>,
+}
+
+/// Query parameters for GET /api/rtc-sessions/:id
+#[derive(Debug, Default, Deserialize)]
+pub struct GetRtcSessionQuery {
+    /// Comma-separated extras; `participants` adds the participant list and occupancy.
+    pub include: Option<String>,
+}
+
+impl GetRtcSessionQuery {
+    fn includes(&self, field: &str) -> bool {
+        self.include
+            .as_deref()
+            .is_some_and(|include| include.split(',').any(|f| f.trim() == field))
+    }
 }
 
 #[derive(Deserialize, Validate)]
 pub struct JoinRtcSessionRequest {
     #[validate(length(min = 1, max = 100))]
     pub name: String,
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+    /// Defaults to audio and video on, screen share off.
+    #[serde(default)]
+    pub capabilities: MediaCapabilities,
+    /// Required when the session has a passcode.
+    #[serde(default)]
+    pub passcode: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -108,114 +830,906 @@ pub struct JoinRtcSessionResponse {
     pub token: String,
     pub uid: u32,
     pub name: String,
+    pub role: ParticipantRole,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+    pub capabilities: MediaCapabilities,
+    /// Secret identifying this participant for self-service updates (with `uid`).
+    pub client_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct SetParticipantRoleRequest {
+    pub role: ParticipantRole,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LockRtcSessionRequest {
+    pub locked: bool,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateCapabilitiesRequest {
+    /// Returned by join; proves the caller is participant `uid`.
+    pub client_id: String,
+    pub capabilities: MediaCapabilities,
+}
+
+#[derive(Deserialize)]
+pub struct TransferHostRequest {
+    /// Participant to become host.
+    #[serde(alias = "new_host_uid")]
+    pub uid: u32,
+    /// Claim host without the host token (co-hosts only, once the host is stale).
+    #[serde(default)]
+    pub claim: bool,
+    /// The claimant's `client_id` from join; required with `claim`.
+    #[serde(default)]
+    pub client_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransferHostResponse {
+    pub host_uid: u32,
+    /// Replaces the previous host token, which stops working.
+    pub host_token: String,
+}
+
+#[derive(Default, Deserialize)]
+pub struct RecordingMarkerRequest {
+    #[serde(default)]
+    pub resource_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ParticipantsResponse {
+    /// In join order.
+    pub participants: Vec<Participant>,
+    pub count: usize,
+    /// Maximum participants, host included.
+    pub capacity: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RecordingStatusResponse {
+    pub recording: bool,
+    pub events: Vec<RecordingEvent>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct RtcSessionError {
     pub error: String,
+    /// Machine-readable reason, set for errors clients are expected to branch on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// Set on 410 responses for expired sessions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Set on 429 responses: sessions the app id currently holds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_count: Option<usize>,
+}
+
+impl RtcSessionError {
+    pub fn new(error: impl Into<String>) -> Self {
+        RtcSessionError {
+            error: error.into(),
+            code: None,
+            expires_at: None,
+            session_count: None,
+        }
+    }
+
+    pub fn with_code(error: impl Into<String>, code: &str) -> Self {
+        RtcSessionError {
+            code: Some(code.to_string()),
+            ..Self::new(error)
+        }
+    }
+
+    fn expired(expires_at: DateTime<Utc>) -> Self {
+        RtcSessionError {
+            expires_at: Some(expires_at),
+            ..Self::with_code("Session has expired", "session_expired")
+        }
+    }
 }
 
 // --- Store ---
 
+/// How long an expired session id keeps answering 410 after cleanup removes it.
+const EXPIRED_TOMBSTONE_GRACE_MINS: i64 = 30;
+
+/// Lifecycle events buffered per subscriber before it starts lagging.
+const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// Why a session was torn down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EndReason {
+    Deleted,
+    Expired,
+}
+
+/// Result of looking up a session id, distinguishing expired from unknown.
+/// `T` is the full snapshot by default, or [`RtcSessionMeta`] for cheap reads.
+#[derive(Debug)]
+pub enum RtcSessionLookup<T = Box<RtcSession>> {
+    Active(T),
+    Expired { expires_at: DateTime<Utc> },
+    NotFound,
+}
+
 #[derive(Clone)]
 pub struct RtcSessionStore {
     sessions: Arc<RwLock<HashMap<String, Arc<RwLock<RtcSessionInner>>>>>,
+    /// Agora app certificate used for server-side token generation.
+    app_certificate: Option<Arc<str>>,
+    /// Set on every mutation; cleared by the persister when it takes a snapshot.
+    dirty: Arc<AtomicBool>,
+    /// Ids removed by cleanup, with their expiry time, kept for the grace period.
+    tombstones: Arc<RwLock<HashMap<String, DateTime<Utc>>>>,
+    /// Creation is refused once this many sessions are held.
+    max_sessions: usize,
+    /// Live session count per app id; entries are removed when they reach zero.
+    app_counts: Arc<RwLock<HashMap<String, usize>>>,
+    /// Creation is refused once an app id holds this many sessions.
+    max_sessions_per_app_id: usize,
+    /// How long a session outlives its last activity (`RTC_MAX_IDLE_HOURS`).
+    max_idle: Duration,
+    /// Receives lifecycle events for every session (`RTC_WEBHOOK_URL`).
+    webhook_url: Option<Arc<str>>,
+    /// Where per-session webhooks may point (`RTC_WEBHOOK_ALLOWED_HOSTS`).
+    webhook_policy: Arc<WebhookPolicy>,
+    /// Default for sessions created without `enforce_unique_names` (`RTC_ENFORCE_UNIQUE_NAMES`).
+    enforce_unique_names: bool,
+    /// In-process lifecycle events, the same ones sent to webhooks.
+    events: broadcast::Sender<WebhookEvent>,
 }
 
 impl RtcSessionStore {
     pub fn new() -> Self {
         RtcSessionStore {
             sessions: Arc::new(RwLock::new(HashMap::new())),
+            app_certificate: None,
+            dirty: Arc::new(AtomicBool::new(false)),
+            tombstones: Arc::new(RwLock::new(HashMap::new())),
+            max_sessions: DEFAULT_MAX_SESSIONS,
+            app_counts: Arc::new(RwLock::new(HashMap::new())),
+            max_sessions_per_app_id: DEFAULT_MAX_SESSIONS_PER_APP_ID,
+            max_idle: Duration::hours(DEFAULT_MAX_IDLE_HOURS),
+            webhook_url: None,
+            webhook_policy: Arc::default(),
+            enforce_unique_names: false,
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
         }
     }
 
-    pub async fn create(&self, id: String, app_id: String, channel: String, token: String, host_uid: u32) -> RtcSession {
-        let now = Utc::now();
-        let inner = RtcSessionInner {
-            id: id.clone(),
-            app_id,
-            channel,
-            token,
-            uid_counter: AtomicU32::new(1000),
-            host_uid,
-            created_at: now,
-            expires_at: now + Duration::hours(4),
-            participants: Vec::new(),
-        };
-        let snapshot = inner.snapshot();
-        let arc_inner = Arc::new(RwLock::new(inner));
-        let mut sessions = self.sessions.write().await;
-        sessions.insert(id, arc_inner);
-        snapshot
+    /// Receive lifecycle events (created, joined, full, deleted, expired) for all sessions.
+    pub fn subscribe(&self) -> broadcast::Receiver<WebhookEvent> {
+        self.events.subscribe()
     }
 
-    pub async fn get(&self, id: &str) -> Option<RtcSession> {
-        let sessions = self.sessions.read().await;
-        if let Some(inner) = sessions.get(id) {
-            let inner = inner.read().await;
-            Some(inner.snapshot())
-        } else {
-            None
-        }
+    /// Send lifecycle events for all sessions to `url`.
+    pub fn with_webhook_url(mut self, url: Option<String>) -> Self {
+        self.webhook_url = url.map(Arc::from);
+        self
     }
 
-    pub async fn join(&self, id: &str, name: String) -> Result<JoinRtcSessionResponse, String> {
-        let sessions = self.sessions.read().await;
-        if let Some(inner_arc) = sessions.get(id) {
-            let mut inner = inner_arc.write().await;
+    /// Only let per-session webhooks reach these hosts.
+    pub fn with_webhook_allowed_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.webhook_policy = Arc::new(WebhookPolicy::new(hosts));
+        self
+    }
 
-            let current_count = inner.participants.len();
-            tracing::info!("Join request for session {}: current participants = {}, name = {}", id, current_count, name);
+    /// Check a per-session `webhook_url` against the webhook policy.
+    pub fn check_webhook_url(&self, url: &str) -> Result<(), WebhookUrlError> {
+        self.webhook_policy.check(url).map(|_| ())
+    }
 
-            // Enforce 8-person limit (including host)
-            if current_count >= 8 {
-                tracing::warn!("Session {} is full ({} participants)", id, current_count);
-                return Err("Session is full (maximum 8 participants)".to_string());
+    /// Publish a lifecycle event to subscribers and queue it for the global
+    /// and per-session webhooks.
+    fn notify(&self, inner: &RtcSessionInner, event: WebhookEventKind, participant: Option<Participant>) {
+        let event = WebhookEvent {
+            event,
+            session_id: inner.id.clone(),
+            app_id: inner.app_id.clone(),
+            channel: inner.channel.clone(),
+            participant_count: inner.participants.len(),
+            participant,
+            at: Utc::now(),
+        };
+        // Err only means nobody is subscribed
+        let _ = self.events.send(event.clone());
+
+        let mut urls: Vec<String> = self.webhook_url.iter().map(|url| url.to_string()).collect();
+        if let Some(url) = inner.webhook_url.as_ref().filter(|url| !urls.contains(url)) {
+            if self.webhook_policy.public_only() {
+                webhook::dispatch_public(url.clone(), event.clone());
+            } else {
+                urls.push(url.clone());
             }
+        }
+        if !urls.is_empty() {
+            webhook::dispatch(urls, event);
+        }
+    }
 
-            let uid = inner.uid_counter.fetch_add(1, Ordering::SeqCst);
-            inner.participants.push(Participant {
-                uid,
-                display_name: Some(name.clone()),
-                joined_at: Utc::now(),
+    /// Limit the number of sessions held at once.
+    pub fn with_max_sessions(mut self, max_sessions: usize) -> Self {
+        self.max_sessions = max_sessions;
+        self
+    }
+
+    pub fn max_sessions(&self) -> usize {
+        self.max_sessions
+    }
+
+    /// Limit the number of sessions held at once for a single app id.
+    pub fn with_max_sessions_per_app_id(mut self, max: usize) -> Self {
+        self.max_sessions_per_app_id = max;
+        self
+    }
+
+    /// Refuse duplicate display names in sessions that do not choose for themselves.
+    pub fn with_enforce_unique_names(mut self, enforce: bool) -> Self {
+        self.enforce_unique_names = enforce;
+        self
+    }
+
+    /// Keep sessions alive for `max_idle` after their last activity, even past `expires_at`.
+    pub fn with_max_idle(mut self, max_idle: Duration) -> Self {
+        self.max_idle = max_idle;
+        self
+    }
+
+    /// When a session actually expires: its scheduled `expires_at`, pushed back
+    /// while it stays active.
+    pub fn expiry(&self, inner: &RtcSessionInner) -> DateTime<Utc> {
+        inner.expires_at.max(inner.last_activity + self.max_idle)
+    }
+
+    /// Number of sessions currently held for `app_id`.
+    pub async fn app_session_count(&self, app_id: &str) -> usize {
+        self.app_counts.read().await.get(app_id).copied().unwrap_or(0)
+    }
+
+    /// Number of sessions currently held, expired ones not yet cleaned up included.
+    pub async fn capacity_used(&self) -> usize {
+        self.sessions.read().await.len()
+    }
+
+    /// Enable server-side token generation with the given app certificate.
+    pub fn with_app_certificate(mut self, app_certificate: Option<String>) -> Self {
+        self.app_certificate = app_certificate.map(Arc::from);
+        self
+    }
+
+    /// Whether sessions may request `generate_tokens`.
+    pub fn can_generate_tokens(&self) -> bool {
+        self.app_certificate.is_some()
+    }
+
+    pub(crate) fn mark_dirty(&self) {
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// Return whether the store changed since the last call, clearing the flag.
+    pub fn take_dirty(&self) -> bool {
+        self.dirty.swap(false, Ordering::AcqRel)
+    }
+
+    /// Snapshot every session (for persistence).
+    pub async fn export(&self) -> Vec<RtcSession> {
+        let sessions = self.sessions.read().await;
+        let mut snapshots = Vec::with_capacity(sessions.len());
+        for inner in sessions.values() {
+            snapshots.push(inner.read().await.snapshot());
+        }
+        snapshots
+    }
+
+    /// Redacted copy of every session, oldest first. Each session is read under
+    /// its own lock, so each entry is consistent in itself.
+    pub async fn export_snapshot(&self) -> Vec<RtcSessionSnapshot> {
+        // Let go of the map before reading sessions so creates and deletes are
+        // not held up behind every per-session lock
+        let handles: Vec<_> = self.sessions.read().await.values().cloned().collect();
+        let mut snapshots = Vec::with_capacity(handles.len());
+        for inner in handles {
+            snapshots.push(inner.read().await.debug_snapshot());
+        }
+        snapshots.sort_by(|a, b| {
+            (a.session.created_at, &a.session.id).cmp(&(b.session.created_at, &b.session.id))
+        });
+        snapshots
+    }
+
+    /// Load sessions from snapshots, skipping expired ones and ids already present.
+    /// Returns the number of sessions restored.
+    pub async fn import(&self, snapshots: Vec<RtcSession>) -> usize {
+        let now = Utc::now();
+        let mut sessions = self.sessions.write().await;
+        let mut restored = 0;
+        for snapshot in snapshots {
+            let expiry = snapshot.expires_at.max(snapshot.last_activity + self.max_idle);
+            if expiry <= now || sessions.contains_key(&snapshot.id) {
+                continue;
+            }
+            let id = snapshot.id.clone();
+            *self.app_counts.write().await.entry(snapshot.app_id.clone()).or_default() += 1;
+            sessions.insert(id, Arc::new(RwLock::new(RtcSessionInner::from_snapshot(snapshot))));
+            restored += 1;
+        }
+        restored
+    }
+
+    pub async fn create(&self, id: String, app_id: String, channel: String, token: String, host_uid: u32) -> Result<RtcSession, CreateError> {
+        self.create_with_options(id, app_id, channel, token, host_uid, RtcSessionOptions::default())
+            .await
+    }
+
+    pub async fn create_with_options(
+        &self,
+        id: String,
+        app_id: String,
+        channel: String,
+        token: String,
+        host_uid: u32,
+        options: RtcSessionOptions,
+    ) -> Result<RtcSession, CreateError> {
+        let now = Utc::now();
+        let inner = RtcSessionInner {
+            id: id.clone(),
+            app_id,
+            channel,
+            token,
+            host_token: crate::auth::generate_session_token(),
+            uid_counter: AtomicU32::new(1000),
+            host_uid,
+            created_at: now,
+            expires_at: now + Duration::hours(4),
+            participants: Vec::new(),
+            generate_tokens: options.generate_tokens,
+            pair_code: options.pair_code,
+            uid_allocation: options.uid_allocation,
+            free_uids: BTreeSet::new(),
+            host_seen_at: now,
+            last_activity: now,
+            client_ids: HashMap::new(),
+            passcode: options.passcode.as_deref().map(PasscodeHash::new),
+            passcode_failures: Vec::new(),
+            recording_events: Vec::new(),
+            webhook_url: options.webhook_url,
+            enforce_unique_names: options.enforce_unique_names.unwrap_or(self.enforce_unique_names),
+            locked: false,
+        };
+        let snapshot = inner.snapshot();
+        let arc_inner = Arc::new(RwLock::new(inner));
+        let mut sessions = self.sessions.write().await;
+        if sessions.len() >= self.max_sessions {
+            tracing::warn!("Refusing RTC session: at capacity ({} sessions)", sessions.len());
+            return Err(CreateError::AtCapacity { max: self.max_sessions });
+        }
+        let mut app_counts = self.app_counts.write().await;
+        let count = app_counts.entry(snapshot.app_id.clone()).or_default();
+        if *count >= self.max_sessions_per_app_id {
+            tracing::warn!("Refusing RTC session for app id {}: {} sessions", snapshot.app_id, count);
+            return Err(CreateError::AppQuotaExceeded {
+                count: *count,
+                max: self.max_sessions_per_app_id,
+            });
+        }
+        *count += 1;
+        sessions.insert(id, arc_inner.clone());
+        self.mark_dirty();
+        self.notify(&*arc_inner.read().await, WebhookEventKind::SessionCreated, None);
+        Ok(snapshot)
+    }
+
+    /// Active (non-expired) session by id.
+    pub async fn get(&self, id: &str) -> Option<RtcSession> {
+        match self.lookup(id).await {
+            RtcSessionLookup::Active(session) => Some(*session),
+            _ => None,
+        }
+    }
+
+    /// Metadata of an active (non-expired) session, without cloning the roster.
+    pub async fn get_meta(&self, id: &str) -> Option<RtcSessionMeta> {
+        match self.lookup_meta(id).await {
+            RtcSessionLookup::Active(meta) => Some(meta),
+            _ => None,
+        }
+    }
+
+    /// Participants of an active session, in join order.
+    pub async fn get_participants(&self, id: &str) -> Option<Vec<Participant>> {
+        match self.lookup_with(id, |inner| inner.participants.clone()).await {
+            RtcSessionLookup::Active(participants) => Some(participants),
+            _ => None,
+        }
+    }
+
+    /// Look up a session, reporting expired ones (including recently cleaned up ids).
+    pub async fn lookup(&self, id: &str) -> RtcSessionLookup {
+        self.lookup_with(id, |inner| Box::new(inner.snapshot())).await
+    }
+
+    /// Like [`lookup`](Self::lookup), but copies only [`RtcSessionMeta`].
+    pub async fn lookup_meta(&self, id: &str) -> RtcSessionLookup<RtcSessionMeta> {
+        self.lookup_with(id, RtcSessionInner::meta).await
+    }
+
+    async fn lookup_with<T>(
+        &self,
+        id: &str,
+        read: impl FnOnce(&RtcSessionInner) -> T,
+    ) -> RtcSessionLookup<T> {
+        {
+            let sessions = self.sessions.read().await;
+            if let Some(inner) = sessions.get(id) {
+                let inner = inner.read().await;
+                let expires_at = self.expiry(&inner);
+                if Utc::now() > expires_at {
+                    return RtcSessionLookup::Expired { expires_at };
+                }
+                return RtcSessionLookup::Active(read(&inner));
+            }
+        }
+        match self.tombstones.read().await.get(id) {
+            Some(&expires_at) => RtcSessionLookup::Expired { expires_at },
+            None => RtcSessionLookup::NotFound,
+        }
+    }
+
+    pub async fn join(&self, id: &str, name: String) -> Result<JoinRtcSessionResponse, JoinError> {
+        self.join_with_options(id, name, JoinOptions::default()).await
+    }
+
+    /// Join as the session host. The participant takes `host_uid` and the `Host` role;
+    /// a repeated host join replaces the previous host entry instead of adding another.
+    pub async fn join_as_host(&self, id: &str, name: String) -> Result<JoinRtcSessionResponse, JoinError> {
+        self.join_with_options(id, name, JoinOptions { as_host: true, ..Default::default() }).await
+    }
+
+    pub async fn join_with_options(&self, id: &str, name: String, options: JoinOptions) -> Result<JoinRtcSessionResponse, JoinError> {
+        let as_host = options.as_host;
+        let sessions = self.sessions.read().await;
+        if let Some(inner_arc) = sessions.get(id) {
+            let mut inner = inner_arc.write().await;
+
+            if Utc::now() > self.expiry(&inner) {
+                return Err(JoinError::Expired);
+            }
+            if !as_host {
+                if inner.locked {
+                    return Err(JoinError::Locked);
+                }
+                inner.check_passcode(options.passcode.as_deref())?;
+            }
+
+            let current_count = inner.participants.len();
+            tracing::info!("Join request for session {}: current participants = {}, name = {}", id, current_count, name);
+
+            let host_uid = inner.host_uid;
+            let rejoining_host = as_host && inner.participants.iter().any(|p| p.uid == host_uid);
+
+            if current_count >= MAX_PARTICIPANTS && !rejoining_host {
+                tracing::warn!("Session {} is full ({} participants)", id, current_count);
+                return Err(JoinError::Full { max: MAX_PARTICIPANTS });
+            }
+            // Checked under the write lock, so two joins with one name cannot both pass.
+            // A host rejoin replaces its own entry, whose name does not count.
+            if inner.enforce_unique_names
+                && inner.participants.iter().any(|p| {
+                    !(as_host && p.uid == host_uid) && p.display_name.as_deref() == Some(name.as_str())
+                })
+            {
+                tracing::info!("Session {}: display name {:?} already in use", id, name);
+                return Err(JoinError::NameTaken);
+            }
+
+            let (uid, role) = if as_host {
+                inner.participants.retain(|p| p.uid != host_uid);
+                (host_uid, ParticipantRole::Host)
+            } else {
+                (inner.allocate_uid(), ParticipantRole::Attendee)
+            };
+            inner.participants.push(Participant {
+                uid,
+                display_name: Some(name.clone()),
+                joined_at: Utc::now(),
+                role,
+                metadata: options.metadata.clone(),
+                capabilities: options.capabilities,
             });
+            let client_id = crate::auth::generate_session_token();
+            inner.client_ids.insert(uid, client_id.clone());
+            inner.last_activity = Utc::now();
 
             tracing::info!("User {} joined session {} with UID {} (total participants: {})",
                 name, id, uid, inner.participants.len());
+            self.mark_dirty();
+            let joined = inner.participants.last().cloned();
+            self.notify(&inner, WebhookEventKind::ParticipantJoined, joined.clone());
+            if inner.participants.len() == MAX_PARTICIPANTS && !rejoining_host {
+                self.notify(&inner, WebhookEventKind::SessionFull, joined);
+            }
+
+            let token = match (&self.app_certificate, inner.generate_tokens) {
+                (Some(app_certificate), true) => {
+                    // Token lifetime matches the remaining session TTL
+                    let expire_secs = (self.expiry(&inner) - Utc::now()).num_seconds().max(0) as u32;
+                    RtcTokenRequest {
+                        app_id: &inner.app_id,
+                        app_certificate,
+                        channel: &inner.channel,
+                        uid,
+                        role: token_role(role, options.capabilities),
+                        expire_secs,
+                    }
+                    .build()
+                }
+                _ => inner.token.clone(),
+            };
 
             Ok(JoinRtcSessionResponse {
                 app_id: inner.app_id.clone(),
                 channel: inner.channel.clone(),
-                token: inner.token.clone(),
+                token,
                 uid,
                 name,
+                role,
+                metadata: options.metadata,
+                capabilities: options.capabilities,
+                client_id,
             })
+        } else if self.tombstones.read().await.contains_key(id) {
+            Err(JoinError::Expired)
         } else {
-            Err("Session not found".to_string())
+            Err(JoinError::NotFound)
         }
     }
 
-    pub async fn delete(&self, id: &str) -> bool {
-        let mut sessions = self.sessions.write().await;
-        sessions.remove(id).is_some()
+    /// Check a presented host token against the session's host token.
+    pub async fn verify_host_token(&self, id: &str, token: &str) -> bool {
+        let sessions = self.sessions.read().await;
+        match sessions.get(id) {
+            Some(inner_arc) => constant_time_eq(token, &inner_arc.read().await.host_token),
+            None => false,
+        }
+    }
+
+    /// Whether `client_id` was issued at join to the session's host or a co-host.
+    pub async fn verify_moderator(&self, id: &str, client_id: &str) -> bool {
+        let sessions = self.sessions.read().await;
+        let Some(inner_arc) = sessions.get(id) else {
+            return false;
+        };
+        let inner = inner_arc.read().await;
+        inner.participants.iter().any(|p| {
+            matches!(p.role, ParticipantRole::Host | ParticipantRole::Cohost)
+                && inner.client_ids.get(&p.uid).is_some_and(|issued| constant_time_eq(client_id, issued))
+        })
+    }
+
+    /// Lock or unlock the session against new joins. Returns None if it does not exist.
+    pub async fn set_locked(&self, id: &str, locked: bool) -> Option<bool> {
+        let sessions = self.sessions.read().await;
+        let mut inner = sessions.get(id)?.write().await;
+        if inner.locked != locked {
+            inner.locked = locked;
+            self.mark_dirty();
+            tracing::info!("Session {} {}", id, if locked { "locked" } else { "unlocked" });
+        }
+        Some(locked)
+    }
+
+    /// Record session activity, pushing back idle expiry.
+    pub async fn touch(&self, id: &str) {
+        if let Some(inner_arc) = self.sessions.read().await.get(id) {
+            inner_arc.write().await.last_activity = Utc::now();
+            self.mark_dirty();
+        }
+    }
+
+    /// Record host activity; keeps co-hosts from claiming host.
+    pub async fn touch_host(&self, id: &str) {
+        if let Some(inner_arc) = self.sessions.read().await.get(id) {
+            inner_arc.write().await.host_seen_at = Utc::now();
+        }
+    }
+
+    /// Make participant `uid` the host and rotate the host token.
+    ///
+    /// A host token is checked under the session lock, so of several concurrent
+    /// transfers presenting the same token only the first wins. A claim must
+    /// carry `uid`'s own join `client_id`; the host must have been silent for
+    /// [`HOST_STALE_AFTER_MINS`] and `uid` must be the longest-present co-host.
+    /// The previous host, if still present, becomes a co-host.
+    pub async fn transfer_host(
+        &self,
+        id: &str,
+        uid: u32,
+        auth: HostAuth<'_>,
+    ) -> Result<TransferHostResponse, TransferHostError> {
+        let claim = matches!(auth, HostAuth::Claim { .. });
+        let sessions = self.sessions.read().await;
+        let inner_arc = sessions.get(id).ok_or(TransferHostError::NotFound)?;
+        let mut inner = inner_arc.write().await;
+        if Utc::now() > self.expiry(&inner) {
+            return Err(TransferHostError::NotFound);
+        }
+        if let HostAuth::HostToken(token) = auth {
+            if !constant_time_eq(token, &inner.host_token) {
+                return Err(TransferHostError::InvalidToken);
+            }
+        }
+        if uid == inner.host_uid {
+            return Err(TransferHostError::AlreadyHost);
+        }
+        if !inner.participants.iter().any(|p| p.uid == uid) {
+            return Err(TransferHostError::ParticipantNotFound);
+        }
+        if let HostAuth::Claim { client_id } = auth {
+            let issued = inner.client_ids.get(&uid);
+            if !issued.is_some_and(|issued| constant_time_eq(client_id, issued)) {
+                return Err(TransferHostError::InvalidClientId);
+            }
+        }
+        if claim {
+            if Utc::now() - inner.host_seen_at < Duration::minutes(HOST_STALE_AFTER_MINS) {
+                return Err(TransferHostError::HostActive);
+            }
+            let oldest_cohost = inner
+                .participants
+                .iter()
+                .filter(|p| p.role == ParticipantRole::Cohost)
+                .min_by_key(|p| p.joined_at)
+                .map(|p| p.uid);
+            if oldest_cohost != Some(uid) {
+                return Err(TransferHostError::NotEligible);
+            }
+        }
+
+        let old_host_uid = inner.host_uid;
+        for participant in inner.participants.iter_mut() {
+            if participant.uid == old_host_uid {
+                participant.role = ParticipantRole::Cohost;
+            } else if participant.uid == uid {
+                participant.role = ParticipantRole::Host;
+            }
+        }
+        inner.host_uid = uid;
+        inner.host_token = crate::auth::generate_session_token();
+        inner.host_seen_at = Utc::now();
+        inner.last_activity = Utc::now();
+        self.mark_dirty();
+        tracing::info!(
+            "Session {} host {} from {} to {}",
+            id,
+            if claim { "claimed" } else { "transferred" },
+            old_host_uid,
+            uid
+        );
+        Ok(TransferHostResponse {
+            host_uid: uid,
+            host_token: inner.host_token.clone(),
+        })
+    }
+
+    /// Promote or demote a participant. The host's own role cannot be changed here.
+    pub async fn set_participant_role(
+        &self,
+        id: &str,
+        uid: u32,
+        role: ParticipantRole,
+    ) -> Result<Participant, ParticipantError> {
+        if role == ParticipantRole::Host {
+            return Err(ParticipantError::HostRoleNotAssignable);
+        }
+        let sessions = self.sessions.read().await;
+        let inner_arc = sessions.get(id).ok_or(ParticipantError::NotFound)?;
+        let mut inner = inner_arc.write().await;
+        let participant = inner
+            .participants
+            .iter_mut()
+            .find(|p| p.uid == uid)
+            .ok_or(ParticipantError::ParticipantNotFound)?;
+        if participant.role == ParticipantRole::Host {
+            return Err(ParticipantError::HostRoleImmutable);
+        }
+        participant.role = role;
+        self.mark_dirty();
+        tracing::info!("Participant {} in session {} is now {:?}", uid, id, role);
+        Ok(participant.clone())
+    }
+
+    /// Update a participant's media capabilities; `client_id` must be the one issued at join.
+    pub async fn set_capabilities(
+        &self,
+        id: &str,
+        uid: u32,
+        client_id: &str,
+        capabilities: MediaCapabilities,
+    ) -> Result<Participant, ParticipantError> {
+        let sessions = self.sessions.read().await;
+        let inner_arc = sessions.get(id).ok_or(ParticipantError::NotFound)?;
+        let mut inner = inner_arc.write().await;
+        if !inner.client_ids.get(&uid).is_some_and(|issued| constant_time_eq(client_id, issued)) {
+            return Err(ParticipantError::InvalidClientId);
+        }
+        let participant = inner
+            .participants
+            .iter_mut()
+            .find(|p| p.uid == uid)
+            .ok_or(ParticipantError::ParticipantNotFound)?;
+        participant.capabilities = capabilities;
+        let participant = participant.clone();
+        self.mark_dirty();
+        Ok(participant)
+    }
+
+    /// Remove a participant (not the host), releasing their uid under `ReuseFreed`.
+    pub async fn remove_participant(&self, id: &str, uid: u32) -> Result<Participant, ParticipantError> {
+        let sessions = self.sessions.read().await;
+        let inner_arc = sessions.get(id).ok_or(ParticipantError::NotFound)?;
+        let mut inner = inner_arc.write().await;
+        if uid == inner.host_uid {
+            return Err(ParticipantError::HostNotRemovable);
+        }
+        let index = inner
+            .participants
+            .iter()
+            .position(|p| p.uid == uid)
+            .ok_or(ParticipantError::ParticipantNotFound)?;
+        let participant = inner.participants.remove(index);
+        inner.client_ids.remove(&uid);
+        inner.release_uid(uid);
+        inner.last_activity = Utc::now();
+        self.mark_dirty();
+        tracing::info!("Participant {} removed from session {}", uid, id);
+        Ok(participant)
+    }
+
+    /// Append a recording marker attributed to the host. Starting while recording
+    /// or stopping while not recording is refused.
+    pub async fn add_recording_event(
+        &self,
+        id: &str,
+        event: RecordingEventKind,
+        resource_id: Option<String>,
+    ) -> Result<RecordingEvent, RecordingError> {
+        let sessions = self.sessions.read().await;
+        let inner_arc = sessions.get(id).ok_or(RecordingError::NotFound)?;
+        let mut inner = inner_arc.write().await;
+        let recording = inner
+            .recording_events
+            .last()
+            .is_some_and(|e| e.event == RecordingEventKind::Started);
+        match (event, recording) {
+            (RecordingEventKind::Started, true) => return Err(RecordingError::AlreadyRecording),
+            (RecordingEventKind::Stopped, false) => return Err(RecordingError::NotRecording),
+            _ => {}
+        }
+        // Keep markers ordered even if the wall clock steps back
+        let now = Utc::now();
+        let at = inner.recording_events.last().map_or(now, |last| last.at.max(now));
+        let marker = RecordingEvent {
+            event,
+            at,
+            initiated_by_uid: inner.host_uid,
+            resource_id,
+        };
+        inner.recording_events.push(marker.clone());
+        self.mark_dirty();
+        tracing::info!("Recording {:?} in session {}", event, id);
+        Ok(marker)
+    }
+
+    /// Remove a session, returning its final state.
+    pub async fn delete(&self, id: &str) -> Option<RtcSession> {
+        let removed = self.sessions.write().await.remove(id)?;
+        self.mark_dirty();
+        let inner = removed.read().await;
+        Some(self.finalize_session(&inner, EndReason::Deleted).await)
     }
 
-    pub async fn cleanup_expired(&self) {
+    /// Remove expired sessions, returning them so callers can tell paired rooms.
+    pub async fn cleanup_expired(&self) -> Vec<RtcSession> {
         let now = Utc::now();
         let mut sessions = self.sessions.write().await;
         let mut expired_ids = Vec::new();
         for (id, inner_arc) in sessions.iter() {
             let inner = inner_arc.read().await;
-            if now > inner.expires_at {
+            if now > self.expiry(&inner) {
                 expired_ids.push(id.clone());
             }
         }
+        if !expired_ids.is_empty() {
+            self.mark_dirty();
+        }
+        // Finalize while still holding the map so lookups never see a gap
+        // between removal and the tombstone.
+        let mut finalized = Vec::with_capacity(expired_ids.len());
         for id in expired_ids {
-            sessions.remove(&id);
+            if let Some(inner_arc) = sessions.remove(&id) {
+                let inner = inner_arc.read().await;
+                finalized.push(self.finalize_session(&inner, EndReason::Expired).await);
+            }
+        }
+        let grace = Duration::minutes(EXPIRED_TOMBSTONE_GRACE_MINS);
+        self.tombstones
+            .write()
+            .await
+            .retain(|_, expires_at| *expires_at + grace > now);
+        finalized
+    }
+
+    /// Teardown shared by delete and expiry for a session already removed from
+    /// the map: emit the end event, free its app slot and, for expiry, leave a
+    /// tombstone so the id keeps answering 410.
+    async fn finalize_session(&self, inner: &RtcSessionInner, reason: EndReason) -> RtcSession {
+        let event = match reason {
+            EndReason::Deleted => WebhookEventKind::SessionDeleted,
+            EndReason::Expired => {
+                self.tombstones
+                    .write()
+                    .await
+                    .insert(inner.id.clone(), self.expiry(inner));
+                WebhookEventKind::SessionExpired
+            }
+        };
+        self.notify(inner, event, None);
+        self.release_app_slot(&inner.app_id).await;
+        inner.snapshot()
+    }
+
+    async fn release_app_slot(&self, app_id: &str) {
+        let mut app_counts = self.app_counts.write().await;
+        if let Some(count) = app_counts.get_mut(app_id) {
+            *count -= 1;
+            if *count == 0 {
+                app_counts.remove(app_id);
+            }
         }
     }
 }
 
+#[cfg(test)]
+impl RtcSessionStore {
+    /// Insert a bare session that expired at `expires_at`.
+    pub(crate) async fn insert_expired(&self, id: &str, expires_at: DateTime<Utc>) {
+        let inner = RtcSessionInner {
+            id: id.into(),
+            app_id: "a".into(),
+            channel: "c".into(),
+            token: "t".into(),
+            host_token: "h".into(),
+            uid_counter: AtomicU32::new(1000),
+            host_uid: 1,
+            created_at: expires_at - Duration::hours(4),
+            expires_at,
+            participants: Vec::new(),
+            generate_tokens: false,
+            pair_code: None,
+            uid_allocation: UidAllocation::Sequential,
+            free_uids: BTreeSet::new(),
+            host_seen_at: expires_at - Duration::hours(4),
+            last_activity: expires_at - Duration::hours(4),
+            client_ids: HashMap::new(),
+            passcode: None,
+            passcode_failures: Vec::new(),
+            recording_events: Vec::new(),
+            webhook_url: None,
+            enforce_unique_names: false,
+            locked: false,
+        };
+        let mut sessions = self.sessions.write().await;
+        sessions.insert(id.into(), Arc::new(RwLock::new(inner)));
+    }
+}
+
 impl Default for RtcSessionStore {
     fn default() -> Self {
         Self::new()
@@ -237,11 +1751,57 @@ pub async fn create_rtc_session_handler(
             Json(CreateRtcSessionResponse {
                 id: String::new(),
                 url: format!("Validation error: {}", e),
+                host_token: String::new(),
             }),
         )
             .into_response();
     }
 
+    if let Some(url) = &body.webhook_url {
+        if let Err(e) = state.rtc_sessions.check_webhook_url(url) {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(RtcSessionError::with_code(format!("Validation error: {}", e.message()), e.code())),
+            )
+                .into_response();
+        }
+    }
+
+    if body.generate_tokens && !state.rtc_sessions.can_generate_tokens() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(RtcSessionError::new("Server-side token generation is not configured")),
+        )
+            .into_response();
+    }
+    if !body.generate_tokens && body.token.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(RtcSessionError::new("Validation error: token is required unless generate_tokens is set")),
+        )
+            .into_response();
+    }
+
+    // Codes typed by hand are stored canonically; anything else (e.g. a
+    // session room) is kept verbatim
+    let pair_code = body.pair_code.map(|code| state.relay.normalize_code(&code).unwrap_or(code));
+    if let Some(code) = &pair_code {
+        let Some(owner_token) = body.pair_owner_token.as_deref() else {
+            return (
+                StatusCode::UNAUTHORIZED,
+                Json(RtcSessionError::with_code("pair_code requires pair_owner_token", "missing_pair_owner_token")),
+            )
+                .into_response();
+        };
+        if !is_room_owner(&state.relay, code, owner_token).await {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(RtcSessionError::with_code("Invalid pair owner token", "invalid_pair_owner_token")),
+            )
+                .into_response();
+        }
+    }
+
     let id = Uuid::new_v4().to_string();
 
     // Log all relevant headers for debugging
@@ -279,83 +1839,462 @@ pub async fn create_rtc_session_handler(
 
     tracing::info!("Generated session URL: {}", url);
 
-    state
+    let options = RtcSessionOptions {
+        generate_tokens: body.generate_tokens,
+        pair_code,
+        uid_allocation: body.uid_allocation,
+        passcode: body.passcode,
+        webhook_url: body.webhook_url,
+        enforce_unique_names: body.enforce_unique_names,
+    };
+    let session = match state
         .rtc_sessions
-        .create(id.clone(), body.app_id, body.channel, body.token, body.host_uid)
-        .await;
+        .create_with_options(id.clone(), body.app_id, body.channel, body.token, body.host_uid, options)
+        .await
+    {
+        Ok(session) => session,
+        Err(error) => {
+            let mut body = RtcSessionError::with_code(error.message(), error.code());
+            if let CreateError::AppQuotaExceeded { count, .. } = error {
+                body.session_count = Some(count);
+            }
+            return (error.status(), Json(body)).into_response();
+        }
+    };
 
     (
         StatusCode::CREATED,
-        Json(CreateRtcSessionResponse { id, url }),
+        Json(CreateRtcSessionResponse {
+            id,
+            url,
+            host_token: session.host_token,
+        }),
     )
         .into_response()
 }
 
 /// GET /api/rtc-sessions/:id
+///
+/// `?include=participants` adds the participant list, count and maximum so
+/// the join page can tell a full room before joining.
 pub async fn get_rtc_session_handler(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(query): Query<GetRtcSessionQuery>,
 ) -> impl IntoResponse {
-    match state.rtc_sessions.get(&id).await {
-        Some(session) => Ok(Json(GetRtcSessionResponse {
-            app_id: session.app_id,
-            channel: session.channel,
-            host_uid: session.host_uid,
-            created_at: session.created_at,
-        })),
-        None => Err((
+    match state.rtc_sessions.lookup_meta(&id).await {
+        RtcSessionLookup::Active(meta) => {
+            let mut response = GetRtcSessionResponse {
+                app_id: meta.app_id,
+                channel: meta.channel,
+                host_uid: meta.host_uid,
+                created_at: meta.created_at,
+                passcode_required: meta.passcode_required,
+                participants: None,
+                participant_count: None,
+                max_participants: None,
+            };
+            if query.includes("participants") {
+                let participants = state.rtc_sessions.get_participants(&id).await.unwrap_or_default();
+                response.participant_count = Some(participants.len());
+                response.max_participants = Some(MAX_PARTICIPANTS);
+                response.participants = Some(participants);
+            }
+            Ok(Json(response))
+        }
+        RtcSessionLookup::Expired { expires_at } => Err((
+            StatusCode::GONE,
+            Json(RtcSessionError::expired(expires_at)),
+        )),
+        RtcSessionLookup::NotFound => Err((
             StatusCode::NOT_FOUND,
-            Json(RtcSessionError {
-                error: "Session not found".to_string(),
-            }),
+            Json(RtcSessionError::new("Session not found")),
         )),
     }
 }
 
+/// Require a valid host token for a privileged operation.
+/// Missing token → 401, unknown session → 404, expired session → 410, wrong token → 403.
+async fn require_host(
+    state: &AppState,
+    id: &str,
+    headers: &HeaderMap,
+) -> Result<(), (StatusCode, Json<RtcSessionError>)> {
+    let token = bearer_token(headers).ok_or((
+        StatusCode::UNAUTHORIZED,
+        Json(RtcSessionError::new("Missing host token")),
+    ))?;
+    match state.rtc_sessions.lookup_meta(id).await {
+        RtcSessionLookup::Active(_) => {}
+        RtcSessionLookup::Expired { expires_at } => {
+            return Err((
+                StatusCode::GONE,
+                Json(RtcSessionError::expired(expires_at)),
+            ));
+        }
+        RtcSessionLookup::NotFound => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(RtcSessionError::new("Session not found")),
+            ));
+        }
+    }
+    if !state.rtc_sessions.verify_host_token(id, token).await {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(RtcSessionError::new("Invalid host token")),
+        ));
+    }
+    state.rtc_sessions.touch_host(id).await;
+    Ok(())
+}
+
+/// [`require_host`], or a bearer that is the join `client_id` of the host or a co-host.
+async fn require_moderator(
+    state: &AppState,
+    id: &str,
+    headers: &HeaderMap,
+) -> Result<(), (StatusCode, Json<RtcSessionError>)> {
+    if let Some(token) = bearer_token(headers) {
+        if state.rtc_sessions.verify_moderator(id, token).await {
+            return Ok(());
+        }
+    }
+    require_host(state, id, headers).await
+}
+
 /// POST /api/rtc-sessions/:id/join
+///
+/// Presenting the host token joins as host (uid = `host_uid`, role = host).
 pub async fn join_rtc_session_handler(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    headers: HeaderMap,
     Json(body): Json<JoinRtcSessionRequest>,
 ) -> impl IntoResponse {
     // Validate input
     if let Err(e) = body.validate() {
         return Err((
             StatusCode::BAD_REQUEST,
-            Json(RtcSessionError {
-                error: format!("Validation error: {}", e),
-            }),
+            Json(RtcSessionError::new(format!("Validation error: {}", e))),
         ));
     }
 
-    match state.rtc_sessions.join(&id, body.name).await {
+    if let Some(metadata) = &body.metadata {
+        if let Err(e) = validate_metadata(metadata) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(RtcSessionError::with_code(e.message(), e.code())),
+            ));
+        }
+    }
+
+    let as_host = bearer_token(&headers).is_some();
+    if as_host {
+        require_host(&state, &id, &headers).await?;
+    }
+    let options = JoinOptions {
+        as_host,
+        metadata: body.metadata,
+        capabilities: body.capabilities,
+        passcode: body.passcode,
+    };
+    let result = state.rtc_sessions.join_with_options(&id, body.name, options).await;
+
+    match result {
         Ok(response) => Ok(Json(response)),
-        Err(error) => {
-            let status = if error.contains("not found") {
-                StatusCode::NOT_FOUND
-            } else if error.contains("full") {
-                StatusCode::CONFLICT
-            } else {
-                StatusCode::INTERNAL_SERVER_ERROR
+        Err(JoinError::Expired) => {
+            let body = match state.rtc_sessions.lookup_meta(&id).await {
+                RtcSessionLookup::Expired { expires_at } => RtcSessionError::expired(expires_at),
+                _ => RtcSessionError::with_code(JoinError::Expired.message(), JoinError::Expired.code()),
             };
-            Err((status, Json(RtcSessionError { error })))
+            Err((StatusCode::GONE, Json(body)))
         }
+        Err(error) => Err((
+            error.status(),
+            Json(RtcSessionError::with_code(error.message(), error.code())),
+        )),
     }
 }
 
-/// DELETE /api/rtc-sessions/:id
-pub async fn delete_rtc_session_handler(
+/// POST /api/rtc-sessions/:id/participants/:uid/role
+///
+/// Promote a participant to co-host or demote back to attendee (host token required).
+pub async fn set_participant_role_handler(
     State(state): State<AppState>,
-    Path(id): Path<String>,
+    Path((id, uid)): Path<(String, u32)>,
+    headers: HeaderMap,
+    Json(body): Json<SetParticipantRoleRequest>,
 ) -> impl IntoResponse {
-    if state.rtc_sessions.delete(&id).await {
-        StatusCode::OK
-    } else {
-        StatusCode::NOT_FOUND
-    }
-}
-
-// --- Tests ---
+    require_host(&state, &id, &headers).await?;
+
+    state
+        .rtc_sessions
+        .set_participant_role(&id, uid, body.role)
+        .await
+        .map(Json)
+        .map_err(|error| {
+            (
+                error.status(),
+                Json(RtcSessionError::with_code(error.message(), error.code())),
+            )
+        })
+}
+
+/// POST /api/rtc-sessions/:id/lock
+///
+/// Lock or unlock the session against new joins (host token, or a host's or
+/// co-host's `client_id`, as the bearer). The host can always rejoin.
+pub async fn lock_rtc_session_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<LockRtcSessionRequest>,
+) -> impl IntoResponse {
+    require_moderator(&state, &id, &headers).await?;
+    match state.rtc_sessions.set_locked(&id, body.locked).await {
+        Some(locked) => Ok(Json(LockRtcSessionRequest { locked })),
+        None => Err((StatusCode::NOT_FOUND, Json(RtcSessionError::new("Session not found")))),
+    }
+}
+
+/// POST /api/rtc-sessions/:id/host-heartbeat
+///
+/// Keep the host marked active (host token required). Any host action also counts.
+/// Also counts as session activity, so a running call is not expired while idle.
+pub async fn host_heartbeat_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    require_host(&state, &id, &headers).await?;
+    state.rtc_sessions.touch(&id).await;
+    Ok::<_, (StatusCode, Json<RtcSessionError>)>(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/rtc-sessions/:id/transfer-host
+///
+/// Hand host to another participant (host token required), or with `claim: true`
+/// and its join `client_id` let the longest-present co-host take over from a host
+/// silent for [`HOST_STALE_AFTER_MINS`]. Returns a fresh host token; the old one
+/// stops working.
+pub async fn transfer_host_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(body): Json<TransferHostRequest>,
+) -> impl IntoResponse {
+    let auth = if body.claim {
+        let client_id = body.client_id.as_deref().ok_or((
+            StatusCode::UNAUTHORIZED,
+            Json(RtcSessionError::with_code("Missing client id", "missing_client_id")),
+        ))?;
+        HostAuth::Claim { client_id }
+    } else {
+        require_host(&state, &id, &headers).await?;
+        // require_host has checked a bearer token is present
+        HostAuth::HostToken(bearer_token(&headers).unwrap_or_default())
+    };
+
+    match state.rtc_sessions.transfer_host(&id, body.uid, auth).await {
+        Ok(response) => Ok(Json(response)),
+        Err(error) => Err((
+            error.status(),
+            Json(RtcSessionError::with_code(error.message(), error.code())),
+        )),
+    }
+}
+
+/// PUT /api/rtc-sessions/:id/participants/:uid/capabilities
+///
+/// A participant updates what it publishes, authenticated by the `client_id` from join.
+pub async fn update_capabilities_handler(
+    State(state): State<AppState>,
+    Path((id, uid)): Path<(String, u32)>,
+    Json(body): Json<UpdateCapabilitiesRequest>,
+) -> impl IntoResponse {
+    state
+        .rtc_sessions
+        .set_capabilities(&id, uid, &body.client_id, body.capabilities)
+        .await
+        .map(Json)
+        .map_err(|error| {
+            (
+                error.status(),
+                Json(RtcSessionError::with_code(error.message(), error.code())),
+            )
+        })
+}
+
+/// POST /api/rtc-sessions/:id/recording/start and /stop (host token required)
+async fn add_recording_marker(
+    state: &AppState,
+    id: &str,
+    headers: &HeaderMap,
+    event: RecordingEventKind,
+    body: Option<Json<RecordingMarkerRequest>>,
+) -> Result<Json<RecordingEvent>, (StatusCode, Json<RtcSessionError>)> {
+    require_host(state, id, headers).await?;
+    let Json(body) = body.unwrap_or_default();
+    state
+        .rtc_sessions
+        .add_recording_event(id, event, body.resource_id)
+        .await
+        .map(Json)
+        .map_err(|error| {
+            (
+                error.status(),
+                Json(RtcSessionError::with_code(error.message(), error.code())),
+            )
+        })
+}
+
+/// POST /api/rtc-sessions/:id/recording/start
+pub async fn start_recording_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Option<Json<RecordingMarkerRequest>>,
+) -> impl IntoResponse {
+    add_recording_marker(&state, &id, &headers, RecordingEventKind::Started, body).await
+}
+
+/// POST /api/rtc-sessions/:id/recording/stop
+pub async fn stop_recording_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Option<Json<RecordingMarkerRequest>>,
+) -> impl IntoResponse {
+    add_recording_marker(&state, &id, &headers, RecordingEventKind::Stopped, body).await
+}
+
+/// GET /api/rtc-sessions/:id/recording
+///
+/// Recording markers, oldest first, and whether recording is currently on.
+pub async fn get_recording_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match state.rtc_sessions.lookup(&id).await {
+        RtcSessionLookup::Active(session) => Ok(Json(RecordingStatusResponse {
+            recording: session.is_recording(),
+            events: session.recording_events,
+        })),
+        RtcSessionLookup::Expired { expires_at } => Err((
+            StatusCode::GONE,
+            Json(RtcSessionError::expired(expires_at)),
+        )),
+        RtcSessionLookup::NotFound => Err((
+            StatusCode::NOT_FOUND,
+            Json(RtcSessionError::new("Session not found")),
+        )),
+    }
+}
+
+/// GET /api/rtc-sessions/:id/participants
+///
+/// Participants in join order with occupancy. No token needed: the session id
+/// is the secret.
+pub async fn get_participants_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match state.rtc_sessions.lookup_with(&id, |inner| inner.participants.clone()).await {
+        RtcSessionLookup::Active(participants) => Ok(Json(ParticipantsResponse {
+            count: participants.len(),
+            capacity: MAX_PARTICIPANTS,
+            participants,
+        })),
+        RtcSessionLookup::Expired { expires_at } => Err((
+            StatusCode::GONE,
+            Json(RtcSessionError::expired(expires_at)),
+        )),
+        RtcSessionLookup::NotFound => Err((
+            StatusCode::NOT_FOUND,
+            Json(RtcSessionError::new("Session not found")),
+        )),
+    }
+}
+
+/// DELETE /api/rtc-sessions/:id/participants/:uid
+///
+/// Remove a participant from the session (host token, or a host's or co-host's
+/// `client_id`, as the bearer).
+pub async fn remove_participant_handler(
+    State(state): State<AppState>,
+    Path((id, uid)): Path<(String, u32)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    require_moderator(&state, &id, &headers).await?;
+
+    state
+        .rtc_sessions
+        .remove_participant(&id, uid)
+        .await
+        .map(Json)
+        .map_err(|error| {
+            (
+                error.status(),
+                Json(RtcSessionError::with_code(error.message(), error.code())),
+            )
+        })
+}
+
+/// DELETE /api/rtc-sessions/:id
+///
+/// Requires the current host token, also for sessions that have expired but
+/// are not yet cleaned up.
+pub async fn delete_rtc_session_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let Some(token) = bearer_token(&headers) else {
+        return StatusCode::UNAUTHORIZED;
+    };
+    if !state.rtc_sessions.verify_host_token(&id, token).await {
+        return match state.rtc_sessions.lookup_meta(&id).await {
+            RtcSessionLookup::NotFound => StatusCode::NOT_FOUND,
+            RtcSessionLookup::Active(_) | RtcSessionLookup::Expired { .. } => StatusCode::FORBIDDEN,
+        };
+    }
+    let Some(session) = state.rtc_sessions.delete(&id).await else {
+        return StatusCode::NOT_FOUND;
+    };
+    notify_pair_room(&state.relay, &session, "rtc_session_deleted").await;
+    StatusCode::OK
+}
+
+/// GET /api/admin/rtc-sessions/snapshot
+///
+/// Every session with credentials redacted (admin token required).
+pub async fn rtc_sessions_snapshot_admin_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    require_admin(&state, &headers)?;
+    let sessions = state.rtc_sessions.export_snapshot().await;
+    Ok::<_, (StatusCode, Json<serde_json::Value>)>(Json(serde_json::json!({
+        "count": sessions.len(),
+        "taken_at": Utc::now(),
+        "sessions": sessions,
+    })))
+}
+
+/// Tell the astation side of the session's pair room (if any) that the session
+/// ended, as `{"type": message_type, "id": ...}`.
+pub async fn notify_pair_room(relay: &RelayHub, session: &RtcSession, message_type: &str) {
+    let Some(code) = &session.pair_code else {
+        return;
+    };
+    let message = serde_json::json!({ "type": message_type, "id": session.id });
+    if !relay.send_to(code, Role::Astation, message.to_string()).await {
+        tracing::debug!("RTC session {} ended; no astation connected for {}", session.id, code);
+    }
+}
+
+// --- Tests ---
 
 #[cfg(test)]
 mod tests {
@@ -376,9 +2315,13 @@ mod tests {
         let state = AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         };
         Router::new()
             .route("/api/rtc-sessions", post(create_rtc_session_handler))
@@ -391,6 +2334,10 @@ mod tests {
                 "/api/rtc-sessions/:id",
                 delete(delete_rtc_session_handler),
             )
+            .route(
+                "/api/rtc-sessions/:id/participants/:uid/role",
+                post(set_participant_role_handler),
+            )
             .with_state(state)
     }
 
@@ -407,7 +2354,7 @@ mod tests {
                 "token-abc".into(),
                 5678,
             )
-            .await;
+            .await.unwrap();
 
         assert_eq!(session.id, "test-id");
         assert_eq!(session.app_id, "app123");
@@ -423,6 +2370,46 @@ mod tests {
         assert_eq!(retrieved.host_uid, 5678);
     }
 
+    #[tokio::test]
+    async fn test_get_meta_matches_get() {
+        let store = RtcSessionStore::new();
+        let options = RtcSessionOptions {
+            passcode: Some("tulip-42".into()),
+            ..Default::default()
+        };
+        store
+            .create_with_options("meta".into(), "app".into(), "room".into(), "t".into(), 7, options)
+            .await
+            .unwrap();
+        for i in 0..3 {
+            store
+                .join_with_options("meta", format!("User {}", i), with_passcode(Some("tulip-42")))
+                .await
+                .unwrap();
+        }
+
+        let full = store.get("meta").await.unwrap();
+        let meta = store.get_meta("meta").await.unwrap();
+        assert_eq!(
+            meta,
+            RtcSessionMeta {
+                id: full.id,
+                app_id: full.app_id,
+                channel: full.channel,
+                host_uid: full.host_uid,
+                created_at: full.created_at,
+                expires_at: full.expires_at,
+                participant_count: full.participants.len(),
+                passcode_required: full.passcode.is_some(),
+                generate_tokens: full.generate_tokens,
+            }
+        );
+        assert_eq!(meta.participant_count, 3);
+        let uids: Vec<u32> = store.get_participants("meta").await.unwrap().iter().map(|p| p.uid).collect();
+        assert_eq!(uids, full.participants.iter().map(|p| p.uid).collect::<Vec<_>>());
+        assert!(store.get_meta("does-not-exist").await.is_none());
+    }
+
     #[tokio::test]
     async fn test_get_nonexistent() {
         let store = RtcSessionStore::new();
@@ -434,9 +2421,9 @@ mod tests {
         let store = RtcSessionStore::new();
         store
             .create("del-me".into(), "app".into(), "ch".into(), "tok".into(), 1)
-            .await;
+            .await.unwrap();
         assert!(store.get("del-me").await.is_some());
-        assert!(store.delete("del-me").await);
+        assert!(store.delete("del-me").await.is_some());
         assert!(store.get("del-me").await.is_none());
     }
 
@@ -445,7 +2432,7 @@ mod tests {
         let store = RtcSessionStore::new();
         store
             .create("join-test".into(), "app".into(), "ch".into(), "tok".into(), 1)
-            .await;
+            .await.unwrap();
 
         let r1 = store.join("join-test", "Alice".into()).await.unwrap();
         let r2 = store.join("join-test", "Bob".into()).await.unwrap();
@@ -467,7 +2454,7 @@ mod tests {
         let store = RtcSessionStore::new();
         store
             .create("info-test".into(), "my-app".into(), "room1".into(), "secret-token".into(), 42)
-            .await;
+            .await.unwrap();
 
         let resp = store.join("info-test", "Dave".into()).await.unwrap();
         assert_eq!(resp.app_id, "my-app");
@@ -481,7 +2468,7 @@ mod tests {
         let store = RtcSessionStore::new();
         store
             .create("part-test".into(), "app".into(), "ch".into(), "tok".into(), 1)
-            .await;
+            .await.unwrap();
 
         let _ = store.join("part-test", "Alice".into()).await;
 
@@ -505,11 +2492,25 @@ mod tests {
                 app_id: "a".into(),
                 channel: "c".into(),
                 token: "t".into(),
+                host_token: "h".into(),
                 uid_counter: AtomicU32::new(1000),
                 host_uid: 1,
                 created_at: Utc::now() - Duration::hours(5),
                 expires_at: Utc::now() - Duration::hours(1),
                 participants: Vec::new(),
+                generate_tokens: false,
+                pair_code: None,
+                uid_allocation: UidAllocation::Sequential,
+                free_uids: BTreeSet::new(),
+                host_seen_at: Utc::now() - Duration::hours(5),
+                last_activity: Utc::now() - Duration::hours(5),
+                client_ids: HashMap::new(),
+                passcode: None,
+                passcode_failures: Vec::new(),
+                recording_events: Vec::new(),
+                webhook_url: None,
+                enforce_unique_names: false,
+                locked: false,
             };
             let mut sessions = store.sessions.write().await;
             sessions.insert("expired".into(), Arc::new(RwLock::new(inner)));
@@ -518,7 +2519,7 @@ mod tests {
         // Create an active session
         store
             .create("active".into(), "a".into(), "c".into(), "t".into(), 1)
-            .await;
+            .await.unwrap();
 
         store.cleanup_expired().await;
 
@@ -526,12 +2527,102 @@ mod tests {
         assert!(store.get("active").await.is_some());
     }
 
+    #[tokio::test]
+    async fn test_activity_keeps_session_past_expiry() {
+        let store = RtcSessionStore::new().with_max_idle(Duration::hours(2));
+        for id in ["idle", "active", "touched"] {
+            store.create(id.into(), "a".into(), "c".into(), "t".into(), 1).await.unwrap();
+            let sessions = store.sessions.read().await;
+            let mut inner = sessions[id].write().await;
+            // Scheduled expiry passed; only idle is past its idle limit too
+            inner.expires_at = Utc::now() - Duration::minutes(1);
+            inner.last_activity = match id {
+                "idle" => Utc::now() - Duration::hours(3),
+                _ => Utc::now() - Duration::minutes(90),
+            };
+        }
+        store.join("active", "Alice".into()).await.unwrap();
+        store.touch("touched").await;
+
+        let expired = store.cleanup_expired().await;
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, "idle");
+        assert!(matches!(store.lookup("idle").await, RtcSessionLookup::Expired { .. }));
+        for id in ["active", "touched"] {
+            let RtcSessionLookup::Active(session) = store.lookup(id).await else {
+                panic!("{} should still be active", id);
+            };
+            assert!(store.expiry(&RtcSessionInner::from_snapshot(*session)) > Utc::now() + Duration::minutes(110));
+        }
+        assert!(store.join("active", "Bob".into()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_kick_and_transfer_count_as_activity() {
+        let store = RtcSessionStore::new();
+        store.create("busy".into(), "a".into(), "c".into(), "t".into(), 1).await.unwrap();
+        let alice = store.join("busy", "Alice".into()).await.unwrap();
+        let bob = store.join("busy", "Bob".into()).await.unwrap();
+        let host_token = store.get("busy").await.unwrap().host_token;
+        let stale = Utc::now() - Duration::hours(3);
+
+        store.sessions.read().await["busy"].write().await.last_activity = stale;
+        store.remove_participant("busy", bob.uid).await.unwrap();
+        assert!(store.get("busy").await.unwrap().last_activity > stale);
+
+        store.sessions.read().await["busy"].write().await.last_activity = stale;
+        store.transfer_host("busy", alice.uid, HostAuth::HostToken(&host_token)).await.unwrap();
+        assert!(store.get("busy").await.unwrap().last_activity > stale);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_finalizes_like_delete() {
+        let store = RtcSessionStore::new();
+        let mut events = store.subscribe();
+        let options = RtcSessionOptions {
+            pair_code: Some("ABCD-EFGH".into()),
+            ..Default::default()
+        };
+        store
+            .create_with_options("ending".into(), "a".into(), "c".into(), "t".into(), 1, options)
+            .await
+            .unwrap();
+        store.create("deleted".into(), "a".into(), "c".into(), "t".into(), 1).await.unwrap();
+        assert_eq!(events.recv().await.unwrap().event, WebhookEventKind::SessionCreated);
+        assert_eq!(events.recv().await.unwrap().event, WebhookEventKind::SessionCreated);
+
+        expire_now(&store, "ending").await;
+        let expired = store.cleanup_expired().await;
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].pair_code.as_deref(), Some("ABCD-EFGH"));
+
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.event, WebhookEventKind::SessionExpired);
+        assert_eq!(event.session_id, "ending");
+        assert!(matches!(store.lookup("ending").await, RtcSessionLookup::Expired { .. }));
+        assert_eq!(store.capacity_used().await, 1);
+
+        // The paired astation hears about it, like an explicit delete
+        let relay = RelayHub::new();
+        let mut astation_rx = relay.connect_test_side("ABCD-EFGH", Role::Astation).await;
+        notify_pair_room(&relay, &expired[0], "rtc_session_expired").await;
+        let message: serde_json::Value = serde_json::from_str(&astation_rx.try_recv().unwrap()).unwrap();
+        assert_eq!(message, serde_json::json!({ "type": "rtc_session_expired", "id": "ending" }));
+
+        store.delete("deleted").await.unwrap();
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.event, WebhookEventKind::SessionDeleted);
+        assert_eq!(event.session_id, "deleted");
+        assert!(matches!(store.lookup("deleted").await, RtcSessionLookup::NotFound));
+        assert!(events.try_recv().is_err());
+    }
+
     #[tokio::test]
     async fn test_cleanup_preserves_active() {
         let store = RtcSessionStore::new();
         store
             .create("keep-me".into(), "a".into(), "c".into(), "t".into(), 1)
-            .await;
+            .await.unwrap();
 
         store.cleanup_expired().await;
 
@@ -543,7 +2634,7 @@ mod tests {
         let store = RtcSessionStore::new();
         store
             .create("uid-test".into(), "a".into(), "c".into(), "t".into(), 1)
-            .await;
+            .await.unwrap();
 
         let resp = store.join("uid-test", "First".into()).await.unwrap();
         assert_eq!(resp.uid, 1000);
@@ -554,7 +2645,7 @@ mod tests {
         let store = RtcSessionStore::new();
         store
             .create("concurrent".into(), "a".into(), "c".into(), "t".into(), 1)
-            .await;
+            .await.unwrap();
 
         let mut handles = Vec::new();
         for i in 0..10 {
@@ -582,281 +2673,691 @@ mod tests {
         assert_eq!(*uids.last().unwrap(), 1007);
     }
 
-    #[tokio::test]
-    async fn test_max_participants_enforced() {
-        let store = RtcSessionStore::new();
+    async fn create_with_unique_names(store: &RtcSessionStore, id: &str, enforce_unique_names: Option<bool>) {
+        let options = RtcSessionOptions { enforce_unique_names, ..Default::default() };
         store
-            .create("full-test".into(), "a".into(), "c".into(), "t".into(), 1)
-            .await;
-
-        // Join 8 people successfully
-        for i in 0..8 {
-            let result = store.join("full-test", format!("User{}", i)).await;
-            assert!(result.is_ok(), "User {} should join successfully", i);
-        }
+            .create_with_options(id.into(), "a".into(), "c".into(), "t".into(), 1, options)
+            .await
+            .unwrap();
+    }
 
-        // 9th person should fail
-        let result = store.join("full-test", "User9".into()).await;
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("full"));
+    #[tokio::test]
+    async fn test_duplicate_names_allowed_by_default() {
+        let store = RtcSessionStore::new();
+        create_with_unique_names(&store, "lenient", None).await;
+        assert!(store.join("lenient", "Alex".into()).await.is_ok());
+        assert!(store.join("lenient", "Alex".into()).await.is_ok());
+        assert_eq!(store.get_participants("lenient").await.unwrap().len(), 2);
     }
 
-    // --- Handler Tests ---
+    #[tokio::test]
+    async fn test_unique_names_refuse_a_taken_name() {
+        let store = RtcSessionStore::new().with_enforce_unique_names(true);
+        create_with_unique_names(&store, "strict", None).await;
+        store.join_as_host("strict", "Alex".into()).await.unwrap();
+        assert_eq!(store.join("strict", "Alex".into()).await.unwrap_err(), JoinError::NameTaken);
+        // Exact match only, and the host may rejoin under its own name
+        assert!(store.join("strict", "alex".into()).await.is_ok());
+        assert!(store.join_as_host("strict", "Alex".into()).await.is_ok());
+        assert_eq!(store.get_participants("strict").await.unwrap().len(), 2);
+
+        // A session's own setting wins over the store default
+        create_with_unique_names(&store, "opted-out", Some(false)).await;
+        assert!(store.join("opted-out", "Sam".into()).await.is_ok());
+        assert!(store.join("opted-out", "Sam".into()).await.is_ok());
+        let lenient = RtcSessionStore::new();
+        create_with_unique_names(&lenient, "opted-in", Some(true)).await;
+        assert!(lenient.join("opted-in", "Sam".into()).await.is_ok());
+        assert_eq!(lenient.join("opted-in", "Sam".into()).await.unwrap_err(), JoinError::NameTaken);
+    }
 
     #[tokio::test]
-    async fn test_create_session_handler() {
-        let app = create_test_app();
+    async fn test_unique_names_hold_under_concurrent_joins() {
+        let store = RtcSessionStore::new();
+        create_with_unique_names(&store, "race", Some(true)).await;
 
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/rtc-sessions")
-                    .header("Content-Type", "application/json")
-                    .header("Host", "station.agora.build")
-                    .body(Body::from(
-                        r#"{"app_id":"app1","channel":"room","token":"tok","host_uid":5678}"#,
-                    ))
-                    .unwrap(),
+        let handles: Vec<_> = (0..6)
+            .map(|_| {
+                let store = store.clone();
+                tokio::spawn(async move { store.join("race", "Same Name".into()).await })
+            })
+            .collect();
+        let mut joined = 0;
+        for handle in handles {
+            match handle.await.unwrap() {
+                Ok(_) => joined += 1,
+                Err(error) => assert_eq!(error, JoinError::NameTaken),
+            }
+        }
+        assert_eq!(joined, 1, "Exactly one join may take the name");
+        assert_eq!(store.get_participants("race").await.unwrap().len(), 1);
+    }
+
+    async fn create_with_allocation(store: &RtcSessionStore, id: &str, uid_allocation: UidAllocation) {
+        store
+            .create_with_options(
+                id.into(),
+                "a".into(),
+                "c".into(),
+                "t".into(),
+                1,
+                RtcSessionOptions { uid_allocation, ..Default::default() },
             )
             .await
             .unwrap();
+    }
 
-        assert_eq!(response.status(), StatusCode::CREATED);
-        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-            .await
-            .unwrap();
-        let resp: CreateRtcSessionResponse = serde_json::from_slice(&body).unwrap();
-        assert!(!resp.id.is_empty());
-        assert!(resp.url.starts_with("https://station.agora.build/session/"));
+    async fn join_concurrently(store: &RtcSessionStore, id: &str, count: usize) -> Vec<u32> {
+        let mut handles = Vec::new();
+        for i in 0..count {
+            let store = store.clone();
+            let id = id.to_string();
+            handles.push(tokio::spawn(async move {
+                store.join(&id, format!("User{}", i)).await.ok().map(|r| r.uid)
+            }));
+        }
+        let mut uids = Vec::new();
+        for handle in handles {
+            if let Some(uid) = handle.await.unwrap() {
+                uids.push(uid);
+            }
+        }
+        uids
     }
 
     #[tokio::test]
-    async fn test_create_session_missing_fields() {
-        let app = create_test_app();
+    async fn test_sequential_allocation_never_reuses() {
+        let store = RtcSessionStore::new();
+        create_with_allocation(&store, "seq", UidAllocation::Sequential).await;
+        let first = store.join("seq", "A".into()).await.unwrap();
+        store.remove_participant("seq", first.uid).await.unwrap();
+        let second = store.join("seq", "B".into()).await.unwrap();
+        assert_eq!((first.uid, second.uid), (1000, 1001));
+    }
 
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/rtc-sessions")
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(r#"{"app_id":"app1"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+    #[tokio::test]
+    async fn test_reuse_freed_allocation() {
+        let store = RtcSessionStore::new();
+        create_with_allocation(&store, "reuse", UidAllocation::ReuseFreed).await;
+        for name in ["A", "B", "C"] {
+            store.join("reuse", name.into()).await.unwrap();
+        }
+        store.remove_participant("reuse", 1002).await.unwrap();
+        store.remove_participant("reuse", 1000).await.unwrap();
 
-        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        // Lowest freed uid first, then the counter resumes
+        assert_eq!(store.join("reuse", "D".into()).await.unwrap().uid, 1000);
+        assert_eq!(store.join("reuse", "E".into()).await.unwrap().uid, 1002);
+        assert_eq!(store.join("reuse", "F".into()).await.unwrap().uid, 1003);
     }
 
     #[tokio::test]
-    async fn test_get_session_handler() {
+    async fn test_concurrent_joins_reuse_freed() {
+        let store = RtcSessionStore::new();
+        create_with_allocation(&store, "reuse-c", UidAllocation::ReuseFreed).await;
+        let first = join_concurrently(&store, "reuse-c", 8).await;
+        assert_eq!(first.len(), 8);
+        for uid in [1001, 1003, 1005, 1007] {
+            store.remove_participant("reuse-c", uid).await.unwrap();
+        }
+
+        let mut rejoined = join_concurrently(&store, "reuse-c", 10).await;
+        rejoined.sort();
+        assert_eq!(rejoined, vec![1001, 1003, 1005, 1007]);
+
+        let session = store.get("reuse-c").await.unwrap();
+        let mut uids: Vec<u32> = session.participants.iter().map(|p| p.uid).collect();
+        uids.sort();
+        assert_eq!(uids, (1000..1008).collect::<Vec<_>>());
+        assert!(session.free_uids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_joins_random_are_unique() {
+        let store = RtcSessionStore::new();
+        create_with_allocation(&store, "rand", UidAllocation::Random).await;
+        let mut uids = join_concurrently(&store, "rand", 10).await;
+        assert_eq!(uids.len(), 8);
+        assert!(uids.iter().all(|&uid| uid > 1000));
+        uids.sort();
+        uids.dedup();
+        assert_eq!(uids.len(), 8, "random uids must not collide");
+    }
+
+    #[tokio::test]
+    async fn test_remove_participant_guards() {
+        let store = RtcSessionStore::new();
+        create_with_allocation(&store, "rm", UidAllocation::ReuseFreed).await;
+        store.join_as_host("rm", "Host".into()).await.unwrap();
+        assert_eq!(store.remove_participant("rm", 1).await.unwrap_err(), ParticipantError::HostNotRemovable);
+        assert_eq!(
+            store.remove_participant("rm", 4242).await.unwrap_err(),
+            ParticipantError::ParticipantNotFound
+        );
+        assert_eq!(
+            store.remove_participant("missing", 1000).await.unwrap_err(),
+            ParticipantError::NotFound
+        );
+        assert!(store.get("rm").await.unwrap().free_uids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_remove_participant_handler_requires_host() {
         let state = AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         };
-        state
+        let created = state
             .rtc_sessions
-            .create("get-test".into(), "app1".into(), "room1".into(), "tok".into(), 99)
-            .await;
-
+            .create("kick".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await
+            .unwrap();
+        let alice = state.rtc_sessions.join("kick", "Alice".into()).await.unwrap();
         let app = Router::new()
-            .route("/api/rtc-sessions/:id", get(get_rtc_session_handler))
-            .with_state(state);
+            .route(
+                "/api/rtc-sessions/:id/participants/:uid",
+                delete(remove_participant_handler),
+            )
+            .with_state(state.clone());
+        let request = |token: Option<&str>| {
+            let mut builder = Request::builder()
+                .method("DELETE")
+                .uri(format!("/api/rtc-sessions/kick/participants/{}", alice.uid));
+            if let Some(token) = token {
+                builder = builder.header("Authorization", format!("Bearer {}", token));
+            }
+            builder.body(Body::empty()).unwrap()
+        };
 
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .uri("/api/rtc-sessions/get-test")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+        let response = app.clone().oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 
+        let response = app.clone().oneshot(request(Some(&created.host_token))).await.unwrap();
         assert_eq!(response.status(), StatusCode::OK);
-        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        assert!(state.rtc_sessions.get("kick").await.unwrap().participants.is_empty());
+
+        let response = app.oneshot(request(Some(&created.host_token))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_cohost_can_kick_and_lock_but_attendee_cannot() {
+        let state = test_state();
+        state.rtc_sessions.create("mod".into(), "a".into(), "c".into(), "t".into(), 1).await.unwrap();
+        let carol = state.rtc_sessions.join("mod", "Carol".into()).await.unwrap();
+        let alice = state.rtc_sessions.join("mod", "Alice".into()).await.unwrap();
+        let bob = state.rtc_sessions.join("mod", "Bob".into()).await.unwrap();
+        state.rtc_sessions.set_participant_role("mod", carol.uid, ParticipantRole::Cohost).await.unwrap();
+        let app = Router::new()
+            .route("/api/rtc-sessions/:id/participants/:uid", delete(remove_participant_handler))
+            .route("/api/rtc-sessions/:id/lock", post(lock_rtc_session_handler))
+            .with_state(state.clone());
+        let kick = |uid: u32, client_id: &str| {
+            authed("DELETE", &format!("/api/rtc-sessions/mod/participants/{}", uid), Some(client_id), "")
+        };
+        let lock = |client_id: &str| authed("POST", "/api/rtc-sessions/mod/lock", Some(client_id), r#"{"locked":true}"#);
+
+        // An attendee's client id is no authority
+        let response = app.clone().oneshot(kick(bob.uid, &alice.client_id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.clone().oneshot(lock(&alice.client_id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // A co-host's is
+        let response = app.clone().oneshot(kick(bob.uid, &carol.client_id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.clone().oneshot(lock(&carol.client_id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            state.rtc_sessions.join("mod", "Dave".into()).await.unwrap_err(),
+            JoinError::Locked
+        );
+        assert!(state.rtc_sessions.join_as_host("mod", "Host".into()).await.is_ok());
+
+        // Demoted, the same client id stops working
+        state.rtc_sessions.set_participant_role("mod", carol.uid, ParticipantRole::Attendee).await.unwrap();
+        let response = app.oneshot(kick(alice.uid, &carol.client_id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_capabilities_default_and_explicit() {
+        let store = RtcSessionStore::new();
+        store.create("caps".into(), "a".into(), "c".into(), "t".into(), 1).await.unwrap();
+        let alice = store.join("caps", "Alice".into()).await.unwrap();
+        assert_eq!(
+            alice.capabilities,
+            MediaCapabilities { audio: true, video: true, screen: false }
+        );
+
+        let screen_only = MediaCapabilities { audio: false, video: false, screen: true };
+        let bob = store
+            .join_with_options(
+                "caps",
+                "Bob".into(),
+                JoinOptions { capabilities: screen_only, ..Default::default() },
+            )
             .await
             .unwrap();
-        let resp: GetRtcSessionResponse = serde_json::from_slice(&body).unwrap();
-        assert_eq!(resp.app_id, "app1");
-        assert_eq!(resp.channel, "room1");
-        assert_eq!(resp.host_uid, 99);
+        assert_eq!(bob.capabilities, screen_only);
+        assert_ne!(alice.client_id, bob.client_id);
+
+        let session = store.get("caps").await.unwrap();
+        assert_eq!(session.participants[0].capabilities, MediaCapabilities::default());
+        assert_eq!(session.participants[1].capabilities, screen_only);
+    }
+
+    #[test]
+    fn test_capabilities_partial_json_uses_defaults() {
+        let caps: MediaCapabilities = serde_json::from_str(r#"{"screen":true}"#).unwrap();
+        assert_eq!(caps, MediaCapabilities { audio: true, video: true, screen: true });
+        let request: JoinRtcSessionRequest = serde_json::from_str(r#"{"name":"A"}"#).unwrap();
+        assert_eq!(request.capabilities, MediaCapabilities::default());
     }
 
     #[tokio::test]
-    async fn test_get_session_not_found() {
-        let app = create_test_app();
+    async fn test_update_capabilities_handler() {
+        let state = test_state();
+        state.rtc_sessions.create("caps-h".into(), "a".into(), "c".into(), "t".into(), 1).await.unwrap();
+        let app = Router::new()
+            .route("/api/rtc-sessions/:id/join", post(join_rtc_session_handler))
+            .route(
+                "/api/rtc-sessions/:id/participants/:uid/capabilities",
+                axum::routing::put(update_capabilities_handler),
+            )
+            .with_state(state.clone());
 
         let response = app
-            .oneshot(
-                Request::builder()
-                    .uri("/api/rtc-sessions/nonexistent")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
+            .clone()
+            .oneshot(authed(
+                "POST",
+                "/api/rtc-sessions/caps-h/join",
+                None,
+                r#"{"name":"Alice","capabilities":{"video":false}}"#,
+            ))
             .await
             .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let joined: JoinRtcSessionResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            joined.capabilities,
+            MediaCapabilities { audio: true, video: false, screen: false }
+        );
 
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let uri = format!("/api/rtc-sessions/caps-h/participants/{}/capabilities", joined.uid);
+        let update = |client_id: &str| {
+            format!(
+                r#"{{"client_id":"{}","capabilities":{{"audio":true,"video":true,"screen":true}}}}"#,
+                client_id
+            )
+        };
+
+        let response = app.clone().oneshot(authed("PUT", &uri, None, &update("wrong"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = app.clone().oneshot(authed("PUT", &uri, None, &update(&joined.client_id))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let participant: Participant = serde_json::from_slice(&body).unwrap();
+        assert!(participant.capabilities.screen);
+
+        // Listing reflects the update
+        let session = state.rtc_sessions.get("caps-h").await.unwrap();
+        assert_eq!(
+            session.participants[0].capabilities,
+            MediaCapabilities { audio: true, video: true, screen: true }
+        );
+
+        // Another participant's uid with this client id is rejected
+        let other = state.rtc_sessions.join("caps-h", "Bob".into()).await.unwrap();
+        let uri = format!("/api/rtc-sessions/caps-h/participants/{}/capabilities", other.uid);
+        let response = app.oneshot(authed("PUT", &uri, None, &update(&joined.client_id))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
     }
 
-    #[tokio::test]
-    async fn test_join_session_handler() {
-        let state = AppState {
+    fn host_transfer_app(state: AppState) -> Router {
+        Router::new()
+            .route("/api/rtc-sessions/:id", delete(delete_rtc_session_handler))
+            .route("/api/rtc-sessions/:id/transfer-host", post(transfer_host_handler))
+            .route("/api/rtc-sessions/:id/host-heartbeat", post(host_heartbeat_handler))
+            .with_state(state)
+    }
+
+    fn authed(method: &str, uri: &str, token: Option<&str>, body: &str) -> Request<Body> {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Content-Type", "application/json");
+        if let Some(token) = token {
+            builder = builder.header("Authorization", format!("Bearer {}", token));
+        }
+        builder.body(Body::from(body.to_string())).unwrap()
+    }
+
+    fn test_state() -> AppState {
+        AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
-        };
-        state
-            .rtc_sessions
-            .create("join-h".into(), "app1".into(), "room1".into(), "tok1".into(), 42)
-            .await;
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        }
+    }
 
-        let app = Router::new()
-            .route(
-                "/api/rtc-sessions/:id/join",
-                post(join_rtc_session_handler),
-            )
-            .with_state(state);
+    #[tokio::test]
+    async fn test_transfer_host_rotates_token() {
+        let state = test_state();
+        let created = state
+            .rtc_sessions
+            .create("xfer".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await
+            .unwrap();
+        state.rtc_sessions.join_as_host("xfer", "Host".into()).await.unwrap();
+        let bob = state.rtc_sessions.join("xfer", "Bob".into()).await.unwrap();
+        let app = host_transfer_app(state.clone());
+        let body = format!(r#"{{"uid":{}}}"#, bob.uid);
 
         let response = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/rtc-sessions/join-h/join")
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(r#"{"name":"Alice"}"#))
-                    .unwrap(),
-            )
+            .clone()
+            .oneshot(authed("POST", "/api/rtc-sessions/xfer/transfer-host", None, &body))
             .await
             .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 
+        let response = app
+            .clone()
+            .oneshot(authed("POST", "/api/rtc-sessions/xfer/transfer-host", Some(&created.host_token), &body))
+            .await
+            .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
         let body = axum::body::to_bytes(response.into_body(), usize::MAX)
             .await
             .unwrap();
-        let resp: JoinRtcSessionResponse = serde_json::from_slice(&body).unwrap();
-        assert_eq!(resp.app_id, "app1");
-        assert_eq!(resp.channel, "room1");
-        assert_eq!(resp.token, "tok1");
-        assert_eq!(resp.uid, 1000);
-        assert_eq!(resp.name, "Alice");
-    }
+        let transferred: TransferHostResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(transferred.host_uid, bob.uid);
+        assert_ne!(transferred.host_token, created.host_token);
 
-    #[tokio::test]
-    async fn test_join_session_not_found() {
-        let app = create_test_app();
+        let session = state.rtc_sessions.get("xfer").await.unwrap();
+        assert_eq!(session.host_uid, bob.uid);
+        let role_of = |uid: u32| session.participants.iter().find(|p| p.uid == uid).unwrap().role;
+        assert_eq!(role_of(bob.uid), ParticipantRole::Host);
+        assert_eq!(role_of(1), ParticipantRole::Cohost);
 
+        // Old token is rejected everywhere
         let response = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/rtc-sessions/nope/join")
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(r#"{"name":"Alice"}"#))
-                    .unwrap(),
-            )
+            .clone()
+            .oneshot(authed("POST", "/api/rtc-sessions/xfer/host-heartbeat", Some(&created.host_token), ""))
             .await
             .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app
+            .clone()
+            .oneshot(authed("DELETE", "/api/rtc-sessions/xfer", Some(&created.host_token), ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
 
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        // Deleting needs a token at all
+        let response = app
+            .clone()
+            .oneshot(authed("DELETE", "/api/rtc-sessions/xfer", None, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // New token can delete
+        let response = app
+            .oneshot(authed("DELETE", "/api/rtc-sessions/xfer", Some(&transferred.host_token), ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(state.rtc_sessions.get("xfer").await.is_none());
     }
 
     #[tokio::test]
-    async fn test_delete_session_handler() {
-        let state = AppState {
-            sessions: SessionStore::new(),
-            relay: RelayHub::new(),
-            rtc_sessions: RtcSessionStore::new(),
-            session_verify_cache: SessionVerifyCache::new(),
-            voice_sessions: VoiceSessionStore::new(),
-        };
-        state
-            .rtc_sessions
-            .create("del-h".into(), "a".into(), "c".into(), "t".into(), 1)
-            .await;
+    async fn test_transfer_host_errors() {
+        let store = RtcSessionStore::new();
+        let created = store.create("xe".into(), "a".into(), "c".into(), "t".into(), 1).await.unwrap();
+        let token = HostAuth::HostToken(&created.host_token);
+        store.join_as_host("xe", "Host".into()).await.unwrap();
+        let bob = store.join("xe", "Bob".into()).await.unwrap();
+        assert_eq!(store.transfer_host("xe", 1, token).await.unwrap_err(), TransferHostError::AlreadyHost);
+        assert_eq!(
+            store.transfer_host("xe", 4242, token).await.unwrap_err(),
+            TransferHostError::ParticipantNotFound
+        );
+        assert_eq!(
+            store.transfer_host("xe", bob.uid, HostAuth::HostToken("stale")).await.unwrap_err(),
+            TransferHostError::InvalidToken
+        );
+        assert_eq!(store.transfer_host("nope", 1000, token).await.unwrap_err(), TransferHostError::NotFound);
+    }
 
-        let app = Router::new()
-            .route(
-                "/api/rtc-sessions/:id",
-                delete(delete_rtc_session_handler),
-            )
-            .with_state(state);
+    #[tokio::test]
+    async fn test_transfer_host_by_new_host_uid() {
+        let state = test_state();
+        let created = state
+            .rtc_sessions
+            .create("xfer-new".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await
+            .unwrap();
+        state.rtc_sessions.join_as_host("xfer-new", "Host".into()).await.unwrap();
+        let bob = state.rtc_sessions.join("xfer-new", "Bob".into()).await.unwrap();
+        let app = host_transfer_app(state.clone());
+        let uri = "/api/rtc-sessions/xfer-new/transfer-host";
+        let token = Some(created.host_token.as_str());
 
         let response = app
-            .oneshot(
-                Request::builder()
-                    .method("DELETE")
-                    .uri("/api/rtc-sessions/del-h")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
+            .clone()
+            .oneshot(authed("POST", uri, token, r#"{"new_host_uid":4242}"#))
             .await
             .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
+        let body = format!(r#"{{"new_host_uid":{}}}"#, bob.uid);
+        let response = app.oneshot(authed("POST", uri, token, &body)).await.unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let transferred: TransferHostResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(transferred.host_uid, bob.uid);
+        assert_eq!(state.rtc_sessions.get("xfer-new").await.unwrap().host_uid, bob.uid);
     }
 
     #[tokio::test]
-    async fn test_delete_session_not_found() {
-        let app = create_test_app();
+    async fn test_concurrent_transfers_with_same_token() {
+        let state = test_state();
+        let created = state
+            .rtc_sessions
+            .create("xfer-race".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await
+            .unwrap();
+        state.rtc_sessions.join_as_host("xfer-race", "Host".into()).await.unwrap();
+        let mut uids = Vec::new();
+        for name in ["Bob", "Carol", "Dave"] {
+            uids.push(state.rtc_sessions.join("xfer-race", name.into()).await.unwrap().uid);
+        }
+        let app = host_transfer_app(state.clone());
+
+        let requests = uids.iter().map(|uid| {
+            let app = app.clone();
+            let body = format!(r#"{{"uid":{}}}"#, uid);
+            let token = created.host_token.clone();
+            tokio::spawn(async move {
+                app.oneshot(authed("POST", "/api/rtc-sessions/xfer-race/transfer-host", Some(&token), &body))
+                    .await
+                    .unwrap()
+                    .status()
+            })
+        });
+        let mut statuses = Vec::new();
+        for request in requests.collect::<Vec<_>>() {
+            statuses.push(request.await.unwrap());
+        }
+
+        // The token rotates on the first transfer, so exactly one wins
+        assert_eq!(statuses.iter().filter(|s| **s == StatusCode::OK).count(), 1);
+        assert!(statuses
+            .iter()
+            .all(|s| *s == StatusCode::OK || *s == StatusCode::FORBIDDEN));
+        let session = state.rtc_sessions.get("xfer-race").await.unwrap();
+        let hosts: Vec<_> = session
+            .participants
+            .iter()
+            .filter(|p| p.role == ParticipantRole::Host)
+            .map(|p| p.uid)
+            .collect();
+        assert_eq!(hosts, [session.host_uid]);
+        assert!(uids.contains(&session.host_uid));
+    }
+
+    #[tokio::test]
+    async fn test_claim_host_only_after_stale_window() {
+        let state = test_state();
+        let created = state
+            .rtc_sessions
+            .create("claim".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await
+            .unwrap();
+        state.rtc_sessions.join_as_host("claim", "Host".into()).await.unwrap();
+        let carol = state.rtc_sessions.join("claim", "Carol".into()).await.unwrap();
+        let dave = state.rtc_sessions.join("claim", "Dave".into()).await.unwrap();
+        for uid in [carol.uid, dave.uid] {
+            state
+                .rtc_sessions
+                .set_participant_role("claim", uid, ParticipantRole::Cohost)
+                .await
+                .unwrap();
+        }
+        let app = host_transfer_app(state.clone());
+        let claim_with = |uid: u32, client_id: Option<&str>| {
+            let mut body = serde_json::json!({ "uid": uid, "claim": true });
+            if let Some(client_id) = client_id {
+                body["client_id"] = client_id.into();
+            }
+            authed("POST", "/api/rtc-sessions/claim/transfer-host", None, &body.to_string())
+        };
+        let client_ids: HashMap<u32, String> =
+            [(carol.uid, carol.client_id.clone()), (dave.uid, dave.client_id.clone())].into();
+        let claim = |uid: u32| claim_with(uid, Some(&client_ids[&uid]));
+        let error_code = |body: &[u8]| -> Option<String> {
+            serde_json::from_slice::<RtcSessionError>(body).unwrap().code
+        };
 
+        // Host is fresh: claim refused
+        let response = app.clone().oneshot(claim(carol.uid)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(error_code(&body).as_deref(), Some("host_active"));
+
+        // A heartbeat keeps the host fresh; then let it go stale
         let response = app
-            .oneshot(
-                Request::builder()
-                    .method("DELETE")
-                    .uri("/api/rtc-sessions/nope")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
+            .clone()
+            .oneshot(authed("POST", "/api/rtc-sessions/claim/host-heartbeat", Some(&created.host_token), ""))
             .await
             .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        state.rtc_sessions.sessions.read().await["claim"].write().await.host_seen_at =
+            Utc::now() - Duration::minutes(HOST_STALE_AFTER_MINS + 1);
+
+        // A claim needs the claimant's own client id
+        let response = app.clone().oneshot(claim_with(carol.uid, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response =
+            app.clone().oneshot(claim_with(carol.uid, Some(&dave.client_id))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(error_code(&body).as_deref(), Some("invalid_client_id"));
+
+        // Only the longest-present co-host may claim
+        let response = app.clone().oneshot(claim(dave.uid)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(error_code(&body).as_deref(), Some("not_eligible"));
+
+        let response = app.clone().oneshot(claim(carol.uid)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let claimed: TransferHostResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(claimed.host_uid, carol.uid);
+        assert!(state.rtc_sessions.verify_host_token("claim", &claimed.host_token).await);
+        assert!(!state.rtc_sessions.verify_host_token("claim", &created.host_token).await);
+
+        // The new host is fresh again, so a second claim is refused
+        let response = app.oneshot(claim(dave.uid)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
 
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    #[tokio::test]
+    async fn test_max_participants_enforced() {
+        let store = RtcSessionStore::new();
+        store
+            .create("full-test".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await.unwrap();
+
+        // Join 8 people successfully
+        for i in 0..8 {
+            let result = store.join("full-test", format!("User{}", i)).await;
+            assert!(result.is_ok(), "User {} should join successfully", i);
+        }
+
+        // 9th person should fail
+        let result = store.join("full-test", "User9".into()).await;
+        assert_eq!(result.unwrap_err(), JoinError::Full { max: MAX_PARTICIPANTS });
     }
 
     #[tokio::test]
-    async fn test_full_lifecycle() {
+    async fn test_delete_notifies_paired_astation() {
         let state = AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         };
+        let mut astation_rx = state.relay.connect_test_side("ABCD-EFGH", Role::Astation).await;
+        let owner_token = state.relay.owner_token("ABCD-EFGH").await;
         let app = Router::new()
             .route("/api/rtc-sessions", post(create_rtc_session_handler))
-            .route("/api/rtc-sessions/:id", get(get_rtc_session_handler))
-            .route(
-                "/api/rtc-sessions/:id/join",
-                post(join_rtc_session_handler),
-            )
-            .route(
-                "/api/rtc-sessions/:id",
-                delete(delete_rtc_session_handler),
-            )
-            .with_state(state);
+            .route("/api/rtc-sessions/:id", delete(delete_rtc_session_handler))
+            .with_state(state.clone());
+        let create = |pair_owner_token: Option<&str>| {
+            let mut body = serde_json::json!({
+                "app_id": "a", "channel": "c", "token": "t", "host_uid": 1, "pair_code": "abcd efgh",
+            });
+            if let Some(token) = pair_owner_token {
+                body["pair_owner_token"] = token.into();
+            }
+            authed("POST", "/api/rtc-sessions", None, &body.to_string())
+        };
+
+        // Naming a pair room needs its owner token
+        let response = app.clone().oneshot(create(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app.clone().oneshot(create(Some("not-the-owner"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let error: RtcSessionError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code.as_deref(), Some("invalid_pair_owner_token"));
 
-        // Step 1: Create
         let response = app
             .clone()
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/rtc-sessions")
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(
-                        r#"{"app_id":"app1","channel":"room","token":"tok","host_uid":5678}"#,
-                    ))
-                    .unwrap(),
-            )
+            .oneshot(create(Some(&owner_token)))
             .await
             .unwrap();
         assert_eq!(response.status(), StatusCode::CREATED);
@@ -864,14 +3365,14 @@ mod tests {
             .await
             .unwrap();
         let created: CreateRtcSessionResponse = serde_json::from_slice(&body).unwrap();
-        let session_id = created.id;
+        assert!(astation_rx.try_recv().is_err());
 
-        // Step 2: Get
         let response = app
-            .clone()
             .oneshot(
                 Request::builder()
-                    .uri(format!("/api/rtc-sessions/{}", session_id))
+                    .method("DELETE")
+                    .uri(format!("/api/rtc-sessions/{}", created.id))
+                    .header("Authorization", format!("Bearer {}", created.host_token))
                     .body(Body::empty())
                     .unwrap(),
             )
@@ -879,53 +3380,188 @@ mod tests {
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
 
-        // Step 3: Join (uid 1000)
-        let response = app
-            .clone()
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri(format!("/api/rtc-sessions/{}/join", session_id))
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(r#"{"name":"Alice"}"#))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
-        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
-            .await
-            .unwrap();
-        let join1: JoinRtcSessionResponse = serde_json::from_slice(&body).unwrap();
-        assert_eq!(join1.uid, 1000);
+        let message: serde_json::Value =
+            serde_json::from_str(&astation_rx.try_recv().unwrap()).unwrap();
+        assert_eq!(
+            message,
+            serde_json::json!({ "type": "rtc_session_deleted", "id": created.id })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_without_pair_code_sends_nothing() {
+        let state = AppState {
+            sessions: SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        let mut astation_rx = state.relay.connect_test_side("ABCD-EFGH", Role::Astation).await;
+        let created = state
+            .rtc_sessions
+            .create("plain".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await.unwrap();
+        let app = Router::new()
+            .route("/api/rtc-sessions/:id", delete(delete_rtc_session_handler))
+            .with_state(state);
 
-        // Step 4: Join again (uid 1001)
         let response = app
-            .clone()
             .oneshot(
                 Request::builder()
-                    .method("POST")
-                    .uri(format!("/api/rtc-sessions/{}/join", session_id))
-                    .header("Content-Type", "application/json")
-                    .body(Body::from(r#"{"name":"Bob"}"#))
+                    .method("DELETE")
+                    .uri("/api/rtc-sessions/plain")
+                    .header("Authorization", format!("Bearer {}", created.host_token))
+                    .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        assert!(astation_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_capacity_limit_is_exact() {
+        let store = RtcSessionStore::new().with_max_sessions(3);
+        for i in 0..3 {
+            store
+                .create(format!("cap-{}", i), "app".into(), "ch".into(), "tok".into(), 1)
+                .await
+                .unwrap();
+        }
+        assert_eq!(store.capacity_used().await, 3);
+        assert_eq!(
+            store
+                .create("cap-3".into(), "app".into(), "ch".into(), "tok".into(), 1)
+                .await
+                .unwrap_err(),
+            CreateError::AtCapacity { max: 3 }
+        );
+        assert_eq!(store.capacity_used().await, 3);
+        assert!(store.get("cap-3").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_delete_frees_capacity() {
+        let store = RtcSessionStore::new().with_max_sessions(1);
+        store
+            .create("one".into(), "app".into(), "ch".into(), "tok".into(), 1)
+            .await
+            .unwrap();
+        assert!(store
+            .create("two".into(), "app".into(), "ch".into(), "tok".into(), 1)
+            .await
+            .is_err());
+        store.delete("one").await;
+        assert!(store
+            .create("two".into(), "app".into(), "ch".into(), "tok".into(), 1)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_default_capacity_allows_normal_use() {
+        let store = RtcSessionStore::new();
+        assert_eq!(store.max_sessions(), DEFAULT_MAX_SESSIONS);
+        for i in 0..50 {
+            store
+                .create(format!("s-{}", i), "app".into(), "ch".into(), "tok".into(), 1)
+                .await
+                .unwrap();
+        }
+        assert_eq!(store.capacity_used().await, 50);
+    }
+
+    #[tokio::test]
+    async fn test_app_id_quota() {
+        let store = RtcSessionStore::new().with_max_sessions_per_app_id(2);
+        store.create("q1".into(), "app-a".into(), "ch".into(), "tok".into(), 1).await.unwrap();
+        store.create("q2".into(), "app-a".into(), "ch".into(), "tok".into(), 1).await.unwrap();
+        assert_eq!(
+            store
+                .create("q3".into(), "app-a".into(), "ch".into(), "tok".into(), 1)
+                .await
+                .unwrap_err(),
+            CreateError::AppQuotaExceeded { count: 2, max: 2 }
+        );
+        // Other app ids are unaffected
+        store.create("b1".into(), "app-b".into(), "ch".into(), "tok".into(), 1).await.unwrap();
+
+        store.delete("q1").await;
+        assert_eq!(store.app_session_count("app-a").await, 1);
+        store.create("q3".into(), "app-a".into(), "ch".into(), "tok".into(), 1).await.unwrap();
+        assert_eq!(store.app_session_count("app-a").await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_releases_app_id_quota() {
+        let store = RtcSessionStore::new().with_max_sessions_per_app_id(1);
+        store.create("old".into(), "app".into(), "ch".into(), "tok".into(), 1).await.unwrap();
+        expire_now(&store, "old").await;
+
+        store.cleanup_expired().await;
+        assert_eq!(store.app_session_count("app").await, 0);
+        store.create("new".into(), "app".into(), "ch".into(), "tok".into(), 1).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_create_handler_app_quota_returns_429() {
+        let state = AppState {
+            sessions: SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new().with_max_sessions_per_app_id(2),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        let app = Router::new()
+            .route("/api/rtc-sessions", post(create_rtc_session_handler))
+            .route("/api/rtc-sessions/:id", delete(delete_rtc_session_handler))
+            .with_state(state);
+        let create = || {
+            Request::builder()
+                .method("POST")
+                .uri("/api/rtc-sessions")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"app_id":"loop","channel":"c","token":"t","host_uid":1}"#))
+                .unwrap()
+        };
+
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let response = app.clone().oneshot(create()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::CREATED);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let created: CreateRtcSessionResponse = serde_json::from_slice(&body).unwrap();
+            ids.push((created.id, created.host_token));
+        }
+
+        let response = app.clone().oneshot(create()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
         let body = axum::body::to_bytes(response.into_body(), usize::MAX)
             .await
             .unwrap();
-        let join2: JoinRtcSessionResponse = serde_json::from_slice(&body).unwrap();
-        assert_eq!(join2.uid, 1001);
+        let error: RtcSessionError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code.as_deref(), Some("app_quota_exceeded"));
+        assert_eq!(error.session_count, Some(2));
 
-        // Step 5: Delete
         let response = app
             .clone()
             .oneshot(
                 Request::builder()
                     .method("DELETE")
-                    .uri(format!("/api/rtc-sessions/{}", session_id))
+                    .uri(format!("/api/rtc-sessions/{}", ids[0].0))
+                    .header("Authorization", format!("Bearer {}", ids[0].1))
                     .body(Body::empty())
                     .unwrap(),
             )
@@ -933,93 +3569,69 @@ mod tests {
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
 
-        // Step 6: Get after delete → 404
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .uri(format!("/api/rtc-sessions/{}", session_id))
-                    .body(Body::empty())
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = app.oneshot(create()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
     }
 
     #[tokio::test]
-    async fn test_join_session_full_handler() {
+    async fn test_create_handler_at_capacity_returns_503() {
         let state = AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
-            rtc_sessions: RtcSessionStore::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new().with_max_sessions(0),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         };
-        state
-            .rtc_sessions
-            .create("full-h".into(), "app1".into(), "room1".into(), "tok1".into(), 42)
-            .await;
-
-        // Fill session to capacity (8 participants)
-        for i in 0..8 {
-            state.rtc_sessions.join("full-h", format!("User{}", i)).await.unwrap();
-        }
-
         let app = Router::new()
-            .route(
-                "/api/rtc-sessions/:id/join",
-                post(join_rtc_session_handler),
-            )
+            .route("/api/rtc-sessions", post(create_rtc_session_handler))
             .with_state(state);
 
-        // 9th person should get 409 Conflict
         let response = app
             .oneshot(
                 Request::builder()
                     .method("POST")
-                    .uri("/api/rtc-sessions/full-h/join")
+                    .uri("/api/rtc-sessions")
                     .header("Content-Type", "application/json")
-                    .body(Body::from(r#"{"name":"User9"}"#))
+                    .body(Body::from(r#"{"app_id":"a","channel":"c","token":"t","host_uid":1}"#))
                     .unwrap(),
             )
             .await
             .unwrap();
-
-        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
         let body = axum::body::to_bytes(response.into_body(), usize::MAX)
             .await
             .unwrap();
         let error: RtcSessionError = serde_json::from_slice(&body).unwrap();
-        assert!(error.error.contains("full"));
+        assert_eq!(error.code.as_deref(), Some("server_at_capacity"));
     }
 
-    #[tokio::test]
-    async fn test_concurrent_cleanup_and_join() {
-        let store = RtcSessionStore::new();
-        store
-            .create("race-test".into(), "a".into(), "c".into(), "t".into(), 1)
-            .await;
-
-        // Spawn concurrent operations: cleanup and join
-        let store1 = store.clone();
-        let cleanup_task = tokio::spawn(async move {
-            store1.cleanup_expired().await;
-        });
-
-        let store2 = store.clone();
-        let join_task = tokio::spawn(async move {
-            store2.join("race-test", "RaceUser".into()).await
-        });
-
-        let _ = tokio::join!(cleanup_task, join_task);
-
-        // Session should still exist and have at least one participant
-        let session = store.get("race-test").await;
-        assert!(session.is_some(), "Session should not be cleaned up while active");
+    #[test]
+    fn test_join_error_mapping() {
+        let cases = [
+            (JoinError::NotFound, StatusCode::NOT_FOUND, "session_not_found"),
+            (JoinError::Full { max: 8 }, StatusCode::CONFLICT, "session_full"),
+            (JoinError::Expired, StatusCode::GONE, "session_expired"),
+            (JoinError::Locked, StatusCode::CONFLICT, "session_locked"),
+            (JoinError::NameTaken, StatusCode::CONFLICT, "display_name_taken"),
+        ];
+        for (error, status, code) in cases {
+            assert_eq!(error.status(), status);
+            assert_eq!(error.code(), code);
+        }
+        assert_eq!(
+            JoinError::Full { max: 8 }.message(),
+            "Session is full (maximum 8 participants)"
+        );
     }
 
+    // --- Handler Tests ---
+
     #[tokio::test]
-    async fn test_session_url_format() {
+    async fn test_create_session_handler() {
         let app = create_test_app();
 
         let response = app
@@ -1037,71 +3649,1490 @@ mod tests {
             .await
             .unwrap();
 
+        assert_eq!(response.status(), StatusCode::CREATED);
         let body = axum::body::to_bytes(response.into_body(), usize::MAX)
             .await
             .unwrap();
         let resp: CreateRtcSessionResponse = serde_json::from_slice(&body).unwrap();
-
+        assert!(!resp.id.is_empty());
         assert!(resp.url.starts_with("https://station.agora.build/session/"));
-        assert!(resp.url.contains(&resp.id));
-        assert!(uuid::Uuid::parse_str(&resp.id).is_ok(), "Session ID should be valid UUID");
     }
 
     #[tokio::test]
-    async fn test_participant_names_persistence() {
-        let store = RtcSessionStore::new();
-        store
-            .create("name-test".into(), "app".into(), "ch".into(), "tok".into(), 1)
-            .await;
-
-        // Join multiple users
-        store.join("name-test", "Alice".into()).await.unwrap();
-        store.join("name-test", "Bob".into()).await.unwrap();
-        store.join("name-test", "Charlie".into()).await.unwrap();
+    async fn test_create_session_missing_fields() {
+        let app = create_test_app();
 
-        let session = store.get("name-test").await.unwrap();
-        assert_eq!(session.participants.len(), 3);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/rtc-sessions")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"app_id":"app1"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
 
-        let names: Vec<String> = session.participants.iter()
-            .filter_map(|p| p.display_name.clone())
-            .collect();
-        assert_eq!(names, vec!["Alice", "Bob", "Charlie"]);
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
     }
 
     #[tokio::test]
-    async fn test_delete_session_with_participants() {
-        let store = RtcSessionStore::new();
-        store
-            .create("del-part".into(), "app".into(), "ch".into(), "tok".into(), 1)
-            .await;
+    async fn test_get_session_handler() {
+        let state = AppState {
+            sessions: SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        state
+            .rtc_sessions
+            .create("get-test".into(), "app1".into(), "room1".into(), "tok".into(), 99)
+            .await.unwrap();
 
-        // Add participants
-        store.join("del-part", "User1".into()).await.unwrap();
-        store.join("del-part", "User2".into()).await.unwrap();
+        let app = Router::new()
+            .route("/api/rtc-sessions/:id", get(get_rtc_session_handler))
+            .with_state(state);
 
-        // Delete should succeed even with participants
-        assert!(store.delete("del-part").await);
-        assert!(store.get("del-part").await.is_none());
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/rtc-sessions/get-test")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let resp: GetRtcSessionResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(resp.app_id, "app1");
+        assert_eq!(resp.channel, "room1");
+        assert_eq!(resp.host_uid, 99);
     }
 
     #[tokio::test]
-    async fn test_cleanup_does_not_remove_active_sessions_with_participants() {
-        let store = RtcSessionStore::new();
-
-        // Create session (not expired)
-        store
-            .create("active-with-parts".into(), "a".into(), "c".into(), "t".into(), 1)
-            .await;
+    async fn test_get_participants_in_join_order() {
+        let state = test_state();
+        state
+            .rtc_sessions
+            .create("roster".into(), "app1".into(), "room1".into(), "secret-rtc-token".into(), 1)
+            .await
+            .unwrap();
+        for name in ["Alice", "Bob", "Carol"] {
+            state.rtc_sessions.join("roster", name.into()).await.unwrap();
+        }
+        let app = Router::new()
+            .route("/api/rtc-sessions/:id/participants", get(get_participants_handler))
+            .with_state(state);
 
-        // Add participants
-        store.join("active-with-parts", "User1".into()).await.unwrap();
-        store.join("active-with-parts", "User2".into()).await.unwrap();
+        let response = app
+            .clone()
+            .oneshot(authed("GET", "/api/rtc-sessions/roster/participants", None, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["count"], 3);
+        assert_eq!(json["capacity"], MAX_PARTICIPANTS);
+        let first = &json["participants"][0];
+        assert_eq!(first["uid"], 1000);
+        assert_eq!(first["display_name"], "Alice");
+        assert!(first["role"].is_string());
+        assert!(first["joined_at"].is_string());
+        let names: Vec<&str> = json["participants"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p["display_name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["Alice", "Bob", "Carol"]);
+        assert!(!String::from_utf8_lossy(&body).contains("secret-rtc-token"));
 
-        store.cleanup_expired().await;
+        let response = app
+            .oneshot(authed("GET", "/api/rtc-sessions/missing/participants", None, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
 
-        // Should still exist
+    #[tokio::test]
+    async fn test_get_session_include_participants() {
+        let state = test_state();
+        state
+            .rtc_sessions
+            .create("inc".into(), "app1".into(), "room1".into(), "secret-rtc-token".into(), 1)
+            .await
+            .unwrap();
+        state.rtc_sessions.join("inc", "Alice".into()).await.unwrap();
+        state.rtc_sessions.join("inc", "Bob".into()).await.unwrap();
+        let app = Router::new()
+            .route("/api/rtc-sessions/:id", get(get_rtc_session_handler))
+            .with_state(state);
+
+        let fetch = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(authed("GET", uri, None, "")).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                String::from_utf8(body.to_vec()).unwrap()
+            }
+        };
+
+        let plain = fetch("/api/rtc-sessions/inc").await;
+        let json: serde_json::Value = serde_json::from_str(&plain).unwrap();
+        for field in ["participants", "participant_count", "max_participants"] {
+            assert!(json.get(field).is_none(), "{} should be omitted", field);
+        }
+
+        let expanded = fetch("/api/rtc-sessions/inc?include=participants").await;
+        let resp: GetRtcSessionResponse = serde_json::from_str(&expanded).unwrap();
+        assert_eq!(resp.participant_count, Some(2));
+        assert_eq!(resp.max_participants, Some(MAX_PARTICIPANTS));
+        let participants = resp.participants.unwrap();
+        assert_eq!(participants[0].uid, 1000);
+        assert_eq!(participants[1].display_name.as_deref(), Some("Bob"));
+
+        for body in [plain, expanded] {
+            assert!(!body.contains("secret-rtc-token"), "{}", body);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_session_not_found() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/rtc-sessions/nonexistent")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_join_session_handler() {
+        let state = AppState {
+            sessions: SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        state
+            .rtc_sessions
+            .create("join-h".into(), "app1".into(), "room1".into(), "tok1".into(), 42)
+            .await.unwrap();
+
+        let app = Router::new()
+            .route(
+                "/api/rtc-sessions/:id/join",
+                post(join_rtc_session_handler),
+            )
+            .with_state(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/rtc-sessions/join-h/join")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"name":"Alice"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let resp: JoinRtcSessionResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(resp.app_id, "app1");
+        assert_eq!(resp.channel, "room1");
+        assert_eq!(resp.token, "tok1");
+        assert_eq!(resp.uid, 1000);
+        assert_eq!(resp.name, "Alice");
+    }
+
+    #[tokio::test]
+    async fn test_join_session_not_found() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/rtc-sessions/nope/join")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"name":"Alice"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let error: RtcSessionError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code.as_deref(), Some("session_not_found"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_session_handler() {
+        let state = AppState {
+            sessions: SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        let created = state
+            .rtc_sessions
+            .create("del-h".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await.unwrap();
+
+        let app = Router::new()
+            .route(
+                "/api/rtc-sessions/:id",
+                delete(delete_rtc_session_handler),
+            )
+            .with_state(state);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/api/rtc-sessions/del-h")
+                    .header("Authorization", format!("Bearer {}", created.host_token))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_delete_expired_session_still_needs_host_token() {
+        let state = test_state();
+        let created = state
+            .rtc_sessions
+            .create("del-x".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await.unwrap();
+        expire_now(&state.rtc_sessions, "del-x").await;
+        let rtc_sessions = state.rtc_sessions.clone();
+        let app = Router::new()
+            .route("/api/rtc-sessions/:id", delete(delete_rtc_session_handler))
+            .with_state(state);
+        let status = |token: Option<&str>| {
+            let app = app.clone();
+            let request = authed("DELETE", "/api/rtc-sessions/del-x", token, "");
+            async move { app.oneshot(request).await.unwrap().status() }
+        };
+
+        assert_eq!(status(None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(Some("wrong")).await, StatusCode::FORBIDDEN);
+        assert!(matches!(rtc_sessions.lookup_meta("del-x").await, RtcSessionLookup::Expired { .. }));
+        assert!(rtc_sessions.sessions.read().await.contains_key("del-x"), "not deleted");
+        assert_eq!(status(Some(&created.host_token)).await, StatusCode::OK);
+        assert!(!rtc_sessions.sessions.read().await.contains_key("del-x"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_session_not_found() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/api/rtc-sessions/nope")
+                    .header("Authorization", "Bearer any")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_full_lifecycle() {
+        let state = AppState {
+            sessions: SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        let app = Router::new()
+            .route("/api/rtc-sessions", post(create_rtc_session_handler))
+            .route("/api/rtc-sessions/:id", get(get_rtc_session_handler))
+            .route(
+                "/api/rtc-sessions/:id/join",
+                post(join_rtc_session_handler),
+            )
+            .route(
+                "/api/rtc-sessions/:id",
+                delete(delete_rtc_session_handler),
+            )
+            .with_state(state);
+
+        // Step 1: Create
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/rtc-sessions")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(
+                        r#"{"app_id":"app1","channel":"room","token":"tok","host_uid":5678}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let created: CreateRtcSessionResponse = serde_json::from_slice(&body).unwrap();
+        let session_id = created.id;
+        let host_token = created.host_token;
+
+        // Step 2: Get
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/rtc-sessions/{}", session_id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Step 3: Join (uid 1000)
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/api/rtc-sessions/{}/join", session_id))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"name":"Alice"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let join1: JoinRtcSessionResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(join1.uid, 1000);
+
+        // Step 4: Join again (uid 1001)
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/api/rtc-sessions/{}/join", session_id))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"name":"Bob"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let join2: JoinRtcSessionResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(join2.uid, 1001);
+
+        // Step 5: Delete
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri(format!("/api/rtc-sessions/{}", session_id))
+                    .header("Authorization", format!("Bearer {}", host_token))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Step 6: Get after delete → 404
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/rtc-sessions/{}", session_id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_join_session_full_handler() {
+        let state = AppState {
+            sessions: SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        state
+            .rtc_sessions
+            .create("full-h".into(), "app1".into(), "room1".into(), "tok1".into(), 42)
+            .await.unwrap();
+
+        // Fill session to capacity (8 participants)
+        for i in 0..8 {
+            state.rtc_sessions.join("full-h", format!("User{}", i)).await.unwrap();
+        }
+
+        let app = Router::new()
+            .route(
+                "/api/rtc-sessions/:id/join",
+                post(join_rtc_session_handler),
+            )
+            .with_state(state);
+
+        // 9th person should get 409 Conflict
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/rtc-sessions/full-h/join")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"name":"User9"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let error: RtcSessionError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code.as_deref(), Some("session_full"));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_cleanup_and_join() {
+        let store = RtcSessionStore::new();
+        store
+            .create("race-test".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await.unwrap();
+
+        // Spawn concurrent operations: cleanup and join
+        let store1 = store.clone();
+        let cleanup_task = tokio::spawn(async move {
+            store1.cleanup_expired().await;
+        });
+
+        let store2 = store.clone();
+        let join_task = tokio::spawn(async move {
+            store2.join("race-test", "RaceUser".into()).await
+        });
+
+        let _ = tokio::join!(cleanup_task, join_task);
+
+        // Session should still exist and have at least one participant
+        let session = store.get("race-test").await;
+        assert!(session.is_some(), "Session should not be cleaned up while active");
+    }
+
+    #[tokio::test]
+    async fn test_session_url_format() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/rtc-sessions")
+                    .header("Content-Type", "application/json")
+                    .header("Host", "station.agora.build")
+                    .body(Body::from(
+                        r#"{"app_id":"app1","channel":"room","token":"tok","host_uid":5678}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let resp: CreateRtcSessionResponse = serde_json::from_slice(&body).unwrap();
+
+        assert!(resp.url.starts_with("https://station.agora.build/session/"));
+        assert!(resp.url.contains(&resp.id));
+        assert!(uuid::Uuid::parse_str(&resp.id).is_ok(), "Session ID should be valid UUID");
+    }
+
+    #[tokio::test]
+    async fn test_participant_names_persistence() {
+        let store = RtcSessionStore::new();
+        store
+            .create("name-test".into(), "app".into(), "ch".into(), "tok".into(), 1)
+            .await.unwrap();
+
+        // Join multiple users
+        store.join("name-test", "Alice".into()).await.unwrap();
+        store.join("name-test", "Bob".into()).await.unwrap();
+        store.join("name-test", "Charlie".into()).await.unwrap();
+
+        let session = store.get("name-test").await.unwrap();
+        assert_eq!(session.participants.len(), 3);
+
+        let names: Vec<String> = session.participants.iter()
+            .filter_map(|p| p.display_name.clone())
+            .collect();
+        assert_eq!(names, vec!["Alice", "Bob", "Charlie"]);
+    }
+
+    #[tokio::test]
+    async fn test_delete_session_with_participants() {
+        let store = RtcSessionStore::new();
+        store
+            .create("del-part".into(), "app".into(), "ch".into(), "tok".into(), 1)
+            .await.unwrap();
+
+        // Add participants
+        store.join("del-part", "User1".into()).await.unwrap();
+        store.join("del-part", "User2".into()).await.unwrap();
+
+        // Delete should succeed even with participants
+        assert!(store.delete("del-part").await.is_some());
+        assert!(store.get("del-part").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_does_not_remove_active_sessions_with_participants() {
+        let store = RtcSessionStore::new();
+
+        // Create session (not expired)
+        store
+            .create("active-with-parts".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await.unwrap();
+
+        // Add participants
+        store.join("active-with-parts", "User1".into()).await.unwrap();
+        store.join("active-with-parts", "User2".into()).await.unwrap();
+
+        store.cleanup_expired().await;
+
+        // Should still exist
         let session = store.get("active-with-parts").await;
         assert!(session.is_some());
         assert_eq!(session.unwrap().participants.len(), 2);
     }
+
+    // --- Participant Role Tests ---
+
+    #[tokio::test]
+    async fn test_join_defaults_to_attendee() {
+        let store = RtcSessionStore::new();
+        store
+            .create("role-default".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await.unwrap();
+
+        let resp = store.join("role-default", "Alice".into()).await.unwrap();
+        assert_eq!(resp.role, ParticipantRole::Attendee);
+    }
+
+    #[tokio::test]
+    async fn test_join_as_host_uses_host_uid() {
+        let store = RtcSessionStore::new();
+        store
+            .create("role-host".into(), "a".into(), "c".into(), "t".into(), 42)
+            .await.unwrap();
+
+        let resp = store.join_as_host("role-host", "Host".into()).await.unwrap();
+        assert_eq!(resp.uid, 42);
+        assert_eq!(resp.role, ParticipantRole::Host);
+
+        // Re-joining as host replaces the existing host entry
+        store.join_as_host("role-host", "Host".into()).await.unwrap();
+        let session = store.get("role-host").await.unwrap();
+        assert_eq!(session.participants.len(), 1);
+        assert_eq!(session.participants[0].role, ParticipantRole::Host);
+    }
+
+    #[tokio::test]
+    async fn test_set_participant_role_promote_and_demote() {
+        let store = RtcSessionStore::new();
+        store
+            .create("role-set".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await.unwrap();
+        let alice = store.join("role-set", "Alice".into()).await.unwrap();
+
+        let p = store
+            .set_participant_role("role-set", alice.uid, ParticipantRole::Cohost)
+            .await
+            .unwrap();
+        assert_eq!(p.role, ParticipantRole::Cohost);
+
+        let p = store
+            .set_participant_role("role-set", alice.uid, ParticipantRole::Attendee)
+            .await
+            .unwrap();
+        assert_eq!(p.role, ParticipantRole::Attendee);
+    }
+
+    #[tokio::test]
+    async fn test_set_participant_role_rejects_host_changes() {
+        let store = RtcSessionStore::new();
+        store
+            .create("role-guard".into(), "a".into(), "c".into(), "t".into(), 7)
+            .await.unwrap();
+        store.join_as_host("role-guard", "Host".into()).await.unwrap();
+        let bob = store.join("role-guard", "Bob".into()).await.unwrap();
+
+        assert_eq!(
+            store.set_participant_role("role-guard", 7, ParticipantRole::Attendee).await.unwrap_err(),
+            ParticipantError::HostRoleImmutable
+        );
+        assert_eq!(
+            store.set_participant_role("role-guard", bob.uid, ParticipantRole::Host).await.unwrap_err(),
+            ParticipantError::HostRoleNotAssignable
+        );
+        assert_eq!(
+            store.set_participant_role("role-guard", 9999, ParticipantRole::Cohost).await.unwrap_err(),
+            ParticipantError::ParticipantNotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_role_handler_promotes_with_host_token() {
+        let state = AppState {
+            sessions: SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        let session = state
+            .rtc_sessions
+            .create("role-h".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await.unwrap();
+        let alice = state.rtc_sessions.join("role-h", "Alice".into()).await.unwrap();
+
+        let app = Router::new()
+            .route(
+                "/api/rtc-sessions/:id/participants/:uid/role",
+                post(set_participant_role_handler),
+            )
+            .with_state(state.clone());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/api/rtc-sessions/role-h/participants/{}/role", alice.uid))
+                    .header("Content-Type", "application/json")
+                    .header("Authorization", format!("Bearer {}", session.host_token))
+                    .body(Body::from(r#"{"role":"cohost"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let participant: Participant = serde_json::from_slice(&body).unwrap();
+        assert_eq!(participant.role, ParticipantRole::Cohost);
+
+        let stored = state.rtc_sessions.get("role-h").await.unwrap();
+        assert_eq!(stored.participants[0].role, ParticipantRole::Cohost);
+    }
+
+    #[tokio::test]
+    async fn test_attendee_cannot_change_roles() {
+        let state = AppState {
+            sessions: SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        state
+            .rtc_sessions
+            .create("role-deny".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await.unwrap();
+        let alice = state.rtc_sessions.join("role-deny", "Alice".into()).await.unwrap();
+
+        let app = Router::new()
+            .route(
+                "/api/rtc-sessions/:id/participants/:uid/role",
+                post(set_participant_role_handler),
+            )
+            .with_state(state.clone());
+
+        // No token at all
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/api/rtc-sessions/role-deny/participants/{}/role", alice.uid))
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"role":"cohost"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Attendee guessing a token
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/api/rtc-sessions/role-deny/participants/{}/role", alice.uid))
+                    .header("Content-Type", "application/json")
+                    .header("Authorization", "Bearer not-the-host-token")
+                    .body(Body::from(r#"{"role":"cohost"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let stored = state.rtc_sessions.get("role-deny").await.unwrap();
+        assert_eq!(stored.participants[0].role, ParticipantRole::Attendee);
+    }
+
+    #[tokio::test]
+    async fn test_join_handler_with_host_token_joins_as_host() {
+        let app = create_test_app();
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/rtc-sessions")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(
+                        r#"{"app_id":"app1","channel":"room","token":"tok","host_uid":5678}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let created: CreateRtcSessionResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(created.host_token.len(), 64);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/api/rtc-sessions/{}/join", created.id))
+                    .header("Content-Type", "application/json")
+                    .header("Authorization", format!("Bearer {}", created.host_token))
+                    .body(Body::from(r#"{"name":"Host"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let joined: JoinRtcSessionResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(joined.uid, 5678);
+        assert_eq!(joined.role, ParticipantRole::Host);
+    }
+
+    #[tokio::test]
+    async fn test_host_token_only_in_create_response() {
+        let app = create_test_app();
+        let create = || {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(
+                        Request::builder()
+                            .method("POST")
+                            .uri("/api/rtc-sessions")
+                            .header("Content-Type", "application/json")
+                            .body(Body::from(r#"{"app_id":"app1","channel":"room","token":"tok","host_uid":5678}"#))
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                serde_json::from_slice::<CreateRtcSessionResponse>(&body).unwrap()
+            }
+        };
+        let created = create().await;
+        assert_ne!(create().await.host_token, created.host_token);
+        assert!(created.host_token.chars().all(|c| c.is_ascii_hexdigit()));
+
+        let get = Request::builder()
+            .uri(format!("/api/rtc-sessions/{}", created.id))
+            .body(Body::empty())
+            .unwrap();
+        let join = Request::builder()
+            .method("POST")
+            .uri(format!("/api/rtc-sessions/{}/join", created.id))
+            .header("Content-Type", "application/json")
+            .body(Body::from(r#"{"name":"Guest"}"#))
+            .unwrap();
+        let join_uri = format!("/api/rtc-sessions/{}/join", created.id);
+        let host_join = authed("POST", &join_uri, Some(&created.host_token), r#"{"name":"Host"}"#);
+        for request in [get, join, host_join] {
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let body = String::from_utf8_lossy(&body);
+            assert!(!body.contains(&created.host_token) && !body.contains("host_token"), "{}", body);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_host_token() {
+        let store = RtcSessionStore::new();
+        let created = store
+            .create("verify".into(), "app1".into(), "room1".into(), "tok".into(), 1)
+            .await
+            .unwrap();
+        assert!(store.verify_host_token("verify", &created.host_token).await);
+        assert!(!store.verify_host_token("verify", &created.host_token[..63]).await);
+        assert!(!store.verify_host_token("verify", "").await);
+        assert!(!store.verify_host_token("verify", &crate::auth::generate_session_token()).await);
+        assert!(!store.verify_host_token("missing", &created.host_token).await);
+    }
+
+    // --- Server-side Token Tests ---
+
+    #[test]
+    fn test_token_role_follows_capabilities() {
+        let listen_only = MediaCapabilities { audio: false, video: false, screen: false };
+        assert_eq!(token_role(ParticipantRole::Attendee, listen_only), RtcTokenRole::Subscriber);
+        assert_eq!(
+            token_role(ParticipantRole::Attendee, MediaCapabilities::default()),
+            RtcTokenRole::Publisher
+        );
+        assert_eq!(
+            token_role(ParticipantRole::Attendee, MediaCapabilities { screen: true, ..listen_only }),
+            RtcTokenRole::Publisher
+        );
+        // Hosts and co-hosts may always publish
+        assert_eq!(token_role(ParticipantRole::Host, listen_only), RtcTokenRole::Publisher);
+        assert_eq!(token_role(ParticipantRole::Cohost, listen_only), RtcTokenRole::Publisher);
+    }
+
+    #[tokio::test]
+    async fn test_generated_tokens_differ_per_participant() {
+        let store = RtcSessionStore::new().with_app_certificate(Some("cert".into()));
+        store
+            .create_with_options(
+                "gen".into(),
+                "app".into(),
+                "ch".into(),
+                String::new(),
+                1,
+                RtcSessionOptions { generate_tokens: true, ..Default::default() },
+            )
+            .await.unwrap();
+
+        let a = store.join("gen", "Alice".into()).await.unwrap();
+        let b = store.join("gen", "Bob".into()).await.unwrap();
+        assert!(a.token.starts_with("007"));
+        assert!(b.token.starts_with("007"));
+        assert_ne!(a.token, b.token);
+    }
+
+    #[tokio::test]
+    async fn test_passthrough_token_when_generation_disabled() {
+        let store = RtcSessionStore::new().with_app_certificate(Some("cert".into()));
+        store
+            .create("legacy".into(), "app".into(), "ch".into(), "shared".into(), 1)
+            .await.unwrap();
+
+        let a = store.join("legacy", "Alice".into()).await.unwrap();
+        let b = store.join("legacy", "Bob".into()).await.unwrap();
+        assert_eq!(a.token, "shared");
+        assert_eq!(b.token, "shared");
+    }
+
+    #[tokio::test]
+    async fn test_create_generate_tokens_without_certificate_rejected() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/rtc-sessions")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(
+                        r#"{"app_id":"app1","channel":"room","host_uid":1,"generate_tokens":true}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_create_without_token_or_generation_rejected() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/rtc-sessions")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"app_id":"app1","channel":"room","host_uid":1}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_join_metadata_round_trips() {
+        let store = RtcSessionStore::new();
+        store.create("meta".into(), "app".into(), "ch".into(), "tok".into(), 1).await.unwrap();
+
+        let metadata = serde_json::json!({"avatar": "https://example.com/a.png", "pronouns": "they/them"});
+        let options = JoinOptions {
+            metadata: Some(metadata.clone()),
+            ..Default::default()
+        };
+        let resp = store.join_with_options("meta", "Alice".into(), options).await.unwrap();
+        assert_eq!(resp.metadata.as_ref(), Some(&metadata));
+
+        let session = store.get("meta").await.unwrap();
+        let json = serde_json::to_value(&session.participants[0]).unwrap();
+        assert_eq!(json["metadata"], metadata);
+    }
+
+    #[tokio::test]
+    async fn test_join_without_metadata_omits_field() {
+        let store = RtcSessionStore::new();
+        store.create("meta-none".into(), "app".into(), "ch".into(), "tok".into(), 1).await.unwrap();
+        let resp = store.join("meta-none", "Bob".into()).await.unwrap();
+
+        let session = store.get("meta-none").await.unwrap();
+        let participant = serde_json::to_value(&session.participants[0]).unwrap();
+        assert!(participant.get("metadata").is_none());
+        let response = serde_json::to_value(&resp).unwrap();
+        assert!(response.get("metadata").is_none());
+    }
+
+    #[test]
+    fn test_validate_metadata() {
+        assert!(validate_metadata(&serde_json::json!({"client": "1.2.3"})).is_ok());
+        assert_eq!(
+            validate_metadata(&serde_json::json!(["not", "an", "object"])),
+            Err(MetadataError::NotAnObject)
+        );
+        let big = "x".repeat(MAX_METADATA_BYTES);
+        assert_eq!(
+            validate_metadata(&serde_json::json!({ "blob": big })),
+            Err(MetadataError::TooLarge)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_join_handler_rejects_invalid_metadata() {
+        let state = AppState {
+            sessions: SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        state
+            .rtc_sessions
+            .create("meta-h".into(), "app1".into(), "room1".into(), "tok1".into(), 42)
+            .await.unwrap();
+        let app = Router::new()
+            .route("/api/rtc-sessions/:id/join", post(join_rtc_session_handler))
+            .with_state(state.clone());
+
+        let oversized = format!(r#"{{"name":"Alice","metadata":{{"blob":"{}"}}}}"#, "x".repeat(4096));
+        for (body, code) in [
+            (oversized, "metadata_too_large"),
+            (r#"{"name":"Alice","metadata":"hi"}"#.to_string(), "metadata_not_object"),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/rtc-sessions/meta-h/join")
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let err: RtcSessionError = serde_json::from_slice(&body).unwrap();
+            assert_eq!(err.code.as_deref(), Some(code));
+        }
+
+        let session = state.rtc_sessions.get("meta-h").await.unwrap();
+        assert!(session.participants.is_empty());
+    }
+
+    /// Move a session past both its scheduled and its idle expiry.
+    async fn expire_now(store: &RtcSessionStore, id: &str) {
+        let sessions = store.sessions.read().await;
+        let mut inner = sessions[id].write().await;
+        inner.expires_at = Utc::now() - Duration::minutes(1);
+        inner.last_activity = inner.expires_at - store.max_idle;
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_returns_410_before_and_after_cleanup() {
+        let state = AppState {
+            sessions: SessionStore::new(),
+            relay: RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
+        };
+        let expires_at = Utc::now() - Duration::minutes(1);
+        state.rtc_sessions.insert_expired("gone", expires_at).await;
+
+        let app = Router::new()
+            .route("/api/rtc-sessions/:id", get(get_rtc_session_handler))
+            .route("/api/rtc-sessions/:id/join", post(join_rtc_session_handler))
+            .route("/api/rtc-sessions/:id/participants", get(get_participants_handler))
+            .with_state(state.clone());
+
+        let check_gone = |app: Router| async move {
+            for request in [
+                Request::builder()
+                    .uri("/api/rtc-sessions/gone")
+                    .body(Body::empty())
+                    .unwrap(),
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/rtc-sessions/gone/join")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"name":"Late"}"#))
+                    .unwrap(),
+                Request::builder()
+                    .uri("/api/rtc-sessions/gone/participants")
+                    .body(Body::empty())
+                    .unwrap(),
+            ] {
+                let response = app.clone().oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::GONE);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let err: RtcSessionError = serde_json::from_slice(&body).unwrap();
+                assert_eq!(err.code.as_deref(), Some("session_expired"));
+                assert_eq!(err.expires_at, Some(expires_at));
+            }
+        };
+
+        check_gone(app.clone()).await;
+        state.rtc_sessions.cleanup_expired().await;
+        assert!(state.rtc_sessions.sessions.read().await.get("gone").is_none());
+        check_gone(app).await;
+    }
+
+    #[tokio::test]
+    async fn test_recording_state_machine() {
+        let store = RtcSessionStore::new();
+        store.create("rec".into(), "a".into(), "c".into(), "t".into(), 7).await.unwrap();
+        let record = |event| store.add_recording_event("rec", event, None);
+
+        assert_eq!(record(RecordingEventKind::Stopped).await.unwrap_err(), RecordingError::NotRecording);
+        let started = record(RecordingEventKind::Started).await.unwrap();
+        assert_eq!(started.initiated_by_uid, 7);
+        assert_eq!(record(RecordingEventKind::Started).await.unwrap_err(), RecordingError::AlreadyRecording);
+        assert!(store.get("rec").await.unwrap().is_recording());
+        record(RecordingEventKind::Stopped).await.unwrap();
+        assert!(!store.get("rec").await.unwrap().is_recording());
+        record(RecordingEventKind::Started).await.unwrap();
+
+        let events = store.get("rec").await.unwrap().recording_events;
+        let kinds: Vec<_> = events.iter().map(|e| e.event).collect();
+        assert_eq!(
+            kinds,
+            [RecordingEventKind::Started, RecordingEventKind::Stopped, RecordingEventKind::Started]
+        );
+        assert!(events.windows(2).all(|w| w[0].at <= w[1].at));
+        assert_eq!(
+            store.add_recording_event("nope", RecordingEventKind::Started, None).await.unwrap_err(),
+            RecordingError::NotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recording_handlers() {
+        let state = test_state();
+        let created = state
+            .rtc_sessions
+            .create("rec-h".into(), "a".into(), "c".into(), "t".into(), 1)
+            .await
+            .unwrap();
+        let token = Some(created.host_token.as_str());
+        let rtc_sessions = state.rtc_sessions.clone();
+        let app = Router::new()
+            .route("/api/rtc-sessions/:id/recording", get(get_recording_handler))
+            .route("/api/rtc-sessions/:id/recording/start", post(start_recording_handler))
+            .route("/api/rtc-sessions/:id/recording/stop", post(stop_recording_handler))
+            .with_state(state);
+        let status = |request: Request<Body>| {
+            let app = app.clone();
+            async move { app.oneshot(request).await.unwrap().status() }
+        };
+
+        let start = "/api/rtc-sessions/rec-h/recording/start";
+        let stop = "/api/rtc-sessions/rec-h/recording/stop";
+        assert_eq!(status(authed("POST", start, None, "")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(authed("POST", start, Some("wrong"), "")).await, StatusCode::FORBIDDEN);
+        assert_eq!(status(authed("POST", start, token, r#"{"resource_id":"res-1"}"#)).await, StatusCode::OK);
+        assert_eq!(status(authed("POST", start, token, "")).await, StatusCode::CONFLICT);
+        assert_eq!(status(authed("POST", stop, token, "")).await, StatusCode::OK);
+        assert_eq!(status(authed("POST", stop, token, "")).await, StatusCode::CONFLICT);
+
+        let response = app
+            .clone()
+            .oneshot(authed("GET", "/api/rtc-sessions/rec-h/recording", None, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let history: RecordingStatusResponse = serde_json::from_slice(&body).unwrap();
+        assert!(!history.recording);
+        assert_eq!(history.events.len(), 2);
+        assert_eq!(history.events[0].event, RecordingEventKind::Started);
+        assert_eq!(history.events[0].resource_id.as_deref(), Some("res-1"));
+        assert_eq!(history.events[1].event, RecordingEventKind::Stopped);
+
+        let response = app
+            .clone()
+            .oneshot(authed("GET", "/api/rtc-sessions/missing/recording", None, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        expire_now(&rtc_sessions, "rec-h").await;
+        let response = app
+            .oneshot(authed("GET", "/api/rtc-sessions/rec-h/recording", None, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::GONE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let error: RtcSessionError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code.as_deref(), Some("session_expired"));
+        assert!(error.expires_at.is_some());
+    }
+
+    async fn create_with_passcode(store: &RtcSessionStore, id: &str, passcode: &str) {
+        let options = RtcSessionOptions {
+            passcode: Some(passcode.into()),
+            ..Default::default()
+        };
+        store
+            .create_with_options(id.into(), "a".into(), "c".into(), "t".into(), 1, options)
+            .await
+            .unwrap();
+    }
+
+    fn with_passcode(passcode: Option<&str>) -> JoinOptions {
+        JoinOptions {
+            passcode: passcode.map(Into::into),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_join_with_passcode() {
+        let store = RtcSessionStore::new();
+        create_with_passcode(&store, "pc", "4821").await;
+
+        let join = |passcode| store.join_with_options("pc", "Alice".into(), with_passcode(passcode));
+        assert_eq!(join(None).await.unwrap_err(), JoinError::PasscodeRequired);
+        assert_eq!(join(Some("0000")).await.unwrap_err(), JoinError::PasscodeInvalid);
+        assert_eq!(join(Some("4821")).await.unwrap().uid, 1000);
+
+        // The host token stands in for the passcode
+        assert!(store.join_as_host("pc", "Host".into()).await.is_ok());
+        // Sessions without a passcode ignore one
+        store.create("open".into(), "a".into(), "c".into(), "t".into(), 1).await.unwrap();
+        assert!(store.join_with_options("open", "Bob".into(), with_passcode(Some("x"))).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_passcode_attempts_are_rate_limited() {
+        let store = RtcSessionStore::new();
+        create_with_passcode(&store, "pc-rl", "4821").await;
+
+        for _ in 0..PASSCODE_MAX_FAILURES {
+            let result = store.join_with_options("pc-rl", "Mallory".into(), with_passcode(Some("0000"))).await;
+            assert_eq!(result.unwrap_err(), JoinError::PasscodeInvalid);
+        }
+        // Even the right passcode is refused until the window passes
+        let result = store.join_with_options("pc-rl", "Alice".into(), with_passcode(Some("4821"))).await;
+        assert_eq!(result.unwrap_err(), JoinError::PasscodeAttemptsExceeded);
+
+        let sessions = store.sessions.read().await;
+        let mut inner = sessions.get("pc-rl").unwrap().write().await;
+        for at in inner.passcode_failures.iter_mut() {
+            *at -= Duration::seconds(PASSCODE_FAILURE_WINDOW_SECS);
+        }
+        assert!(inner.check_passcode(Some("4821")).is_ok());
+        assert!(inner.passcode_failures.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_passcode_handlers_never_expose_hash() {
+        let state = test_state();
+        let app = Router::new()
+            .route("/api/rtc-sessions", post(create_rtc_session_handler))
+            .route("/api/rtc-sessions/:id", get(get_rtc_session_handler))
+            .route("/api/rtc-sessions/:id/join", post(join_rtc_session_handler))
+            .with_state(state.clone());
+        let send = |request: Request<Body>| {
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, String::from_utf8(body.to_vec()).unwrap())
+            }
+        };
+
+        let (status, _) = send(authed(
+            "POST",
+            "/api/rtc-sessions",
+            None,
+            r#"{"app_id":"a","channel":"c","token":"t","host_uid":1,"passcode":"123"}"#,
+        ))
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, created) = send(authed(
+            "POST",
+            "/api/rtc-sessions",
+            None,
+            r#"{"app_id":"a","channel":"c","token":"t","host_uid":1,"passcode":"tulip-42"}"#,
+        ))
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let id = serde_json::from_str::<CreateRtcSessionResponse>(&created).unwrap().id;
+        let hash = state.rtc_sessions.get(&id).await.unwrap().passcode.unwrap();
+        let mut bodies = vec![created];
+
+        let uri = format!("/api/rtc-sessions/{}", id);
+        let (status, info) = send(authed("GET", &uri, None, "")).await;
+        assert_eq!(status, StatusCode::OK);
+        let parsed: GetRtcSessionResponse = serde_json::from_str(&info).unwrap();
+        assert!(parsed.passcode_required);
+        bodies.push(info);
+
+        let join_uri = format!("{}/join", uri);
+        for (body, code) in [
+            (r#"{"name":"Alice"}"#, "passcode_required"),
+            (r#"{"name":"Alice","passcode":"0000"}"#, "passcode_invalid"),
+        ] {
+            let (status, error) = send(authed("POST", &join_uri, None, body)).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            let parsed: RtcSessionError = serde_json::from_str(&error).unwrap();
+            assert_eq!(parsed.code.as_deref(), Some(code));
+            bodies.push(error);
+        }
+
+        let (status, joined) = send(authed("POST", &join_uri, None, r#"{"name":"Alice","passcode":"tulip-42"}"#)).await;
+        assert_eq!(status, StatusCode::OK);
+        bodies.push(joined);
+
+        for body in bodies {
+            assert!(!body.contains(&hash.hash) && !body.contains(&hash.salt), "{}", body);
+            assert!(!body.contains("tulip-42"), "{}", body);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expired_lookup_and_tombstone_grace() {
+        let store = RtcSessionStore::new();
+        store.insert_expired("recent", Utc::now() - Duration::minutes(1)).await;
+        store.insert_expired("ancient", Utc::now() - Duration::hours(2)).await;
+
+        assert!(store.get("recent").await.is_none());
+        assert!(matches!(store.lookup("recent").await, RtcSessionLookup::Expired { .. }));
+        assert_eq!(store.join("recent", "Late".into()).await.unwrap_err(), JoinError::Expired);
+
+        store.cleanup_expired().await;
+
+        assert!(matches!(store.lookup("recent").await, RtcSessionLookup::Expired { .. }));
+        assert!(matches!(store.lookup("ancient").await, RtcSessionLookup::NotFound));
+        assert!(matches!(store.lookup("never").await, RtcSessionLookup::NotFound));
+        assert!(matches!(store.lookup_meta("recent").await, RtcSessionLookup::Expired { .. }));
+        assert!(matches!(store.lookup_meta("never").await, RtcSessionLookup::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_export_snapshot_is_complete_and_redacted() {
+        let mut state = test_state();
+        state.admin_token = Some("admin".into());
+        let store = &state.rtc_sessions;
+        let first = store.create("snap-a".into(), "app".into(), "ch".into(), "rtc-token".into(), 1).await.unwrap();
+        store
+            .create_with_options(
+                "snap-b".into(),
+                "app".into(),
+                "ch".into(),
+                "t".into(),
+                2,
+                RtcSessionOptions { passcode: Some("1234".into()), ..Default::default() },
+            )
+            .await
+            .unwrap();
+        store.join("snap-a", "Alice".into()).await.unwrap();
+        store.join("snap-a", "Bob".into()).await.unwrap();
+
+        let snapshots = store.export_snapshot().await;
+        assert_eq!(snapshots.iter().map(|s| s.session.id.as_str()).collect::<Vec<_>>(), ["snap-a", "snap-b"]);
+        let a = &snapshots[0];
+        assert_eq!(a.participant_count, 2);
+        assert_eq!(a.session.uid_counter_value, 1002);
+        assert!(!a.session.locked && !a.passcode_required && snapshots[1].passcode_required);
+        assert_eq!((a.session.token.as_str(), a.session.host_token.as_str()), (REDACTED, REDACTED));
+        assert!(a.session.client_ids.values().all(|id| id == REDACTED));
+
+        let app = Router::new()
+            .route("/api/admin/rtc-sessions/snapshot", get(rtc_sessions_snapshot_admin_handler))
+            .with_state(state.clone());
+        let request = |token: &str| {
+            Request::builder()
+                .uri("/api/admin/rtc-sessions/snapshot")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app.clone().oneshot(request("wrong")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.oneshot(request("admin")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let text = String::from_utf8(body.to_vec()).unwrap();
+        assert!(!text.contains(&first.host_token) && !text.contains("rtc-token") && !text.contains("salt"));
+        let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json["count"], 2);
+        assert_eq!(json["sessions"][0]["participant_count"], 2);
+        assert_eq!(json["sessions"][0]["participants"].as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_export_snapshot_is_consistent_under_writes() {
+        let store = RtcSessionStore::new();
+        store.create("busy".into(), "app".into(), "ch".into(), "t".into(), 1).await.unwrap();
+        let writer_store = store.clone();
+        let writer = tokio::spawn(async move {
+            for i in 0..200 {
+                let uid = writer_store.join("busy", format!("user {}", i)).await.unwrap().uid;
+                writer_store.remove_participant("busy", uid).await.unwrap();
+                // Sessions come and go alongside
+                let id = format!("churn-{}", i);
+                writer_store.create(id.clone(), "app".into(), "ch".into(), "t".into(), 1).await.unwrap();
+                writer_store.delete(&id).await.unwrap();
+            }
+        });
+
+        while !writer.is_finished() {
+            for snapshot in store.export_snapshot().await {
+                let session = &snapshot.session;
+                assert_eq!(snapshot.participant_count, session.participants.len());
+                assert!(session.participants.iter().all(|p| p.uid < session.uid_counter_value));
+                assert!(session.participants.iter().all(|p| session.client_ids.contains_key(&p.uid)));
+            }
+            tokio::task::yield_now().await;
+        }
+        writer.await.unwrap();
+        let snapshots = store.export_snapshot().await;
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].participant_count, 0);
+        assert_eq!(snapshots[0].session.uid_counter_value, 1200);
+    }
 }
@@ -1,57 +1,294 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
+
+use crate::auth::{self, Session, SessionStatus};
+use chrono::{DateTime, Utc};
+
+/// Default cap on pending sessions per hostname (`MAX_PENDING_PER_HOSTNAME`).
+pub const DEFAULT_MAX_PENDING_PER_HOSTNAME: usize = 3;
+
+/// Status changes buffered per subscriber before it starts lagging.
+const STATUS_CHANNEL_CAPACITY: usize = 64;
+
+/// Outcome of [`SessionStore::grant_if_pending`].
+#[derive(Debug, PartialEq)]
+pub enum GrantResult {
+    /// Granted; carries the new session token.
+    Granted(String),
+    /// Already granted or denied; carries the current status.
+    AlreadyProcessed(SessionStatus),
+    InvalidOtp,
+    Expired,
+    NotFound,
+}
 
-use crate::auth::{Session, SessionStatus};
-use chrono::Utc;
+/// Outcome of [`SessionStore::deny_if_pending`].
+#[derive(Debug, PartialEq)]
+pub enum DenyResult {
+    Denied,
+    /// Already granted, denied or expired; carries the current status.
+    AlreadyProcessed(SessionStatus),
+    NotFound,
+}
 
 #[derive(Clone)]
 pub struct SessionStore {
     sessions: Arc<RwLock<HashMap<String, Session>>>,
+    record_client_ip: bool,
+    /// `TRUST_PROXY`: take the client IP from forwarding headers.
+    trust_proxy: bool,
+    max_pending_per_hostname: usize,
+    /// `OTP_HMAC_KEY`: OTPs are signed with it rather than random.
+    otp_hmac_key: Option<Arc<[u8]>>,
+    /// `(session id, new status)` on every update.
+    status_changes: broadcast::Sender<(String, SessionStatus)>,
+    /// Ids of unanswered sessions by `expires_at`, so cleanup visits only the
+    /// expired ones. Only changed while `sessions` is write-locked.
+    expiry_index: Arc<std::sync::RwLock<BTreeMap<DateTime<Utc>, HashSet<String>>>>,
 }
 
 impl SessionStore {
     pub fn new() -> Self {
         SessionStore {
             sessions: Arc::new(RwLock::new(HashMap::new())),
+            record_client_ip: true,
+            trust_proxy: false,
+            max_pending_per_hostname: DEFAULT_MAX_PENDING_PER_HOSTNAME,
+            otp_hmac_key: None,
+            status_changes: broadcast::channel(STATUS_CHANNEL_CAPACITY).0,
+            expiry_index: Arc::new(std::sync::RwLock::new(BTreeMap::new())),
+        }
+    }
+
+    /// Receive `(session id, status)` whenever a session is updated.
+    pub fn subscribe_status(&self) -> broadcast::Receiver<(String, SessionStatus)> {
+        self.status_changes.subscribe()
+    }
+
+    /// Enable or disable recording the requester's IP on new sessions.
+    pub fn with_record_client_ip(mut self, record: bool) -> Self {
+        self.record_client_ip = record;
+        self
+    }
+
+    pub fn records_client_ip(&self) -> bool {
+        self.record_client_ip
+    }
+
+    /// Read the client IP from `X-Forwarded-For`/`X-Real-IP`, as set by a
+    /// reverse proxy in front of the server.
+    pub fn with_trust_proxy(mut self, trust: bool) -> Self {
+        self.trust_proxy = trust;
+        self
+    }
+
+    pub fn trusts_proxy(&self) -> bool {
+        self.trust_proxy
+    }
+
+    /// Limit how many pending sessions one hostname may hold.
+    pub fn with_max_pending_per_hostname(mut self, max: usize) -> Self {
+        self.max_pending_per_hostname = max;
+        self
+    }
+
+    pub fn max_pending_per_hostname(&self) -> usize {
+        self.max_pending_per_hostname
+    }
+
+    /// Issue and check HMAC-signed OTPs under `key`; None keeps random ones.
+    pub fn with_otp_hmac_key(mut self, key: Option<&str>) -> Self {
+        self.otp_hmac_key = key.map(|key| key.as_bytes().into());
+        self
+    }
+
+    pub fn otp_hmac_key(&self) -> Option<&[u8]> {
+        self.otp_hmac_key.as_deref()
+    }
+
+    /// Number of stored sessions, expired ones included until cleanup.
+    pub async fn session_count(&self) -> usize {
+        self.sessions.read().await.len()
+    }
+
+    /// Number of unexpired pending sessions for `hostname`.
+    pub async fn count_pending_for_hostname(&self, hostname: &str) -> usize {
+        let now = Utc::now();
+        let sessions = self.sessions.read().await;
+        sessions
+            .values()
+            .filter(|s| {
+                s.hostname == hostname && s.status == SessionStatus::Pending && now <= s.expires_at
+            })
+            .count()
+    }
+
+    /// Number of unanswered sessions waiting in the expiry index, expired ones
+    /// included until cleanup.
+    pub fn pending_expiry_count(&self) -> usize {
+        self.expiry_index.read().unwrap().values().map(HashSet::len).sum()
+    }
+
+    /// Point the expiry index at `new` in place of `old` (either may be None).
+    fn reindex(&self, old: Option<&Session>, new: Option<&Session>) {
+        let mut index = self.expiry_index.write().unwrap();
+        if let Some(old) = old.filter(|s| is_unanswered(s)) {
+            if let Some(ids) = index.get_mut(&old.expires_at) {
+                ids.remove(&old.id);
+                if ids.is_empty() {
+                    index.remove(&old.expires_at);
+                }
+            }
+        }
+        if let Some(new) = new.filter(|s| is_unanswered(s)) {
+            index.entry(new.expires_at).or_default().insert(new.id.clone());
         }
     }
 
     pub async fn create(&self, session: Session) {
         let id = session.id.clone();
         let mut sessions = self.sessions.write().await;
-        sessions.insert(id, session);
+        self.reindex(None, Some(&session));
+        if let Some(previous) = sessions.insert(id, session) {
+            self.reindex(Some(&previous), None);
+        }
     }
 
+    /// The session, with a pending session past `expires_at` first marked
+    /// `Expired` in the store so every caller sees the same status.
     pub async fn get(&self, id: &str) -> Option<Session> {
+        {
+            let sessions = self.sessions.read().await;
+            let session = sessions.get(id)?;
+            if !is_lapsed(session) {
+                return Some(session.clone());
+            }
+        }
+        let mut sessions = self.sessions.write().await;
+        let session = sessions.get_mut(id)?;
+        // Re-checked: another caller may have expired or granted it meanwhile
+        if !is_lapsed(session) {
+            return Some(session.clone());
+        }
+        session.status = SessionStatus::Expired;
+        let expired = session.clone();
+        drop(sessions);
+        // Err only means nobody is subscribed
+        let _ = self.status_changes.send((id.to_string(), SessionStatus::Expired));
+        Some(expired)
+    }
+
+    /// All sessions, oldest first.
+    pub async fn list(&self) -> Vec<Session> {
         let sessions = self.sessions.read().await;
-        sessions.get(id).cloned()
+        let mut list: Vec<Session> = sessions.values().cloned().collect();
+        list.sort_by_key(|s| s.created_at);
+        list
     }
 
     pub async fn update(&self, id: &str, session: Session) {
+        let status = session.status.clone();
         let mut sessions = self.sessions.write().await;
+        let previous = sessions.get(id);
+        self.reindex(previous, None);
+        self.reindex(None, Some(&session));
         sessions.insert(id.to_string(), session);
+        drop(sessions);
+        // Err only means nobody is subscribed
+        let _ = self.status_changes.send((id.to_string(), status));
+    }
+
+    /// Grant a pending session if `otp` matches, checking and updating under one
+    /// write lock so concurrent grants cannot both succeed.
+    pub async fn grant_if_pending(&self, id: &str, otp: &str) -> GrantResult {
+        let mut sessions = self.sessions.write().await;
+        let Some(session) = sessions.get_mut(id) else {
+            return GrantResult::NotFound;
+        };
+        if is_lapsed(session) {
+            session.status = SessionStatus::Expired;
+            drop(sessions);
+            let _ = self.status_changes.send((id.to_string(), SessionStatus::Expired));
+            return GrantResult::Expired;
+        }
+        match session.status {
+            SessionStatus::Pending => {}
+            SessionStatus::Expired => return GrantResult::Expired,
+            ref status => return GrantResult::AlreadyProcessed(status.clone()),
+        }
+        if !auth::validate_otp(session, otp, self.otp_hmac_key()) {
+            return GrantResult::InvalidOtp;
+        }
+        let token = auth::generate_session_token();
+        let pending = session.clone();
+        session.status = SessionStatus::Granted;
+        session.token = Some(token.clone());
+        self.reindex(Some(&pending), None);
+        drop(sessions);
+        // Err only means nobody is subscribed
+        let _ = self.status_changes.send((id.to_string(), SessionStatus::Granted));
+        GrantResult::Granted(token)
+    }
+
+    /// Deny a pending session, checking and updating under one write lock so a
+    /// concurrent grant cannot be overwritten.
+    pub async fn deny_if_pending(&self, id: &str) -> DenyResult {
+        let mut sessions = self.sessions.write().await;
+        let Some(session) = sessions.get_mut(id) else {
+            return DenyResult::NotFound;
+        };
+        if is_lapsed(session) {
+            session.status = SessionStatus::Expired;
+            drop(sessions);
+            let _ = self.status_changes.send((id.to_string(), SessionStatus::Expired));
+            return DenyResult::AlreadyProcessed(SessionStatus::Expired);
+        }
+        if session.status != SessionStatus::Pending {
+            return DenyResult::AlreadyProcessed(session.status.clone());
+        }
+        let pending = session.clone();
+        session.status = SessionStatus::Denied;
+        self.reindex(Some(&pending), None);
+        drop(sessions);
+        // Err only means nobody is subscribed
+        let _ = self.status_changes.send((id.to_string(), SessionStatus::Denied));
+        DenyResult::Denied
     }
 
     pub async fn delete(&self, id: &str) {
         let mut sessions = self.sessions.write().await;
-        sessions.remove(id);
+        if let Some(removed) = sessions.remove(id) {
+            self.reindex(Some(&removed), None);
+        }
     }
 
-    /// Remove all sessions that have expired and are still pending.
+    /// Remove all sessions that expired without being granted or denied.
+    /// Only the expired end of the expiry index is visited, so a sweep costs
+    /// O(k log n) for k expired sessions rather than a scan of the store.
     pub async fn cleanup_expired(&self) {
         let now = Utc::now();
         let mut sessions = self.sessions.write().await;
-        sessions.retain(|_, session| {
-            if now > session.expires_at && session.status == SessionStatus::Pending {
-                false
-            } else {
-                true
+        let mut index = self.expiry_index.write().unwrap();
+        let expired: Vec<DateTime<Utc>> = index.range(..=now).map(|(expires_at, _)| *expires_at).collect();
+        for expires_at in expired {
+            for id in index.remove(&expires_at).unwrap_or_default() {
+                sessions.remove(&id);
             }
-        });
+        }
     }
 }
 
+/// Neither granted nor denied, so cleanup removes it once it expires.
+fn is_unanswered(session: &Session) -> bool {
+    matches!(session.status, SessionStatus::Pending | SessionStatus::Expired)
+}
+
+/// Pending past its expiry, but not yet marked `Expired`.
+fn is_lapsed(session: &Session) -> bool {
+    session.status == SessionStatus::Pending && Utc::now() > session.expires_at
+}
+
 impl Default for SessionStore {
     fn default() -> Self {
         Self::new()
@@ -118,6 +355,28 @@ mod tests {
         assert!(store.get(&id).await.is_none());
     }
 
+    #[tokio::test]
+    async fn test_count_pending_for_hostname() {
+        let store = SessionStore::new();
+        store.create(create_session("busy-host")).await;
+        store.create(create_session("busy-host")).await;
+        store.create(create_session("other-host")).await;
+        assert_eq!(store.count_pending_for_hostname("busy-host").await, 2);
+        assert_eq!(store.count_pending_for_hostname("idle-host").await, 0);
+
+        // Granted, denied and expired sessions do not count
+        let mut granted = create_session("busy-host");
+        granted.status = SessionStatus::Granted;
+        store.create(granted).await;
+        let mut denied = create_session("busy-host");
+        denied.status = SessionStatus::Denied;
+        store.create(denied).await;
+        let mut expired = create_session("busy-host");
+        expired.expires_at = Utc::now() - Duration::minutes(1);
+        store.create(expired).await;
+        assert_eq!(store.count_pending_for_hostname("busy-host").await, 2);
+    }
+
     #[tokio::test]
     async fn test_cleanup_expired_sessions() {
         let store = SessionStore::new();
@@ -132,6 +391,8 @@ mod tests {
             token: None,
             created_at: now - Duration::minutes(10),
             expires_at: now - Duration::minutes(5),
+            client_ip: None,
+            created_by: None,
         };
         let expired_id = expired_session.id.clone();
         store.create(expired_session).await;
@@ -150,6 +411,8 @@ mod tests {
             token: Some("some-token".to_string()),
             created_at: now - Duration::minutes(10),
             expires_at: now - Duration::minutes(5),
+            client_ip: None,
+            created_by: None,
         };
         let granted_id = granted_session.id.clone();
         store.create(granted_session).await;
@@ -164,6 +427,56 @@ mod tests {
         assert!(store.get(&granted_id).await.is_some());
     }
 
+    #[tokio::test]
+    async fn test_get_expires_lapsed_pending_session() {
+        let store = SessionStore::new();
+        let mut changes = store.subscribe_status();
+        let mut lapsed = create_session("late-host");
+        lapsed.expires_at = Utc::now() - Duration::seconds(1);
+        let id = lapsed.id.clone();
+        store.create(lapsed).await;
+
+        assert_eq!(store.get(&id).await.unwrap().status, SessionStatus::Expired);
+        // Stored, not just reported, and announced once
+        let stored = store.sessions.read().await[&id].clone();
+        assert_eq!(stored.status, SessionStatus::Expired);
+        assert_eq!(changes.try_recv().unwrap(), (id.clone(), SessionStatus::Expired));
+        assert_eq!(store.get(&id).await.unwrap().status, SessionStatus::Expired);
+        assert!(changes.try_recv().is_err());
+
+        // The expired session is cleaned up like a pending one
+        store.cleanup_expired().await;
+        assert!(store.get(&id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_leaves_live_and_answered_sessions_alone() {
+        let store = SessionStore::new();
+        let live = create_session("live-host");
+        let live_id = live.id.clone();
+        store.create(live).await;
+        let mut denied = create_session("denied-host");
+        denied.status = SessionStatus::Denied;
+        denied.expires_at = Utc::now() - Duration::minutes(1);
+        let denied_id = denied.id.clone();
+        store.create(denied).await;
+
+        assert_eq!(store.get(&live_id).await.unwrap().status, SessionStatus::Pending);
+        assert_eq!(store.get(&denied_id).await.unwrap().status, SessionStatus::Denied);
+    }
+
+    #[tokio::test]
+    async fn test_grant_after_get_expired_is_still_expired() {
+        let store = SessionStore::new();
+        let mut lapsed = create_session("late-host");
+        lapsed.expires_at = Utc::now() - Duration::seconds(1);
+        let (id, otp) = (lapsed.id.clone(), lapsed.otp.clone());
+        store.create(lapsed).await;
+
+        store.get(&id).await;
+        assert_eq!(store.grant_if_pending(&id, &otp).await, GrantResult::Expired);
+    }
+
     #[tokio::test]
     async fn test_session_lifecycle_grant() {
         let store = SessionStore::new();
@@ -181,7 +494,7 @@ mod tests {
 
         // Grant session
         let mut s = store.get(&id).await.unwrap();
-        if crate::auth::validate_otp(&s, &otp) {
+        if crate::auth::validate_otp(&s, &otp, None) {
             s.status = SessionStatus::Granted;
             s.token = Some(crate::auth::generate_session_token());
             store.update(&id, s).await;
@@ -194,6 +507,89 @@ mod tests {
         assert_eq!(s.token.as_ref().unwrap().len(), 64);
     }
 
+    #[tokio::test]
+    async fn test_grant_if_pending_outcomes() {
+        let store = SessionStore::new();
+        assert_eq!(store.grant_if_pending("missing", "123456").await, GrantResult::NotFound);
+
+        let session = create_session("test-host");
+        let (id, otp) = (session.id.clone(), session.otp.clone());
+        store.create(session).await;
+        assert_eq!(store.grant_if_pending(&id, "wrong").await, GrantResult::InvalidOtp);
+
+        let GrantResult::Granted(token) = store.grant_if_pending(&id, &otp).await else {
+            panic!("expected grant");
+        };
+        let granted = store.get(&id).await.unwrap();
+        assert_eq!(granted.status, SessionStatus::Granted);
+        assert_eq!(granted.token, Some(token));
+        assert_eq!(
+            store.grant_if_pending(&id, &otp).await,
+            GrantResult::AlreadyProcessed(SessionStatus::Granted)
+        );
+
+        let mut expired = create_session("test-host");
+        expired.expires_at = Utc::now() - Duration::minutes(1);
+        let (id, otp) = (expired.id.clone(), expired.otp.clone());
+        store.create(expired).await;
+        assert_eq!(store.grant_if_pending(&id, &otp).await, GrantResult::Expired);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_grants_succeed_once() {
+        let store = SessionStore::new();
+        let session = create_session("test-host");
+        let (id, otp) = (session.id.clone(), session.otp.clone());
+        store.create(session).await;
+
+        let tasks: Vec<_> = (0..100)
+            .map(|_| {
+                let (store, id, otp) = (store.clone(), id.clone(), otp.clone());
+                tokio::spawn(async move { store.grant_if_pending(&id, &otp).await })
+            })
+            .collect();
+        let mut tokens = Vec::new();
+        for task in tasks {
+            match task.await.unwrap() {
+                GrantResult::Granted(token) => tokens.push(token),
+                other => assert_eq!(other, GrantResult::AlreadyProcessed(SessionStatus::Granted)),
+            }
+        }
+
+        assert_eq!(tokens.len(), 1, "exactly one grant wins");
+        assert_eq!(store.get(&id).await.unwrap().token, Some(tokens.remove(0)));
+    }
+
+    #[tokio::test]
+    async fn test_deny_if_pending_outcomes() {
+        let store = SessionStore::new();
+        assert_eq!(store.deny_if_pending("missing").await, DenyResult::NotFound);
+
+        let session = create_session("test-host");
+        let (id, otp) = (session.id.clone(), session.otp.clone());
+        store.create(session).await;
+        assert_eq!(store.deny_if_pending(&id).await, DenyResult::Denied);
+        assert_eq!(store.get(&id).await.unwrap().status, SessionStatus::Denied);
+        assert_eq!(store.pending_expiry_count(), 0);
+        assert_eq!(
+            store.deny_if_pending(&id).await,
+            DenyResult::AlreadyProcessed(SessionStatus::Denied)
+        );
+        assert_eq!(
+            store.grant_if_pending(&id, &otp).await,
+            GrantResult::AlreadyProcessed(SessionStatus::Denied)
+        );
+
+        let mut expired = create_session("test-host");
+        expired.expires_at = Utc::now() - Duration::minutes(1);
+        let id = expired.id.clone();
+        store.create(expired).await;
+        assert_eq!(
+            store.deny_if_pending(&id).await,
+            DenyResult::AlreadyProcessed(SessionStatus::Expired)
+        );
+    }
+
     #[tokio::test]
     async fn test_session_lifecycle_deny() {
         let store = SessionStore::new();
@@ -213,4 +609,142 @@ mod tests {
         assert_eq!(s.status, SessionStatus::Denied);
         assert!(s.token.is_none());
     }
+
+    /// Every unanswered session in the store is indexed under its own expiry,
+    /// and the index holds nothing else.
+    async fn assert_index_matches_store(store: &SessionStore) {
+        let sessions = store.sessions.read().await;
+        let index = store.expiry_index.read().unwrap();
+        let indexed: Vec<(&String, &DateTime<Utc>)> =
+            index.iter().flat_map(|(at, ids)| ids.iter().map(move |id| (id, at))).collect();
+        let unanswered = sessions.values().filter(|s| is_unanswered(s)).count();
+        assert_eq!(indexed.len(), unanswered);
+        for (id, at) in indexed {
+            let session = &sessions[id];
+            assert!(is_unanswered(session), "{} is {:?}", id, session.status);
+            assert_eq!(session.expires_at, *at);
+        }
+        assert!(index.values().all(|ids| !ids.is_empty()));
+    }
+
+    fn expired_session(hostname: &str) -> Session {
+        let mut session = create_session(hostname);
+        session.expires_at = Utc::now() - Duration::minutes(1);
+        session
+    }
+
+    #[tokio::test]
+    async fn test_expiry_index_follows_status_changes() {
+        let store = SessionStore::new();
+        let (first, second) = (create_session("a"), create_session("b"));
+        store.create(first.clone()).await;
+        store.create(second.clone()).await;
+        assert_eq!(store.pending_expiry_count(), 2);
+
+        assert!(matches!(store.grant_if_pending(&first.id, &first.otp).await, GrantResult::Granted(_)));
+        assert_eq!(store.pending_expiry_count(), 1);
+
+        let mut denied = second.clone();
+        denied.status = SessionStatus::Denied;
+        store.update(&second.id, denied).await;
+        assert_eq!(store.pending_expiry_count(), 0);
+        // Re-opened with a new expiry, it is indexed under that one
+        let mut reopened = second.clone();
+        reopened.expires_at += Duration::minutes(10);
+        store.update(&second.id, reopened).await;
+        assert_eq!(store.pending_expiry_count(), 1);
+        assert_index_matches_store(&store).await;
+
+        store.delete(&second.id).await;
+        assert_eq!(store.pending_expiry_count(), 0);
+
+        // Marked Expired by a read, it stays indexed until cleanup removes it
+        let lapsed = expired_session("c");
+        store.create(lapsed.clone()).await;
+        assert_eq!(store.get(&lapsed.id).await.unwrap().status, SessionStatus::Expired);
+        assert_eq!(store.pending_expiry_count(), 1);
+        store.cleanup_expired().await;
+        assert_eq!(store.pending_expiry_count(), 0);
+        assert!(store.get(&lapsed.id).await.is_none());
+        assert_index_matches_store(&store).await;
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_removes_only_expired_entries_from_a_large_store() {
+        let store = SessionStore::new();
+        for n in 0..10_000 {
+            store.create(create_session(&format!("live-{}", n))).await;
+        }
+        for n in 0..50 {
+            store.create(expired_session(&format!("expired-{}", n))).await;
+            let mut granted = expired_session(&format!("granted-{}", n));
+            granted.status = SessionStatus::Granted;
+            store.create(granted).await;
+        }
+        assert_eq!(store.pending_expiry_count(), 10_050);
+
+        store.cleanup_expired().await;
+        assert_eq!(store.session_count().await, 10_050);
+        assert_eq!(store.pending_expiry_count(), 10_000);
+        // A second sweep has nothing left to visit
+        store.cleanup_expired().await;
+        assert_eq!(store.session_count().await, 10_050);
+        assert_index_matches_store(&store).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_expiry_index_stays_consistent_under_concurrent_changes() {
+        let store = SessionStore::new();
+        let writers: Vec<_> = (0..8)
+            .map(|worker| {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    for n in 0..200 {
+                        let host = format!("w{}-{}", worker, n);
+                        let session = if n % 2 == 0 { expired_session(&host) } else { create_session(&host) };
+                        store.create(session.clone()).await;
+                        match n % 5 {
+                            0 => store.delete(&session.id).await,
+                            1 => {
+                                store.grant_if_pending(&session.id, &session.otp).await;
+                            }
+                            2 => {
+                                let mut denied = session.clone();
+                                denied.status = SessionStatus::Denied;
+                                store.update(&session.id, denied).await;
+                            }
+                            _ => {
+                                store.get(&session.id).await;
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        let sweeper = {
+            let store = store.clone();
+            tokio::spawn(async move {
+                for _ in 0..50 {
+                    store.cleanup_expired().await;
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+        for writer in writers {
+            writer.await.unwrap();
+        }
+        sweeper.await.unwrap();
+        assert_index_matches_store(&store).await;
+
+        store.cleanup_expired().await;
+        assert_index_matches_store(&store).await;
+        let sessions = store.list().await;
+        let now = Utc::now();
+        assert!(sessions.iter().all(|s| !is_unanswered(s) || s.expires_at > now));
+        // Per worker, 40 sessions in each of deleted/granted/denied/read/untouched,
+        // half of them expired: the live halves of the last two groups stay
+        // unanswered, beside the 20 granted and 40 denied sessions
+        assert_eq!(store.pending_expiry_count(), 8 * 40);
+        assert_eq!(sessions.len(), 8 * (20 + 40 + 40));
+    }
 }
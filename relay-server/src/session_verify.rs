@@ -1,9 +1,19 @@
+use axum::{
+    extract::{Path as UrlPath, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 
+use crate::admin::require_admin;
+use crate::AppState;
+
 /// Cache for verified sessions from Astation.
 /// Reduces load on Astation by caching validation results.
 #[derive(Clone)]
@@ -19,6 +29,46 @@ struct CachedSession {
     ttl_seconds: u64,
 }
 
+impl CachedSession {
+    fn is_expired(&self, now: u64) -> bool {
+        now.saturating_sub(self.cached_at) >= self.ttl_seconds
+    }
+}
+
+/// On-disk form of a cache entry (see `VERIFY_CACHE_PERSIST_PATH`).
+#[derive(Debug, Serialize, Deserialize)]
+struct SerializedCacheEntry {
+    session_id: String,
+    astation_id: String,
+    valid: bool,
+    cached_at: u64,
+    ttl_seconds: u64,
+}
+
+impl From<&CachedSession> for SerializedCacheEntry {
+    fn from(cached: &CachedSession) -> Self {
+        SerializedCacheEntry {
+            session_id: cached.session_id.clone(),
+            astation_id: cached.astation_id.clone(),
+            valid: cached.valid,
+            cached_at: cached.cached_at,
+            ttl_seconds: cached.ttl_seconds,
+        }
+    }
+}
+
+impl From<SerializedCacheEntry> for CachedSession {
+    fn from(entry: SerializedCacheEntry) -> Self {
+        CachedSession {
+            session_id: entry.session_id,
+            astation_id: entry.astation_id,
+            valid: entry.valid,
+            cached_at: entry.cached_at,
+            ttl_seconds: entry.ttl_seconds,
+        }
+    }
+}
+
 impl SessionVerifyCache {
     pub fn new() -> Self {
         Self {
@@ -49,6 +99,38 @@ impl SessionVerifyCache {
         None
     }
 
+    /// Like [`get`](Self::get), but also returns which Astation verified the session.
+    pub async fn get_with_astation_id(&self, session_id: &str) -> Option<(bool, String)> {
+        let cache = self.cache.read().await;
+        let cached = cache.get(session_id)?;
+        if cached.is_expired(now_timestamp()) {
+            return None;
+        }
+        Some((cached.valid, cached.astation_id.clone()))
+    }
+
+    /// Admin view of a non-expired entry.
+    pub async fn entry(&self, session_id: &str) -> Option<VerifyCacheEntry> {
+        let cache = self.cache.read().await;
+        let cached = cache.get(session_id)?;
+        let now = now_timestamp();
+        if cached.is_expired(now) {
+            return None;
+        }
+        Some(VerifyCacheEntry {
+            session_id: cached.session_id.clone(),
+            astation_id: cached.astation_id.clone(),
+            valid: cached.valid,
+            age_secs: now.saturating_sub(cached.cached_at),
+            ttl_secs: cached.ttl_seconds,
+        })
+    }
+
+    /// Number of cached results, expired ones included until cleanup.
+    pub async fn entry_count(&self) -> usize {
+        self.cache.read().await.len()
+    }
+
     /// Cache a session validation result.
     pub async fn set(&self, session_id: String, astation_id: String, valid: bool, ttl_seconds: u64) {
         let mut cache = self.cache.write().await;
@@ -77,6 +159,26 @@ impl SessionVerifyCache {
         tracing::debug!("Session {} removed from cache", session_id);
     }
 
+    /// Remove every entry verified by `astation_id` (e.g. after that Astation is
+    /// reset or decommissioned). Returns the number of entries removed.
+    pub async fn invalidate_by_astation_id(&self, astation_id: &str) -> usize {
+        let mut cache = self.cache.write().await;
+        let before_count = cache.len();
+        cache.retain(|_, cached| cached.astation_id != astation_id);
+        let removed = before_count - cache.len();
+        tracing::info!("Removed {} session cache entries for astation {}", removed, astation_id);
+        removed
+    }
+
+    /// Remove every entry, expired or not. Returns the number removed.
+    pub async fn invalidate_all(&self) -> usize {
+        let mut cache = self.cache.write().await;
+        let removed = cache.len();
+        cache.clear();
+        tracing::info!("Removed all {} session cache entries", removed);
+        removed
+    }
+
     /// Clean up expired entries (called periodically).
     pub async fn cleanup_expired(&self) {
         let now = now_timestamp();
@@ -94,6 +196,52 @@ impl SessionVerifyCache {
         }
     }
 
+    /// Write all non-expired entries to `path` (temp file, then rename).
+    /// Returns the number of entries written.
+    pub async fn save(&self, path: &Path) -> io::Result<usize> {
+        let now = now_timestamp();
+        let entries: Vec<SerializedCacheEntry> = {
+            let cache = self.cache.read().await;
+            cache
+                .values()
+                .filter(|cached| !cached.is_expired(now))
+                .map(SerializedCacheEntry::from)
+                .collect()
+        };
+        let json = serde_json::to_vec(&entries)?;
+
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(".tmp");
+        let tmp: PathBuf = path.with_file_name(tmp_name);
+        tokio::fs::write(&tmp, json).await?;
+        tokio::fs::rename(&tmp, path).await?;
+        Ok(entries.len())
+    }
+
+    /// Add the non-expired entries saved at `path`. A missing file loads nothing.
+    /// Returns the number of entries loaded.
+    pub async fn load(&self, path: &Path) -> io::Result<usize> {
+        let bytes = match tokio::fs::read(path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let entries: Vec<SerializedCacheEntry> = serde_json::from_slice(&bytes)?;
+
+        let now = now_timestamp();
+        let mut cache = self.cache.write().await;
+        let mut loaded = 0;
+        for entry in entries {
+            let cached = CachedSession::from(entry);
+            if cached.is_expired(now) {
+                continue;
+            }
+            cache.insert(cached.session_id.clone(), cached);
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+
     /// Get cache statistics.
     pub async fn stats(&self) -> CacheStats {
         let cache = self.cache.read().await;
@@ -136,7 +284,62 @@ pub struct CacheStats {
     pub expired: usize,
 }
 
+/// A cache entry as shown by the admin API.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyCacheEntry {
+    pub session_id: String,
+    pub astation_id: String,
+    pub valid: bool,
+    pub age_secs: u64,
+    pub ttl_secs: u64,
+}
+
+/// GET /api/admin/verify-cache/:session_id
+pub async fn get_verify_cache_entry_handler(
+    State(state): State<AppState>,
+    UrlPath(session_id): UrlPath<String>,
+    headers: HeaderMap,
+) -> Result<Json<VerifyCacheEntry>, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&state, &headers)?;
+    state
+        .session_verify_cache
+        .entry(&session_id)
+        .await
+        .map(Json)
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Session not cached" })),
+        ))
+}
+
+/// DELETE /api/admin/verify-cache/astation/:id
+///
+/// Drop every cached verification from one Astation →
+/// `{"invalidated": N, "astation_id": "..."}`.
+pub async fn invalidate_by_astation_handler(
+    State(state): State<AppState>,
+    UrlPath(astation_id): UrlPath<String>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&state, &headers)?;
+    let invalidated = state.session_verify_cache.invalidate_by_astation_id(&astation_id).await;
+    Ok(Json(serde_json::json!({ "invalidated": invalidated, "astation_id": astation_id })))
+}
+
+/// DELETE /api/admin/verify-cache
+///
+/// Drop every cached verification → `{"invalidated": N}`.
+pub async fn invalidate_all_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    require_admin(&state, &headers)?;
+    let invalidated = state.session_verify_cache.invalidate_all().await;
+    Ok(Json(serde_json::json!({ "invalidated": invalidated })))
+}
+
 /// Message sent from Relay to Astation to verify a session.
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SessionVerifyRequest {
     pub session_id: String,
@@ -144,6 +347,7 @@ pub struct SessionVerifyRequest {
 }
 
 /// Message sent from Astation to Relay with verification result.
+#[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SessionVerifyResponse {
     pub session_id: String,
@@ -232,6 +436,116 @@ mod tests {
         assert!(cache.get("sess-abc").await.is_none());
     }
 
+    #[tokio::test]
+    async fn test_get_with_astation_id() {
+        let cache = SessionVerifyCache::new();
+        cache.set("sess-a".to_string(), "astation-home".to_string(), true, 300).await;
+        cache.set("sess-b".to_string(), "astation-work".to_string(), false, 300).await;
+        cache.set("sess-old".to_string(), "astation-home".to_string(), true, 0).await;
+
+        assert_eq!(cache.get_with_astation_id("sess-a").await, Some((true, "astation-home".to_string())));
+        assert_eq!(cache.get_with_astation_id("sess-b").await, Some((false, "astation-work".to_string())));
+        assert!(cache.get_with_astation_id("sess-old").await.is_none());
+        assert!(cache.get_with_astation_id("missing").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_by_astation() {
+        let cache = SessionVerifyCache::new();
+        cache.set("sess-1".to_string(), "astation-home".to_string(), true, 300).await;
+        cache.set("sess-2".to_string(), "astation-home".to_string(), false, 300).await;
+        cache.set("sess-3".to_string(), "astation-work".to_string(), true, 300).await;
+
+        assert_eq!(cache.invalidate_by_astation_id("astation-home").await, 2);
+        assert!(cache.get("sess-1").await.is_none());
+        assert!(cache.get("sess-2").await.is_none());
+        assert_eq!(cache.get("sess-3").await, Some(true));
+        assert_eq!(cache.entry_count().await, 1);
+        assert_eq!(cache.invalidate_by_astation_id("astation-home").await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_all() {
+        let cache = SessionVerifyCache::new();
+        cache.set("sess-1".to_string(), "astation-home".to_string(), true, 300).await;
+        cache.set("sess-2".to_string(), "astation-work".to_string(), false, 300).await;
+        // Expired entries still awaiting cleanup are counted too
+        cache.set("sess-3".to_string(), "astation-work".to_string(), true, 0).await;
+
+        let before = cache.entry_count().await;
+        assert_eq!(cache.invalidate_all().await, before);
+        assert_eq!(cache.entry_count().await, 0);
+        assert_eq!(cache.invalidate_all().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_admin_verify_cache_endpoints() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use axum::routing::{delete, get};
+        use axum::Router;
+        use tower::ServiceExt;
+
+        let state = AppState {
+            sessions: crate::session_store::SessionStore::new(),
+            relay: crate::relay::RelayHub::new(),
+            device_pairs: Default::default(),
+            rtc_sessions: crate::rtc_session::RtcSessionStore::new(),
+            session_verify_cache: SessionVerifyCache::new(),
+            voice_sessions: crate::voice_session::VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: Some("admin-secret".into()),
+        };
+        state.session_verify_cache.set("sess-1".to_string(), "ast-1".to_string(), true, 300).await;
+        let state_cache = state.session_verify_cache.clone();
+        let app = Router::new()
+            .route("/api/admin/verify-cache/:session_id", get(get_verify_cache_entry_handler))
+            .route("/api/admin/verify-cache", delete(invalidate_all_handler))
+            .route(
+                "/api/admin/verify-cache/astation/:id",
+                delete(invalidate_by_astation_handler),
+            )
+            .with_state(state);
+        let send = |method: &str, uri: &str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header("authorization", "Bearer admin-secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+        let json = |response: axum::response::Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        let response = send("GET", "/api/admin/verify-cache/sess-1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let entry = json(response).await;
+        assert_eq!(entry["session_id"], "sess-1");
+        assert_eq!(entry["astation_id"], "ast-1");
+        assert_eq!(entry["valid"], true);
+        assert_eq!(entry["ttl_secs"], 300);
+        assert!(entry["age_secs"].as_u64().unwrap() <= 1);
+
+        let response = send("DELETE", "/api/admin/verify-cache/astation/ast-1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await, serde_json::json!({ "invalidated": 1, "astation_id": "ast-1" }));
+
+        let response = send("GET", "/api/admin/verify-cache/sess-1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        state_cache.set("sess-2".to_string(), "ast-2".to_string(), true, 300).await;
+        state_cache.set("sess-3".to_string(), "ast-3".to_string(), false, 300).await;
+        let response = send("DELETE", "/api/admin/verify-cache").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await["invalidated"], 2);
+        assert_eq!(state_cache.entry_count().await, 0);
+    }
+
     #[tokio::test]
     async fn test_cleanup_expired() {
         let cache = SessionVerifyCache::new();
@@ -252,6 +566,61 @@ mod tests {
         assert_eq!(stats.total, 1);
     }
 
+    #[tokio::test]
+    async fn test_save_and_load_round_trip() {
+        let path = std::env::temp_dir().join(format!("verify-cache-{}.json", uuid::Uuid::new_v4()));
+        let cache = SessionVerifyCache::new();
+        cache.set("sess-ok".to_string(), "ast-1".to_string(), true, 300).await;
+        cache.set("sess-bad".to_string(), "ast-2".to_string(), false, 300).await;
+        cache.set("sess-old".to_string(), "ast-3".to_string(), true, 0).await;
+
+        assert_eq!(cache.save(&path).await.unwrap(), 2);
+
+        let restored = SessionVerifyCache::new();
+        assert_eq!(restored.load(&path).await.unwrap(), 2);
+        assert_eq!(restored.get("sess-ok").await, Some(true));
+        assert_eq!(restored.get("sess-bad").await, Some(false));
+        assert!(restored.get("sess-old").await.is_none());
+        assert_eq!(restored.stats().await.total, 2);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_load_discards_entries_expired_on_disk() {
+        let path = std::env::temp_dir().join(format!("verify-cache-{}.json", uuid::Uuid::new_v4()));
+        let entries = vec![
+            SerializedCacheEntry {
+                session_id: "fresh".into(),
+                astation_id: "ast".into(),
+                valid: true,
+                cached_at: now_timestamp(),
+                ttl_seconds: 300,
+            },
+            SerializedCacheEntry {
+                session_id: "stale".into(),
+                astation_id: "ast".into(),
+                valid: true,
+                cached_at: now_timestamp() - 600,
+                ttl_seconds: 300,
+            },
+        ];
+        std::fs::write(&path, serde_json::to_vec(&entries).unwrap()).unwrap();
+
+        let cache = SessionVerifyCache::new();
+        assert_eq!(cache.load(&path).await.unwrap(), 1);
+        assert_eq!(cache.get("fresh").await, Some(true));
+        assert!(cache.get("stale").await.is_none());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_load_missing_file() {
+        let path = std::env::temp_dir().join(format!("verify-cache-{}.json", uuid::Uuid::new_v4()));
+        assert_eq!(SessionVerifyCache::new().load(&path).await.unwrap(), 0);
+    }
+
     #[tokio::test]
     async fn test_cache_stats() {
         let cache = SessionVerifyCache::new();
@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{extract::State, Json};
+use dashmap::DashMap;
+use serde::Serialize;
+use tokio::task::JoinHandle;
+
+use crate::AppState;
+
+/// Pause before a background task that exited or panicked is started again.
+pub const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+/// How many times each supervised background task has been restarted.
+#[derive(Default)]
+pub struct TaskRestarts {
+    tasks: DashMap<String, Arc<AtomicU32>>,
+}
+
+impl TaskRestarts {
+    fn counter(&self, name: &str) -> Arc<AtomicU32> {
+        self.tasks.entry(name.to_string()).or_default().clone()
+    }
+
+    /// Restart count per task name; tasks that never restarted report 0.
+    pub fn snapshot(&self) -> BTreeMap<String, u32> {
+        self.tasks
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().load(Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+/// Run the future `task` builds in the background, starting a fresh one
+/// [`RESTART_DELAY`] after it returns or panics. A plain `tokio::spawn`
+/// would stop for good on the first panic.
+pub fn spawn_supervised<F, Fut>(restarts: &Arc<TaskRestarts>, name: &str, task: F) -> JoinHandle<()>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    spawn_supervised_with_delay(restarts, name, RESTART_DELAY, task)
+}
+
+fn spawn_supervised_with_delay<F, Fut>(
+    restarts: &Arc<TaskRestarts>,
+    name: &str,
+    delay: Duration,
+    task: F,
+) -> JoinHandle<()>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let counter = restarts.counter(name);
+    let name = name.to_string();
+    tokio::spawn(async move {
+        loop {
+            match tokio::spawn(task()).await {
+                Ok(()) => tracing::error!("Background task {} exited, restarting in {:?}", name, delay),
+                Err(e) => tracing::error!("Background task {} failed: {}, restarting in {:?}", name, e, delay),
+            }
+            tokio::time::sleep(delay).await;
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthResponse {
+    pub status: &'static str,
+    pub task_restarts: BTreeMap<String, u32>,
+}
+
+/// GET /health
+pub async fn health_handler(State(state): State<AppState>) -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok",
+        task_restarts: state.task_restarts.snapshot(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[tokio::test]
+    async fn test_panicking_task_is_restarted() {
+        let restarts = Arc::new(TaskRestarts::default());
+        let runs = Arc::new(AtomicUsize::new(0));
+        let task_runs = runs.clone();
+        let handle = spawn_supervised_with_delay(&restarts, "flaky", Duration::from_millis(10), move || {
+            let runs = task_runs.clone();
+            async move {
+                if runs.fetch_add(1, Ordering::SeqCst) < 2 {
+                    panic!("injected failure");
+                }
+                // Third run stays up
+                std::future::pending::<()>().await;
+            }
+        });
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while runs.load(Ordering::SeqCst) < 3 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("task was not restarted");
+        assert_eq!(restarts.snapshot()["flaky"], 2);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert_eq!(restarts.snapshot()["flaky"], 2);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn test_tasks_are_listed_before_any_restart() {
+        let restarts = Arc::new(TaskRestarts::default());
+        let handle = spawn_supervised(&restarts, "steady", std::future::pending::<()>);
+        assert_eq!(restarts.snapshot(), BTreeMap::from([("steady".to_string(), 0)]));
+        handle.abort();
+    }
+}
@@ -0,0 +1,54 @@
+use axum::Json;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// Binary name, as built by cargo.
+pub const BINARY_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// Crate version from Cargo.toml.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// When the binary was built, RFC 3339 in UTC. Stamped by `build.rs`.
+pub fn build_timestamp() -> String {
+    env!("BUILD_TIMESTAMP")
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .map(|at| at.to_rfc3339())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VersionResponse {
+    pub version: String,
+    pub build_timestamp: String,
+}
+
+/// GET /api/version — Server version and build time (no auth).
+pub async fn version_handler() -> Json<VersionResponse> {
+    Json(VersionResponse {
+        version: VERSION.to_string(),
+        build_timestamp: build_timestamp(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_version_endpoint_reports_version_and_build_time() {
+        let app = Router::new().route("/api/version", get(version_handler));
+        let response = app
+            .oneshot(Request::builder().uri("/api/version").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let version: VersionResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+        assert!(DateTime::parse_from_rfc3339(&version.build_timestamp).is_ok(), "{}", version.build_timestamp);
+    }
+}
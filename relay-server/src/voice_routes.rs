@@ -1,12 +1,16 @@
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
     Json,
 };
+use validator::Validate;
+use crate::admin::require_admin;
 use crate::AppState;
 use crate::voice_session::{
-    CreateVoiceSessionRequest, CreateVoiceSessionResponse,
+    CreateVoiceSessionRequest, CreateVoiceSessionResponse, SetSystemPromptRequest,
     TriggerResponse, AtemResponseRequest, AtemResponseResponse,
+    VoiceBufferResponse,
 };
 
 /// POST /api/voice-sessions
@@ -16,13 +20,25 @@ pub async fn create_voice_session_handler(
     State(state): State<AppState>,
     Json(req): Json<CreateVoiceSessionRequest>,
 ) -> Result<Json<CreateVoiceSessionResponse>, StatusCode> {
+    req.validate().map_err(|_| StatusCode::BAD_REQUEST)?;
     let session_id = uuid::Uuid::new_v4().to_string();
 
     let session = state.voice_sessions.create(
         session_id.clone(),
         req.atem_id.clone(),
         req.channel.clone(),
-    ).await;
+    ).await.map_err(|e| {
+        tracing::warn!("Voice session for Atem {} refused: {}", req.atem_id, e);
+        StatusCode::TOO_MANY_REQUESTS
+    })?;
+    if req.system_prompt.is_some() {
+        state.voice_sessions.set_system_prompt(&session_id, req.system_prompt).await
+            .map_err(|e| e.status())?;
+    }
+    if !req.trigger_keywords.is_empty() {
+        state.voice_sessions.set_trigger_keywords(&session_id, req.trigger_keywords).await
+            .map_err(|e| e.status())?;
+    }
 
     tracing::info!(
         "Created voice session {} for Atem {} in channel {}",
@@ -50,8 +66,8 @@ pub async fn trigger_voice_session_handler(
     State(state): State<AppState>,
     Path(session_id): Path<String>,
 ) -> Result<Json<TriggerResponse>, StatusCode> {
-    let accumulated_text = state.voice_sessions.trigger(&session_id).await
-        .ok_or(StatusCode::NOT_FOUND)?;
+    let transcript = state.voice_sessions.trigger(&session_id).await
+        .map_err(|e| e.status())?;
 
     let session = state.voice_sessions.get(&session_id).await
         .ok_or(StatusCode::NOT_FOUND)?;
@@ -59,16 +75,47 @@ pub async fn trigger_voice_session_handler(
     tracing::info!(
         "Triggered session {}: accumulated_text = \"{}\"",
         session_id,
-        accumulated_text
+        transcript.text
     );
 
     Ok(Json(TriggerResponse {
         session_id: session.session_id,
-        accumulated_text,
+        accumulated_text: transcript.text,
+        chunks: transcript.chunks,
         atem_id: session.atem_id,
+        triggered_by_keyword: session.triggered_by_keyword,
     }))
 }
 
+/// POST /api/voice-sessions/:id/reset
+///
+/// Start the next turn after a response was delivered: clears the transcript and
+/// response and returns the session to accumulating (409 from any other state)
+pub async fn reset_voice_session_handler(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    state.voice_sessions.reset(&session_id).await.map_err(|e| e.status())?;
+    tracing::info!("Reset voice session {}", session_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// PUT /api/voice-sessions/:id/system-prompt
+///
+/// Replace the system prompt sent ahead of the session's chat requests
+/// (called by Atem; applies from the next triggered request)
+pub async fn set_system_prompt_handler(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Json(req): Json<SetSystemPromptRequest>,
+) -> Result<StatusCode, StatusCode> {
+    req.validate().map_err(|_| StatusCode::BAD_REQUEST)?;
+    state.voice_sessions.set_system_prompt(&session_id, req.system_prompt).await
+        .map_err(|e| e.status())?;
+    tracing::info!("Updated system prompt for voice session {}", session_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
 /// POST /api/voice-sessions/response
 ///
 /// Receive LLM response from Atem and wake up waiting /api/llm/chat requests
@@ -77,8 +124,8 @@ pub async fn atem_response_handler(
     State(state): State<AppState>,
     Json(req): Json<AtemResponseRequest>,
 ) -> Result<Json<AtemResponseResponse>, StatusCode> {
-    state.voice_sessions.set_response(&req.session_id, req.response.clone()).await
-        .ok_or(StatusCode::NOT_FOUND)?;
+    state.voice_sessions.set_response_with_usage(&req.session_id, req.response.clone(), req.usage).await
+        .map_err(|e| e.status())?;
 
     tracing::info!(
         "Received response for session {}: {} chars",
@@ -113,9 +160,35 @@ pub async fn get_voice_session_handler(
         "created_at": session.created_at,
         "last_activity": session.last_activity,
         "request_count": session.request_count,
+        "llm_params": session.llm_params,
+        "total_prompt_tokens": session.total_prompt_tokens,
+        "total_completion_tokens": session.total_completion_tokens,
+        "dedup_skipped": session.dedup_skipped,
+        "dedup_words_removed": session.dedup_words_removed,
+        "system_prompt": session.system_prompt,
+        "llm_messages": session.llm_messages,
+        "trigger_keywords": session.trigger_keywords,
+        "triggered_by_keyword": session.triggered_by_keyword,
     })))
 }
 
+/// GET /api/voice-sessions/:id/buffer
+///
+/// Buffered transcription chunks with timestamps (for debugging)
+pub async fn get_voice_buffer_handler(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+) -> Result<Json<VoiceBufferResponse>, StatusCode> {
+    let session = state.voice_sessions.get(&session_id).await
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(VoiceBufferResponse {
+        accumulated_text: session.get_accumulated_text(),
+        session_id: session.session_id,
+        chunks: session.buffer,
+    }))
+}
+
 /// DELETE /api/voice-sessions/:id
 ///
 /// Delete session (cleanup)
@@ -142,6 +215,17 @@ pub async fn list_voice_sessions_handler(
     })))
 }
 
+/// GET /api/admin/voice-sessions/token-usage
+///
+/// LLM token totals across all active sessions (admin token required)
+pub async fn voice_token_usage_admin_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    require_admin(&state, &headers)?;
+    Ok::<_, (StatusCode, Json<serde_json::Value>)>(Json(state.voice_sessions.token_usage().await))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -155,9 +239,13 @@ mod tests {
         AppState {
             sessions: SessionStore::new(),
             relay: RelayHub::new(),
+            device_pairs: Default::default(),
             rtc_sessions: RtcSessionStore::new(),
             session_verify_cache: SessionVerifyCache::new(),
             voice_sessions: VoiceSessionStore::new(),
+            request_metrics: Default::default(),
+            task_restarts: Default::default(),
+            admin_token: None,
         }
     }
 
@@ -167,6 +255,8 @@ mod tests {
         let req = CreateVoiceSessionRequest {
             atem_id: "atem-123".to_string(),
             channel: "test-channel".to_string(),
+            system_prompt: None,
+            trigger_keywords: Vec::new(),
         };
 
         let result = create_voice_session_handler(State(state), Json(req)).await;
@@ -184,9 +274,9 @@ mod tests {
             "test-123".to_string(),
             "atem-456".to_string(),
             "channel-789".to_string(),
-        ).await;
+        ).await.unwrap();
 
-        state.voice_sessions.add_transcription("test-123", "Hello world".to_string()).await;
+        state.voice_sessions.add_transcription("test-123", "Hello world".to_string(), None).await;
 
         let result = trigger_voice_session_handler(
             State(state),
@@ -196,6 +286,29 @@ mod tests {
         assert!(result.is_ok());
         let response = result.unwrap().0;
         assert_eq!(response.accumulated_text, "Hello world");
+        assert!(!response.triggered_by_keyword);
+    }
+
+    #[tokio::test]
+    async fn test_trigger_reports_keyword_trigger() {
+        let state = create_test_state();
+        let created = create_voice_session_handler(
+            State(state.clone()),
+            Json(CreateVoiceSessionRequest {
+                atem_id: "atem-123".to_string(),
+                channel: "test-channel".to_string(),
+                system_prompt: None,
+                trigger_keywords: vec!["Over".to_string(), " ".to_string()],
+            }),
+        ).await.unwrap().0;
+        let id = created.session_id;
+        let info = get_voice_session_handler(State(state.clone()), Path(id.clone())).await.unwrap().0;
+        assert_eq!(info["trigger_keywords"], serde_json::json!(["Over"]));
+
+        state.voice_sessions.add_transcription(&id, "rename the module, over".to_string(), None).await;
+        let response = trigger_voice_session_handler(State(state.clone()), Path(id.clone())).await.unwrap().0;
+        assert!(response.triggered_by_keyword);
+        assert_eq!(response.accumulated_text, "rename the module, over");
     }
 
     #[tokio::test]
@@ -205,11 +318,13 @@ mod tests {
             "test-123".to_string(),
             "atem-456".to_string(),
             "channel-789".to_string(),
-        ).await;
+        ).await.unwrap();
+        state.voice_sessions.trigger("test-123").await.unwrap();
 
         let req = AtemResponseRequest {
             session_id: "test-123".to_string(),
             response: "Here's the implementation...".to_string(),
+            usage: None,
         };
 
         let result = atem_response_handler(State(state.clone()), Json(req)).await;
@@ -226,7 +341,7 @@ mod tests {
             "test-123".to_string(),
             "atem-456".to_string(),
             "channel-789".to_string(),
-        ).await;
+        ).await.unwrap();
 
         let result = get_voice_session_handler(
             State(state),
@@ -246,7 +361,7 @@ mod tests {
             "test-123".to_string(),
             "atem-456".to_string(),
             "channel-789".to_string(),
-        ).await;
+        ).await.unwrap();
 
         let result = delete_voice_session_handler(
             State(state.clone()),
@@ -263,8 +378,8 @@ mod tests {
     #[tokio::test]
     async fn test_list_voice_sessions() {
         let state = create_test_state();
-        state.voice_sessions.create("test-1".to_string(), "atem".to_string(), "ch".to_string()).await;
-        state.voice_sessions.create("test-2".to_string(), "atem".to_string(), "ch".to_string()).await;
+        state.voice_sessions.create("test-1".to_string(), "atem".to_string(), "ch".to_string()).await.unwrap();
+        state.voice_sessions.create("test-2".to_string(), "atem".to_string(), "ch".to_string()).await.unwrap();
 
         let result = list_voice_sessions_handler(State(state)).await;
         assert!(result.is_ok());
@@ -293,6 +408,7 @@ mod tests {
         let req = AtemResponseRequest {
             session_id: "nonexistent".to_string(),
             response: "Some response".to_string(),
+            usage: None,
         };
 
         let result = atem_response_handler(State(state), Json(req)).await;
@@ -321,7 +437,7 @@ mod tests {
             "test-empty".to_string(),
             "atem-1".to_string(),
             "channel-1".to_string(),
-        ).await;
+        ).await.unwrap();
 
         // Trigger with no transcriptions added
         let result = trigger_voice_session_handler(
@@ -341,10 +457,10 @@ mod tests {
             "test-state".to_string(),
             "atem-1".to_string(),
             "channel-1".to_string(),
-        ).await;
+        ).await.unwrap();
 
-        state.voice_sessions.add_transcription("test-state", "Hello".to_string()).await;
-        trigger_voice_session_handler(
+        state.voice_sessions.add_transcription("test-state", "Hello".to_string(), None).await;
+        let _ = trigger_voice_session_handler(
             State(state.clone()),
             Path("test-state".to_string()),
         ).await.unwrap();
@@ -360,19 +476,57 @@ mod tests {
             "test-resp".to_string(),
             "atem-1".to_string(),
             "channel-1".to_string(),
-        ).await;
+        ).await.unwrap();
+        state.voice_sessions.trigger("test-resp").await.unwrap();
 
         let req = AtemResponseRequest {
             session_id: "test-resp".to_string(),
             response: "Done!".to_string(),
+            usage: None,
         };
-        atem_response_handler(State(state.clone()), Json(req)).await.unwrap();
+        let _ = atem_response_handler(State(state.clone()), Json(req)).await.unwrap();
 
         let session = state.voice_sessions.get("test-resp").await.unwrap();
         assert_eq!(session.state, crate::voice_session::VoiceSessionState::ResponseReady);
         assert_eq!(session.response, Some("Done!".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_out_of_order_transitions_conflict() {
+        let state = create_test_state();
+        state.voice_sessions.create(
+            "test-order".to_string(),
+            "atem-1".to_string(),
+            "channel-1".to_string(),
+        ).await.unwrap();
+        let respond = || AtemResponseRequest {
+            session_id: "test-order".to_string(),
+            response: "Done!".to_string(),
+            usage: None,
+        };
+        let path = || Path("test-order".to_string());
+
+        // Accumulating: no response or reset yet
+        assert_eq!(atem_response_handler(State(state.clone()), Json(respond())).await.unwrap_err(), StatusCode::CONFLICT);
+        assert_eq!(reset_voice_session_handler(State(state.clone()), path()).await.unwrap_err(), StatusCode::CONFLICT);
+
+        // Triggered: a second trigger is refused
+        let _ = trigger_voice_session_handler(State(state.clone()), path()).await.unwrap();
+        assert_eq!(trigger_voice_session_handler(State(state.clone()), path()).await.unwrap_err(), StatusCode::CONFLICT);
+
+        // ResponseReady: a repeated response is accepted and ignored; only reset moves on
+        let _ = atem_response_handler(State(state.clone()), Json(respond())).await.unwrap();
+        assert!(atem_response_handler(State(state.clone()), Json(respond())).await.is_ok());
+        assert_eq!(trigger_voice_session_handler(State(state.clone()), path()).await.unwrap_err(), StatusCode::CONFLICT);
+        assert_eq!(reset_voice_session_handler(State(state.clone()), path()).await.unwrap(), StatusCode::NO_CONTENT);
+        let _ = trigger_voice_session_handler(State(state.clone()), path()).await.unwrap();
+
+        assert_eq!(
+            reset_voice_session_handler(State(state), Path("nonexistent".to_string())).await.unwrap_err(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
     #[tokio::test]
     async fn test_multiple_transcriptions_accumulated() {
         let state = create_test_state();
@@ -380,11 +534,11 @@ mod tests {
             "test-multi".to_string(),
             "atem-1".to_string(),
             "channel-1".to_string(),
-        ).await;
+        ).await.unwrap();
 
-        state.voice_sessions.add_transcription("test-multi", "Please".to_string()).await;
-        state.voice_sessions.add_transcription("test-multi", "create".to_string()).await;
-        state.voice_sessions.add_transcription("test-multi", "a function".to_string()).await;
+        state.voice_sessions.add_transcription("test-multi", "Please".to_string(), None).await;
+        state.voice_sessions.add_transcription("test-multi", "create".to_string(), None).await;
+        state.voice_sessions.add_transcription("test-multi", "a function".to_string(), None).await;
 
         let result = trigger_voice_session_handler(
             State(state),
@@ -395,4 +549,164 @@ mod tests {
         let response = result.unwrap().0;
         assert_eq!(response.accumulated_text, "Please create a function");
     }
+
+    #[tokio::test]
+    async fn test_get_voice_buffer() {
+        let state = create_test_state();
+        state.voice_sessions.create(
+            "test-buf".to_string(),
+            "atem-1".to_string(),
+            "channel-1".to_string(),
+        ).await.unwrap();
+        state.voice_sessions.add_transcription("test-buf", "Open".to_string(), Some(0.8)).await;
+        state.voice_sessions.add_transcription("test-buf", "the file".to_string(), None).await;
+
+        let response = get_voice_buffer_handler(
+            State(state),
+            Path("test-buf".to_string()),
+        ).await.unwrap().0;
+
+        assert_eq!(response.accumulated_text, "Open the file");
+        assert_eq!(response.chunks.len(), 2);
+        assert_eq!(response.chunks[0].confidence, Some(0.8));
+        assert!(response.chunks[0].received_at <= response.chunks[1].received_at);
+    }
+
+    #[tokio::test]
+    async fn test_get_voice_buffer_nonexistent() {
+        let state = create_test_state();
+        let result = get_voice_buffer_handler(
+            State(state),
+            Path("nonexistent".to_string()),
+        ).await;
+        assert_eq!(result.unwrap_err(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_system_prompt_set_at_creation_and_updated() {
+        let state = create_test_state();
+        let created = create_voice_session_handler(
+            State(state.clone()),
+            Json(CreateVoiceSessionRequest {
+                atem_id: "atem-123".to_string(),
+                channel: "test-channel".to_string(),
+                system_prompt: Some("Answer in French.".to_string()),
+                trigger_keywords: Vec::new(),
+            }),
+        ).await.unwrap().0;
+        let id = created.session_id;
+        let info = get_voice_session_handler(State(state.clone()), Path(id.clone())).await.unwrap().0;
+        assert_eq!(info["system_prompt"], "Answer in French.");
+
+        let status = set_system_prompt_handler(
+            State(state.clone()),
+            Path(id.clone()),
+            Json(SetSystemPromptRequest { system_prompt: Some("Be terse.".to_string()) }),
+        ).await.unwrap();
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let info = get_voice_session_handler(State(state.clone()), Path(id.clone())).await.unwrap().0;
+        assert_eq!(info["system_prompt"], "Be terse.");
+
+        set_system_prompt_handler(
+            State(state.clone()),
+            Path(id.clone()),
+            Json(SetSystemPromptRequest { system_prompt: None }),
+        ).await.unwrap();
+        assert!(state.voice_sessions.get(&id).await.unwrap().system_prompt.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_system_prompt_is_limited_to_4096_chars() {
+        let state = create_test_state();
+        let result = create_voice_session_handler(
+            State(state.clone()),
+            Json(CreateVoiceSessionRequest {
+                atem_id: "atem-123".to_string(),
+                channel: "test-channel".to_string(),
+                system_prompt: Some("x".repeat(4097)),
+                trigger_keywords: Vec::new(),
+            }),
+        ).await;
+        assert_eq!(result.unwrap_err(), StatusCode::BAD_REQUEST);
+        assert!(state.voice_sessions.list_session_ids().await.is_empty());
+
+        state.voice_sessions.create(
+            "test-123".to_string(),
+            "atem-456".to_string(),
+            "channel-789".to_string(),
+        ).await.unwrap();
+        let set = |prompt: String| set_system_prompt_handler(
+            State(state.clone()),
+            Path("test-123".to_string()),
+            Json(SetSystemPromptRequest { system_prompt: Some(prompt) }),
+        );
+        assert_eq!(set("é".repeat(4096)).await.unwrap(), StatusCode::NO_CONTENT);
+        assert_eq!(set("x".repeat(4097)).await.unwrap_err(), StatusCode::BAD_REQUEST);
+
+        let missing = set_system_prompt_handler(
+            State(state.clone()),
+            Path("nonexistent".to_string()),
+            Json(SetSystemPromptRequest { system_prompt: None }),
+        ).await;
+        assert_eq!(missing.unwrap_err(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_create_voice_session_over_atem_limit() {
+        let state = create_test_state();
+        let create = || create_voice_session_handler(
+            State(state.clone()),
+            Json(CreateVoiceSessionRequest {
+                atem_id: "atem-busy".to_string(),
+                channel: "test-channel".to_string(),
+                system_prompt: None,
+                trigger_keywords: Vec::new(),
+            }),
+        );
+        for _ in 0..crate::voice_session::DEFAULT_MAX_SESSIONS_PER_ATEM {
+            assert!(create().await.is_ok());
+        }
+        assert_eq!(create().await.unwrap_err(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_voice_token_usage_admin() {
+        let mut state = create_test_state();
+        state.admin_token = Some("admin-secret".into());
+        state.voice_sessions.create(
+            "test-usage".to_string(),
+            "atem-1".to_string(),
+            "channel-1".to_string(),
+        ).await.unwrap();
+        state.voice_sessions.trigger("test-usage").await.unwrap();
+        let req = AtemResponseRequest {
+            session_id: "test-usage".to_string(),
+            response: "Done!".to_string(),
+            usage: Some(crate::llm_proxy::Usage { prompt_tokens: 12, completion_tokens: 4 }),
+        };
+        let _ = atem_response_handler(State(state.clone()), Json(req)).await.unwrap();
+        state.voice_sessions.claim_usage("test-usage").await;
+
+        let session = get_voice_session_handler(State(state.clone()), Path("test-usage".to_string()))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(session["total_prompt_tokens"], 12);
+        assert_eq!(session["total_completion_tokens"], 4);
+
+        let mut headers = HeaderMap::new();
+        let response = voice_token_usage_admin_handler(State(state.clone()), headers.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        headers.insert("Authorization", "Bearer admin-secret".parse().unwrap());
+        let response = voice_token_usage_admin_handler(State(state), headers).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let totals: crate::voice_session::VoiceTokenUsageResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(totals.session_count, 1);
+        assert_eq!(totals.total_prompt_tokens, 12);
+        assert_eq!(totals.total_completion_tokens, 4);
+    }
 }
@@ -1,8 +1,12 @@
+use axum::http::StatusCode;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
-use tokio::sync::{oneshot, RwLock};
+use tokio::sync::{broadcast, oneshot, RwLock};
 use chrono::{DateTime, Utc};
+use validator::Validate;
+
+use crate::llm_proxy::{ChatMessage, Usage};
 
 /// Voice session state machine for LLM request accumulation
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
@@ -15,6 +19,96 @@ pub enum VoiceSessionState {
     ResponseReady,
 }
 
+/// Check a state change against the session state machine:
+/// `Accumulating → Triggered` (trigger), `Triggered → ResponseReady` (response),
+/// `ResponseReady → Accumulating` (reset). Anything else is refused.
+pub fn validate_transition(from: &VoiceSessionState, to: &VoiceSessionState) -> Result<(), &'static str> {
+    use VoiceSessionState::*;
+    match (from, to) {
+        (Accumulating, Triggered) | (Triggered, ResponseReady) | (ResponseReady, Accumulating) => Ok(()),
+        _ => Err("Invalid state transition"),
+    }
+}
+
+/// Why a voice session operation was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VoiceSessionError {
+    NotFound,
+    /// The session's current state does not allow the operation
+    InvalidTransition(&'static str),
+}
+
+impl VoiceSessionError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            VoiceSessionError::NotFound => StatusCode::NOT_FOUND,
+            VoiceSessionError::InvalidTransition(_) => StatusCode::CONFLICT,
+        }
+    }
+}
+
+/// One transcription fragment as received from Agora
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionChunk {
+    pub text: String,
+    pub received_at: DateTime<Utc>,
+    /// ASR confidence, when the upstream provides one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+}
+
+/// Buffered transcription returned when a session is triggered
+#[derive(Debug, Clone)]
+pub struct AccumulatedTranscript {
+    pub text: String,
+    pub chunks: Vec<TranscriptionChunk>,
+}
+
+/// Sampling parameters from the latest LLM request, kept so Atem can see what ConvoAI asked for
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LlmRequestParams {
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+}
+
+/// Default number of recent transcription texts checked for repeats (`LLM_DEDUP_WINDOW`)
+pub const DEFAULT_DEDUP_WINDOW: usize = 5;
+
+/// A transcription repeating one of the recent texts within this many seconds is dropped
+pub const DEDUP_WINDOW_SECS: i64 = 2;
+
+/// Default cap on the words a chunk may repeat from the end of the buffer
+/// before they are trimmed (`DEDUP_OVERLAP_WORDS`)
+pub const DEFAULT_DEDUP_OVERLAP_WORDS: usize = 5;
+
+/// Default number of long-triggered sessions at which `/api/llm/health`
+/// reports unhealthy (`LLM_HEALTH_STUCK_THRESHOLD`)
+pub const DEFAULT_LLM_HEALTH_STUCK_THRESHOLD: usize = 10;
+
+/// Default seconds a session may stay triggered before it counts as stuck
+/// (`LLM_HEALTH_STUCK_SECS`)
+pub const DEFAULT_LLM_HEALTH_STUCK_SECS: u64 = 60;
+
+/// State events buffered per subscriber before it starts missing some
+const VOICE_EVENT_CAPACITY: usize = 64;
+
+/// A session changed state. Triggers say what caused them.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VoiceStateEvent {
+    pub session_id: String,
+    pub state: VoiceSessionState,
+    /// `api` for `POST /trigger`, `keyword` when a trigger keyword was heard
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger_source: Option<&'static str>,
+    pub at: DateTime<Utc>,
+}
+
+/// Word compared for overlap: case and surrounding punctuation ignored
+fn overlap_key(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()
+}
+
 /// A voice coding session that accumulates transcriptions until triggered
 #[derive(Debug, Clone)]
 pub struct VoiceSession {
@@ -22,11 +116,35 @@ pub struct VoiceSession {
     pub atem_id: String,
     pub channel: String,
     pub state: VoiceSessionState,
-    pub buffer: Vec<String>, // Accumulated transcriptions
+    pub buffer: Vec<TranscriptionChunk>, // Accumulated transcriptions
     pub response: Option<String>, // LLM response from Atem
     pub created_at: DateTime<Utc>,
     pub last_activity: DateTime<Utc>,
+    /// When the session entered its current state
+    pub state_changed_at: DateTime<Utc>,
     pub request_count: u32,
+    pub llm_params: LlmRequestParams,
+    /// Usage Atem reported with the current response, until a chat request claims it
+    pub pending_usage: Option<Usage>,
+    pub total_prompt_tokens: u64,
+    pub total_completion_tokens: u64,
+    /// Last `dedup_window` transcription texts with when they arrived
+    recent_texts: VecDeque<(String, DateTime<Utc>)>,
+    dedup_window: usize,
+    /// Transcriptions dropped as repeats of a recent one
+    pub dedup_skipped: u64,
+    /// Most leading words of a chunk trimmed as a repeat of the buffer's end
+    overlap_words: usize,
+    /// Words trimmed from chunks that re-included the end of the buffer
+    pub dedup_words_removed: u64,
+    /// Sent as the first message of every chat request Atem answers
+    pub system_prompt: Option<String>,
+    /// Messages of the chat request waiting on Atem, system prompt first
+    pub llm_messages: Vec<ChatMessage>,
+    /// Phrases that trigger the session when the transcript ends with them
+    pub trigger_keywords: Vec<String>,
+    /// The current trigger came from a keyword rather than `POST /trigger`
+    pub triggered_by_keyword: bool,
 }
 
 impl VoiceSession {
@@ -41,32 +159,190 @@ impl VoiceSession {
             response: None,
             created_at: now,
             last_activity: now,
+            state_changed_at: now,
             request_count: 0,
+            llm_params: LlmRequestParams::default(),
+            pending_usage: None,
+            total_prompt_tokens: 0,
+            total_completion_tokens: 0,
+            recent_texts: VecDeque::new(),
+            dedup_window: DEFAULT_DEDUP_WINDOW,
+            dedup_skipped: 0,
+            overlap_words: DEFAULT_DEDUP_OVERLAP_WORDS,
+            dedup_words_removed: 0,
+            system_prompt: None,
+            llm_messages: Vec::new(),
+            trigger_keywords: Vec::new(),
+            triggered_by_keyword: false,
         }
     }
 
-    /// Add transcription chunk to buffer
-    pub fn add_transcription(&mut self, text: String) {
-        self.buffer.push(text);
-        self.last_activity = Utc::now();
+    /// Check this many recent texts for repeats (0 disables deduplication)
+    pub fn with_dedup_window(mut self, window: usize) -> Self {
+        self.dedup_window = window;
+        self
+    }
+
+    /// Trim at most this many repeated leading words from each chunk (0 disables trimming)
+    pub fn with_overlap_words(mut self, overlap_words: usize) -> Self {
+        self.overlap_words = overlap_words;
+        self
+    }
+
+    /// Add transcription chunk to buffer. Returns false when it was dropped as a
+    /// repeat: ConvoAI sometimes sends the same chunk twice in quick succession.
+    /// Leading words that repeat the end of the buffer are trimmed, see
+    /// [`Self::add_transcription_deduped`].
+    pub fn add_transcription(&mut self, text: String, confidence: Option<f32>) -> bool {
+        let now = Utc::now();
+        let cutoff = now - chrono::Duration::seconds(DEDUP_WINDOW_SECS);
+        if self.recent_texts.iter().any(|(recent, at)| *recent == text && *at >= cutoff) {
+            self.dedup_skipped += 1;
+            self.last_activity = now;
+            return false;
+        }
+        if self.dedup_window > 0 {
+            if self.recent_texts.len() >= self.dedup_window {
+                self.recent_texts.pop_front();
+            }
+            self.recent_texts.push_back((text.clone(), now));
+        }
+        self.push_trimmed(text, confidence, self.overlap_words);
+        true
+    }
+
+    /// Append `text`, first dropping its leading words that repeat the last words
+    /// of the buffer (at most `overlap_words` of them, longest match first).
+    /// Agora's rolling transcription re-sends the tail of the previous chunk
+    /// ahead of the new words. At least one word of the chunk is always kept;
+    /// whole-chunk repeats are left to the recent-text check.
+    pub fn add_transcription_deduped(&mut self, text: String, overlap_words: usize) {
+        self.push_trimmed(text, None, overlap_words);
+    }
+
+    fn push_trimmed(&mut self, text: String, confidence: Option<f32>, overlap_words: usize) {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let overlap = self.overlap_with_buffer(&words, overlap_words);
+        let text = if overlap > 0 {
+            self.dedup_words_removed += overlap as u64;
+            words[overlap..].join(" ")
+        } else {
+            text
+        };
+
+        let now = Utc::now();
+        // Keep chunk timestamps non-decreasing even if the wall clock steps back
+        let received_at = self
+            .buffer
+            .last()
+            .map_or(now, |last| last.received_at.max(now));
+        self.buffer.push(TranscriptionChunk {
+            text,
+            received_at,
+            confidence,
+        });
+        self.last_activity = now;
+    }
+
+    /// Largest `n <= max_words`, below the chunk's word count, such that the
+    /// chunk's first `n` words are the buffer's last `n` words.
+    fn overlap_with_buffer(&self, words: &[&str], max_words: usize) -> usize {
+        let limit = max_words.min(words.len().saturating_sub(1));
+        if limit == 0 {
+            return 0;
+        }
+        let mut tail: Vec<String> = self
+            .buffer
+            .iter()
+            .rev()
+            .flat_map(|chunk| chunk.text.split_whitespace().rev())
+            .take(limit)
+            .map(overlap_key)
+            .collect();
+        tail.reverse();
+        let head: Vec<String> = words[..limit].iter().map(|word| overlap_key(word)).collect();
+        (1..=tail.len())
+            .rev()
+            .find(|&n| tail[tail.len() - n..] == head[..n])
+            .unwrap_or(0)
+    }
+
+    /// The trigger keyword the transcript ends with, if any. Matching is by
+    /// whole words with case and punctuation ignored, so "go" does not fire on
+    /// "forgo" and "Send it." matches "send it".
+    pub fn keyword_at_end(&self) -> Option<&str> {
+        let keys = |text: &str| -> Vec<String> {
+            text.split_whitespace()
+                .map(overlap_key)
+                .filter(|key| !key.is_empty())
+                .collect()
+        };
+        let longest = self.trigger_keywords.iter().map(|k| k.split_whitespace().count()).max()?;
+        let mut tail: Vec<String> = self
+            .buffer
+            .iter()
+            .rev()
+            .flat_map(|chunk| keys(&chunk.text).into_iter().rev())
+            .take(longest)
+            .collect();
+        tail.reverse();
+        self.trigger_keywords
+            .iter()
+            .find(|keyword| {
+                let keyword = keys(keyword);
+                !keyword.is_empty() && tail.ends_with(&keyword)
+            })
+            .map(String::as_str)
     }
 
     /// Get accumulated transcription as single string
     pub fn get_accumulated_text(&self) -> String {
-        self.buffer.join(" ")
+        self.buffer
+            .iter()
+            .map(|chunk| chunk.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
     }
 
-    /// Mark session as triggered (user pressed hotkey or timeout)
-    pub fn trigger(&mut self) {
-        self.state = VoiceSessionState::Triggered;
+    /// Accumulated text together with the chunks it was built from
+    pub fn accumulated(&self) -> AccumulatedTranscript {
+        AccumulatedTranscript {
+            text: self.get_accumulated_text(),
+            chunks: self.buffer.clone(),
+        }
+    }
+
+    /// Move to `to` if the state machine allows it
+    fn transition(&mut self, to: VoiceSessionState) -> Result<(), &'static str> {
+        validate_transition(&self.state, &to)?;
+        self.state = to;
         self.last_activity = Utc::now();
+        self.state_changed_at = self.last_activity;
+        Ok(())
+    }
+
+    /// Mark session as triggered (user pressed hotkey or timeout)
+    pub fn trigger(&mut self) -> Result<(), &'static str> {
+        self.transition(VoiceSessionState::Triggered)?;
+        self.triggered_by_keyword = false;
+        Ok(())
     }
 
     /// Set LLM response and mark as ready
-    pub fn set_response(&mut self, response: String) {
+    pub fn set_response(&mut self, response: String) -> Result<(), &'static str> {
+        self.transition(VoiceSessionState::ResponseReady)?;
         self.response = Some(response);
-        self.state = VoiceSessionState::ResponseReady;
-        self.last_activity = Utc::now();
+        Ok(())
+    }
+
+    /// Start a new turn: clear the transcript and response and accumulate again
+    pub fn reset(&mut self) -> Result<(), &'static str> {
+        self.transition(VoiceSessionState::Accumulating)?;
+        self.buffer.clear();
+        self.response = None;
+        self.llm_messages.clear();
+        self.triggered_by_keyword = false;
+        Ok(())
     }
 
     /// Check if session is expired (60 seconds of inactivity)
@@ -80,14 +356,38 @@ impl VoiceSession {
     pub fn increment_requests(&mut self) {
         self.request_count += 1;
     }
+
+    /// Hold usage reported with a response; unclaimed usage adds up
+    pub fn add_pending_usage(&mut self, usage: Usage) {
+        let pending = self.pending_usage.get_or_insert_with(Usage::default);
+        pending.prompt_tokens = pending.prompt_tokens.saturating_add(usage.prompt_tokens);
+        pending.completion_tokens = pending.completion_tokens.saturating_add(usage.completion_tokens);
+    }
+
+    /// Move pending usage into the session totals, returning it
+    pub fn claim_usage(&mut self) -> Option<Usage> {
+        let usage = self.pending_usage.take()?;
+        self.total_prompt_tokens += u64::from(usage.prompt_tokens);
+        self.total_completion_tokens += u64::from(usage.completion_tokens);
+        Some(usage)
+    }
 }
 
+/// Default cap on concurrent voice sessions per Atem (`MAX_VOICE_SESSIONS_PER_ATEM`)
+pub const DEFAULT_MAX_SESSIONS_PER_ATEM: usize = 5;
+
 /// Store for managing multiple voice sessions
 #[derive(Clone)]
 pub struct VoiceSessionStore {
     sessions: Arc<RwLock<HashMap<String, VoiceSession>>>,
     // Map session_id -> oneshot sender for blocking /api/llm/chat requests
     waiters: Arc<RwLock<HashMap<String, Vec<oneshot::Sender<String>>>>>,
+    max_sessions_per_atem: usize,
+    dedup_window: usize,
+    overlap_words: usize,
+    stuck_threshold: usize,
+    stuck_secs: u64,
+    events: broadcast::Sender<VoiceStateEvent>,
 }
 
 impl VoiceSessionStore {
@@ -95,16 +395,66 @@ impl VoiceSessionStore {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
             waiters: Arc::new(RwLock::new(HashMap::new())),
+            max_sessions_per_atem: DEFAULT_MAX_SESSIONS_PER_ATEM,
+            dedup_window: DEFAULT_DEDUP_WINDOW,
+            overlap_words: DEFAULT_DEDUP_OVERLAP_WORDS,
+            stuck_threshold: DEFAULT_LLM_HEALTH_STUCK_THRESHOLD,
+            stuck_secs: DEFAULT_LLM_HEALTH_STUCK_SECS,
+            events: broadcast::channel(VOICE_EVENT_CAPACITY).0,
         }
     }
 
-    /// Create a new voice session
-    pub async fn create(&self, session_id: String, atem_id: String, channel: String) -> VoiceSession {
-        let session = VoiceSession::new(session_id.clone(), atem_id, channel);
+    /// Limit how many sessions one Atem may hold at once
+    pub fn with_max_sessions_per_atem(mut self, max: usize) -> Self {
+        self.max_sessions_per_atem = max;
+        self
+    }
+
+    /// Number of recent transcriptions each new session checks for repeats
+    pub fn with_dedup_window(mut self, window: usize) -> Self {
+        self.dedup_window = window;
+        self
+    }
+
+    /// Most repeated leading words trimmed from each transcription chunk
+    pub fn with_overlap_words(mut self, overlap_words: usize) -> Self {
+        self.overlap_words = overlap_words;
+        self
+    }
+
+    /// Report unhealthy once `threshold` sessions have been triggered for
+    /// more than `stuck_secs` seconds
+    pub fn with_health_thresholds(mut self, threshold: usize, stuck_secs: u64) -> Self {
+        self.stuck_threshold = threshold;
+        self.stuck_secs = stuck_secs;
+        self
+    }
+
+    /// Create a new voice session, refusing once the Atem holds its limit
+    pub async fn create(&self, session_id: String, atem_id: String, channel: String) -> Result<VoiceSession, String> {
         let mut sessions = self.sessions.write().await;
+        let count = sessions.values().filter(|s| s.atem_id == atem_id).count();
+        if count >= self.max_sessions_per_atem {
+            tracing::warn!("Refusing voice session for Atem {}: {} sessions", atem_id, count);
+            return Err("Too many sessions for this Atem".to_string());
+        }
+        let session = VoiceSession::new(session_id.clone(), atem_id, channel)
+            .with_dedup_window(self.dedup_window)
+            .with_overlap_words(self.overlap_words);
         sessions.insert(session_id.clone(), session.clone());
         tracing::info!("Created voice session: {}", session_id);
-        session
+        Ok(session)
+    }
+
+    /// Number of active sessions
+    pub async fn session_count(&self) -> usize {
+        self.sessions.read().await.len()
+    }
+
+    /// Number of sessions held by an Atem (without cloning them)
+    pub async fn count_by_atem(&self, atem_id: &str) -> usize {
+        let sessions = self.sessions.read().await;
+        sessions.values().filter(|s| s.atem_id == atem_id).count()
     }
 
     /// Get session by ID
@@ -113,39 +463,99 @@ impl VoiceSessionStore {
         sessions.get(session_id).cloned()
     }
 
-    /// Add transcription to session buffer
-    pub async fn add_transcription(&self, session_id: &str, text: String) -> Option<()> {
+    /// Tell state event subscribers about a session's new state
+    fn emit(&self, session_id: &str, state: VoiceSessionState, trigger_source: Option<&'static str>) {
+        // Fails only when nobody is subscribed
+        let _ = self.events.send(VoiceStateEvent {
+            session_id: session_id.to_string(),
+            state,
+            trigger_source,
+            at: Utc::now(),
+        });
+    }
+
+    /// Add transcription to session buffer. An accumulating session whose
+    /// transcript now ends with one of its trigger keywords is triggered.
+    pub async fn add_transcription(
+        &self,
+        session_id: &str,
+        text: String,
+        confidence: Option<f32>,
+    ) -> Option<()> {
         let mut sessions = self.sessions.write().await;
-        if let Some(session) = sessions.get_mut(session_id) {
-            session.add_transcription(text);
-            Some(())
-        } else {
-            None
+        let session = sessions.get_mut(session_id)?;
+        if !session.add_transcription(text, confidence) {
+            tracing::debug!("Dropped repeated transcription for session {}", session_id);
+            return Some(());
         }
+        if session.state != VoiceSessionState::Accumulating {
+            return Some(());
+        }
+        if let Some(keyword) = session.keyword_at_end() {
+            tracing::info!("Session {}: trigger keyword {:?} heard", session_id, keyword);
+            if session.trigger().is_ok() {
+                session.triggered_by_keyword = true;
+                self.emit(session_id, VoiceSessionState::Triggered, Some("keyword"));
+            }
+        }
+        Some(())
     }
 
-    /// Trigger session (user pressed hotkey or timeout)
-    pub async fn trigger(&self, session_id: &str) -> Option<String> {
+    /// Trigger session (user pressed hotkey or timeout). A session a keyword
+    /// already triggered is returned as is, so a late explicit trigger for the
+    /// same turn is not refused.
+    pub async fn trigger(&self, session_id: &str) -> Result<AccumulatedTranscript, VoiceSessionError> {
         let mut sessions = self.sessions.write().await;
-        if let Some(session) = sessions.get_mut(session_id) {
-            session.trigger();
-            Some(session.get_accumulated_text())
-        } else {
-            None
+        let session = sessions.get_mut(session_id).ok_or(VoiceSessionError::NotFound)?;
+        if session.state == VoiceSessionState::Triggered && session.triggered_by_keyword {
+            return Ok(session.accumulated());
         }
+        session.trigger().map_err(VoiceSessionError::InvalidTransition)?;
+        self.emit(session_id, VoiceSessionState::Triggered, Some("api"));
+        Ok(session.accumulated())
+    }
+
+    /// Return a session whose response was delivered to accumulating
+    pub async fn reset(&self, session_id: &str) -> Result<(), VoiceSessionError> {
+        let mut sessions = self.sessions.write().await;
+        let session = sessions.get_mut(session_id).ok_or(VoiceSessionError::NotFound)?;
+        session.reset().map_err(VoiceSessionError::InvalidTransition)?;
+        self.emit(session_id, VoiceSessionState::Accumulating, None);
+        Ok(())
     }
 
     /// Set LLM response for session (called by Atem)
-    pub async fn set_response(&self, session_id: &str, response: String) -> Option<()> {
+    pub async fn set_response(&self, session_id: &str, response: String) -> Result<(), VoiceSessionError> {
+        self.set_response_with_usage(session_id, response, None).await
+    }
+
+    /// Set LLM response along with the token usage Atem reported for it
+    pub async fn set_response_with_usage(
+        &self,
+        session_id: &str,
+        response: String,
+        usage: Option<Usage>,
+    ) -> Result<(), VoiceSessionError> {
         // Update session state
         {
             let mut sessions = self.sessions.write().await;
-            if let Some(session) = sessions.get_mut(session_id) {
-                session.set_response(response.clone());
-            } else {
+            let Some(session) = sessions.get_mut(session_id) else {
                 tracing::warn!("Attempted to set response for nonexistent session: {}", session_id);
-                return None;
+                return Err(VoiceSessionError::NotFound);
+            };
+            // A repeated response (Atem retrying) keeps the first one
+            if session.state == VoiceSessionState::ResponseReady && session.response.is_some() {
+                tracing::debug!("Ignoring repeated response for session {}", session_id);
+                return Ok(());
+            }
+            if let Err(e) = session.set_response(response.clone()) {
+                tracing::warn!("Refusing response for session {} in {:?}", session_id, session.state);
+                return Err(VoiceSessionError::InvalidTransition(e));
             }
+            if let Some(usage) = usage {
+                session.add_pending_usage(usage);
+            }
+            self.emit(session_id, VoiceSessionState::ResponseReady, None);
         }
 
         // Wake up any waiting /api/llm/chat requests
@@ -157,7 +567,7 @@ impl VoiceSessionStore {
             }
         }
 
-        Some(())
+        Ok(())
     }
 
     /// Register a waiter for LLM response (blocking /api/llm/chat request)
@@ -168,6 +578,40 @@ impl VoiceSessionStore {
         rx
     }
 
+    /// Wait for the session's response: a receiver that is already complete
+    /// when the response is ready, otherwise a registered waiter.
+    /// Returns None for an unknown session.
+    pub async fn get_or_wait(&self, session_id: &str) -> Option<oneshot::Receiver<String>> {
+        // Held while registering so `set_response` cannot slip in between the
+        // check and the registration and miss this waiter
+        let sessions = self.sessions.read().await;
+        let session = sessions.get(session_id)?;
+        if session.state == VoiceSessionState::ResponseReady {
+            if let Some(response) = &session.response {
+                let (tx, rx) = oneshot::channel();
+                let _ = tx.send(response.clone());
+                return Some(rx);
+            }
+        }
+        let (tx, rx) = oneshot::channel();
+        let mut waiters = self.waiters.write().await;
+        waiters.entry(session_id.to_string()).or_default().push(tx);
+        Some(rx)
+    }
+
+    /// Drop all pending waiters for a session without delivering a response.
+    /// Blocked /api/llm/chat requests observe this as a closed channel.
+    pub async fn drop_waiters(&self, session_id: &str) -> usize {
+        let mut waiters = self.waiters.write().await;
+        waiters.remove(session_id).map(|senders| senders.len()).unwrap_or(0)
+    }
+
+    /// Number of pending waiters for a session
+    pub async fn waiter_count(&self, session_id: &str) -> usize {
+        let waiters = self.waiters.read().await;
+        waiters.get(session_id).map(|senders| senders.len()).unwrap_or(0)
+    }
+
     /// Increment request counter for session
     pub async fn increment_requests(&self, session_id: &str) -> Option<u32> {
         let mut sessions = self.sessions.write().await;
@@ -179,6 +623,106 @@ impl VoiceSessionStore {
         }
     }
 
+    /// Record the parameters of the latest LLM request
+    pub async fn set_llm_params(&self, session_id: &str, params: LlmRequestParams) -> Option<()> {
+        let mut sessions = self.sessions.write().await;
+        let session = sessions.get_mut(session_id)?;
+        session.llm_params = params;
+        Some(())
+    }
+
+    /// Replace the phrases that trigger the session when heard (empty disables)
+    pub async fn set_trigger_keywords(&self, session_id: &str, keywords: Vec<String>) -> Result<(), VoiceSessionError> {
+        let mut sessions = self.sessions.write().await;
+        let session = sessions.get_mut(session_id).ok_or(VoiceSessionError::NotFound)?;
+        session.trigger_keywords = keywords
+            .into_iter()
+            .map(|keyword| keyword.trim().to_string())
+            .filter(|keyword| !keyword.is_empty())
+            .collect();
+        Ok(())
+    }
+
+    /// Replace the session's system prompt (`None` clears it)
+    pub async fn set_system_prompt(&self, session_id: &str, prompt: Option<String>) -> Result<(), VoiceSessionError> {
+        let mut sessions = self.sessions.write().await;
+        let session = sessions.get_mut(session_id).ok_or(VoiceSessionError::NotFound)?;
+        session.system_prompt = prompt;
+        Ok(())
+    }
+
+    /// Keep the messages of the chat request now waiting on Atem
+    pub async fn set_llm_messages(&self, session_id: &str, messages: Vec<ChatMessage>) -> Option<()> {
+        let mut sessions = self.sessions.write().await;
+        sessions.get_mut(session_id)?.llm_messages = messages;
+        Some(())
+    }
+
+    /// Count the pending usage toward the session totals (once per response)
+    pub async fn claim_usage(&self, session_id: &str) -> Option<Usage> {
+        let mut sessions = self.sessions.write().await;
+        sessions.get_mut(session_id)?.claim_usage()
+    }
+
+    /// Token totals summed over all active sessions
+    pub async fn token_usage(&self) -> VoiceTokenUsageResponse {
+        let sessions = self.sessions.read().await;
+        VoiceTokenUsageResponse {
+            session_count: sessions.len(),
+            total_prompt_tokens: sessions.values().map(|s| s.total_prompt_tokens).sum(),
+            total_completion_tokens: sessions.values().map(|s| s.total_completion_tokens).sum(),
+        }
+    }
+
+    /// Snapshot of all sessions and waiters for `/api/llm/health`
+    pub async fn health(&self) -> LlmHealthResponse {
+        let now = Utc::now();
+        let sessions = self.sessions.read().await;
+        let mut by_state = VoiceSessionStateCounts::default();
+        let mut stuck_sessions = 0;
+        for session in sessions.values() {
+            match session.state {
+                VoiceSessionState::Accumulating => by_state.accumulating += 1,
+                VoiceSessionState::Triggered => {
+                    by_state.triggered += 1;
+                    let triggered_secs = now.signed_duration_since(session.state_changed_at).num_seconds();
+                    if triggered_secs > self.stuck_secs as i64 {
+                        stuck_sessions += 1;
+                    }
+                }
+                VoiceSessionState::ResponseReady => by_state.response_ready += 1,
+            }
+        }
+        let buffered: usize = sessions.values().map(|s| s.buffer.len()).sum();
+        let average_buffer_size = if sessions.is_empty() {
+            0.0
+        } else {
+            buffered as f64 / sessions.len() as f64
+        };
+        let oldest_session_age_secs = sessions
+            .values()
+            .map(|s| now.signed_duration_since(s.created_at).num_seconds())
+            .max();
+        let active_waiters = self
+            .waiters
+            .read()
+            .await
+            .values()
+            .flatten()
+            .filter(|tx| !tx.is_closed())
+            .count();
+        LlmHealthResponse {
+            healthy: stuck_sessions < self.stuck_threshold,
+            total_sessions: sessions.len(),
+            by_state,
+            active_waiters,
+            responses_ready: sessions.values().filter(|s| s.response.is_some()).count(),
+            average_buffer_size,
+            oldest_session_age_secs,
+            stuck_sessions,
+        }
+    }
+
     /// Get session state
     pub async fn get_state(&self, session_id: &str) -> Option<VoiceSessionState> {
         let sessions = self.sessions.read().await;
@@ -186,24 +730,51 @@ impl VoiceSessionStore {
     }
 
     /// Delete session
+    ///
+    /// Pending waiters are dropped too, so a blocked `/api/llm/chat` request
+    /// fails right away instead of holding its sender until the timeout.
     pub async fn delete(&self, session_id: &str) {
-        let mut sessions = self.sessions.write().await;
-        sessions.remove(session_id);
+        self.sessions.write().await.remove(session_id);
         tracing::info!("Deleted voice session: {}", session_id);
+        let dropped = self.drop_waiters(session_id).await;
+        if dropped > 0 {
+            tracing::info!(
+                "Discarded {} pending waiters for deleted voice session: {}",
+                dropped,
+                session_id
+            );
+        }
     }
 
     /// Cleanup expired sessions (called by background task)
+    ///
+    /// Waiters registered against an expired session are drained as well, so
+    /// their senders don't accumulate once the session itself is gone.
     pub async fn cleanup_expired(&self) {
-        let mut sessions = self.sessions.write().await;
-        let expired: Vec<String> = sessions
-            .iter()
-            .filter(|(_, session)| session.is_expired())
-            .map(|(id, _)| id.clone())
-            .collect();
+        let expired: Vec<String> = {
+            let mut sessions = self.sessions.write().await;
+            let expired: Vec<String> = sessions
+                .iter()
+                .filter(|(_, session)| session.is_expired())
+                .map(|(id, _)| id.clone())
+                .collect();
+
+            for session_id in &expired {
+                sessions.remove(session_id);
+                tracing::info!("Cleaned up expired voice session: {}", session_id);
+            }
+            expired
+        };
 
-        for session_id in expired {
-            sessions.remove(&session_id);
-            tracing::info!("Cleaned up expired voice session: {}", session_id);
+        let mut waiters = self.waiters.write().await;
+        for session_id in &expired {
+            if let Some(senders) = waiters.remove(session_id) {
+                tracing::info!(
+                    "Discarded {} pending waiters for expired voice session: {}",
+                    senders.len(),
+                    session_id
+                );
+            }
         }
     }
 
@@ -224,10 +795,38 @@ impl VoiceSessionStore {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[cfg(test)]
+impl VoiceSessionStore {
+    /// Store `session` as is, bypassing the per-Atem limit
+    pub(crate) async fn insert_session(&self, session: VoiceSession) {
+        self.sessions.write().await.insert(session.session_id.clone(), session);
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<VoiceStateEvent> {
+        self.events.subscribe()
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateVoiceSessionRequest {
     pub atem_id: String,
     pub channel: String,
+    /// Personality for the coding assistant (language, style, context)
+    #[serde(default)]
+    #[validate(length(max = 4096))]
+    pub system_prompt: Option<String>,
+    /// Phrases that trigger the session when the transcript ends with them,
+    /// e.g. "over" or "send it" (case-insensitive, whole words; at most 16)
+    #[serde(default)]
+    #[validate(length(max = 16))]
+    pub trigger_keywords: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetSystemPromptRequest {
+    /// `null` removes the prompt
+    #[validate(length(max = 4096))]
+    pub system_prompt: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -238,6 +837,7 @@ pub struct CreateVoiceSessionResponse {
     pub created_at: DateTime<Utc>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct TriggerRequest {
     // No body needed - session_id is in URL path
@@ -247,13 +847,57 @@ pub struct TriggerRequest {
 pub struct TriggerResponse {
     pub session_id: String,
     pub accumulated_text: String,
+    pub chunks: Vec<TranscriptionChunk>,
     pub atem_id: String,
+    /// A trigger keyword fired this turn before the explicit trigger arrived
+    pub triggered_by_keyword: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VoiceBufferResponse {
+    pub session_id: String,
+    pub accumulated_text: String,
+    pub chunks: Vec<TranscriptionChunk>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct AtemResponseRequest {
     pub session_id: String,
     pub response: String,
+    /// Token usage of the completion, if Atem's provider reported it
+    #[serde(default)]
+    pub usage: Option<Usage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VoiceTokenUsageResponse {
+    pub session_count: usize,
+    pub total_prompt_tokens: u64,
+    pub total_completion_tokens: u64,
+}
+
+/// Voice sessions counted per state
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VoiceSessionStateCounts {
+    pub accumulating: usize,
+    pub triggered: usize,
+    pub response_ready: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LlmHealthResponse {
+    /// False once too many sessions are stuck waiting on Atem
+    pub healthy: bool,
+    pub total_sessions: usize,
+    pub by_state: VoiceSessionStateCounts,
+    /// Blocked `/api/llm/chat` requests still waiting for a response
+    pub active_waiters: usize,
+    pub responses_ready: usize,
+    /// Transcription chunks per session
+    pub average_buffer_size: f64,
+    pub oldest_session_age_secs: Option<i64>,
+    /// Sessions triggered for longer than `LLM_HEALTH_STUCK_SECS`
+    pub stuck_sessions: usize,
 }
 
 #[derive(Debug, Serialize)]
@@ -288,12 +932,142 @@ mod tests {
             "atem".to_string(),
             "channel".to_string(),
         );
-        session.add_transcription("Hello".to_string());
-        session.add_transcription("world".to_string());
+        session.add_transcription("Hello".to_string(), None);
+        session.add_transcription("world".to_string(), None);
         assert_eq!(session.buffer.len(), 2);
         assert_eq!(session.get_accumulated_text(), "Hello world");
     }
 
+    #[test]
+    fn voice_session_drops_immediate_repeats() {
+        let mut session = VoiceSession::new("test".to_string(), "atem".to_string(), "channel".to_string());
+        assert!(session.add_transcription("Open the file".to_string(), None));
+        assert!(session.add_transcription("and save it".to_string(), None));
+        assert!(!session.add_transcription("Open the file".to_string(), None));
+        assert!(!session.add_transcription("and save it".to_string(), None));
+        assert_eq!(session.get_accumulated_text(), "Open the file and save it");
+        assert_eq!(session.dedup_skipped, 2);
+    }
+
+    #[test]
+    fn voice_session_keeps_repeats_minutes_apart() {
+        let mut session = VoiceSession::new("test".to_string(), "atem".to_string(), "channel".to_string());
+        session.add_transcription("Run the tests".to_string(), None);
+        // Spoken again much later
+        for (_, at) in session.recent_texts.iter_mut() {
+            *at -= chrono::Duration::minutes(3);
+        }
+        assert!(session.add_transcription("Run the tests".to_string(), None));
+        assert_eq!(session.buffer.len(), 2);
+        assert_eq!(session.dedup_skipped, 0);
+    }
+
+    #[test]
+    fn voice_session_dedup_window_is_bounded() {
+        let mut session = VoiceSession::new("test".to_string(), "atem".to_string(), "channel".to_string())
+            .with_dedup_window(2);
+        for text in ["one", "two", "three", "one"] {
+            assert!(session.add_transcription(text.to_string(), None));
+        }
+        assert!(!session.add_transcription("one".to_string(), None));
+        assert_eq!(session.recent_texts.len(), 2);
+
+        let mut disabled = VoiceSession::new("test".to_string(), "atem".to_string(), "channel".to_string())
+            .with_dedup_window(0);
+        assert!(disabled.add_transcription("yes".to_string(), None));
+        assert!(disabled.add_transcription("yes".to_string(), None));
+        assert_eq!(disabled.dedup_skipped, 0);
+    }
+
+    fn deduped_session() -> VoiceSession {
+        VoiceSession::new("test".to_string(), "atem".to_string(), "channel".to_string())
+    }
+
+    fn buffer_words(session: &VoiceSession) -> Vec<String> {
+        session
+            .buffer
+            .iter()
+            .flat_map(|chunk| chunk.text.split_whitespace().map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn voice_session_trims_rolling_overlap() {
+        let mut session = deduped_session();
+        session.add_transcription_deduped("please open the".to_string(), 5);
+        session.add_transcription_deduped("open the settings file".to_string(), 5);
+        session.add_transcription_deduped("File, and save".to_string(), 5);
+        assert_eq!(session.get_accumulated_text(), "please open the settings file and save");
+        assert_eq!(session.dedup_words_removed, 3);
+
+        // A chunk never shrinks to nothing; whole repeats are the recent-text check's job
+        session.add_transcription_deduped("save".to_string(), 5);
+        assert_eq!(session.buffer.last().unwrap().text, "save");
+        assert_eq!(session.dedup_words_removed, 3);
+
+        // Only the configured number of words is looked at
+        let mut capped = deduped_session();
+        capped.add_transcription_deduped("a b c d".to_string(), 2);
+        capped.add_transcription_deduped("b c d e".to_string(), 2);
+        assert_eq!(capped.dedup_words_removed, 0);
+
+        let mut disabled = deduped_session().with_overlap_words(0);
+        disabled.add_transcription("go to".to_string(), None);
+        disabled.add_transcription("to the end".to_string(), None);
+        assert_eq!(disabled.get_accumulated_text(), "go to to the end");
+        assert_eq!(disabled.dedup_words_removed, 0);
+    }
+
+    #[test]
+    fn voice_session_dedup_keeps_non_overlapping_input() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        // Consecutive chunks draw from disjoint vocabularies, so no chunk can
+        // start with the words the previous one ended on
+        let vocabularies = [["alpha", "bravo", "charlie", "delta"], ["echo", "foxtrot", "golf", "hotel"]];
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut session = deduped_session();
+            let mut expected = Vec::new();
+            for i in 0..rng.gen_range(1..20) {
+                let vocabulary = &vocabularies[i % 2];
+                let chunk: Vec<&str> = (0..rng.gen_range(1..8))
+                    .map(|_| vocabulary[rng.gen_range(0..vocabulary.len())])
+                    .collect();
+                let overlap_words = rng.gen_range(0..10);
+                session.add_transcription_deduped(chunk.join(" "), overlap_words);
+                expected.extend(chunk);
+            }
+            assert_eq!(buffer_words(&session), expected, "seed {}", seed);
+            assert_eq!(session.dedup_words_removed, 0, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn voice_session_dedup_rebuilds_rolling_windows() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        // Each chunk re-sends up to DEFAULT_DEDUP_OVERLAP_WORDS earlier words of a
+        // stream of distinct words before its new ones
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut session = deduped_session();
+            let mut next = 0;
+            let mut repeated = 0;
+            for _ in 0..rng.gen_range(1..20) {
+                let resent = rng.gen_range(0..=DEFAULT_DEDUP_OVERLAP_WORDS.min(next));
+                let fresh = rng.gen_range(1..6);
+                let chunk: Vec<String> = (next - resent..next + fresh).map(|i| format!("w{}", i)).collect();
+                session.add_transcription(chunk.join(" "), None);
+                next += fresh;
+                repeated += resent as u64;
+            }
+            let expected: Vec<String> = (0..next).map(|i| format!("w{}", i)).collect();
+            assert_eq!(buffer_words(&session), expected, "seed {}", seed);
+            assert_eq!(session.dedup_words_removed, repeated, "seed {}", seed);
+        }
+    }
+
     #[test]
     fn voice_session_trigger() {
         let mut session = VoiceSession::new(
@@ -301,8 +1075,8 @@ mod tests {
             "atem".to_string(),
             "channel".to_string(),
         );
-        session.add_transcription("Create a function".to_string());
-        session.trigger();
+        session.add_transcription("Create a function".to_string(), None);
+        session.trigger().unwrap();
         assert_eq!(session.state, VoiceSessionState::Triggered);
     }
 
@@ -313,11 +1087,54 @@ mod tests {
             "atem".to_string(),
             "channel".to_string(),
         );
-        session.set_response("Here's the function...".to_string());
+        session.trigger().unwrap();
+        session.set_response("Here's the function...".to_string()).unwrap();
         assert_eq!(session.state, VoiceSessionState::ResponseReady);
         assert_eq!(session.response, Some("Here's the function...".to_string()));
     }
 
+    #[test]
+    fn validate_transition_allows_only_the_cycle() {
+        use VoiceSessionState::*;
+        let states = [Accumulating, Triggered, ResponseReady];
+        let allowed = [(Accumulating, Triggered), (Triggered, ResponseReady), (ResponseReady, Accumulating)];
+        for from in &states {
+            for to in &states {
+                let expected = allowed.iter().any(|(f, t)| f == from && t == to);
+                let result = validate_transition(from, to);
+                assert_eq!(result.is_ok(), expected, "{:?} -> {:?}", from, to);
+                if !expected {
+                    assert_eq!(result, Err("Invalid state transition"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn voice_session_rejects_out_of_order_calls() {
+        let mut session = VoiceSession::new("test".to_string(), "atem".to_string(), "channel".to_string());
+        assert!(session.set_response("early".to_string()).is_err());
+        assert!(session.reset().is_err());
+        assert_eq!(session.state, VoiceSessionState::Accumulating);
+        assert!(session.response.is_none());
+
+        session.add_transcription("Hello".to_string(), None);
+        session.trigger().unwrap();
+        assert!(session.trigger().is_err());
+        assert!(session.reset().is_err());
+        assert_eq!(session.state, VoiceSessionState::Triggered);
+
+        session.set_response("Hi".to_string()).unwrap();
+        assert!(session.set_response("again".to_string()).is_err());
+        assert!(session.trigger().is_err());
+        assert_eq!(session.response.as_deref(), Some("Hi"));
+
+        session.reset().unwrap();
+        assert_eq!(session.state, VoiceSessionState::Accumulating);
+        assert!(session.buffer.is_empty());
+        assert!(session.response.is_none());
+    }
+
     #[tokio::test]
     async fn store_create_and_get() {
         let store = VoiceSessionStore::new();
@@ -325,7 +1142,7 @@ mod tests {
             "test-123".to_string(),
             "atem-456".to_string(),
             "channel-789".to_string(),
-        ).await;
+        ).await.unwrap();
 
         let retrieved = store.get("test-123").await.unwrap();
         assert_eq!(retrieved.session_id, session.session_id);
@@ -335,10 +1152,10 @@ mod tests {
     #[tokio::test]
     async fn store_add_transcription() {
         let store = VoiceSessionStore::new();
-        store.create("test".to_string(), "atem".to_string(), "channel".to_string()).await;
+        store.create("test".to_string(), "atem".to_string(), "channel".to_string()).await.unwrap();
 
-        store.add_transcription("test", "Hello".to_string()).await;
-        store.add_transcription("test", "world".to_string()).await;
+        store.add_transcription("test", "Hello".to_string(), None).await;
+        store.add_transcription("test", "world".to_string(), None).await;
 
         let session = store.get("test").await.unwrap();
         assert_eq!(session.get_accumulated_text(), "Hello world");
@@ -347,12 +1164,14 @@ mod tests {
     #[tokio::test]
     async fn store_trigger() {
         let store = VoiceSessionStore::new();
-        store.create("test".to_string(), "atem".to_string(), "channel".to_string()).await;
+        store.create("test".to_string(), "atem".to_string(), "channel".to_string()).await.unwrap();
 
-        store.add_transcription("test", "Create a function".to_string()).await;
-        let text = store.trigger("test").await.unwrap();
+        store.add_transcription("test", "Create a function".to_string(), None).await;
+        let transcript = store.trigger("test").await.unwrap();
 
-        assert_eq!(text, "Create a function");
+        assert_eq!(transcript.text, "Create a function");
+        assert_eq!(transcript.chunks.len(), 1);
+        assert_eq!(transcript.chunks[0].text, "Create a function");
         let session = store.get("test").await.unwrap();
         assert_eq!(session.state, VoiceSessionState::Triggered);
     }
@@ -360,21 +1179,43 @@ mod tests {
     #[tokio::test]
     async fn store_set_response() {
         let store = VoiceSessionStore::new();
-        store.create("test".to_string(), "atem".to_string(), "channel".to_string()).await;
+        store.create("test".to_string(), "atem".to_string(), "channel".to_string()).await.unwrap();
 
-        store.set_response("test", "Here's the response".to_string()).await;
+        store.trigger("test").await.unwrap();
+        store.set_response("test", "Here's the response".to_string()).await.unwrap();
 
         let session = store.get("test").await.unwrap();
         assert_eq!(session.state, VoiceSessionState::ResponseReady);
         assert_eq!(session.response, Some("Here's the response".to_string()));
     }
 
+    #[tokio::test]
+    async fn store_limits_sessions_per_atem() {
+        let store = VoiceSessionStore::new().with_max_sessions_per_atem(2);
+        store.create("a1".to_string(), "atem-1".to_string(), "ch".to_string()).await.unwrap();
+        store.create("a2".to_string(), "atem-1".to_string(), "ch".to_string()).await.unwrap();
+        assert_eq!(store.count_by_atem("atem-1").await, 2);
+
+        let err = store.create("a3".to_string(), "atem-1".to_string(), "ch".to_string()).await.unwrap_err();
+        assert_eq!(err, "Too many sessions for this Atem");
+        assert!(store.get("a3").await.is_none());
+
+        // Other Atems are unaffected
+        store.create("b1".to_string(), "atem-2".to_string(), "ch".to_string()).await.unwrap();
+        assert_eq!(store.count_by_atem("atem-2").await, 1);
+
+        // Deleting a session frees a slot
+        store.delete("a1").await;
+        assert_eq!(store.count_by_atem("atem-1").await, 1);
+        store.create("a3".to_string(), "atem-1".to_string(), "ch".to_string()).await.unwrap();
+    }
+
     #[tokio::test]
     async fn store_get_by_atem() {
         let store = VoiceSessionStore::new();
-        store.create("test1".to_string(), "atem-1".to_string(), "channel-1".to_string()).await;
-        store.create("test2".to_string(), "atem-1".to_string(), "channel-2".to_string()).await;
-        store.create("test3".to_string(), "atem-2".to_string(), "channel-3".to_string()).await;
+        store.create("test1".to_string(), "atem-1".to_string(), "channel-1".to_string()).await.unwrap();
+        store.create("test2".to_string(), "atem-1".to_string(), "channel-2".to_string()).await.unwrap();
+        store.create("test3".to_string(), "atem-2".to_string(), "channel-3".to_string()).await.unwrap();
 
         let atem1_sessions = store.get_by_atem("atem-1").await;
         assert_eq!(atem1_sessions.len(), 2);
@@ -386,7 +1227,7 @@ mod tests {
     #[tokio::test]
     async fn store_increment_requests() {
         let store = VoiceSessionStore::new();
-        store.create("test".to_string(), "atem".to_string(), "channel".to_string()).await;
+        store.create("test".to_string(), "atem".to_string(), "channel".to_string()).await.unwrap();
 
         let count1 = store.increment_requests("test").await.unwrap();
         assert_eq!(count1, 1);
@@ -395,10 +1236,70 @@ mod tests {
         assert_eq!(count2, 2);
     }
 
+    #[tokio::test]
+    async fn store_rejects_invalid_transitions() {
+        let store = VoiceSessionStore::new();
+        store.create("test".to_string(), "atem".to_string(), "channel".to_string()).await.unwrap();
+        let conflict = VoiceSessionError::InvalidTransition("Invalid state transition");
+
+        // A response while accumulating does not wake waiters
+        let mut rx = store.register_waiter("test".to_string()).await;
+        assert_eq!(store.set_response("test", "early".to_string()).await, Err(conflict.clone()));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(store.reset("test").await, Err(conflict.clone()));
+
+        store.trigger("test").await.unwrap();
+        assert_eq!(store.trigger("test").await.unwrap_err(), conflict);
+
+        store.set_response("test", "Done".to_string()).await.unwrap();
+        assert_eq!(rx.await.unwrap(), "Done");
+
+        store.reset("test").await.unwrap();
+        assert_eq!(store.get_state("test").await, Some(VoiceSessionState::Accumulating));
+        assert_eq!(store.reset("missing").await, Err(VoiceSessionError::NotFound));
+    }
+
+    #[tokio::test]
+    async fn repeated_response_is_ignored() {
+        let store = VoiceSessionStore::new();
+        store.create("test".to_string(), "atem".to_string(), "channel".to_string()).await.unwrap();
+        store.trigger("test").await.unwrap();
+        let usage = Some(Usage { prompt_tokens: 10, completion_tokens: 5 });
+        store.set_response_with_usage("test", "first".to_string(), usage).await.unwrap();
+
+        let mut late = store.register_waiter("test".to_string()).await;
+        assert_eq!(store.set_response_with_usage("test", "second".to_string(), usage).await, Ok(()));
+        let session = store.get("test").await.unwrap();
+        assert_eq!(session.state, VoiceSessionState::ResponseReady);
+        assert_eq!(session.response.as_deref(), Some("first"));
+        assert_eq!(session.pending_usage, usage);
+        assert!(late.try_recv().is_err(), "a repeat wakes nobody");
+    }
+
+    #[tokio::test]
+    async fn get_or_wait_returns_a_ready_response_at_once() {
+        let store = VoiceSessionStore::new();
+        store.create("test".to_string(), "atem".to_string(), "channel".to_string()).await.unwrap();
+        assert!(store.get_or_wait("missing").await.is_none());
+
+        store.trigger("test").await.unwrap();
+        let early = store.get_or_wait("test").await.unwrap();
+        assert_eq!(store.waiter_count("test").await, 1);
+        store.set_response("test", "Done".to_string()).await.unwrap();
+        assert_eq!(early.await.unwrap(), "Done");
+
+        // Registered after the response: completed from the cache, not queued
+        let mut late = store.get_or_wait("test").await.unwrap();
+        assert_eq!(late.try_recv().unwrap(), "Done");
+        assert_eq!(store.waiter_count("test").await, 0);
+    }
+
     #[tokio::test]
     async fn waiter_mechanism() {
         let store = VoiceSessionStore::new();
-        store.create("test".to_string(), "atem".to_string(), "channel".to_string()).await;
+        store.create("test".to_string(), "atem".to_string(), "channel".to_string()).await.unwrap();
+
+        store.trigger("test").await.unwrap();
 
         // Register waiter (simulates blocking /api/llm/chat request)
         let rx = store.register_waiter("test".to_string()).await;
@@ -408,7 +1309,7 @@ mod tests {
             let store = store.clone();
             async move {
                 tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                store.set_response("test", "Response!".to_string()).await;
+                store.set_response("test", "Response!".to_string()).await.unwrap();
             }
         });
 
@@ -420,7 +1321,7 @@ mod tests {
     #[tokio::test]
     async fn store_delete_removes_session() {
         let store = VoiceSessionStore::new();
-        store.create("test".to_string(), "atem".to_string(), "ch".to_string()).await;
+        store.create("test".to_string(), "atem".to_string(), "ch".to_string()).await.unwrap();
         assert!(store.get("test").await.is_some());
 
         store.delete("test").await;
@@ -435,23 +1336,23 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn store_trigger_nonexistent_returns_none() {
+    async fn store_trigger_nonexistent_returns_not_found() {
         let store = VoiceSessionStore::new();
         let result = store.trigger("nonexistent").await;
-        assert!(result.is_none());
+        assert_eq!(result.unwrap_err(), VoiceSessionError::NotFound);
     }
 
     #[tokio::test]
-    async fn store_set_response_nonexistent_returns_none() {
+    async fn store_set_response_nonexistent_returns_not_found() {
         let store = VoiceSessionStore::new();
         let result = store.set_response("nonexistent", "resp".to_string()).await;
-        assert!(result.is_none());
+        assert_eq!(result, Err(VoiceSessionError::NotFound));
     }
 
     #[tokio::test]
     async fn store_add_transcription_nonexistent_returns_none() {
         let store = VoiceSessionStore::new();
-        let result = store.add_transcription("nonexistent", "text".to_string()).await;
+        let result = store.add_transcription("nonexistent", "text".to_string(), None).await;
         assert!(result.is_none());
     }
 
@@ -472,7 +1373,7 @@ mod tests {
     #[tokio::test]
     async fn store_cleanup_expired_removes_old_sessions() {
         let store = VoiceSessionStore::new();
-        store.create("fresh".to_string(), "atem".to_string(), "ch".to_string()).await;
+        store.create("fresh".to_string(), "atem".to_string(), "ch".to_string()).await.unwrap();
 
         // Manually age a session by manipulating its last_activity
         {
@@ -489,7 +1390,7 @@ mod tests {
     #[tokio::test]
     async fn store_cleanup_preserves_active_sessions() {
         let store = VoiceSessionStore::new();
-        store.create("active".to_string(), "atem".to_string(), "ch".to_string()).await;
+        store.create("active".to_string(), "atem".to_string(), "ch".to_string()).await.unwrap();
 
         store.cleanup_expired().await;
         assert!(store.get("active").await.is_some());
@@ -523,14 +1424,267 @@ mod tests {
     #[tokio::test]
     async fn waiter_multiple_waiters_all_notified() {
         let store = VoiceSessionStore::new();
-        store.create("test".to_string(), "atem".to_string(), "ch".to_string()).await;
+        store.create("test".to_string(), "atem".to_string(), "ch".to_string()).await.unwrap();
 
         let rx1 = store.register_waiter("test".to_string()).await;
         let rx2 = store.register_waiter("test".to_string()).await;
 
-        store.set_response("test", "Response!".to_string()).await;
+        store.trigger("test").await.unwrap();
+        store.set_response("test", "Response!".to_string()).await.unwrap();
 
         assert_eq!(rx1.await.unwrap(), "Response!");
         assert_eq!(rx2.await.unwrap(), "Response!");
     }
+
+    #[tokio::test]
+    async fn waiter_dropped_sender_returns_error() {
+        let store = VoiceSessionStore::new();
+        store.create("test".to_string(), "atem".to_string(), "ch".to_string()).await.unwrap();
+
+        let rx = store.register_waiter("test".to_string()).await;
+        assert_eq!(store.drop_waiters("test").await, 1);
+
+        assert!(rx.await.is_err());
+        assert_eq!(store.waiter_count("test").await, 0);
+    }
+
+    #[tokio::test]
+    async fn delete_drops_pending_waiters() {
+        let store = VoiceSessionStore::new();
+        store.create("gone".to_string(), "atem".to_string(), "ch".to_string()).await.unwrap();
+        let rx = store.register_waiter("gone".to_string()).await;
+
+        store.delete("gone").await;
+
+        assert_eq!(store.waiter_count("gone").await, 0);
+        let result = tokio::time::timeout(std::time::Duration::from_secs(1), rx)
+            .await
+            .expect("waiter should be released as soon as the session is deleted");
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn cleanup_expired_discards_waiters() {
+        let store = VoiceSessionStore::new();
+        store.create("old".to_string(), "atem".to_string(), "ch".to_string()).await.unwrap();
+        store.create("active".to_string(), "atem".to_string(), "ch".to_string()).await.unwrap();
+
+        let old_rx = store.register_waiter("old".to_string()).await;
+        let _active_rx = store.register_waiter("active".to_string()).await;
+
+        {
+            let mut sessions = store.sessions.write().await;
+            if let Some(session) = sessions.get_mut("old") {
+                session.last_activity = Utc::now() - chrono::Duration::seconds(120);
+            }
+        }
+
+        store.cleanup_expired().await;
+
+        assert_eq!(store.waiter_count("old").await, 0);
+        assert_eq!(store.waiter_count("active").await, 1);
+        assert!(old_rx.await.is_err());
+    }
+
+    #[test]
+    fn transcription_chunk_timestamps_are_monotonic() {
+        let mut session = VoiceSession::new("test".to_string(), "atem".to_string(), "ch".to_string());
+        for i in 0..50 {
+            session.add_transcription(format!("chunk {}", i), None);
+        }
+        assert!(session
+            .buffer
+            .windows(2)
+            .all(|w| w[0].received_at <= w[1].received_at));
+    }
+
+    #[test]
+    fn transcription_chunk_timestamp_never_goes_backwards() {
+        let mut session = VoiceSession::new("test".to_string(), "atem".to_string(), "ch".to_string());
+        session.add_transcription("first".to_string(), None);
+        // Simulate a chunk stamped ahead of the wall clock
+        let ahead = Utc::now() + chrono::Duration::seconds(30);
+        session.buffer[0].received_at = ahead;
+
+        session.add_transcription("second".to_string(), None);
+        assert_eq!(session.buffer[1].received_at, ahead);
+    }
+
+    #[tokio::test]
+    async fn store_add_transcription_keeps_confidence() {
+        let store = VoiceSessionStore::new();
+        store.create("conf".to_string(), "atem".to_string(), "ch".to_string()).await.unwrap();
+        store.add_transcription("conf", "Hello".to_string(), Some(0.92)).await;
+        store.add_transcription("conf", "world".to_string(), None).await;
+
+        let session = store.get("conf").await.unwrap();
+        assert_eq!(session.buffer[0].confidence, Some(0.92));
+        assert_eq!(session.buffer[1].confidence, None);
+
+        let json = serde_json::to_value(&session.buffer).unwrap();
+        assert_eq!(json[0]["text"], "Hello");
+        assert!(json[0]["received_at"].is_string());
+        assert!(json[1].get("confidence").is_none());
+    }
+
+    #[tokio::test]
+    async fn store_accumulates_token_usage() {
+        let store = VoiceSessionStore::new();
+        store.create("usage".to_string(), "atem".to_string(), "ch".to_string()).await.unwrap();
+        store.create("other".to_string(), "atem".to_string(), "ch".to_string()).await.unwrap();
+        let usage = |prompt_tokens, completion_tokens| Some(Usage { prompt_tokens, completion_tokens });
+
+        // One full turn: trigger, respond, then back to accumulating
+        async fn respond(store: &VoiceSessionStore, id: &str, text: &str, usage: Option<Usage>) {
+            store.trigger(id).await.unwrap();
+            store.set_response_with_usage(id, text.to_string(), usage).await.unwrap();
+            store.reset(id).await.unwrap();
+        }
+
+        // Unclaimed usage from two responses adds up, and is counted once
+        respond(&store, "usage", "a", usage(100, 20)).await;
+        respond(&store, "usage", "b", usage(50, 5)).await;
+        assert_eq!(store.claim_usage("usage").await, usage(150, 25));
+        assert_eq!(store.claim_usage("usage").await, None);
+
+        respond(&store, "usage", "c", usage(10, 1)).await;
+        store.claim_usage("usage").await;
+        respond(&store, "other", "d", usage(7, 3)).await;
+        store.claim_usage("other").await;
+        // A response without usage leaves the totals alone
+        respond(&store, "other", "e", None).await;
+        assert_eq!(store.claim_usage("other").await, None);
+
+        let session = store.get("usage").await.unwrap();
+        assert_eq!((session.total_prompt_tokens, session.total_completion_tokens), (160, 26));
+        let totals = store.token_usage().await;
+        assert_eq!(totals.session_count, 2);
+        assert_eq!(totals.total_prompt_tokens, 167);
+        assert_eq!(totals.total_completion_tokens, 29);
+    }
+
+    /// A session triggered `secs` seconds ago
+    fn triggered_for(id: &str, secs: i64) -> VoiceSession {
+        let mut session = VoiceSession::new(id.to_string(), "atem".to_string(), "ch".to_string());
+        session.trigger().unwrap();
+        session.state_changed_at = Utc::now() - chrono::Duration::seconds(secs);
+        session
+    }
+
+    #[tokio::test]
+    async fn health_is_ok_below_the_stuck_threshold() {
+        let store = VoiceSessionStore::new().with_health_thresholds(2, 60);
+        store.create("idle".to_string(), "atem".to_string(), "ch".to_string()).await.unwrap();
+        store.add_transcription("idle", "hello there".to_string(), None).await.unwrap();
+        store.add_transcription("idle", "and more".to_string(), None).await.unwrap();
+        store.insert_session(triggered_for("stuck", 120)).await;
+        store.insert_session(triggered_for("fresh", 5)).await;
+        let _rx = store.register_waiter("fresh".to_string()).await;
+
+        let health = store.health().await;
+        assert!(health.healthy);
+        assert_eq!(health.total_sessions, 3);
+        assert_eq!(
+            health.by_state,
+            VoiceSessionStateCounts { accumulating: 1, triggered: 2, response_ready: 0 }
+        );
+        assert_eq!(health.stuck_sessions, 1);
+        assert_eq!(health.active_waiters, 1);
+        assert_eq!(health.responses_ready, 0);
+        assert!((health.average_buffer_size - 2.0 / 3.0).abs() < 1e-9);
+        assert!(health.oldest_session_age_secs.is_some());
+    }
+
+    #[tokio::test]
+    async fn health_fails_once_enough_sessions_are_stuck() {
+        let store = VoiceSessionStore::new().with_health_thresholds(2, 60);
+        assert!(store.health().await.healthy);
+        assert_eq!(store.health().await.oldest_session_age_secs, None);
+
+        store.insert_session(triggered_for("one", 61)).await;
+        store.insert_session(triggered_for("two", 300)).await;
+        let health = store.health().await;
+        assert!(!health.healthy);
+        assert_eq!(health.stuck_sessions, 2);
+
+        // Answering one brings the count back under the threshold
+        store.set_response("one", "done".to_string()).await.unwrap();
+        let health = store.health().await;
+        assert!(health.healthy);
+        assert_eq!(health.responses_ready, 1);
+        assert_eq!(health.by_state.response_ready, 1);
+    }
+
+    async fn keyword_store(keywords: &[&str]) -> VoiceSessionStore {
+        let store = VoiceSessionStore::new();
+        store.create("kw".into(), "atem".into(), "ch".into()).await.unwrap();
+        store
+            .set_trigger_keywords("kw", keywords.iter().map(|k| k.to_string()).collect())
+            .await
+            .unwrap();
+        store
+    }
+
+    #[tokio::test]
+    async fn trigger_keyword_at_the_end_triggers_the_session() {
+        let store = keyword_store(&["send it", "over"]).await;
+        let mut events = store.subscribe();
+
+        store.add_transcription("kw", "refactor the parser".into(), None).await.unwrap();
+        assert_eq!(store.get_state("kw").await, Some(VoiceSessionState::Accumulating));
+        // Said mid-sentence it is just a word
+        store.add_transcription("kw", "then send it to review and test".into(), None).await.unwrap();
+        assert_eq!(store.get_state("kw").await, Some(VoiceSessionState::Accumulating));
+        assert!(events.try_recv().is_err());
+
+        store.add_transcription("kw", "okay send it".into(), None).await.unwrap();
+        let session = store.get("kw").await.unwrap();
+        assert_eq!(session.state, VoiceSessionState::Triggered);
+        assert!(session.triggered_by_keyword);
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.session_id, "kw");
+        assert_eq!(event.state, VoiceSessionState::Triggered);
+        assert_eq!(event.trigger_source, Some("keyword"));
+
+        // An explicit trigger for the same turn gets the transcript, not a 409
+        let transcript = store.trigger("kw").await.unwrap();
+        assert!(transcript.text.ends_with("okay send it"));
+    }
+
+    #[tokio::test]
+    async fn trigger_keywords_ignore_case_and_punctuation() {
+        let store = keyword_store(&["Over"]).await;
+        store.add_transcription("kw", "that's all, OVER!".into(), None).await.unwrap();
+        assert_eq!(store.get_state("kw").await, Some(VoiceSessionState::Triggered));
+
+        // A keyword split across chunks still counts
+        let store = keyword_store(&["send it"]).await;
+        store.add_transcription("kw", "fix the bug and send".into(), None).await.unwrap();
+        store.add_transcription("kw", "It.".into(), None).await.unwrap();
+        assert_eq!(store.get_state("kw").await, Some(VoiceSessionState::Triggered));
+    }
+
+    #[tokio::test]
+    async fn partial_keyword_matches_do_not_trigger() {
+        let store = keyword_store(&["go", "send it"]).await;
+        for text in ["we can forgo", "let's go home", "send items", "resend it?"] {
+            store.add_transcription("kw", text.into(), None).await.unwrap();
+            assert_eq!(store.get_state("kw").await, Some(VoiceSessionState::Accumulating), "{}", text);
+        }
+    }
+
+    #[tokio::test]
+    async fn explicit_trigger_emits_an_api_event() {
+        let store = VoiceSessionStore::new();
+        store.create("plain".into(), "atem".into(), "ch".into()).await.unwrap();
+        let mut events = store.subscribe();
+        store.add_transcription("plain", "over".into(), None).await.unwrap();
+        assert_eq!(store.get_state("plain").await, Some(VoiceSessionState::Accumulating));
+
+        store.trigger("plain").await.unwrap();
+        assert!(!store.get("plain").await.unwrap().triggered_by_keyword);
+        assert_eq!(events.try_recv().unwrap().trigger_source, Some("api"));
+        // Triggering again is still refused
+        assert!(matches!(store.trigger("plain").await, Err(VoiceSessionError::InvalidTransition(_))));
+    }
 }
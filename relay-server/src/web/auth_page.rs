@@ -1,8 +1,12 @@
+use super::theme;
+
 /// Render the HTML fallback page for auth grant/deny.
 ///
 /// This page is shown when the Astation macOS app is not reachable locally,
 /// allowing the user to grant or deny access via a web browser.
-pub fn render_auth_page(session_id: &str, hostname: &str, otp: &str) -> String {
+/// `_theme_class` is reserved for server-side theming and currently ignored;
+/// the theme follows the browser's preference or the page's toggle.
+pub fn render_auth_page(session_id: &str, hostname: &str, otp: &str, _theme_class: &str) -> String {
     format!(
         r#"<!DOCTYPE html>
 <html lang="en">
@@ -10,7 +14,58 @@ pub fn render_auth_page(session_id: &str, hostname: &str, otp: &str) -> String {
     <meta charset="UTF-8">
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
     <title>Astation Auth</title>
+    {theme_init}
     <style>
+        :root {{
+            --bg: #0a0a0a;
+            --text: #e0e0e0;
+            --card: #1a1a2e;
+            --heading: #ffffff;
+            --muted: #888;
+            --faint: #666;
+            --panel: #16213e;
+            --panel-hover: #1f2b4d;
+            --border: #333;
+            --accent: #64b5f6;
+            --close-bg: #333;
+            --close-hover: #444;
+            --close-text: #ccc;
+            --shadow: rgba(0, 0, 0, 0.4);
+        }}
+        @media (prefers-color-scheme: light) {{
+            html:not([data-theme="dark"]) {{
+                --bg: #f5f5f7;
+                --text: #1d1d1f;
+                --card: #ffffff;
+                --heading: #111111;
+                --muted: #666;
+                --faint: #888;
+                --panel: #eef2fa;
+                --panel-hover: #dde5f5;
+                --border: #d0d0d5;
+                --accent: #1565c0;
+                --close-bg: #e4e4e8;
+                --close-hover: #d4d4da;
+                --close-text: #333;
+                --shadow: rgba(0, 0, 0, 0.1);
+            }}
+        }}
+        html[data-theme="light"] {{
+            --bg: #f5f5f7;
+            --text: #1d1d1f;
+            --card: #ffffff;
+            --heading: #111111;
+            --muted: #666;
+            --faint: #888;
+            --panel: #eef2fa;
+            --panel-hover: #dde5f5;
+            --border: #d0d0d5;
+            --accent: #1565c0;
+            --close-bg: #e4e4e8;
+            --close-hover: #d4d4da;
+            --close-text: #333;
+            --shadow: rgba(0, 0, 0, 0.1);
+        }}
         * {{
             margin: 0;
             padding: 0;
@@ -18,8 +73,8 @@ pub fn render_auth_page(session_id: &str, hostname: &str, otp: &str) -> String {
         }}
         body {{
             font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Oxygen, sans-serif;
-            background: #0a0a0a;
-            color: #e0e0e0;
+            background: var(--bg);
+            color: var(--text);
             display: flex;
             justify-content: center;
             align-items: center;
@@ -27,44 +82,71 @@ pub fn render_auth_page(session_id: &str, hostname: &str, otp: &str) -> String {
             padding: 20px;
         }}
         .container {{
-            background: #1a1a2e;
+            background: var(--card);
             border-radius: 16px;
             padding: 48px;
             max-width: 480px;
             width: 100%;
             text-align: center;
-            box-shadow: 0 8px 32px rgba(0, 0, 0, 0.4);
+            box-shadow: 0 8px 32px var(--shadow);
         }}
         h1 {{
             font-size: 24px;
             margin-bottom: 8px;
-            color: #ffffff;
+            color: var(--heading);
         }}
         .subtitle {{
             font-size: 16px;
-            color: #888;
+            color: var(--muted);
             margin-bottom: 32px;
         }}
         .hostname {{
-            color: #64b5f6;
+            color: var(--accent);
             font-weight: 600;
         }}
         .otp-display {{
             font-size: 48px;
             font-weight: 700;
             letter-spacing: 8px;
-            color: #ffffff;
-            background: #16213e;
+            color: var(--heading);
+            background: var(--panel);
             border-radius: 12px;
             padding: 24px;
             margin: 24px 0;
             font-family: 'SF Mono', 'Fira Code', monospace;
         }}
+        .btn-copy {{
+            padding: 8px 20px;
+            border: 1px solid var(--border);
+            border-radius: 8px;
+            font-size: 14px;
+            font-weight: 600;
+            cursor: pointer;
+            background: var(--panel);
+            color: var(--accent);
+            transition: background 0.2s;
+        }}
+        .btn-copy:hover {{
+            background: var(--panel-hover);
+        }}
+        #otp-fallback {{
+            display: none;
+            width: 100%;
+            margin-top: 12px;
+            padding: 10px;
+            border: 1px solid var(--border);
+            border-radius: 8px;
+            background: var(--bg);
+            color: var(--heading);
+            font-size: 18px;
+            text-align: center;
+            font-family: 'SF Mono', 'Fira Code', monospace;
+        }}
         .otp-label {{
             font-size: 12px;
             text-transform: uppercase;
             letter-spacing: 2px;
-            color: #666;
+            color: var(--faint);
             margin-bottom: 8px;
         }}
         .buttons {{
@@ -131,21 +213,21 @@ pub fn render_auth_page(session_id: &str, hostname: &str, otp: &str) -> String {
             font-size: 15px;
             font-weight: 600;
             cursor: pointer;
-            background: #333;
-            color: #ccc;
+            background: var(--close-bg);
+            color: var(--close-text);
             transition: background 0.2s;
         }}
         .btn-close:hover {{
-            background: #444;
-            color: #fff;
+            background: var(--close-hover);
+            color: var(--heading);
         }}
         .download-link {{
             margin-top: 32px;
             padding-top: 24px;
-            border-top: 1px solid #333;
+            border-top: 1px solid var(--border);
         }}
         .download-link a {{
-            color: #64b5f6;
+            color: var(--accent);
             text-decoration: none;
         }}
         .download-link a:hover {{
@@ -155,8 +237,10 @@ pub fn render_auth_page(session_id: &str, hostname: &str, otp: &str) -> String {
             display: none;
         }}
     </style>
+    {theme_css}
 </head>
 <body>
+    {theme_toggle}
     <div class="container">
         <h1>Astation Auth</h1>
         <p class="subtitle">
@@ -164,7 +248,9 @@ pub fn render_auth_page(session_id: &str, hostname: &str, otp: &str) -> String {
         </p>
 
         <div class="otp-label">Verification Code</div>
-        <div class="otp-display">{otp}</div>
+        <div class="otp-display" id="otp-value">{otp}</div>
+        <button class="btn-copy" id="copy-btn" onclick="copyOtp()">Copy</button>
+        <input type="text" readonly value="{otp}" id="otp-fallback">
 
         <div class="buttons" id="buttons">
             <button class="btn btn-grant" id="grant-btn" onclick="grantAccess()">Grant Access</button>
@@ -236,6 +322,45 @@ pub fn render_auth_page(session_id: &str, hostname: &str, otp: &str) -> String {
             }}
         }}
 
+        function copyOtp() {{
+            if (navigator.clipboard && navigator.clipboard.writeText) {{
+                navigator.clipboard.writeText(otp).then(markCopied, selectOtp);
+            }} else {{
+                selectOtp();
+            }}
+        }}
+
+        function markCopied() {{
+            const btn = document.getElementById('copy-btn');
+            btn.textContent = 'Copied ✓';
+            setTimeout(function() {{
+                btn.textContent = 'Copy';
+            }}, 2000);
+        }}
+
+        // Older browsers: select the code so it can be copied by hand, and try execCommand.
+        function selectOtp() {{
+            const range = document.createRange();
+            range.selectNodeContents(document.getElementById('otp-value'));
+            const selection = window.getSelection();
+            selection.removeAllRanges();
+            selection.addRange(range);
+            let copied = false;
+            try {{
+                copied = document.execCommand('copy');
+            }} catch (e) {{
+                // Not supported
+            }}
+            if (copied) {{
+                markCopied();
+                return;
+            }}
+            const input = document.getElementById('otp-fallback');
+            input.style.display = 'block';
+            input.focus();
+            input.select();
+        }}
+
         function showStatus(type, message) {{
             const box = document.getElementById('status-box');
             const text = document.getElementById('status-text');
@@ -257,7 +382,22 @@ pub fn render_auth_page(session_id: &str, hostname: &str, otp: &str) -> String {
             }}, 300);
         }}
 
-        // Auto-refresh: poll session status every 2 seconds
+        // Show the final outcome once; later updates are ignored.
+        function finish(status) {{
+            if (!polling) return;
+            if (status === 'granted') {{
+                showStatus('granted', 'Access has been granted.');
+            }} else if (status === 'denied') {{
+                showStatus('denied', 'Access has been denied.');
+            }} else if (status === 'expired') {{
+                showStatus('expired', 'Session has expired. Please request a new session.');
+            }} else {{
+                return;
+            }}
+            polling = false;
+        }}
+
+        // Fallback: poll session status every 2 seconds
         async function checkStatus() {{
             if (!polling) return;
 
@@ -265,29 +405,44 @@ pub fn render_auth_page(session_id: &str, hostname: &str, otp: &str) -> String {
                 const resp = await fetch(`/api/sessions/${{sessionId}}/status`);
                 if (resp.ok) {{
                     const data = await resp.json();
-                    if (data.status === 'granted') {{
-                        showStatus('granted', 'Access has been granted.');
-                        polling = false;
-                    }} else if (data.status === 'denied') {{
-                        showStatus('denied', 'Access has been denied.');
-                        polling = false;
-                    }} else if (data.status === 'expired') {{
-                        showStatus('expired', 'Session has expired. Please request a new session.');
-                        polling = false;
-                    }}
+                    finish(data.status);
                 }}
             }} catch (e) {{
                 // Silently continue polling
             }}
         }}
 
-        setInterval(checkStatus, 2000);
+        function startPolling() {{
+            setInterval(checkStatus, 2000);
+        }}
+
+        // Prefer a Server-Sent Events stream: the outcome arrives as soon as it happens.
+        if (typeof EventSource !== 'undefined') {{
+            const events = new EventSource(`/api/sessions/${{sessionId}}/events`);
+            ['granted', 'denied', 'expired'].forEach(function(status) {{
+                events.addEventListener(status, function() {{
+                    events.close();
+                    finish(status);
+                }});
+            }});
+            events.onerror = function() {{
+                // The browser gave up (e.g. the stream is unavailable): poll instead
+                if (events.readyState === EventSource.CLOSED && polling) {{
+                    startPolling();
+                }}
+            }};
+        }} else {{
+            startPolling();
+        }}
     </script>
 </body>
 </html>"#,
         hostname = hostname,
         otp = otp,
         session_id = session_id,
+        theme_init = theme::INIT_SCRIPT,
+        theme_css = theme::TOGGLE_CSS,
+        theme_toggle = theme::TOGGLE_BUTTON,
     )
 }
 
@@ -297,58 +452,90 @@ mod tests {
 
     #[test]
     fn test_render_auth_page_contains_hostname() {
-        let html = render_auth_page("test-session-id", "my-machine", "12345678");
+        let html = render_auth_page("test-session-id", "my-machine", "12345678", "");
         assert!(html.contains("my-machine"));
     }
 
     #[test]
     fn test_render_auth_page_contains_otp() {
-        let html = render_auth_page("test-session-id", "my-machine", "12345678");
+        let html = render_auth_page("test-session-id", "my-machine", "12345678", "");
         assert!(html.contains("12345678"));
     }
 
     #[test]
     fn test_render_auth_page_contains_session_id() {
-        let html = render_auth_page("test-session-id", "my-machine", "12345678");
+        let html = render_auth_page("test-session-id", "my-machine", "12345678", "");
         assert!(html.contains("test-session-id"));
     }
 
     #[test]
     fn test_render_auth_page_contains_title() {
-        let html = render_auth_page("test-session-id", "my-machine", "12345678");
+        let html = render_auth_page("test-session-id", "my-machine", "12345678", "");
         assert!(html.contains("<title>Astation Auth</title>"));
     }
 
     #[test]
     fn test_render_auth_page_contains_grant_button() {
-        let html = render_auth_page("test-session-id", "my-machine", "12345678");
+        let html = render_auth_page("test-session-id", "my-machine", "12345678", "");
         assert!(html.contains("Grant Access"));
     }
 
     #[test]
     fn test_render_auth_page_contains_deny_button() {
-        let html = render_auth_page("test-session-id", "my-machine", "12345678");
+        let html = render_auth_page("test-session-id", "my-machine", "12345678", "");
         assert!(html.contains("Deny"));
     }
 
     #[test]
     fn test_render_auth_page_contains_download_link() {
-        let html = render_auth_page("test-session-id", "my-machine", "12345678");
+        let html = render_auth_page("test-session-id", "my-machine", "12345678", "");
         assert!(html.contains("download the Astation macOS app"));
     }
 
     #[test]
     fn test_render_auth_page_is_valid_html() {
-        let html = render_auth_page("test-session-id", "my-machine", "12345678");
+        let html = render_auth_page("test-session-id", "my-machine", "12345678", "");
         assert!(html.starts_with("<!DOCTYPE html>"));
         assert!(html.contains("</html>"));
     }
 
+    #[test]
+    fn test_render_auth_page_contains_copy_button() {
+        let html = render_auth_page("test-session-id", "my-machine", "12345678", "");
+        assert!(html.contains(r#"<button class="btn-copy" id="copy-btn" onclick="copyOtp()">Copy</button>"#));
+        assert!(html.contains(r#"const otp = "12345678";"#));
+        assert!(html.contains("navigator.clipboard.writeText(otp)"));
+        assert!(html.contains("document.createRange()"));
+        assert!(html.contains(r#"<input type="text" readonly value="12345678" id="otp-fallback">"#));
+    }
+
     #[test]
     fn test_render_auth_page_contains_close_button() {
-        let html = render_auth_page("test-session-id", "my-machine", "12345678");
+        let html = render_auth_page("test-session-id", "my-machine", "12345678", "");
         assert!(html.contains("close-btn"));
         assert!(html.contains("Close this page"));
         assert!(html.contains("closePage()"));
     }
+
+    #[test]
+    fn test_render_auth_page_uses_sse_with_polling_fallback() {
+        let html = render_auth_page("test-session-id", "my-machine", "12345678", "");
+        assert!(html.contains(r#"const sessionId = "test-session-id";"#));
+        assert!(html.contains("if (typeof EventSource !== 'undefined')"));
+        assert!(html.contains("new EventSource(`/api/sessions/${sessionId}/events`)"));
+        for status in ["'granted'", "'denied'", "'expired'"] {
+            assert!(html.contains(status));
+        }
+        assert!(html.contains("fetch(`/api/sessions/${sessionId}/status`)"));
+        assert!(html.contains("setInterval(checkStatus, 2000)"));
+    }
+
+    #[test]
+    fn test_render_auth_page_supports_light_theme() {
+        let html = render_auth_page("test-session-id", "my-machine", "12345678", "");
+        assert!(html.contains("@media (prefers-color-scheme: light)"));
+        assert!(html.contains(r#"html[data-theme="light"]"#));
+        assert!(html.contains(r#"id="theme-toggle""#));
+        assert!(html.contains("localStorage.getItem('astation-theme')"));
+    }
 }
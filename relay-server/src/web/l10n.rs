@@ -0,0 +1,151 @@
+//! Translations for the pair page, chosen from the browser's `Accept-Language`.
+//!
+//! Each language is a flat key → text table. A key missing from a language
+//! falls back to English, so a partial translation still renders a full page.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// Used when nothing in `Accept-Language` matches.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Languages the pair page is translated into, in tie-break order.
+pub const SUPPORTED_LANGUAGES: &[&str] = &["en", "zh", "ja", "es"];
+
+const EN: &[(&str, &str)] = &[
+    ("title", "Atem Pairing"),
+    ("subtitle", "Enter this code in Astation to connect"),
+    ("host", "Host"),
+    ("button", "Open in Astation"),
+    ("footer", "Don't have Astation?"),
+    ("download", "Download"),
+    ("expires_in", "Expires in"),
+    ("expired", "This code has expired"),
+];
+
+const ZH: &[(&str, &str)] = &[
+    ("title", "Atem 配对"),
+    ("subtitle", "在 Astation 中输入此代码以连接"),
+    ("host", "主机"),
+    ("button", "在 Astation 中打开"),
+    ("footer", "还没有 Astation？"),
+    ("download", "下载"),
+    ("expires_in", "剩余时间"),
+    ("expired", "此代码已过期"),
+];
+
+const JA: &[(&str, &str)] = &[
+    ("title", "Atem ペアリング"),
+    ("subtitle", "Astation にこのコードを入力して接続してください"),
+    ("host", "ホスト"),
+    ("button", "Astation で開く"),
+    ("footer", "Astation をお持ちでないですか？"),
+    ("download", "ダウンロード"),
+    ("expires_in", "有効期限まで"),
+    ("expired", "このコードは期限切れです"),
+];
+
+const ES: &[(&str, &str)] = &[
+    ("title", "Emparejamiento de Atem"),
+    ("subtitle", "Introduce este código en Astation para conectar"),
+    ("host", "Equipo"),
+    ("button", "Abrir en Astation"),
+    ("footer", "¿No tienes Astation?"),
+    ("download", "Descargar"),
+    ("expires_in", "Caduca en"),
+    ("expired", "Este código ha caducado"),
+];
+
+/// Language → key → text for the pair page.
+pub static PAIR_PAGE_STRINGS: LazyLock<HashMap<&'static str, HashMap<&'static str, &'static str>>> =
+    LazyLock::new(|| {
+        [("en", EN), ("zh", ZH), ("ja", JA), ("es", ES)]
+            .into_iter()
+            .map(|(lang, table)| (lang, table.iter().copied().collect()))
+            .collect()
+    });
+
+/// The text for `key` in `lang`, falling back to English and then to the key.
+pub fn text(lang: &str, key: &'static str) -> &'static str {
+    PAIR_PAGE_STRINGS
+        .get(lang)
+        .and_then(|table| table.get(key))
+        .or_else(|| PAIR_PAGE_STRINGS[DEFAULT_LANGUAGE].get(key))
+        .copied()
+        .unwrap_or(key)
+}
+
+/// Pick the best supported language for an `Accept-Language` header value.
+///
+/// Entries are ranked by their `q` weight (default 1); a region such as
+/// `zh-CN` matches its base language, `*` matches the default, and entries
+/// with `q=0` or an unparseable weight are ignored. Ties keep header order.
+pub fn negotiate_language(accept_language: Option<&str>) -> &'static str {
+    let Some(header) = accept_language else {
+        return DEFAULT_LANGUAGE;
+    };
+    let mut best: Option<(&'static str, f32)> = None;
+    for entry in header.split(',') {
+        let mut parts = entry.split(';').map(str::trim);
+        let tag = parts.next().unwrap_or_default();
+        let mut weight = Some(1.0);
+        for param in parts {
+            if let Some(q) = param.strip_prefix("q=").or_else(|| param.strip_prefix("Q=")) {
+                weight = q.parse::<f32>().ok().filter(|q| (0.0..=1.0).contains(q));
+            }
+        }
+        let Some(weight) = weight.filter(|q| *q > 0.0) else {
+            continue;
+        };
+        let base = tag.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+        let lang = if base == "*" {
+            DEFAULT_LANGUAGE
+        } else if let Some(lang) = SUPPORTED_LANGUAGES.iter().find(|lang| **lang == base) {
+            lang
+        } else {
+            continue;
+        };
+        if best.is_none_or(|(_, best_weight)| weight > best_weight) {
+            best = Some((lang, weight));
+        }
+    }
+    best.map_or(DEFAULT_LANGUAGE, |(lang, _)| lang)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiation_follows_quality_weights() {
+        assert_eq!(negotiate_language(Some("ja")), "ja");
+        assert_eq!(negotiate_language(Some("zh-CN,zh;q=0.9,en;q=0.8")), "zh");
+        assert_eq!(negotiate_language(Some("en;q=0.5, es-MX;q=0.9")), "es");
+        assert_eq!(negotiate_language(Some("fr-FR, ja;q=0.3, en;q=0.2")), "ja");
+        // Equal weights keep header order
+        assert_eq!(negotiate_language(Some("es, ja")), "es");
+        assert_eq!(negotiate_language(Some("ZH-tw")), "zh");
+    }
+
+    #[test]
+    fn test_negotiation_falls_back_to_english() {
+        assert_eq!(negotiate_language(None), "en");
+        assert_eq!(negotiate_language(Some("")), "en");
+        assert_eq!(negotiate_language(Some("fr, de;q=0.8")), "en");
+        assert_eq!(negotiate_language(Some("*")), "en");
+        assert_eq!(negotiate_language(Some("ja;q=0")), "en");
+        assert_eq!(negotiate_language(Some("ja;q=banana")), "en");
+    }
+
+    #[test]
+    fn test_every_language_translates_the_page() {
+        for lang in SUPPORTED_LANGUAGES {
+            let table = &PAIR_PAGE_STRINGS[lang];
+            for (key, _) in EN {
+                assert!(table.contains_key(key), "{} is missing {}", lang, key);
+            }
+        }
+        assert_eq!(text("ja", "button"), "Astation で開く");
+        assert_eq!(text("xx", "button"), "Open in Astation");
+    }
+}
@@ -1 +1,3 @@
 pub mod auth_page;
+pub mod l10n;
+pub mod theme;
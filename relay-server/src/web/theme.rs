@@ -0,0 +1,54 @@
+//! Light/dark theme switching shared by the HTML pages.
+//!
+//! Each page declares its colours as CSS variables, overrides them inside a
+//! `@media (prefers-color-scheme: light)` block, and repeats the override for
+//! `html[data-theme="light"]`. The toggle button sets `data-theme` on the root
+//! element and remembers the choice in `localStorage` under `astation-theme`,
+//! so an explicit choice wins over the system preference and survives a
+//! refresh.
+
+/// Goes in `<head>`: applies a stored choice before first paint, so the page
+/// doesn't flash the system theme first.
+pub const INIT_SCRIPT: &str = r#"<script>
+    try {
+      const saved = localStorage.getItem('astation-theme');
+      if (saved === 'light' || saved === 'dark') document.documentElement.dataset.theme = saved;
+    } catch (e) {
+      // Storage unavailable (e.g. private mode): follow the system preference
+    }
+  </script>"#;
+
+/// Styling for the toggle button, pinned to the top-right corner.
+pub const TOGGLE_CSS: &str = r#"<style>
+    .theme-toggle { position: fixed; top: 16px; right: 16px; width: 40px; height: 40px; border: none; border-radius: 50%; background: transparent; font-size: 20px; cursor: pointer; }
+    .theme-toggle:hover { background: rgba(128, 128, 128, 0.2); }
+  </style>"#;
+
+/// The ☀️/🌙 button and the script that drives it. Goes at the end of `<body>`.
+pub const TOGGLE_BUTTON: &str = r#"<button class="theme-toggle" id="theme-toggle" type="button" aria-label="Toggle light/dark theme">🌙</button>
+  <script>
+    (function () {
+      const root = document.documentElement;
+      const btn = document.getElementById('theme-toggle');
+      function current() {
+        if (root.dataset.theme) return root.dataset.theme;
+        return window.matchMedia('(prefers-color-scheme: light)').matches ? 'light' : 'dark';
+      }
+      function label() {
+        // Show the theme a click switches to
+        btn.textContent = current() === 'light' ? '🌙' : '☀️';
+      }
+      btn.addEventListener('click', function () {
+        const next = current() === 'light' ? 'dark' : 'light';
+        root.dataset.theme = next;
+        try {
+          localStorage.setItem('astation-theme', next);
+        } catch (e) {
+          // Not persisted; the choice still applies until the page is left
+        }
+        label();
+      });
+      window.matchMedia('(prefers-color-scheme: light)').addEventListener('change', label);
+      label();
+    })();
+  </script>"#;
@@ -0,0 +1,401 @@
+use std::net::IpAddr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use url::{Host, Url};
+
+use crate::rtc_session::Participant;
+
+/// Per-attempt timeout for a webhook POST.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pause before the single retry of a failed delivery.
+const WEBHOOK_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Longest per-session `webhook_url` accepted.
+pub const MAX_WEBHOOK_URL_LEN: usize = 2048;
+
+/// Why a per-session `webhook_url` was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookUrlError {
+    TooLong,
+    /// Not an absolute http(s) URL with a host, or it carries credentials.
+    Invalid,
+    /// Not on `RTC_WEBHOOK_ALLOWED_HOSTS`, or (without that list) a private,
+    /// loopback or link-local destination.
+    HostNotAllowed,
+}
+
+impl WebhookUrlError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            WebhookUrlError::TooLong => "webhook_url_too_long",
+            WebhookUrlError::Invalid => "invalid_webhook_url",
+            WebhookUrlError::HostNotAllowed => "webhook_host_not_allowed",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            WebhookUrlError::TooLong => format!("webhook_url must be at most {} bytes", MAX_WEBHOOK_URL_LEN),
+            WebhookUrlError::Invalid => "webhook_url must be an http(s) URL".to_string(),
+            WebhookUrlError::HostNotAllowed => "webhook_url host is not allowed".to_string(),
+        }
+    }
+}
+
+/// Where per-session webhooks may point: only `RTC_WEBHOOK_ALLOWED_HOSTS` when
+/// that is set, otherwise anywhere public. The operator's own `RTC_WEBHOOK_URL`
+/// is trusted and not checked.
+#[derive(Clone, Debug, Default)]
+pub struct WebhookPolicy {
+    allowed_hosts: Vec<String>,
+}
+
+impl WebhookPolicy {
+    pub fn new(allowed_hosts: Vec<String>) -> Self {
+        WebhookPolicy {
+            allowed_hosts: allowed_hosts.into_iter().map(|host| host.to_ascii_lowercase()).collect(),
+        }
+    }
+
+    /// Parse `raw` and check it may receive a session's events.
+    pub fn check(&self, raw: &str) -> Result<Url, WebhookUrlError> {
+        if raw.len() > MAX_WEBHOOK_URL_LEN {
+            return Err(WebhookUrlError::TooLong);
+        }
+        let url = Url::parse(raw).map_err(|_| WebhookUrlError::Invalid)?;
+        if !matches!(url.scheme(), "http" | "https") || !url.username().is_empty() || url.password().is_some() {
+            return Err(WebhookUrlError::Invalid);
+        }
+        let host = url.host().ok_or(WebhookUrlError::Invalid)?;
+        let allowed = if self.allowed_hosts.is_empty() {
+            match host {
+                Host::Domain(domain) => domain != "localhost" && !domain.ends_with(".localhost"),
+                Host::Ipv4(ip) => is_public(IpAddr::V4(ip)),
+                Host::Ipv6(ip) => is_public(IpAddr::V6(ip)),
+            }
+        } else {
+            let name = url.host_str().unwrap_or_default().trim_start_matches('[').trim_end_matches(']');
+            self.allowed_hosts.iter().any(|allowed| allowed == name)
+        };
+        if !allowed {
+            return Err(WebhookUrlError::HostNotAllowed);
+        }
+        Ok(url)
+    }
+
+    /// Whether per-session deliveries must check where the host resolves to.
+    pub fn public_only(&self) -> bool {
+        self.allowed_hosts.is_empty()
+    }
+}
+
+/// Not private, loopback, link-local, shared, multicast or unspecified.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// Whether every address `url`'s host resolves to is public.
+async fn resolves_public(url: &str) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+    let port = url.port_or_known_default().unwrap_or(80);
+    match url.host() {
+        Some(Host::Domain(domain)) => match tokio::net::lookup_host((domain, port)).await {
+            Ok(addrs) => {
+                let addrs: Vec<_> = addrs.collect();
+                !addrs.is_empty() && addrs.iter().all(|addr| is_public(addr.ip()))
+            }
+            Err(_) => false,
+        },
+        Some(Host::Ipv4(ip)) => is_public(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_public(IpAddr::V6(ip)),
+        None => false,
+    }
+}
+
+/// RTC session lifecycle event sent to webhooks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    SessionCreated,
+    ParticipantJoined,
+    /// The join that took the session to its participant limit.
+    SessionFull,
+    SessionDeleted,
+    SessionExpired,
+}
+
+/// Webhook payload. Carries no tokens: neither the Agora token nor the host token.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    pub event: WebhookEventKind,
+    pub session_id: String,
+    pub app_id: String,
+    pub channel: String,
+    pub participant_count: usize,
+    /// Set on `participant_joined` and `session_full`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub participant: Option<Participant>,
+    pub at: DateTime<Utc>,
+}
+
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("Failed to build webhook HTTP client")
+    })
+}
+
+/// POST `event` to every url in the background; never blocks the caller.
+/// Each delivery is retried once on a transport error or non-2xx status.
+/// Redirects are not followed.
+pub fn dispatch(urls: Vec<String>, event: WebhookEvent) {
+    for url in urls {
+        tokio::spawn(deliver(url, event.clone()));
+    }
+}
+
+/// [`dispatch`] to a single caller-supplied url, dropped unless its host
+/// resolves only to public addresses.
+pub fn dispatch_public(url: String, event: WebhookEvent) {
+    tokio::spawn(async move {
+        if resolves_public(&url).await {
+            deliver(url, event).await;
+        } else {
+            tracing::warn!("Webhook {:?} to {} skipped: host is not public", event.event, url);
+        }
+    });
+}
+
+async fn deliver(url: String, event: WebhookEvent) {
+    for attempt in 1..=2 {
+        match client().post(&url).json(&event).send().await {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => tracing::warn!(
+                "Webhook {:?} to {} returned {} (attempt {})",
+                event.event,
+                url,
+                response.status(),
+                attempt
+            ),
+            Err(e) => tracing::warn!(
+                "Webhook {:?} to {} failed (attempt {}): {}",
+                event.event,
+                url,
+                attempt,
+                e
+            ),
+        }
+        if attempt == 1 {
+            tokio::time::sleep(WEBHOOK_RETRY_DELAY).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtc_session::{RtcSessionOptions, RtcSessionStore, MAX_PARTICIPANTS};
+    use axum::{extract::State, http::StatusCode, routing::post, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    /// Serve `POST /hook` in-process, failing the first `failures` deliveries with 500.
+    /// Returns the hook url and a stream of raw request bodies.
+    async fn receiver(failures: usize) -> (String, mpsc::UnboundedReceiver<String>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let seen = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(
+                    move |State((tx, seen)): State<(mpsc::UnboundedSender<String>, Arc<AtomicUsize>)>,
+                     body: String| async move {
+                        tx.send(body).unwrap();
+                        if seen.fetch_add(1, Ordering::SeqCst) < failures {
+                            StatusCode::INTERNAL_SERVER_ERROR
+                        } else {
+                            StatusCode::NO_CONTENT
+                        }
+                    },
+                ),
+            )
+            .with_state((tx, seen));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/hook", addr), rx)
+    }
+
+    async fn next_event(rx: &mut mpsc::UnboundedReceiver<String>) -> (String, WebhookEvent) {
+        let body = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("webhook not delivered")
+            .unwrap();
+        let event = serde_json::from_str(&body).unwrap();
+        (body, event)
+    }
+
+    #[tokio::test]
+    async fn test_create_and_delete_events_are_delivered() {
+        let (url, mut rx) = receiver(0).await;
+        let store = RtcSessionStore::new().with_webhook_url(Some(url));
+        let created = store
+            .create("hook-1".into(), "app-1".into(), "room-1".into(), "secret-agora-token".into(), 1)
+            .await
+            .unwrap();
+
+        let (body, event) = next_event(&mut rx).await;
+        assert_eq!(event.event, WebhookEventKind::SessionCreated);
+        assert_eq!(event.session_id, "hook-1");
+        assert_eq!(event.app_id, "app-1");
+        assert_eq!(event.channel, "room-1");
+        assert_eq!(event.participant_count, 0);
+        assert!(event.participant.is_none());
+        assert!(!body.contains("secret-agora-token") && !body.contains(&created.host_token));
+
+        store.join("hook-1", "Alice".into()).await.unwrap();
+        let (_, event) = next_event(&mut rx).await;
+        assert_eq!(event.event, WebhookEventKind::ParticipantJoined);
+        assert_eq!(event.participant.unwrap().display_name.as_deref(), Some("Alice"));
+
+        store.delete("hook-1").await.unwrap();
+        let (body, event) = next_event(&mut rx).await;
+        assert_eq!(event.event, WebhookEventKind::SessionDeleted);
+        assert_eq!(event.session_id, "hook-1");
+        assert_eq!(event.participant_count, 1);
+        assert!(!body.contains("secret-agora-token"));
+    }
+
+    #[tokio::test]
+    async fn test_per_session_url_receives_session_full() {
+        let (url, mut rx) = receiver(0).await;
+        let store = RtcSessionStore::new().with_webhook_allowed_hosts(vec!["127.0.0.1".into()]);
+        let options = RtcSessionOptions {
+            webhook_url: Some(url),
+            ..Default::default()
+        };
+        store
+            .create_with_options("hook-full".into(), "a".into(), "c".into(), "t".into(), 1, options)
+            .await
+            .unwrap();
+        for i in 0..MAX_PARTICIPANTS {
+            store.join("hook-full", format!("User {}", i)).await.unwrap();
+        }
+
+        let mut kinds = Vec::new();
+        for _ in 0..MAX_PARTICIPANTS + 2 {
+            kinds.push(next_event(&mut rx).await.1.event);
+        }
+        assert_eq!(kinds.iter().filter(|k| **k == WebhookEventKind::ParticipantJoined).count(), MAX_PARTICIPANTS);
+        assert_eq!(kinds.iter().filter(|k| **k == WebhookEventKind::SessionFull).count(), 1);
+        assert!(kinds.contains(&WebhookEventKind::SessionCreated));
+    }
+
+    #[tokio::test]
+    async fn test_per_session_url_to_private_host_is_not_delivered() {
+        let (url, mut rx) = receiver(0).await;
+        let store = RtcSessionStore::new();
+        let options = RtcSessionOptions {
+            webhook_url: Some(url),
+            ..Default::default()
+        };
+        store
+            .create_with_options("hook-private".into(), "a".into(), "c".into(), "t".into(), 1, options)
+            .await
+            .unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(300), rx.recv()).await.is_err());
+    }
+
+    #[test]
+    fn test_policy_refuses_private_hosts_without_allowlist() {
+        let policy = WebhookPolicy::default();
+        assert!(policy.check("https://hooks.example.com/rtc").is_ok());
+        assert!(policy.check("http://203.0.113.7:8080/hook").is_ok());
+        for url in [
+            "http://127.0.0.1/hook",
+            "http://localhost:9000/hook",
+            "http://10.0.0.5/hook",
+            "http://192.168.1.1/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://100.64.0.1/hook",
+            "http://[::1]/hook",
+            "http://[fd00::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+            "http://0.0.0.0/hook",
+        ] {
+            assert_eq!(policy.check(url).unwrap_err(), WebhookUrlError::HostNotAllowed, "{}", url);
+        }
+        for url in ["ftp://example.com/hook", "/relative", "https://user:pw@example.com/hook", "not a url"] {
+            assert_eq!(policy.check(url).unwrap_err(), WebhookUrlError::Invalid, "{}", url);
+        }
+        let long = format!("https://example.com/{}", "a".repeat(MAX_WEBHOOK_URL_LEN));
+        assert_eq!(policy.check(&long).unwrap_err(), WebhookUrlError::TooLong);
+    }
+
+    #[test]
+    fn test_policy_allowlist_is_exclusive() {
+        let policy = WebhookPolicy::new(vec!["Hooks.Internal".into(), "127.0.0.1".into()]);
+        assert!(policy.check("http://hooks.internal/rtc").is_ok());
+        assert!(policy.check("http://127.0.0.1:8080/hook").is_ok());
+        assert_eq!(
+            policy.check("https://hooks.example.com/rtc").unwrap_err(),
+            WebhookUrlError::HostNotAllowed
+        );
+        assert!(!policy.public_only());
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_is_retried_once() {
+        let (url, mut rx) = receiver(usize::MAX).await;
+        dispatch(
+            vec![url],
+            WebhookEvent {
+                event: WebhookEventKind::SessionExpired,
+                session_id: "gone".into(),
+                app_id: "a".into(),
+                channel: "c".into(),
+                participant_count: 0,
+                participant: None,
+                at: Utc::now(),
+            },
+        );
+
+        assert_eq!(next_event(&mut rx).await.1.session_id, "gone");
+        assert_eq!(next_event(&mut rx).await.1.session_id, "gone");
+        let third = tokio::time::timeout(WEBHOOK_RETRY_DELAY * 3, rx.recv()).await;
+        assert!(third.is_err(), "only one retry expected");
+    }
+}
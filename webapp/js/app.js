@@ -33,12 +33,16 @@ function extractSessionId() {
 
 async function verifySession(id) {
     try {
-        const resp = await fetch(`/api/rtc-sessions/${id}`);
+        const resp = await fetch(`/api/rtc-sessions/${id}?include=participants`);
         if (!resp.ok) {
             showError("This session does not exist or has expired.");
             return;
         }
         const data = await resp.json();
+        if (data.participant_count >= data.max_participants) {
+            showError("This session is full.");
+            return;
+        }
         showNameDialog(data);
     } catch (err) {
         showError("Failed to connect to server.");
@@ -97,6 +101,13 @@ function showNameDialog(sessionData) {
     document.getElementById("session-info").textContent =
         `Channel: ${sessionData.channel}`;
 
+    if (sessionData.passcode_required) {
+        document.getElementById("passcode-group").style.display = "block";
+        document.getElementById("passcode-input").addEventListener("keydown", (e) => {
+            if (e.key === "Enter") handleJoin();
+        });
+    }
+
     const saved = checkSavedUser();
     if (saved) {
         document.getElementById("name-input").value = saved.name;
@@ -137,13 +148,28 @@ async function handleJoin() {
     joinBtn.disabled = true;
     joinBtn.textContent = "Joining...";
 
+    const passcode = document.getElementById("passcode-input").value;
+    const body = { name: name };
+    if (passcode) body.passcode = passcode;
+
     try {
         const resp = await fetch(`/api/rtc-sessions/${sessionId}/join`, {
             method: "POST",
             headers: { "Content-Type": "application/json" },
-            body: JSON.stringify({ name: name })
+            body: JSON.stringify(body)
         });
 
+        if (resp.status === 401 || resp.status === 429) {
+            // Wrong or missing passcode: let the user try again
+            const err = await resp.json().catch(() => ({}));
+            document.getElementById("passcode-group").style.display = "block";
+            document.getElementById("passcode-error").textContent = err.error || "Invalid passcode";
+            document.getElementById("passcode-input").focus();
+            joinBtn.disabled = false;
+            joinBtn.textContent = "Join";
+            return;
+        }
+
         if (!resp.ok) {
             showError("Failed to join session.");
             return;